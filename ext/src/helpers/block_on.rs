@@ -0,0 +1,115 @@
+use super::nogvl;
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    mem::transmute,
+    pin::{pin, Pin},
+    ptr,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+thread_local! {
+    /// Whether the current thread is polling a future in [`block_on`].
+    static POLLING: Cell<bool> = Cell::new(false);
+
+    /// The call scheduled by a [`Deferred`] future, to be run by [`block_on`]
+    /// once the polled future is suspended.
+    static DEFERRED_CALL: RefCell<Option<Box<dyn FnOnce()>>> = RefCell::new(None);
+}
+
+/// Polls `future` to completion on the current thread, without the GVL.
+///
+/// Every time `future` is suspended by a [`Deferred`] future, the deferred
+/// call is run with the GVL held, outside the future. This lets Wasm running
+/// on one of Wasmtime's fiber stacks call back into Ruby from Ruby's own
+/// stack, where Ruby may in turn switch to other Fibers.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+
+    loop {
+        let poll = nogvl(|| {
+            let previous = POLLING.with(|polling| polling.replace(true));
+            let poll = future.as_mut().poll(&mut cx);
+            POLLING.with(|polling| polling.set(previous));
+            poll
+        });
+
+        if let Poll::Ready(output) = poll {
+            return output;
+        }
+
+        if let Some(call) = DEFERRED_CALL.with(|deferred| deferred.borrow_mut().take()) {
+            call();
+        }
+    }
+}
+
+/// Whether the current thread is polling a future in [`block_on`], as opposed
+/// to running a deferred call.
+pub fn is_polling() -> bool {
+    POLLING.with(|polling| polling.get())
+}
+
+/// Returns a future that suspends the future polled in [`block_on`] to run
+/// `call` outside of it, and resolves to `call`'s return value.
+pub fn defer<F, R>(call: F) -> Deferred<F, R>
+where
+    F: FnOnce() -> R,
+{
+    Deferred {
+        call: Some(call),
+        result: None,
+    }
+}
+
+pub struct Deferred<F, R> {
+    call: Option<F>,
+    result: Option<R>,
+}
+
+impl<F, R> Future for Deferred<F, R>
+where
+    F: FnOnce() -> R,
+{
+    type Output = R;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<R> {
+        // SAFETY: neither `call` nor `result` are structurally pinned, but
+        // `result`'s address must stay the same until the deferred call ran,
+        // which pinning guarantees.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if let Some(result) = this.result.take() {
+            return Poll::Ready(result);
+        }
+
+        let call = this.call.take().expect("Deferred polled after completion");
+        let result = ptr::addr_of_mut!(this.result);
+        let deferred: Box<dyn FnOnce() + '_> = Box::new(move || unsafe { *result = Some(call()) });
+        // SAFETY: `block_on` runs the call as soon as the polled future is
+        // suspended, before polling it again or dropping it.
+        let deferred: Box<dyn FnOnce()> = unsafe { transmute(deferred) };
+
+        DEFERRED_CALL.with(|slot| {
+            let previous = slot.borrow_mut().replace(deferred);
+            assert!(previous.is_none(), "a deferred call is already scheduled");
+        });
+        cx.waker().wake_by_ref();
+
+        Poll::Pending
+    }
+}
+
+fn noop_waker() -> Waker {
+    const VTABLE: RawWakerVTable = RawWakerVTable::new(
+        |_| RawWaker::new(ptr::null(), &VTABLE),
+        |_| {},
+        |_| {},
+        |_| {},
+    );
+
+    // SAFETY: the vtable's functions do nothing.
+    unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) }
+}
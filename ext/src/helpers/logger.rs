@@ -0,0 +1,139 @@
+use super::{block_on::is_polling, nogvl::with_gvl};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use magnus::{prelude::*, RModule, Ruby, Value};
+use std::{
+    cell::Cell,
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, Once,
+    },
+};
+
+/// The maximum number of records kept while waiting for a Ruby thread to
+/// deliver them. Older records are dropped first.
+const MAX_PENDING: usize = 10_000;
+
+static LOGGER: RubyLogger = RubyLogger;
+static INSTALL: Once = Once::new();
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PENDING: Mutex<VecDeque<Entry>> = Mutex::new(VecDeque::new());
+
+thread_local! {
+    /// Whether the current thread is calling the Ruby logger, whose own
+    /// records must not be delivered re-entrantly.
+    static DELIVERING: Cell<bool> = Cell::new(false);
+}
+
+struct Entry {
+    level: Level,
+    target: String,
+    message: String,
+}
+
+impl Entry {
+    /// The matching +Logger::Severity+ constant.
+    fn severity(&self) -> u8 {
+        match self.level {
+            Level::Trace | Level::Debug => 0,
+            Level::Info => 1,
+            Level::Warn => 2,
+            Level::Error => 3,
+        }
+    }
+}
+
+/// A [`Log`] forwarding records to +Wasmtime.logger+. Records can be logged
+/// from any thread, but Ruby can only be called from Ruby threads: records
+/// of other threads (e.g. parallel compilation) are queued until a Ruby
+/// thread delivers them, see [`flush_pending`].
+struct RubyLogger;
+
+impl Log for RubyLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        ENABLED.load(Ordering::Relaxed) && metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let entry = Entry {
+            level: record.level(),
+            target: record.target().to_owned(),
+            message: record.args().to_string(),
+        };
+
+        if can_deliver() {
+            with_gvl(|| {
+                deliver_pending();
+                deliver(entry);
+            });
+        } else {
+            let mut pending = PENDING.lock().unwrap();
+            if pending.len() >= MAX_PENDING {
+                pending.pop_front();
+            }
+            pending.push_back(entry);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Whether the current thread can call Ruby, possibly after re-acquiring
+/// the GVL.
+fn can_deliver() -> bool {
+    let ruby_thread = unsafe { rb_sys::ruby_native_thread_p() } != 0;
+    ruby_thread && !is_polling() && !DELIVERING.with(|delivering| delivering.get())
+}
+
+/// Forwards records of level `filter` and above to +Wasmtime.logger+, or
+/// stops forwarding when `filter` is [`LevelFilter::Off`].
+pub fn set_log_level(filter: LevelFilter) {
+    INSTALL.call_once(|| {
+        // Fails when the embedding application installed its own logger,
+        // which then keeps receiving the records.
+        let _ = log::set_logger(&LOGGER);
+    });
+
+    ENABLED.store(filter != LevelFilter::Off, Ordering::Relaxed);
+    log::set_max_level(filter);
+    if filter == LevelFilter::Off {
+        PENDING.lock().unwrap().clear();
+    }
+}
+
+/// Delivers the records queued by non-Ruby threads. Must be called with the
+/// GVL held.
+pub fn flush_pending() {
+    if ENABLED.load(Ordering::Relaxed) && can_deliver() {
+        deliver_pending();
+    }
+}
+
+fn deliver_pending() {
+    let pending = std::mem::take(&mut *PENDING.lock().unwrap());
+    pending.into_iter().for_each(deliver);
+}
+
+fn deliver(entry: Entry) {
+    let Some(logger) = ruby_logger() else {
+        return;
+    };
+
+    DELIVERING.with(|delivering| delivering.set(true));
+    // Exceptions raised by the logger are dropped: logging must not make
+    // Wasm calls fail.
+    let _: Result<Value, _> =
+        logger.funcall("add", (entry.severity(), entry.message, entry.target));
+    DELIVERING.with(|delivering| delivering.set(false));
+}
+
+fn ruby_logger() -> Option<Value> {
+    let ruby = Ruby::get().ok()?;
+    let wasmtime: RModule = ruby.class_object().const_get("Wasmtime").ok()?;
+    let logger: Value = wasmtime.ivar_get("@logger").ok()?;
+    (!logger.is_nil()).then_some(logger)
+}
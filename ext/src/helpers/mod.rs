@@ -1,10 +1,20 @@
+mod block_on;
+mod logger;
 mod macros;
 mod nogvl;
+mod output_limited_buffer;
+mod permissioned_dir;
+mod ruby_io;
 mod static_id;
 mod symbol_enum;
 mod tmplock;
 
-pub use nogvl::nogvl;
+pub use block_on::{block_on, defer, is_polling};
+pub use logger::set_log_level;
+pub use nogvl::{check_interrupts, nogvl, nogvl_interruptible, with_gvl};
+pub use output_limited_buffer::OutputLimitedBuffer;
+pub use permissioned_dir::{DirPerms, FilePerms, PermissionedDir};
+pub use ruby_io::{RubyIoReader, RubyIoWriter};
 pub use static_id::StaticId;
 pub use symbol_enum::SymbolEnum;
 pub use tmplock::Tmplock;
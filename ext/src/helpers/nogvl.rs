@@ -1,29 +1,140 @@
-use std::{ffi::c_void, mem::MaybeUninit, ptr::null_mut};
+use super::logger::flush_pending;
+use std::{cell::Cell, ffi::c_void, mem::MaybeUninit, ptr::null_mut};
 
-use rb_sys::rb_thread_call_without_gvl;
+use magnus::{
+    rb_sys::{protect, AsRawValue},
+    Error, Ruby,
+};
+use rb_sys::{
+    rb_nogvl, rb_thread_call_with_gvl, rb_thread_call_without_gvl, rb_thread_check_ints,
+    RB_NOGVL_INTR_FAIL,
+};
 
-unsafe extern "C" fn call_without_gvl<F, R>(arg: *mut c_void) -> *mut c_void
+thread_local! {
+    /// Whether the current thread released the GVL through [`nogvl`].
+    static GVL_RELEASED: Cell<bool> = Cell::new(false);
+}
+
+unsafe extern "C" fn call_once<F, R>(arg: *mut c_void) -> *mut c_void
 where
-    F: FnMut() -> R,
+    F: FnOnce() -> R,
     R: Sized,
 {
-    let arg = arg as *mut (&mut F, &mut MaybeUninit<R>);
+    let arg = arg as *mut (Option<F>, MaybeUninit<R>);
     let (func, result) = unsafe { &mut *arg };
+    let func = func.take().expect("nogvl callback called twice");
     result.write(func());
 
     null_mut()
 }
 
-pub fn nogvl<F, R>(mut func: F) -> R
+unsafe extern "C" fn call_unblock<U>(arg: *mut c_void)
+where
+    U: Fn() + Sync,
+{
+    let unblock = unsafe { &*(arg as *const U) };
+    unblock();
+}
+
+/// Runs `func` without holding Ruby's GVL, allowing other Ruby threads to run
+/// in the meantime. `func` must not call into Ruby unless it goes through
+/// [`with_gvl`].
+///
+/// Log records of the threads `func` used (e.g. for parallel compilation)
+/// are delivered once it returns.
+pub fn nogvl<F, R>(func: F) -> R
 where
-    F: FnMut() -> R,
+    F: FnOnce() -> R,
     R: Sized,
 {
-    let result = MaybeUninit::uninit();
-    let arg_ptr = &(&mut func, &result) as *const _ as *mut c_void;
+    let mut arg = (Some(func), MaybeUninit::<R>::uninit());
+    let arg_ptr = &mut arg as *mut _ as *mut c_void;
+    let previous = GVL_RELEASED.with(|released| released.replace(true));
 
     unsafe {
-        rb_thread_call_without_gvl(Some(call_without_gvl::<F, R>), arg_ptr, None, null_mut());
-        result.assume_init()
+        rb_thread_call_without_gvl(Some(call_once::<F, R>), arg_ptr, None, null_mut());
     }
+
+    GVL_RELEASED.with(|released| released.set(previous));
+    flush_pending();
+    unsafe { arg.1.assume_init() }
+}
+
+/// Like [`nogvl`], but calls `unblock` when Ruby interrupts the thread (on a
+/// signal such as +SIGINT+, or on +Thread#raise+ and +Thread#kill+), which
+/// must make `func` return early. `unblock` runs on another thread, possibly
+/// several times.
+///
+/// The interrupt is left pending when `func` runs to completion, to be handled
+/// by Ruby once it regains control. If the interrupt arrives before `func` is
+/// started, `func` doesn't run and the interrupt is raised instead.
+pub fn nogvl_interruptible<F, R, U>(func: F, unblock: U) -> Result<R, Error>
+where
+    F: FnOnce() -> R,
+    R: Sized,
+    U: Fn() + Sync,
+{
+    let mut arg = (Some(func), MaybeUninit::<R>::uninit());
+    let arg_ptr = &mut arg as *mut _ as *mut c_void;
+    let unblock_ptr = &unblock as *const U as *mut c_void;
+
+    loop {
+        let previous = GVL_RELEASED.with(|released| released.replace(true));
+
+        // `RB_NOGVL_INTR_FAIL` keeps Ruby from raising pending interrupts
+        // here, which would unwind through this frame.
+        unsafe {
+            rb_nogvl(
+                Some(call_once::<F, R>),
+                arg_ptr,
+                Some(call_unblock::<U>),
+                unblock_ptr,
+                RB_NOGVL_INTR_FAIL as _,
+            );
+        }
+
+        GVL_RELEASED.with(|released| released.set(previous));
+
+        if arg.0.is_none() {
+            flush_pending();
+            return Ok(unsafe { arg.1.assume_init() });
+        }
+
+        // `func` didn't run because of a pending interrupt: raise it, or try
+        // again if it was handled without raising (e.g. a trapped signal).
+        check_interrupts()?;
+    }
+}
+
+/// Handles the thread's pending interrupts, e.g. left by
+/// [`nogvl_interruptible`], raising if one raises (such as +Thread#raise+).
+pub fn check_interrupts() -> Result<(), Error> {
+    protect(|| {
+        unsafe { rb_thread_check_ints() };
+        Ruby::get().unwrap().qnil().as_raw()
+    })?;
+    Ok(())
+}
+
+/// Runs `func` with Ruby's GVL held. Re-acquires the GVL when called from
+/// within [`nogvl`], calls `func` directly otherwise.
+pub fn with_gvl<F, R>(func: F) -> R
+where
+    F: FnOnce() -> R,
+    R: Sized,
+{
+    if !GVL_RELEASED.with(|released| released.get()) {
+        return func();
+    }
+
+    let mut arg = (Some(func), MaybeUninit::<R>::uninit());
+    let arg_ptr = &mut arg as *mut _ as *mut c_void;
+    GVL_RELEASED.with(|released| released.set(false));
+
+    unsafe {
+        rb_thread_call_with_gvl(Some(call_once::<F, R>), arg_ptr);
+    }
+
+    GVL_RELEASED.with(|released| released.set(true));
+    unsafe { arg.1.assume_init() }
 }
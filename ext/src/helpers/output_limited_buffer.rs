@@ -0,0 +1,53 @@
+use super::with_gvl;
+use magnus::{prelude::*, value::Opaque, RString, Ruby};
+use std::io::{self, Write};
+
+/// A [`Write`] implementation that appends to a Ruby `String`, up to
+/// `capacity` bytes. Bytes written past the capacity are silently dropped so
+/// that a guest can't grow the host's memory unboundedly.
+pub struct OutputLimitedBuffer {
+    buffer: Opaque<RString>,
+    capacity: usize,
+}
+
+impl OutputLimitedBuffer {
+    /// Creates a new [`OutputLimitedBuffer`]. The caller is responsible for
+    /// keeping `buffer` alive (i.e. marking it) while this writer is in use.
+    pub fn new(buffer: Opaque<RString>, capacity: usize) -> Self {
+        Self { buffer, capacity }
+    }
+}
+
+impl Write for OutputLimitedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        with_gvl(|| self.write_with_gvl(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl OutputLimitedBuffer {
+    fn write_with_gvl(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let ruby = Ruby::get().map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        let buffer = ruby.get_inner(self.buffer);
+
+        if buffer.is_frozen() {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "cannot write to a frozen buffer",
+            ));
+        }
+
+        let available = self.capacity.saturating_sub(buffer.len());
+        let amount = buf.len().min(available);
+        if amount > 0 {
+            buffer.cat(&buf[..amount]);
+        }
+
+        // Report everything as written, otherwise a guest would keep retrying
+        // once the buffer is full.
+        Ok(buf.len())
+    }
+}
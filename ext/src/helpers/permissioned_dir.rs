@@ -0,0 +1,208 @@
+use std::{any::Any, path::PathBuf};
+use wasi_common::{
+    dir::{OpenResult, ReaddirCursor, ReaddirEntity, WasiDir},
+    file::{FdFlags, Filestat, OFlags},
+    Error, ErrorExt, SystemTimeSpec,
+};
+
+/// What a guest may do with a preopened directory (and its subdirectories).
+#[derive(Clone, Copy, Debug)]
+pub struct DirPerms {
+    /// List entries, stat and open files or subdirectories.
+    pub read: bool,
+    /// Create, rename and remove entries.
+    pub mutate: bool,
+}
+
+impl DirPerms {
+    pub const ALL: Self = Self {
+        read: true,
+        mutate: true,
+    };
+}
+
+/// What a guest may do with the files of a preopened directory.
+#[derive(Clone, Copy, Debug)]
+pub struct FilePerms {
+    pub read: bool,
+    pub write: bool,
+}
+
+impl FilePerms {
+    pub const ALL: Self = Self {
+        read: true,
+        write: true,
+    };
+}
+
+/// A [`WasiDir`] that denies the operations not allowed by its [`DirPerms`]
+/// and [`FilePerms`], as WASI preview 1 contexts have no notion of
+/// permissions. Subdirectories opened through it inherit its permissions.
+pub struct PermissionedDir {
+    inner: Box<dyn WasiDir>,
+    dir_perms: DirPerms,
+    file_perms: FilePerms,
+}
+
+impl PermissionedDir {
+    pub fn new(inner: Box<dyn WasiDir>, dir_perms: DirPerms, file_perms: FilePerms) -> Self {
+        Self {
+            inner,
+            dir_perms,
+            file_perms,
+        }
+    }
+
+    fn check_read(&self) -> Result<(), Error> {
+        if self.dir_perms.read {
+            Ok(())
+        } else {
+            Err(Error::perm())
+        }
+    }
+
+    fn check_mutate(&self) -> Result<(), Error> {
+        if self.dir_perms.mutate {
+            Ok(())
+        } else {
+            Err(Error::perm())
+        }
+    }
+
+    /// Unwraps `dir` when it's a [`PermissionedDir`], checking it can be
+    /// mutated: the underlying directories expect to be given one another.
+    fn mutable_target(dir: &dyn WasiDir) -> Result<&dyn WasiDir, Error> {
+        match dir.as_any().downcast_ref::<Self>() {
+            Some(dir) => {
+                dir.check_mutate()?;
+                Ok(dir.inner.as_ref())
+            }
+            None => Ok(dir),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl WasiDir for PermissionedDir {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    async fn open_file(
+        &self,
+        symlink_follow: bool,
+        path: &str,
+        oflags: OFlags,
+        read: bool,
+        write: bool,
+        fdflags: FdFlags,
+    ) -> Result<OpenResult, Error> {
+        self.check_read()?;
+
+        if oflags.contains(OFlags::CREATE) {
+            self.check_mutate()?;
+        }
+
+        let writes =
+            write || oflags.contains(OFlags::TRUNCATE) || fdflags.contains(FdFlags::APPEND);
+        if (read && !self.file_perms.read) || (writes && !self.file_perms.write) {
+            return Err(Error::perm());
+        }
+
+        let result = self
+            .inner
+            .open_file(symlink_follow, path, oflags, read, write, fdflags)
+            .await?;
+
+        match result {
+            OpenResult::Dir(dir) => Ok(OpenResult::Dir(Box::new(Self::new(
+                dir,
+                self.dir_perms,
+                self.file_perms,
+            )))),
+            file => Ok(file),
+        }
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<(), Error> {
+        self.check_mutate()?;
+        self.inner.create_dir(path).await
+    }
+
+    async fn readdir(
+        &self,
+        cursor: ReaddirCursor,
+    ) -> Result<Box<dyn Iterator<Item = Result<ReaddirEntity, Error>> + Send>, Error> {
+        self.check_read()?;
+        self.inner.readdir(cursor).await
+    }
+
+    async fn symlink(&self, old_path: &str, new_path: &str) -> Result<(), Error> {
+        self.check_mutate()?;
+        self.inner.symlink(old_path, new_path).await
+    }
+
+    async fn remove_dir(&self, path: &str) -> Result<(), Error> {
+        self.check_mutate()?;
+        self.inner.remove_dir(path).await
+    }
+
+    async fn unlink_file(&self, path: &str) -> Result<(), Error> {
+        self.check_mutate()?;
+        self.inner.unlink_file(path).await
+    }
+
+    async fn read_link(&self, path: &str) -> Result<PathBuf, Error> {
+        self.check_read()?;
+        self.inner.read_link(path).await
+    }
+
+    async fn get_filestat(&self) -> Result<Filestat, Error> {
+        self.check_read()?;
+        self.inner.get_filestat().await
+    }
+
+    async fn get_path_filestat(
+        &self,
+        path: &str,
+        follow_symlinks: bool,
+    ) -> Result<Filestat, Error> {
+        self.check_read()?;
+        self.inner.get_path_filestat(path, follow_symlinks).await
+    }
+
+    async fn rename(
+        &self,
+        path: &str,
+        dest_dir: &dyn WasiDir,
+        dest_path: &str,
+    ) -> Result<(), Error> {
+        self.check_mutate()?;
+        let dest_dir = Self::mutable_target(dest_dir)?;
+        self.inner.rename(path, dest_dir, dest_path).await
+    }
+
+    async fn hard_link(
+        &self,
+        path: &str,
+        target_dir: &dyn WasiDir,
+        target_path: &str,
+    ) -> Result<(), Error> {
+        self.check_mutate()?;
+        let target_dir = Self::mutable_target(target_dir)?;
+        self.inner.hard_link(path, target_dir, target_path).await
+    }
+
+    async fn set_times(
+        &self,
+        path: &str,
+        atime: Option<SystemTimeSpec>,
+        mtime: Option<SystemTimeSpec>,
+        follow_symlinks: bool,
+    ) -> Result<(), Error> {
+        self.check_mutate()?;
+        self.inner
+            .set_times(path, atime, mtime, follow_symlinks)
+            .await
+    }
+}
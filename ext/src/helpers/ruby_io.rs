@@ -0,0 +1,120 @@
+use super::{is_polling, with_gvl};
+use magnus::{exception, prelude::*, value::Opaque, RString, Ruby, Value};
+use std::io::{self, Read, Write};
+
+/// A [`Read`] implementation that forwards to a Ruby `IO`-like object
+/// (anything responding to +readpartial+ or +read+).
+pub struct RubyIoReader {
+    io: Opaque<Value>,
+}
+
+impl RubyIoReader {
+    /// Creates a new [`RubyIoReader`]. The caller is responsible for keeping
+    /// `io` alive (i.e. marking it) while this reader is in use.
+    pub fn new(io: Opaque<Value>) -> Self {
+        Self { io }
+    }
+}
+
+impl Read for RubyIoReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        ensure_ruby_stack()?;
+
+        with_gvl(|| {
+            let ruby = ruby()?;
+            let io = ruby.get_inner(self.io);
+
+            // `readpartial` returns as soon as some data is available, which
+            // matters for interactive streams; `read(n)` waits for `n` bytes.
+            let chunk = if io.respond_to("readpartial", false).map_err(to_io_error)? {
+                match io.funcall::<_, _, RString>("readpartial", (buf.len(),)) {
+                    Ok(chunk) => chunk,
+                    Err(e) if e.is_kind_of(exception::eof_error()) => return Ok(0),
+                    Err(e) => return Err(to_io_error(e)),
+                }
+            } else {
+                match io
+                    .funcall::<_, _, Option<RString>>("read", (buf.len(),))
+                    .map_err(to_io_error)?
+                {
+                    Some(chunk) => chunk,
+                    None => return Ok(0),
+                }
+            };
+
+            // SAFETY: the bytes are copied before calling into Ruby again.
+            let bytes = unsafe { chunk.as_slice() };
+            let len = bytes.len().min(buf.len());
+            buf[..len].copy_from_slice(&bytes[..len]);
+            Ok(len)
+        })
+    }
+}
+
+/// A [`Write`] implementation that forwards to a Ruby `IO`-like object
+/// (anything responding to +write+ and +flush+).
+pub struct RubyIoWriter {
+    io: Opaque<Value>,
+}
+
+impl RubyIoWriter {
+    /// Creates a new [`RubyIoWriter`]. The caller is responsible for keeping
+    /// `io` alive (i.e. marking it) while this writer is in use.
+    pub fn new(io: Opaque<Value>) -> Self {
+        Self { io }
+    }
+}
+
+impl Write for RubyIoWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        ensure_ruby_stack()?;
+
+        with_gvl(|| {
+            let ruby = ruby()?;
+            let io = ruby.get_inner(self.io);
+
+            io.funcall::<_, _, usize>("write", (RString::from_slice(buf),))
+                .map_err(to_io_error)
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        ensure_ruby_stack()?;
+
+        with_gvl(|| {
+            let ruby = ruby()?;
+            let io = ruby.get_inner(self.io);
+
+            if io.respond_to("flush", false).map_err(to_io_error)? {
+                io.funcall::<_, _, Value>("flush", ())
+                    .map_err(to_io_error)?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Ruby can't run on the stacks async Wasm runs on, which WASI calls of async
+/// engines are made from.
+fn ensure_ruby_stack() -> io::Result<()> {
+    if is_polling() {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Ruby IO streams are not supported in engines with async_support: true",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn ruby() -> io::Result<Ruby> {
+    Ruby::get().map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+}
+
+fn to_io_error(error: magnus::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, error.to_string())
+}
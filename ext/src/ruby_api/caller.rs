@@ -1,6 +1,14 @@
-use super::{convert::WrapWasmtimeType, externals::Extern, root, store::StoreData};
+use super::{
+    convert::WrapWasmtimeType,
+    externals::Extern,
+    memory::Memory,
+    root,
+    store::{self, StoreData},
+};
 use crate::error;
-use magnus::{class, method, typed_data::Obj, Error, Module as _, RString, Value};
+use magnus::{
+    class, method, scan_args::scan_args, typed_data::Obj, Error, Module as _, RString, Value,
+};
 use std::cell::UnsafeCell;
 use wasmtime::{AsContext, AsContextMut, Caller as CallerImpl, StoreContext, StoreContextMut};
 
@@ -56,12 +64,25 @@ impl<'a> Caller<'a> {
     }
 
     /// @yard
-    /// Returns the store's data. Akin to {Store#data}.
+    /// Returns the store's data. Akin to {Store#data}, also available as
+    /// +data+ so host functions can read the context of the current call
+    /// without a reference to the {Store}.
     /// @return [Object] The store's data (the object passed to {Store.new}).
     pub fn store_data(&self) -> Result<Value, Error> {
         self.context().map(|ctx| ctx.data().user_data())
     }
 
+    /// @yard
+    /// Replaces the store's data. Akin to {Store#data=}, also available as
+    /// +data=+.
+    /// @def store_data=(data)
+    /// @param data [Object]
+    /// @return [Object] +data+
+    pub fn set_store_data(&self, data: Value) -> Result<Value, Error> {
+        self.context_mut()?.data_mut().set_user_data(data);
+        Ok(data)
+    }
+
     /// @yard
     /// @def export(name)
     /// @see Instance#export
@@ -75,8 +96,60 @@ impl<'a> Caller<'a> {
         }
     }
 
+    /// @yard
+    /// Returns the {Memory} exported by the calling instance under +name+.
+    /// Useful to access guest data passed to a host function as a
+    /// pointer/length pair.
+    ///
+    /// @def memory(name = "memory")
+    /// @param name [String] The memory's export name.
+    /// @return [Memory]
+    /// @raise [Error] if there is no memory exported under +name+.
+    ///
+    /// @example Read a guest string passed as (ptr, len)
+    ///   Wasmtime::Func.new(store, [:i32, :i32], []) do |caller, ptr, len|
+    ///     puts caller.memory.read_utf8(ptr, len)
+    ///   end
+    pub fn memory(rb_self: Obj<Caller<'a>>, args: &[Value]) -> Result<Memory<'a>, Error> {
+        let args = scan_args::<(), (Option<RString>,), (), (), (), ()>(args)?;
+        let (name,) = args.optional;
+        match name {
+            Some(name) => Self::memory_named(rb_self, unsafe { name.as_str() }?),
+            None => Self::memory_named(rb_self, "memory"),
+        }
+    }
+
+    /// @yard
+    /// Reads from the memory exported as +"memory"+. Akin to +caller.memory.read(offset, size)+.
+    /// @def read(offset, size)
+    /// @see Memory#read
+    pub fn read(rb_self: Obj<Caller<'a>>, offset: usize, size: usize) -> Result<RString, Error> {
+        Self::memory_named(rb_self, "memory")?.read(offset, size)
+    }
+
+    /// @yard
+    /// Reads UTF-8 from the memory exported as +"memory"+. Akin to +caller.memory.read_utf8(offset, size)+.
+    /// @def read_utf8(offset, size)
+    /// @see Memory#read_utf8
+    pub fn read_utf8(
+        rb_self: Obj<Caller<'a>>,
+        offset: usize,
+        size: usize,
+    ) -> Result<RString, Error> {
+        Self::memory_named(rb_self, "memory")?.read_utf8(offset, size)
+    }
+
+    /// @yard
+    /// Writes to the memory exported as +"memory"+. Akin to +caller.memory.write(offset, value)+.
+    /// @def write(offset, value)
+    /// @see Memory#write
+    pub fn write(rb_self: Obj<Caller<'a>>, offset: usize, value: RString) -> Result<(), Error> {
+        Self::memory_named(rb_self, "memory")?.write(offset, value)
+    }
+
     /// @yard
     /// (see Store#get_fuel)
+    /// Also available as +fuel_remaining+.
     /// @def get_fuel
     pub fn get_fuel(&self) -> Result<u64, Error> {
         self.handle
@@ -89,11 +162,41 @@ impl<'a> Caller<'a> {
     /// (see Store#set_fuel)
     /// @def set_fuel(fuel)
     pub fn set_fuel(&self, fuel: u64) -> Result<(), Error> {
-        self.handle
-            .get_mut()
-            .and_then(|c| c.set_fuel(fuel).map_err(|e| error!("{}", e)))?;
+        self.context_mut()
+            .and_then(|context| store::set_fuel(context, fuel))
+    }
+
+    /// @yard
+    /// (see Store#add_fuel)
+    /// @def add_fuel(fuel)
+    pub fn add_fuel(&self, fuel: u64) -> Result<(), Error> {
+        self.context_mut()
+            .and_then(|context| store::add_fuel(context, fuel))
+    }
 
-        Ok(())
+    /// @yard
+    /// (see Store#fuel_consumed)
+    /// @def fuel_consumed
+    pub fn fuel_consumed(&self) -> Result<u64, Error> {
+        self.context().and_then(store::fuel_consumed)
+    }
+
+    /// @yard
+    /// (see Store#set_epoch_deadline)
+    /// @def set_epoch_deadline(ticks_beyond_current)
+    pub fn set_epoch_deadline(&self, ticks_beyond_current: u64) -> Result<(), Error> {
+        self.context_mut()
+            .map(|mut context| context.set_epoch_deadline(ticks_beyond_current))
+    }
+
+    fn memory_named(rb_self: Obj<Caller<'a>>, name: &str) -> Result<Memory<'a>, Error> {
+        let inner = rb_self.handle.get_mut()?;
+
+        match inner.get_export(name) {
+            Some(wasmtime::Extern::Memory(memory)) => Memory::from_inner(rb_self.into(), memory),
+            Some(_) => Err(error!("export `{}` is not a memory", name)),
+            None => Err(error!("no memory exported as `{}`", name)),
+        }
     }
 
     pub fn context(&self) -> Result<StoreContext<StoreData>, Error> {
@@ -114,9 +217,20 @@ unsafe impl Send for Caller<'_> {}
 pub fn init() -> Result<(), Error> {
     let klass = root().define_class("Caller", class::object())?;
     klass.define_method("store_data", method!(Caller::store_data, 0))?;
+    klass.define_method("store_data=", method!(Caller::set_store_data, 1))?;
+    klass.define_alias("data", "store_data")?;
+    klass.define_alias("data=", "store_data=")?;
     klass.define_method("export", method!(Caller::export, 1))?;
+    klass.define_method("memory", method!(Caller::memory, -1))?;
+    klass.define_method("read", method!(Caller::read, 2))?;
+    klass.define_method("read_utf8", method!(Caller::read_utf8, 2))?;
+    klass.define_method("write", method!(Caller::write, 2))?;
     klass.define_method("get_fuel", method!(Caller::get_fuel, 0))?;
+    klass.define_alias("fuel_remaining", "get_fuel")?;
     klass.define_method("set_fuel", method!(Caller::set_fuel, 1))?;
+    klass.define_method("add_fuel", method!(Caller::add_fuel, 1))?;
+    klass.define_method("fuel_consumed", method!(Caller::fuel_consumed, 0))?;
+    klass.define_method("set_epoch_deadline", method!(Caller::set_epoch_deadline, 1))?;
 
     Ok(())
 }
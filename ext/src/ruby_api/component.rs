@@ -0,0 +1,160 @@
+mod convert;
+mod func;
+mod instance;
+mod linker;
+mod resource;
+mod wasi_http;
+mod wasi_keyvalue;
+mod wit;
+
+use super::{engine::Engine, errors::compile_error, root};
+use crate::{
+    error,
+    helpers::{nogvl, Tmplock},
+};
+use magnus::{
+    class, function, method, value::Lazy, Error, Module as _, Object, RModule, RString, Ruby,
+};
+use wasmtime::component::Component as ComponentImpl;
+
+pub use func::Func;
+pub use instance::Instance;
+pub use linker::Linker;
+pub use resource::HostResources;
+pub use wasi_http::{WasiHttpCtxBuilder, WasiHttpState};
+pub use wasi_keyvalue::check_backend as check_keyvalue_backend;
+
+/// The "Wasmtime::Component" Ruby module.
+pub fn component_namespace() -> RModule {
+    static COMPONENT_NAMESPACE: Lazy<RModule> =
+        Lazy::new(|_| root().define_module("Component").unwrap());
+    let ruby = Ruby::get().unwrap();
+    ruby.get_inner(&COMPONENT_NAMESPACE)
+}
+
+/// @yard
+/// @rename Wasmtime::Component::Component
+/// Represents a WebAssembly component.
+/// @see https://docs.rs/wasmtime/latest/wasmtime/component/struct.Component.html Wasmtime's Rust doc
+#[magnus::wrap(
+    class = "Wasmtime::Component::Component",
+    size,
+    free_immediately,
+    frozen_shareable
+)]
+pub struct Component {
+    inner: ComponentImpl,
+}
+
+impl Component {
+    /// @yard
+    /// @def new(engine, wat_or_wasm)
+    /// @param engine [Wasmtime::Engine]
+    /// @param wat_or_wasm [String] The String of WAT or Wasm.
+    /// @return [Wasmtime::Component::Component]
+    pub fn new(engine: &Engine, wat_or_wasm: RString) -> Result<Self, Error> {
+        let eng = &engine.get()?;
+        let (locked_slice, _locked_slice_guard) = wat_or_wasm.as_locked_slice()?;
+        let inner = nogvl(|| ComponentImpl::new(eng, locked_slice)).map_err(|e| {
+            Error::new(compile_error(), format!("Could not build component: {}", e))
+        })?;
+
+        Ok(Self { inner })
+    }
+
+    /// @yard
+    /// @def from_file(engine, path)
+    /// @param engine [Wasmtime::Engine]
+    /// @param path [String]
+    /// @return [Wasmtime::Component::Component]
+    pub fn from_file(engine: &Engine, path: RString) -> Result<Self, Error> {
+        let eng = &engine.get()?;
+        let (path, _locked_str_guard) = path.as_locked_str()?;
+        // SAFETY: this string is immediately copied and never moved off the stack
+        let inner = nogvl(|| ComponentImpl::from_file(eng, path)).map_err(|e| {
+            Error::new(
+                compile_error(),
+                format!("Could not build component from file: {}", e),
+            )
+        })?;
+
+        Ok(Self { inner })
+    }
+
+    /// @yard
+    /// Instantiates a serialized component coming from either {#serialize} or
+    /// {Wasmtime::Engine#precompile_component}.
+    ///
+    /// The engine serializing and the engine deserializing must:
+    /// * have the same configuration
+    /// * be of the same gem version
+    ///
+    /// @def deserialize(engine, compiled)
+    /// @param engine [Wasmtime::Engine]
+    /// @param compiled [String] String obtained with either
+    ///   {Wasmtime::Engine#precompile_component} or {#serialize}.
+    /// @return [Wasmtime::Component::Component]
+    pub fn deserialize(engine: &Engine, compiled: RString) -> Result<Self, Error> {
+        // SAFETY: this string is immediately copied and never moved off the stack
+        unsafe { ComponentImpl::deserialize(&engine.get()?, compiled.as_slice()) }
+            .map(|inner| Self { inner })
+            .map_err(|e| error!("Could not deserialize component: {}", e))
+    }
+
+    /// @yard
+    /// Instantiates a serialized component from a file.
+    ///
+    /// @def deserialize_file(engine, path)
+    /// @param engine [Wasmtime::Engine]
+    /// @param path [String]
+    /// @return [Wasmtime::Component::Component]
+    /// @see .deserialize
+    pub fn deserialize_file(engine: &Engine, path: RString) -> Result<Self, Error> {
+        unsafe { ComponentImpl::deserialize_file(&engine.get()?, path.as_str()?) }
+            .map(|inner| Self { inner })
+            .map_err(|e| error!("Could not deserialize component from file: {}", e))
+    }
+
+    /// @yard
+    /// Serialize the component.
+    /// @return [String]
+    /// @see .deserialize
+    pub fn serialize(&self) -> Result<RString, Error> {
+        self.inner
+            .serialize()
+            .map(|bytes| RString::from_slice(&bytes))
+            .map_err(|e| error!("{:?}", e))
+    }
+
+    pub fn get(&self) -> &ComponentImpl {
+        &self.inner
+    }
+}
+
+mod bundled {
+    include!(concat!(env!("OUT_DIR"), "/bundled/component.rs"));
+}
+
+pub fn init() -> Result<(), Error> {
+    bundled::init()?;
+
+    let namespace = component_namespace();
+    let class = namespace.define_class("Component", class::object())?;
+    class.define_singleton_method("new", function!(Component::new, 2))?;
+    class.define_singleton_method("from_file", function!(Component::from_file, 2))?;
+    class.define_singleton_method("deserialize", function!(Component::deserialize, 2))?;
+    class.define_singleton_method(
+        "deserialize_file",
+        function!(Component::deserialize_file, 2),
+    )?;
+    class.define_method("serialize", method!(Component::serialize, 0))?;
+
+    namespace.define_singleton_method("wit_world", function!(wit::wit_world, 2))?;
+
+    linker::init(&namespace)?;
+    instance::init(&namespace)?;
+    func::init(&namespace)?;
+    wasi_http::init(&namespace)?;
+
+    Ok(())
+}
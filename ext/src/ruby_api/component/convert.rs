@@ -0,0 +1,281 @@
+use super::{
+    component_namespace,
+    resource::{rb_to_resource, resource_to_rb},
+};
+use crate::{err, error, ruby_api::store::StoreData};
+use magnus::{
+    prelude::*, value::Lazy, Error, IntoValue, RArray, RClass, RHash, RString, Ruby, Symbol,
+    TryConvert, Value,
+};
+use wasmtime::{
+    component::{Enum, Flags, List, OptionVal, Record, ResultVal, Tuple, Type, Val, Variant},
+    StoreContextMut,
+};
+
+/// The +Wasmtime::Component::Variant+ class, defined in Ruby.
+fn variant_class(ruby: &Ruby) -> RClass {
+    static CLASS: Lazy<RClass> = Lazy::new(|_| component_namespace().const_get("Variant").unwrap());
+    ruby.get_inner(&CLASS)
+}
+
+/// The +Wasmtime::Component::Result+ class, defined in Ruby.
+fn result_class(ruby: &Ruby) -> RClass {
+    static CLASS: Lazy<RClass> = Lazy::new(|_| component_namespace().const_get("Result").unwrap());
+    ruby.get_inner(&CLASS)
+}
+
+/// The name of a variant case, enum case or flag, given as a +Symbol+ or a
+/// +String+.
+fn case_name(value: Value) -> Result<String, Error> {
+    match Symbol::from_value(value) {
+        Some(symbol) => Ok(symbol.name()?.into_owned()),
+        None => RString::try_convert(value)?.to_string(),
+    }
+}
+
+/// The fields of a record given as a +Hash+, or as any object responding to
+/// +to_h+ such as a +Struct+.
+fn record_hash(value: Value) -> Result<RHash, Error> {
+    if let Some(hash) = RHash::from_value(value) {
+        return Ok(hash);
+    }
+    if !value.respond_to("to_h", false)? {
+        return err!("expected a Hash or a Struct, got {}", value.inspect());
+    }
+    RHash::try_convert(value.funcall("to_h", ())?)
+}
+
+/// Looks up the record field +name+ by its name as a +String+ or a +Symbol+,
+/// then as a +Symbol+ with dashes replaced by underscores (e.g.
+/// +:first_name+ for +first-name+).
+fn record_field(hash: RHash, name: &str) -> Option<Value> {
+    hash.get(name)
+        .or_else(|| hash.get(Symbol::new(name)))
+        .or_else(|| hash.get(Symbol::new(name.replace('-', "_"))))
+}
+
+/// Converts `val` to Ruby. Resources are converted through `store`'s host
+/// resources, see [`resource_to_rb`].
+pub(crate) fn component_val_to_rb(
+    ruby: &Ruby,
+    store: &mut StoreContextMut<'_, StoreData>,
+    val: Val,
+) -> Result<Value, Error> {
+    match val {
+        Val::Bool(v) => Ok(v.into_value_with(ruby)),
+        Val::S8(v) => Ok(v.into_value_with(ruby)),
+        Val::U8(v) => Ok(v.into_value_with(ruby)),
+        Val::S16(v) => Ok(v.into_value_with(ruby)),
+        Val::U16(v) => Ok(v.into_value_with(ruby)),
+        Val::S32(v) => Ok(v.into_value_with(ruby)),
+        Val::U32(v) => Ok(v.into_value_with(ruby)),
+        Val::S64(v) => Ok(v.into_value_with(ruby)),
+        Val::U64(v) => Ok(v.into_value_with(ruby)),
+        Val::Float32(v) => Ok(v.into_value_with(ruby)),
+        Val::Float64(v) => Ok(v.into_value_with(ruby)),
+        Val::Char(v) => Ok(v.to_string().into_value_with(ruby)),
+        Val::String(v) => Ok(RString::new(&v).into_value_with(ruby)),
+        Val::List(list) => {
+            let array = RArray::with_capacity(list.len());
+            for item in list.iter() {
+                array.push(component_val_to_rb(ruby, store, item.clone())?)?;
+            }
+            Ok(array.into_value_with(ruby))
+        }
+        Val::Record(record) => {
+            let hash = RHash::new();
+            for (name, value) in record.fields() {
+                hash.aset(name, component_val_to_rb(ruby, store, value.clone())?)?;
+            }
+            Ok(hash.into_value_with(ruby))
+        }
+        Val::Tuple(tuple) => {
+            let array = RArray::with_capacity(tuple.values().len());
+            for item in tuple.values() {
+                array.push(component_val_to_rb(ruby, store, item.clone())?)?;
+            }
+            Ok(array.into_value_with(ruby))
+        }
+        Val::Variant(variant) => {
+            let payload = match variant.payload() {
+                Some(payload) => component_val_to_rb(ruby, store, payload.clone())?,
+                None => ruby.qnil().as_value(),
+            };
+            variant_class(ruby).new_instance((variant.discriminant(), payload))
+        }
+        Val::Enum(enum_) => Ok(Symbol::new(enum_.discriminant()).into_value_with(ruby)),
+        Val::Option(option) => match option.value() {
+            Some(value) => component_val_to_rb(ruby, store, value.clone()),
+            None => Ok(ruby.qnil().as_value()),
+        },
+        Val::Result(result) => {
+            let (constructor, payload) = match result.value() {
+                Ok(payload) => ("ok", payload),
+                Err(payload) => ("error", payload),
+            };
+            let payload = match payload {
+                Some(payload) => component_val_to_rb(ruby, store, payload.clone())?,
+                None => ruby.qnil().as_value(),
+            };
+            result_class(ruby).funcall(constructor, (payload,))
+        }
+        Val::Flags(flags) => {
+            let array = RArray::new();
+            for flag in flags.flags() {
+                array.push(Symbol::new(flag))?;
+            }
+            Ok(array.into_value_with(ruby))
+        }
+        Val::Resource(resource) => resource_to_rb(store, resource, None),
+    }
+}
+
+/// Converts `value` to a component value of type `ty`. Ruby objects given as
+/// resources are handed to Wasm through `store`, see [`rb_to_resource`].
+pub(crate) fn rb_to_component_val(
+    store: &mut StoreContextMut<'_, StoreData>,
+    value: Value,
+    ty: &Type,
+) -> Result<Val, Error> {
+    let ruby = Ruby::get().unwrap();
+
+    match ty {
+        Type::Bool => Ok(Val::Bool(bool::try_convert(value)?)),
+        Type::S8 => Ok(Val::S8(i8::try_convert(value)?)),
+        Type::U8 => Ok(Val::U8(u8::try_convert(value)?)),
+        Type::S16 => Ok(Val::S16(i16::try_convert(value)?)),
+        Type::U16 => Ok(Val::U16(u16::try_convert(value)?)),
+        Type::S32 => Ok(Val::S32(i32::try_convert(value)?)),
+        Type::U32 => Ok(Val::U32(u32::try_convert(value)?)),
+        Type::S64 => Ok(Val::S64(i64::try_convert(value)?)),
+        Type::U64 => Ok(Val::U64(u64::try_convert(value)?)),
+        Type::Float32 => Ok(Val::Float32(f32::try_convert(value)?)),
+        Type::Float64 => Ok(Val::Float64(f64::try_convert(value)?)),
+        Type::Char => {
+            let string = RString::try_convert(value)?.to_string()?;
+            let mut chars = string.chars();
+            match (chars.next(), chars.next()) {
+                (Some(char), None) => Ok(Val::Char(char)),
+                _ => err!("expected a single character, got {:?}", string),
+            }
+        }
+        Type::String => Ok(Val::String(
+            RString::try_convert(value)?.to_string()?.into(),
+        )),
+        Type::List(list) => {
+            let array = RArray::try_convert(value)?;
+            let item_ty = list.ty();
+            let mut values = Vec::with_capacity(array.len());
+            // SAFETY: the array is not mutated in the loop.
+            for item in unsafe { array.as_slice() } {
+                values.push(rb_to_component_val(store, *item, &item_ty)?);
+            }
+            List::new(list, values.into_boxed_slice())
+                .map(Val::List)
+                .map_err(|e| error!("{}", e))
+        }
+        Type::Record(record) => {
+            let hash = record_hash(value)?;
+            let mut fields = Vec::with_capacity(record.fields().len());
+            for field in record.fields() {
+                let value = record_field(hash, field.name)
+                    .ok_or_else(|| error!("record field missing: {}", field.name))?;
+                fields.push((field.name, rb_to_component_val(store, value, &field.ty)?));
+            }
+            Record::new(record, fields)
+                .map(Val::Record)
+                .map_err(|e| error!("{}", e))
+        }
+        Type::Tuple(tuple) => {
+            let array = RArray::try_convert(value)?;
+            if array.len() != tuple.types().len() {
+                return err!(
+                    "expected a tuple of {} values, got {}",
+                    tuple.types().len(),
+                    array.len()
+                );
+            }
+            let mut values = Vec::with_capacity(array.len());
+            for (item_ty, item) in tuple.types().zip(unsafe { array.as_slice() }) {
+                values.push(rb_to_component_val(store, *item, &item_ty)?);
+            }
+            Tuple::new(tuple, values.into_boxed_slice())
+                .map(Val::Tuple)
+                .map_err(|e| error!("{}", e))
+        }
+        Type::Variant(variant) => {
+            if !value.is_kind_of(variant_class(&ruby)) {
+                return err!(
+                    "expected a Wasmtime::Component::Variant, got {}",
+                    value.inspect()
+                );
+            }
+            let name = case_name(value.funcall("name", ())?)?;
+            let case = variant
+                .cases()
+                .find(|case| case.name == name)
+                .ok_or_else(|| error!("unknown variant case: {}", name))?;
+            let payload = match case.ty {
+                Some(payload_ty) => Some(rb_to_component_val(
+                    store,
+                    value.funcall("value", ())?,
+                    &payload_ty,
+                )?),
+                None => None,
+            };
+            Variant::new(variant, &name, payload)
+                .map(Val::Variant)
+                .map_err(|e| error!("{}", e))
+        }
+        Type::Enum(enum_) => {
+            let name = case_name(value)?;
+            Enum::new(enum_, &name)
+                .map(Val::Enum)
+                .map_err(|e| error!("{}", e))
+        }
+        Type::Option(option) => {
+            let value = if value.is_nil() {
+                None
+            } else {
+                Some(rb_to_component_val(store, value, &option.ty())?)
+            };
+            OptionVal::new(option, value)
+                .map(Val::Option)
+                .map_err(|e| error!("{}", e))
+        }
+        Type::Result(result) => {
+            if !value.is_kind_of(result_class(&ruby)) {
+                return err!(
+                    "expected a Wasmtime::Component::Result, got {}",
+                    value.inspect()
+                );
+            }
+            let mut payload = |payload_ty: Option<Type>, method: &str| match payload_ty {
+                Some(payload_ty) => {
+                    rb_to_component_val(store, value.funcall(method, ())?, &payload_ty).map(Some)
+                }
+                None => Ok(None),
+            };
+            let value = if value.funcall::<_, _, bool>("ok?", ())? {
+                Ok(payload(result.ok(), "ok")?)
+            } else {
+                Err(payload(result.err(), "error")?)
+            };
+            ResultVal::new(result, value)
+                .map(Val::Result)
+                .map_err(|e| error!("{}", e))
+        }
+        Type::Flags(flags) => {
+            let array = RArray::try_convert(value)?;
+            let mut names = Vec::with_capacity(array.len());
+            for name in unsafe { array.as_slice() } {
+                names.push(case_name(*name)?);
+            }
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            Flags::new(flags, &names)
+                .map(Val::Flags)
+                .map_err(|e| error!("{}", e))
+        }
+        Type::Own(_) | Type::Borrow(_) => rb_to_resource(store, value, ty, None),
+    }
+}
@@ -0,0 +1,155 @@
+use super::convert::{component_val_to_rb, rb_to_component_val};
+use crate::{
+    helpers::{block_on, nogvl},
+    ruby_api::store::{Store, StoreContextValue},
+};
+use magnus::{
+    class, gc::Marker, method, prelude::*, typed_data::Obj, DataTypeFunctions, Error, IntoValue,
+    RArray, RModule, Ruby, TypedData, Value,
+};
+use wasmtime::component::{Func as FuncImpl, Type, Val};
+
+/// @yard
+/// @rename Wasmtime::Component::Func
+/// Represents a function exported by a WebAssembly component.
+///
+/// Component values are converted from and to Ruby as follows:
+///
+/// | Wasm                   | Ruby                            |
+/// |------------------------|---------------------------------|
+/// | bool                   | +true+ or +false+               |
+/// | s8, u8 ... s64, u64    | +Integer+                       |
+/// | float32, float64       | +Float+                         |
+/// | char, string           | +String+                        |
+/// | list<T>, tuple<...>    | +Array+                         |
+/// | record                 | +Hash+ with +String+ keys       |
+/// | variant                | {Variant}                       |
+/// | enum                   | +Symbol+                        |
+/// | option<T>              | +nil+ or the value              |
+/// | result<O, E>           | {Result}                        |
+/// | flags                  | +Array+ of +Symbol+s            |
+/// | own<T>, borrow<T>      | See {Linker#define_resource}    |
+///
+/// Records can also be given as +Hash+es with +Symbol+ keys, in which dashes
+/// can be underscores, or as objects responding to +to_h+ (e.g. +Struct+s).
+/// Variant case names, enum cases and flags can be given as +String+s or
+/// +Symbol+s.
+///
+/// The +result+ a function returns is unwrapped by {#call} unless the
+/// function was retrieved with +unwrap_results: false+ (see
+/// {Instance#get_func}): its ok payload is returned, its error payload
+/// raised as a {Result::ErrorResult}.
+///
+/// @see https://docs.rs/wasmtime/latest/wasmtime/component/struct.Func.html Wasmtime's Rust doc
+#[derive(TypedData)]
+#[magnus(class = "Wasmtime::Component::Func", mark, free_immediately)]
+pub struct Func {
+    store: Obj<Store>,
+    inner: FuncImpl,
+    unwrap_results: bool,
+}
+
+unsafe impl Send for Func {}
+
+impl DataTypeFunctions for Func {
+    fn mark(&self, marker: &Marker) {
+        marker.mark(self.store)
+    }
+}
+
+impl Func {
+    pub fn from_inner(store: Obj<Store>, inner: FuncImpl, unwrap_results: bool) -> Self {
+        Self {
+            store,
+            inner,
+            unwrap_results,
+        }
+    }
+
+    /// @yard
+    /// Calls the function with the given arguments, converted according to the
+    /// function's parameter types.
+    ///
+    /// @def call(*args)
+    /// @param args [Array<Object>]
+    /// @return [nil, Object, Array<Object>] +nil+ when the function has no results,
+    ///   the result when it has one, an +Array+ of results otherwise.
+    /// @raise [Result::ErrorResult] When the function returns an error
+    ///   +result+, unless retrieved with +unwrap_results: false+.
+    pub fn call(&self, args: &[Value]) -> Result<Value, Error> {
+        let ruby = Ruby::get().unwrap();
+        let store = self.store;
+        let params_ty = self.inner.params(store.context());
+        let results_ty = self.inner.results(store.context());
+
+        if args.len() != params_ty.len() {
+            return Err(Error::new(
+                magnus::exception::arg_error(),
+                format!(
+                    "wrong number of arguments (given {}, expected {})",
+                    args.len(),
+                    params_ty.len()
+                ),
+            ));
+        }
+
+        let func = self.inner;
+        let _lock = store.lock()?;
+        let mut context = store.context_mut();
+
+        // Resources lent to the function are only borrowed for the call.
+        let loans = context.data().host_resources().loans();
+        let params = params_ty
+            .iter()
+            .zip(args.iter())
+            .map(|(ty, arg)| rb_to_component_val(&mut context, *arg, ty))
+            .collect::<Result<Vec<Val>, Error>>();
+        let params = match params {
+            Ok(params) => params,
+            Err(e) => {
+                context.data_mut().host_resources_mut().end_loans(loans);
+                return Err(e);
+            }
+        };
+        let mut results = vec![Val::Bool(false); results_ty.len()];
+
+        let result = if context.data().is_async() {
+            block_on(async {
+                func.call_async(&mut context, &params, &mut results).await?;
+                func.post_return_async(&mut context).await
+            })
+        } else {
+            nogvl(|| {
+                func.call(&mut context, &params, &mut results)?;
+                func.post_return(&mut context)
+            })
+        };
+        context.data_mut().host_resources_mut().end_loans(loans);
+        result.map_err(|e| StoreContextValue::from(store).handle_wasm_error(e))?;
+
+        match results.len() {
+            0 => Ok(ruby.qnil().as_value()),
+            1 => {
+                let result = component_val_to_rb(&ruby, &mut context, results.pop().unwrap())?;
+                match results_ty[0] {
+                    Type::Result(_) if self.unwrap_results => result.funcall("unwrap", ()),
+                    _ => Ok(result),
+                }
+            }
+            _ => {
+                let array = RArray::with_capacity(results.len());
+                for result in results {
+                    array.push(component_val_to_rb(&ruby, &mut context, result)?)?;
+                }
+                Ok(array.into_value())
+            }
+        }
+    }
+}
+
+pub fn init(namespace: &RModule) -> Result<(), Error> {
+    let class = namespace.define_class("Func", class::object())?;
+    class.define_method("call", method!(Func::call, -1))?;
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use super::Func;
+use crate::{define_rb_intern, err, ruby_api::store::Store};
+use magnus::{
+    class, gc::Marker, method, prelude::*, scan_args, typed_data::Obj, DataTypeFunctions, Error,
+    RModule, RString, TryConvert, TypedData, Value,
+};
+use wasmtime::component::{Func as FuncImpl, Instance as InstanceImpl};
+
+define_rb_intern!(
+    UNWRAP_RESULTS => "unwrap_results",
+);
+
+/// @yard
+/// @rename Wasmtime::Component::Instance
+/// Represents a WebAssembly component instance.
+/// @see https://docs.rs/wasmtime/latest/wasmtime/component/struct.Instance.html Wasmtime's Rust doc
+#[derive(TypedData)]
+#[magnus(class = "Wasmtime::Component::Instance", mark, free_immediately)]
+pub struct Instance {
+    inner: InstanceImpl,
+    store: Obj<Store>,
+}
+
+unsafe impl Send for Instance {}
+
+impl DataTypeFunctions for Instance {
+    fn mark(&self, marker: &Marker) {
+        marker.mark(self.store)
+    }
+}
+
+impl Instance {
+    /// Wraps an instance just created in `store`.
+    pub fn from_inner(store: Obj<Store>, inner: InstanceImpl) -> Self {
+        store.context_mut().data_mut().count_instance();
+        Self { inner, store }
+    }
+
+    /// @yard
+    /// Get an exported function by name.
+    ///
+    /// @def get_func(name, unwrap_results: true)
+    /// @param name [String]
+    /// @param unwrap_results [Boolean] Whether {Func#call} unwraps the
+    ///   +result+ the function returns, raising its errors. When +false+, the
+    ///   {Result} is returned.
+    /// @return [Func, nil] The function if it exists, nil otherwise.
+    pub fn get_func(&self, args: &[Value]) -> Result<Option<Func>, Error> {
+        let args = scan_args::scan_args::<(RString,), (), (), (), _, ()>(args)?;
+        let kw = scan_args::get_kwargs::<_, (), (Option<bool>,), ()>(
+            args.keywords,
+            &[],
+            &[*UNWRAP_RESULTS],
+        )?;
+        let (name,) = args.required;
+        let unwrap_results = kw.optional.0.unwrap_or(true);
+
+        Ok(self
+            .func(name)?
+            .map(|func| Func::from_inner(self.store, func, unwrap_results)))
+    }
+
+    fn func(&self, name: RString) -> Result<Option<FuncImpl>, Error> {
+        Ok(self
+            .inner
+            .get_func(self.store.context_mut(), unsafe { name.as_str()? }))
+    }
+
+    /// @yard
+    /// Retrieves an exported function from the instance and calls it.
+    /// Essentially a shortcut for +instance.get_func(name).call(...)+.
+    ///
+    /// @def invoke(name, *args)
+    /// @param name [String] The name of function to run.
+    /// @param (see Func#call)
+    /// @return (see Func#call)
+    /// @see Func#call
+    pub fn invoke(&self, args: &[Value]) -> Result<Value, Error> {
+        let name = RString::try_convert(*args.first().ok_or_else(|| {
+            Error::new(
+                magnus::exception::type_error(),
+                "wrong number of arguments (given 0, expected 1+)",
+            )
+        })?)?;
+
+        match self.func(name)? {
+            Some(func) => Func::from_inner(self.store, func, true).call(&args[1..]),
+            None => err!("function \"{}\" not found", name),
+        }
+    }
+}
+
+pub fn init(namespace: &RModule) -> Result<(), Error> {
+    let class = namespace.define_class("Instance", class::object())?;
+    class.define_method("get_func", method!(Instance::get_func, -1))?;
+    class.define_method("invoke", method!(Instance::invoke, -1))?;
+
+    Ok(())
+}
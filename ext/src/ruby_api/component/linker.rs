@@ -0,0 +1,354 @@
+use super::{resource, wasi_http, wasi_keyvalue, Component, Instance};
+use crate::{
+    define_rb_intern, err, error,
+    helpers::{block_on, nogvl},
+    ruby_api::{
+        engine::Engine,
+        errors::wasi_exit_error,
+        store::{Store, StoreContextValue, StoreData},
+        wasi_p2_ctx_builder,
+    },
+};
+#[cfg(feature = "tokio")]
+use magnus::RArray;
+use magnus::{
+    class, function, gc::Marker, method, prelude::*, scan_args, typed_data::Obj, DataTypeFunctions,
+    Error, Module as _, Object, RClass, RModule, RString, TypedData, Value,
+};
+use std::cell::RefCell;
+use wasmtime::component::{types::ComponentItem, InstancePre, Linker as LinkerImpl};
+use wasmtime_wasi::preview2::command::sync::Command;
+
+define_rb_intern!(
+    WASI => "wasi",
+    WASI_HTTP => "wasi_http",
+    WASI_NN => "wasi_nn",
+    WASI_KEYVALUE => "wasi_keyvalue",
+);
+
+/// @yard
+/// @rename Wasmtime::Component::Linker
+/// Resolves the imports of {Component}s and instantiates them.
+/// @see https://docs.rs/wasmtime/latest/wasmtime/component/struct.Linker.html Wasmtime's Rust doc
+#[derive(TypedData)]
+#[magnus(class = "Wasmtime::Component::Linker", size, mark, free_immediately)]
+pub struct Linker {
+    inner: RefCell<LinkerImpl<StoreData>>,
+    refs: RefCell<Vec<Value>>,
+    async_support: bool,
+    has_wasi: bool,
+    has_wasi_http: bool,
+    has_wasi_keyvalue: bool,
+}
+
+unsafe impl Send for Linker {}
+
+impl DataTypeFunctions for Linker {
+    fn mark(&self, marker: &Marker) {
+        marker.mark_slice(self.refs.borrow().as_slice());
+    }
+}
+
+impl Linker {
+    /// @yard
+    /// @def new(engine, wasi: false, wasi_http: false, wasi_nn: false, wasi_keyvalue: false)
+    /// @param engine [Engine]
+    /// @param wasi [Boolean] Whether WASI preview 2 (the interfaces of the
+    ///   +wasi:cli/command+ world) should be defined in this Linker. Stores
+    ///   must then be created with a +wasi_p2_ctx+ (see {WasiP2CtxBuilder}).
+    /// @param wasi_http [Boolean] Whether WASI HTTP, with the WASI preview 2
+    ///   interfaces it relies on, should be defined in this Linker, letting
+    ///   components make outbound HTTP requests through
+    ///   +wasi:http/outgoing-handler+. Stores must then be created with a
+    ///   +wasi_http_ctx+ (see {WasiHttpCtxBuilder}).
+    /// @param wasi_nn [Boolean] Whether the +wasi:nn+ interfaces, for
+    ///   machine learning inference, should be defined in this Linker. Their
+    ///   backends and graphs are configured by the Store's +wasi_nn_ctx+ (see
+    ///   {WasiNnCtxBuilder}). Requires the gem to be built with the +wasi-nn+
+    ///   feature.
+    /// @param wasi_keyvalue [Boolean] Whether +wasi:keyvalue/store+ should be
+    ///   defined in this Linker. Stores must then be created with a
+    ///   +wasi_keyvalue+ backend (see {Store#initialize}).
+    /// @return [Linker]
+    pub fn new(args: &[Value]) -> Result<Self, Error> {
+        let args = scan_args::scan_args::<(&Engine,), (), (), (), _, ()>(args)?;
+        let kw = scan_args::get_kwargs::<
+            _,
+            (),
+            (Option<bool>, Option<bool>, Option<bool>, Option<bool>),
+            (),
+        >(
+            args.keywords,
+            &[],
+            &[*WASI, *WASI_HTTP, *WASI_NN, *WASI_KEYVALUE],
+        )?;
+        let (engine,) = args.required;
+        let has_wasi = kw.optional.0.unwrap_or(false);
+        let has_wasi_http = kw.optional.1.unwrap_or(false);
+        let has_wasi_nn = kw.optional.2.unwrap_or(false);
+        let has_wasi_keyvalue = kw.optional.3.unwrap_or(false);
+
+        let mut inner = LinkerImpl::new(&engine.get()?);
+        if has_wasi {
+            wasi_p2_ctx_builder::add_to_linker(&mut inner, engine.is_async())?;
+        }
+        if has_wasi_http {
+            wasi_http::add_to_linker(&mut inner, engine.is_async(), has_wasi)?;
+        }
+        if has_wasi_nn {
+            #[cfg(feature = "wasi-nn")]
+            crate::ruby_api::wasi_nn::add_to_component_linker(&mut inner, engine.is_async())?;
+            #[cfg(not(feature = "wasi-nn"))]
+            return err!("wasi_nn: true requires the gem to be built with the wasi-nn feature");
+        }
+        if has_wasi_keyvalue {
+            wasi_keyvalue::add_to_linker(&mut inner, engine.is_async())?;
+        }
+
+        Ok(Self {
+            inner: RefCell::new(inner),
+            refs: Default::default(),
+            async_support: engine.is_async(),
+            has_wasi,
+            has_wasi_http,
+            has_wasi_keyvalue,
+        })
+    }
+
+    /// @yard
+    /// Instantiates a {Component} in a {Store} using the defined imports in the linker.
+    /// @def instantiate(store, component)
+    /// @param store [Store]
+    /// @param component [Component]
+    /// @return [Instance]
+    pub fn instantiate(&self, store: Obj<Store>, component: &Component) -> Result<Instance, Error> {
+        self.ensure_wasi_ctxs(&store, "Component::Linker#instantiate")?;
+
+        let pre = self.instantiate_pre(store, component)?;
+        let _lock = store.lock()?;
+        let context = store.context_mut();
+        let result = if context.data().is_async() {
+            block_on(pre.instantiate_async(context))
+        } else {
+            nogvl(|| pre.instantiate(context))
+        };
+        let instance = result.map_err(|e| StoreContextValue::from(store).handle_wasm_error(e))?;
+        self.refs.borrow().iter().for_each(|val| store.retain(*val));
+        Ok(Instance::from_inner(store, instance))
+    }
+
+    /// @yard
+    /// Instantiates a {Component} targeting the +wasi:cli/command+ world in a
+    /// {Store}, and runs it. Requires a Linker created with +wasi: true+.
+    ///
+    /// @def run_command(store, component)
+    /// @param store [Store]
+    /// @param component [Component]
+    /// @raise [WasiExit] When the command fails or exits with a non-zero code.
+    /// @return [nil]
+    pub fn run_command(&self, store: Obj<Store>, component: &Component) -> Result<(), Error> {
+        if !self.has_wasi {
+            return err!("Linker#run_command requires a Linker created with wasi: true");
+        }
+        self.ensure_wasi_ctxs(&store, "Component::Linker#run_command")?;
+
+        let pre = self.instantiate_pre(store, component)?;
+        let _lock = store.lock()?;
+        let mut context = store.context_mut();
+        let result = nogvl(|| {
+            let (command, _instance) = Command::instantiate_pre(&mut context, &pre)?;
+            command.wasi_cli_run().call_run(&mut context)
+        });
+
+        match result {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(())) => Err(wasi_exit_error().new_instance((1,))?.into()),
+            Err(e) => Err(StoreContextValue::from(store).handle_wasm_error(e)),
+        }
+    }
+
+    /// @yard
+    /// Instantiates a {Component} exporting +wasi:http/incoming-handler+ in a
+    /// {Store}, and calls its handler with an HTTP request. Requires a Linker
+    /// created with +wasi_http: true+. See {RackApp} to serve Rack requests.
+    ///
+    /// @def handle_http_request(store, component, method, url, headers, body)
+    /// @param store [Store] Components can only handle a single request,
+    ///   stores should not be reused.
+    /// @param component [Component]
+    /// @param method [String]
+    /// @param url [String] The absolute URL of the request.
+    /// @param headers [Array<Array(String, String)>] The name and value of each
+    ///   header.
+    /// @param body [String]
+    /// @return [Array(Integer, Array<Array(String, String)>, String)] The
+    ///   status, headers and body of the response.
+    #[cfg(feature = "tokio")]
+    pub fn handle_http_request(
+        &self,
+        store: Obj<Store>,
+        component: &Component,
+        method: RString,
+        url: RString,
+        headers: RArray,
+        body: RString,
+    ) -> Result<(u16, RArray, RString), Error> {
+        if !self.has_wasi_http {
+            return err!(
+                "Linker#handle_http_request requires a Linker created with wasi_http: true"
+            );
+        }
+        self.ensure_wasi_ctxs(&store, "Component::Linker#handle_http_request")?;
+
+        let pre = self.instantiate_pre(store, component)?;
+        let _lock = store.lock()?;
+        wasi_http::handle_request(&pre, store, method, url, headers, body)
+    }
+
+    /// @yard
+    /// Defines the resource +name+ of the +instance+ interface imported by
+    /// +component+, implemented by the Ruby class +klass+: the resource's
+    /// handles are instances of +klass+.
+    ///
+    /// The resource's functions dispatch to +klass+ by name, with dashes
+    /// replaced by underscores:
+    /// * +[constructor]name+ calls +klass.new+,
+    /// * +[method]name.get-value+ calls +get_value+ on the +self+ object,
+    /// * +[static]name.open+ calls +klass.open+.
+    ///
+    /// Objects handed to the component as +own<name>+ are retained by the
+    /// store until the component drops its last handle. Objects the
+    /// component hands back as +own<name>+ no longer are, and +borrow<name>+
+    /// handles only live for the duration of a call.
+    ///
+    /// All the resources defined by Linkers share a single host type, which
+    /// components can't tell apart: a handle of one resource can be passed
+    /// where another is expected. The resource's functions raise when given
+    /// such a handle as one of +klass+, or return an object that isn't one.
+    ///
+    /// @example
+    ///   class Counter
+    ///     def initialize(start)
+    ///       @value = start
+    ///     end
+    ///
+    ///     def increment
+    ///       @value += 1
+    ///     end
+    ///   end
+    ///   linker.define_resource(component, "example:counter/types", "counter", Counter)
+    ///
+    /// @def define_resource(component, instance, name, klass)
+    /// @param component [Component] The component importing the resource,
+    ///   which the resource's function types are taken from.
+    /// @param instance [String] The name of the interface, e.g.
+    ///   +example:counter/types+.
+    /// @param name [String] The name of the resource in the interface.
+    /// @param klass [Class]
+    /// @return [nil]
+    pub fn define_resource(
+        &self,
+        component: &Component,
+        instance: RString,
+        name: RString,
+        class: RClass,
+    ) -> Result<(), Error> {
+        if self.async_support {
+            return err!("resources are not supported in engines with async_support: true");
+        }
+        let instance = instance.to_string()?;
+        let name = name.to_string()?;
+        let component = component.get();
+
+        let import = component
+            .component_type()
+            .imports()
+            .find(|(import, _)| *import == instance)
+            .map(|(_, item)| item);
+        let Some(ComponentItem::ComponentInstance(instance_ty)) = import else {
+            return err!("component does not import instance {}", instance);
+        };
+        let mut resource_ty = None;
+        let mut funcs = vec![];
+        for (export, item) in instance_ty.exports() {
+            match item {
+                ComponentItem::Resource(ty) if export == name => resource_ty = Some(ty),
+                ComponentItem::ComponentFunc(func) => funcs.push((export.to_string(), func)),
+                _ => {}
+            }
+        }
+        let Some(resource_ty) = resource_ty else {
+            return err!("instance {} does not export resource {}", instance, name);
+        };
+
+        let mut inner = self.inner.borrow_mut();
+        let mut linker = inner.instance(&instance).map_err(|e| error!("{}", e))?;
+        resource::define(&mut linker, component, &name, resource_ty, class, funcs)?;
+        self.refs.borrow_mut().push(class.as_value());
+
+        Ok(())
+    }
+
+    /// Resolves the imports of `component` with the GVL held, not to borrow
+    /// the linker while other threads may define items in it.
+    fn instantiate_pre(
+        &self,
+        store: Obj<Store>,
+        component: &Component,
+    ) -> Result<InstancePre<StoreData>, Error> {
+        self.inner
+            .borrow()
+            .instantiate_pre(component.get())
+            .map_err(|e| StoreContextValue::from(store).handle_wasm_error(e))
+    }
+
+    fn ensure_wasi_ctxs(&self, store: &Store, method: &str) -> Result<(), Error> {
+        if self.has_wasi && !store.context().data().has_wasi_p2_ctx() {
+            return err!(
+                "Store is missing WASI preview 2 configuration.\n\n\
+                When using `wasi: true`, the Store given to\n\
+                `{}` must have a WASI preview 2 configuration.\n\
+                To fix this, provide the `wasi_p2_ctx` when creating the Store:\n\
+                    Wasmtime::Store.new(engine, wasi_p2_ctx: Wasmtime::WasiP2CtxBuilder.new)",
+                method
+            );
+        }
+        if self.has_wasi_http && !store.context().data().has_wasi_http_ctx() {
+            return err!(
+                "Store is missing WASI HTTP configuration.\n\n\
+                When using `wasi_http: true`, the Store given to\n\
+                `{}` must have a WASI HTTP configuration.\n\
+                To fix this, provide the `wasi_http_ctx` when creating the Store:\n\
+                    Wasmtime::Store.new(engine, wasi_http_ctx: Wasmtime::Component::WasiHttpCtxBuilder.new)",
+                method
+            );
+        }
+        if self.has_wasi_keyvalue && store.context().data().wasi_keyvalue_backend().is_none() {
+            return err!(
+                "Store is missing a wasi_keyvalue backend.\n\n\
+                When using `wasi_keyvalue: true`, the Store given to\n\
+                `{}` must have a key-value backend.\n\
+                To fix this, provide the `wasi_keyvalue` when creating the Store:\n\
+                    Wasmtime::Store.new(engine, wasi_keyvalue: backend)",
+                method
+            );
+        }
+
+        Ok(())
+    }
+}
+
+pub fn init(namespace: &RModule) -> Result<(), Error> {
+    let class = namespace.define_class("Linker", class::object())?;
+    class.define_singleton_method("new", function!(Linker::new, -1))?;
+    class.define_method("instantiate", method!(Linker::instantiate, 2))?;
+    class.define_method("run_command", method!(Linker::run_command, 2))?;
+    class.define_method("define_resource", method!(Linker::define_resource, 4))?;
+    #[cfg(feature = "tokio")]
+    class.define_method(
+        "handle_http_request",
+        method!(Linker::handle_http_request, 6),
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,372 @@
+use super::convert::{component_val_to_rb, rb_to_component_val};
+use crate::{err, error, helpers::with_gvl, ruby_api::store::StoreData};
+use magnus::{gc::Marker, prelude::*, value::Opaque, Error, RArray, RClass, Ruby, Value};
+use std::collections::HashMap;
+use wasmtime::component::{
+    types::ComponentFunc, LinkerInstance, Resource, ResourceAny, ResourceType, Type, Val,
+};
+use wasmtime::StoreContextMut;
+
+/// The Rust type of all the resources defined by {Linker#define_resource},
+/// whose reps index [`HostResources`].
+pub struct HostResource;
+
+/// Whether `ty` is the type of resources defined by the host.
+pub fn is_host_resource(ty: &ResourceType) -> bool {
+    *ty == ResourceType::host::<HostResource>()
+}
+
+/// The Ruby objects of the host resources handed to Wasm, by rep. Each object
+/// has a single rep, released once the last handle referencing it is dropped.
+#[derive(Default)]
+pub struct HostResources {
+    entries: HashMap<u32, HostEntry>,
+    reps: HashMap<rb_sys::VALUE, u32>,
+    next_rep: u32,
+    /// The reps lent to Wasm as borrows for the duration of a call.
+    loans: Vec<u32>,
+}
+
+struct HostEntry {
+    value: Value,
+    /// The class of the resource `value` was handed to Wasm as, see
+    /// {Linker#define_resource}, or its own class when not known.
+    class: RClass,
+    handles: usize,
+}
+
+impl HostResources {
+    /// Hands `value` to Wasm as a resource of `class`, returning its rep.
+    pub fn insert(&mut self, value: Value, class: RClass) -> u32 {
+        let rep = *self.reps.entry(value.as_raw()).or_insert_with(|| {
+            self.next_rep += 1;
+            self.next_rep
+        });
+        self.entries
+            .entry(rep)
+            .or_insert(HostEntry {
+                value,
+                class,
+                handles: 0,
+            })
+            .handles += 1;
+        rep
+    }
+
+    /// The object of `rep` and the class of the resource it was handed to
+    /// Wasm as.
+    pub fn get(&self, rep: u32) -> Option<(Value, RClass)> {
+        self.entries
+            .get(&rep)
+            .map(|entry| (entry.value, entry.class))
+    }
+
+    /// Releases a handle to `rep`, returning its object.
+    pub fn release(&mut self, rep: u32) -> Option<Value> {
+        let entry = self.entries.get_mut(&rep)?;
+        let value = entry.value;
+        entry.handles -= 1;
+        if entry.handles == 0 {
+            self.entries.remove(&rep);
+            self.reps.remove(&value.as_raw());
+        }
+        Some(value)
+    }
+
+    /// Hands `value` to Wasm until [`Self::end_loans`].
+    pub fn lend(&mut self, value: Value, class: RClass) -> u32 {
+        let rep = self.insert(value, class);
+        self.loans.push(rep);
+        rep
+    }
+
+    /// The number of loans, to end the ones of a call with [`Self::end_loans`].
+    pub fn loans(&self) -> usize {
+        self.loans.len()
+    }
+
+    pub fn end_loans(&mut self, from: usize) {
+        for rep in self.loans.split_off(from) {
+            self.release(rep);
+        }
+    }
+
+    pub fn mark(&self, marker: &Marker) {
+        // Pinned: `reps` is keyed by address.
+        for entry in self.entries.values() {
+            marker.mark(entry.value);
+            marker.mark(entry.class);
+        }
+    }
+}
+
+/// Converts a resource handed by Wasm to its Ruby object. When `class` is
+/// given, raises unless the resource was handed to Wasm as one of `class`:
+/// components can't tell host resources apart (see [`HostResource`]).
+pub fn resource_to_rb(
+    store: &mut StoreContextMut<'_, StoreData>,
+    resource: ResourceAny,
+    class: Option<RClass>,
+) -> Result<Value, Error> {
+    if !is_host_resource(&resource.ty()) {
+        return crate::not_implemented!("resources defined by components are not supported");
+    }
+    let resource: Resource<HostResource> = resource
+        .try_into_resource(&mut *store)
+        .map_err(|e| error!("{}", e))?;
+
+    let resources = store.data_mut().host_resources_mut();
+    let (value, actual) = resources
+        .get(resource.rep())
+        .ok_or_else(|| error!("unknown resource: {}", resource.rep()))?;
+    // Owned handles move to Ruby, which now retains the object, even if it's
+    // of the wrong class.
+    if resource.owned() {
+        resources.release(resource.rep());
+    }
+    if let Some(class) = class {
+        if !actual.funcall::<_, _, bool>("<=", (class,))? {
+            return err!("expected a {} resource, got a {} resource", class, actual);
+        }
+    }
+    Ok(value)
+}
+
+/// Converts a Ruby object to a resource handle of type `ty`. When `class` is
+/// given, `ty` is the type of the resource defined for `class` (as found in
+/// the component's imports, not the host type) and the object must be one of
+/// its instances.
+pub fn rb_to_resource(
+    store: &mut StoreContextMut<'_, StoreData>,
+    value: Value,
+    ty: &Type,
+    class: Option<RClass>,
+) -> Result<Val, Error> {
+    let (resource_ty, owned) = match ty {
+        Type::Own(resource_ty) => (resource_ty, true),
+        Type::Borrow(resource_ty) => (resource_ty, false),
+        _ => unreachable!("not a resource type"),
+    };
+    if class.is_none() && !is_host_resource(resource_ty) {
+        return crate::not_implemented!("resources defined by components are not supported");
+    }
+    if value.is_nil() {
+        return err!("expected a resource, got nil");
+    }
+    let class = match class {
+        Some(class) if !value.is_kind_of(class) => {
+            return err!("expected a {} resource, got {}", class, value.class());
+        }
+        Some(class) => class,
+        None => value.class(),
+    };
+
+    let resources = store.data_mut().host_resources_mut();
+    let resource = if owned {
+        Resource::<HostResource>::new_own(resources.insert(value, class))
+    } else {
+        Resource::<HostResource>::new_borrow(resources.lend(value, class))
+    };
+    resource
+        .try_into_resource_any(&mut *store)
+        .map(Val::Resource)
+        .map_err(|e| error!("{}", e))
+}
+
+/// How a function of a resource maps to its Ruby class.
+enum ResourceFunc {
+    Constructor,
+    Method(String),
+    Static(String),
+}
+
+impl ResourceFunc {
+    /// Parses the name of a function of `resource`, e.g.
+    /// +[method]counter.get-value+, returning +None+ for other functions.
+    fn parse(name: &str, resource: &str) -> Option<Self> {
+        let ruby_name = |name: &str| name.replace('-', "_");
+        if let Some(rest) = name.strip_prefix("[constructor]") {
+            return (rest == resource).then_some(Self::Constructor);
+        }
+        if let Some(rest) = name.strip_prefix("[method]") {
+            let method = rest.strip_prefix(resource)?.strip_prefix('.')?;
+            return Some(Self::Method(ruby_name(method)));
+        }
+        let rest = name.strip_prefix("[static]")?;
+        let function = rest.strip_prefix(resource)?.strip_prefix('.')?;
+        Some(Self::Static(ruby_name(function)))
+    }
+}
+
+/// Defines resource `name` in `linker`, with its functions imported by
+/// `funcs` dispatching to `class`. `ty` is the resource's type in the
+/// component, which its functions' params and results refer to.
+pub fn define(
+    linker: &mut LinkerInstance<'_, StoreData>,
+    component: &wasmtime::component::Component,
+    name: &str,
+    ty: ResourceType,
+    class: RClass,
+    funcs: Vec<(String, ComponentFunc)>,
+) -> Result<(), Error> {
+    // Dropping the last handle releases the object, for Ruby to garbage
+    // collect. The GVL is held as the store's objects are marked with it.
+    linker
+        .resource(
+            name,
+            ResourceType::host::<HostResource>(),
+            |mut store, rep| {
+                with_gvl(|| store.data_mut().host_resources_mut().release(rep));
+                Ok(())
+            },
+        )
+        .map_err(|e| error!("{}", e))?;
+
+    for (func_name, func_ty) in funcs {
+        let Some(func) = ResourceFunc::parse(&func_name, name) else {
+            continue;
+        };
+        match &func {
+            ResourceFunc::Constructor => {}
+            ResourceFunc::Method(method) => {
+                if !class.funcall::<_, _, bool>("method_defined?", (method.as_str(),))? {
+                    return err!("{} does not define #{} for {}", class, method, func_name);
+                }
+            }
+            ResourceFunc::Static(function) => {
+                if !class.respond_to(function.as_str(), false)? {
+                    return err!(
+                        "{} does not respond to .{} for {}",
+                        class,
+                        function,
+                        func_name
+                    );
+                }
+            }
+        }
+
+        let closure = make_resource_func(func, class.into(), ty, func_ty);
+        linker
+            .func_new(component, &func_name, closure)
+            .map_err(|e| error!("{}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Calls `func` on `class` or the +self+ resource. Like core host functions
+/// (see [`crate::ruby_api::func::make_func_closure`]), Ruby errors are stored
+/// on the store, to be raised by the caller.
+///
+/// The params and results of resource `ty` must be instances of `class`.
+fn make_resource_func(
+    func: ResourceFunc,
+    class: Opaque<RClass>,
+    ty: ResourceType,
+    func_ty: ComponentFunc,
+) -> impl Fn(StoreContextMut<'_, StoreData>, &[Val], &mut [Val]) -> anyhow::Result<()>
+       + Send
+       + Sync
+       + 'static {
+    let is_resource = |param_ty: &Type| match param_ty {
+        Type::Own(param_ty) | Type::Borrow(param_ty) => *param_ty == ty,
+        _ => false,
+    };
+    let params_class: Vec<bool> = func_ty.params().map(|ty| is_resource(&ty)).collect();
+    let results_ty: Vec<(Type, bool)> = func_ty
+        .results()
+        .map(|ty| {
+            let is_class = is_resource(&ty);
+            (ty, is_class)
+        })
+        .collect();
+
+    move |mut store, params, results| {
+        with_gvl(|| {
+            let ruby = Ruby::get().unwrap();
+            let class = ruby.get_inner(class);
+            let result = call_resource_func(&ruby, &mut store, &func, class, &params_class, params)
+                .and_then(|value| write_results(&mut store, value, class, &results_ty, results));
+            result.map_err(|e| {
+                store.data_mut().set_error(e);
+                anyhow::anyhow!("")
+            })
+        })
+    }
+}
+
+/// Calls `func`, checking the params for which `params_class` is true are
+/// resources of `class`.
+fn call_resource_func(
+    ruby: &Ruby,
+    store: &mut StoreContextMut<'_, StoreData>,
+    func: &ResourceFunc,
+    class: RClass,
+    params_class: &[bool],
+    params: &[Val],
+) -> Result<Value, Error> {
+    let args = RArray::with_capacity(params.len());
+    for (param, is_class) in params.iter().zip(params_class) {
+        let arg = match param {
+            Val::Resource(resource) if *is_class => resource_to_rb(store, *resource, Some(class))?,
+            param => component_val_to_rb(ruby, store, param.clone())?,
+        };
+        args.push(arg)?;
+    }
+
+    match func {
+        ResourceFunc::Constructor => class.funcall("new", unsafe { args.as_slice() }),
+        ResourceFunc::Method(method) => {
+            let receiver = args.shift()?;
+            receiver.funcall(method.as_str(), unsafe { args.as_slice() })
+        }
+        ResourceFunc::Static(function) => {
+            class.funcall(function.as_str(), unsafe { args.as_slice() })
+        }
+    }
+}
+
+/// Converts the value returned by a resource function to its `results`,
+/// checking the ones of the resource's type are instances of `class`.
+fn write_results(
+    store: &mut StoreContextMut<'_, StoreData>,
+    value: Value,
+    class: RClass,
+    results_ty: &[(Type, bool)],
+    results: &mut [Val],
+) -> Result<(), Error> {
+    match results_ty {
+        [] => Ok(()),
+        [ty] => {
+            results[0] = write_result(store, value, class, ty)?;
+            Ok(())
+        }
+        _ => {
+            let array = RArray::to_ary(value)?;
+            if array.len() != results_ty.len() {
+                return err!(
+                    "wrong number of results (given {}, expected {})",
+                    array.len(),
+                    results_ty.len()
+                );
+            }
+            for ((result, ty), value) in results.iter_mut().zip(results_ty).zip(array.each()) {
+                *result = write_result(store, value?, class, ty)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn write_result(
+    store: &mut StoreContextMut<'_, StoreData>,
+    value: Value,
+    class: RClass,
+    (ty, is_class): &(Type, bool),
+) -> Result<Val, Error> {
+    if *is_class {
+        rb_to_resource(store, value, ty, Some(class))
+    } else {
+        rb_to_component_val(store, value, ty)
+    }
+}
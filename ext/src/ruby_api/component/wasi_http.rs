@@ -0,0 +1,230 @@
+#[cfg(feature = "tokio")]
+mod incoming;
+
+use crate::{err, error, ruby_api::store::StoreData};
+use magnus::{
+    class, exception::arg_error, function, method, typed_data::Obj, Error, Module as _, Object,
+    RModule, RString,
+};
+use std::{cell::RefCell, time::Duration};
+use wasmtime::component::{Resource, ResourceTable};
+use wasmtime_wasi::preview2::WasiView;
+use wasmtime_wasi_http::{
+    types::{default_send_request, HostFutureIncomingResponse, OutgoingRequest},
+    WasiHttpCtx, WasiHttpView,
+};
+
+#[cfg(feature = "tokio")]
+pub use incoming::handle_request;
+
+/// What the outbound HTTP requests of a {Store} may do.
+#[derive(Clone, Default)]
+struct WasiHttpConfig {
+    allowed_hosts: Vec<String>,
+    connect_timeout: Option<Duration>,
+    first_byte_timeout: Option<Duration>,
+    between_bytes_timeout: Option<Duration>,
+}
+
+impl WasiHttpConfig {
+    /// Whether requests to `authority` (a +host:port+ pair) are allowed. Hosts
+    /// starting with +*.+ allow all of their subdomains.
+    fn allows(&self, authority: &str) -> bool {
+        let authority = authority.to_ascii_lowercase();
+        let host = authority
+            .rsplit_once(':')
+            .map_or(authority.as_str(), |(host, _)| host);
+
+        self.allowed_hosts.iter().any(|allowed| {
+            let allowed = allowed.to_ascii_lowercase();
+            match allowed.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .map_or(false, |subdomain| subdomain.ends_with('.')),
+                None => allowed == host || allowed == authority,
+            }
+        })
+    }
+
+    /// Lowers the timeouts requested by the guest to the configured ones.
+    fn limit(&self, request: &mut OutgoingRequest) {
+        let limit = |timeout: &mut Duration, max: Option<Duration>| {
+            if let Some(max) = max {
+                *timeout = (*timeout).min(max);
+            }
+        };
+
+        limit(&mut request.connect_timeout, self.connect_timeout);
+        limit(&mut request.first_byte_timeout, self.first_byte_timeout);
+        limit(
+            &mut request.between_bytes_timeout,
+            self.between_bytes_timeout,
+        );
+    }
+}
+
+/// The per-{Store} state of WASI HTTP. Its resources (e.g. streams) live in
+/// the table of the store's WASI preview 2 state.
+pub struct WasiHttpState {
+    config: WasiHttpConfig,
+    http: WasiHttpCtx,
+}
+
+/// @yard
+/// @rename Wasmtime::Component::WasiHttpCtxBuilder
+/// Outbound HTTP configuration to be sent as {Store#new}’s +wasi_http_ctx+
+/// keyword argument, for components instantiated by a {Linker} created with
+/// +wasi_http: true+.
+///
+/// No request is allowed by default: hosts must be allowed with {#allow_host}.
+/// Requests to other hosts trap.
+///
+/// Instance methods mutate the current object and return +self+.
+///
+/// @example
+///   wasi_http_ctx = Wasmtime::Component::WasiHttpCtxBuilder.new
+///     .allow_host("api.example.com")
+///     .set_first_byte_timeout(5)
+///   store = Wasmtime::Store.new(engine, wasi_http_ctx: wasi_http_ctx)
+///
+/// @see https://docs.rs/wasmtime-wasi-http/latest/wasmtime_wasi_http/ Wasmtime's Rust doc
+#[derive(Default)]
+#[magnus::wrap(
+    class = "Wasmtime::Component::WasiHttpCtxBuilder",
+    size,
+    free_immediately
+)]
+pub struct WasiHttpCtxBuilder {
+    inner: RefCell<WasiHttpConfig>,
+}
+
+unsafe impl Send for WasiHttpCtxBuilder {}
+
+type RbSelf = Obj<WasiHttpCtxBuilder>;
+
+impl WasiHttpCtxBuilder {
+    /// @yard
+    /// @return [WasiHttpCtxBuilder]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// @yard
+    /// Allow requests to +host+, on any port. When +host+ starts with +*.+,
+    /// requests to any of its subdomains are allowed.
+    /// @def allow_host(host)
+    /// @param host [String] e.g. +example.com+, +example.com:8080+ or +*.example.com+.
+    /// @return [WasiHttpCtxBuilder] +self+
+    pub fn allow_host(rb_self: RbSelf, host: RString) -> Result<RbSelf, Error> {
+        let host = host.to_string()?;
+        rb_self.inner.borrow_mut().allowed_hosts.push(host);
+        Ok(rb_self)
+    }
+
+    /// @yard
+    /// Limit the time to establish each connection.
+    /// @def set_connect_timeout(seconds)
+    /// @param seconds [Float]
+    /// @return [WasiHttpCtxBuilder] +self+
+    pub fn set_connect_timeout(rb_self: RbSelf, seconds: f64) -> Result<RbSelf, Error> {
+        rb_self.inner.borrow_mut().connect_timeout = Some(timeout(seconds)?);
+        Ok(rb_self)
+    }
+
+    /// @yard
+    /// Limit the time to receive the first byte of each response.
+    /// @def set_first_byte_timeout(seconds)
+    /// @param seconds [Float]
+    /// @return [WasiHttpCtxBuilder] +self+
+    pub fn set_first_byte_timeout(rb_self: RbSelf, seconds: f64) -> Result<RbSelf, Error> {
+        rb_self.inner.borrow_mut().first_byte_timeout = Some(timeout(seconds)?);
+        Ok(rb_self)
+    }
+
+    /// @yard
+    /// Limit the time between two bytes of each response.
+    /// @def set_between_bytes_timeout(seconds)
+    /// @param seconds [Float]
+    /// @return [WasiHttpCtxBuilder] +self+
+    pub fn set_between_bytes_timeout(rb_self: RbSelf, seconds: f64) -> Result<RbSelf, Error> {
+        rb_self.inner.borrow_mut().between_bytes_timeout = Some(timeout(seconds)?);
+        Ok(rb_self)
+    }
+
+    pub fn build_state(&self) -> WasiHttpState {
+        WasiHttpState {
+            config: self.inner.borrow().clone(),
+            http: WasiHttpCtx,
+        }
+    }
+}
+
+fn timeout(seconds: f64) -> Result<Duration, Error> {
+    Duration::try_from_secs_f64(seconds)
+        .map_err(|e| Error::new(arg_error(), format!("invalid timeout: {e}")))
+}
+
+impl WasiHttpView for StoreData {
+    fn ctx(&mut self) -> &mut WasiHttpCtx {
+        &mut self.wasi_http_mut().http
+    }
+
+    fn table(&mut self) -> &mut ResourceTable {
+        WasiView::table(self)
+    }
+
+    fn send_request(
+        &mut self,
+        mut request: OutgoingRequest,
+    ) -> wasmtime::Result<Resource<HostFutureIncomingResponse>> {
+        let config = &self.wasi_http_mut().config;
+        if !config.allows(&request.authority) {
+            anyhow::bail!(
+                "HTTP requests to {} are not allowed, see WasiHttpCtxBuilder#allow_host",
+                request.authority
+            );
+        }
+        config.limit(&mut request);
+
+        default_send_request(self, request)
+    }
+}
+
+/// Adds the WASI HTTP imports to `linker`, and the WASI preview 2 ones they
+/// rely on unless `linker` already has all of them.
+pub fn add_to_linker(
+    linker: &mut wasmtime::component::Linker<StoreData>,
+    async_support: bool,
+    has_wasi: bool,
+) -> Result<(), Error> {
+    if async_support {
+        return err!("WASI HTTP is not supported in engines with async_support: true");
+    }
+
+    if has_wasi {
+        wasmtime_wasi_http::proxy::sync::add_only_http_to_linker(linker)
+    } else {
+        wasmtime_wasi_http::proxy::sync::add_to_linker(linker)
+    }
+    .map_err(|e| error!("{}", e))
+}
+
+pub fn init(namespace: &RModule) -> Result<(), Error> {
+    let class = namespace.define_class("WasiHttpCtxBuilder", class::object())?;
+    class.define_singleton_method("new", function!(WasiHttpCtxBuilder::new, 0))?;
+    class.define_method("allow_host", method!(WasiHttpCtxBuilder::allow_host, 1))?;
+    class.define_method(
+        "set_connect_timeout",
+        method!(WasiHttpCtxBuilder::set_connect_timeout, 1),
+    )?;
+    class.define_method(
+        "set_first_byte_timeout",
+        method!(WasiHttpCtxBuilder::set_first_byte_timeout, 1),
+    )?;
+    class.define_method(
+        "set_between_bytes_timeout",
+        method!(WasiHttpCtxBuilder::set_between_bytes_timeout, 1),
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use crate::{
+    error,
+    helpers::nogvl,
+    ruby_api::store::{Store, StoreContextValue, StoreData},
+};
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use magnus::{typed_data::Obj, Error, RArray, RString, TryConvert};
+use std::{pin::pin, thread};
+use tokio::sync::oneshot;
+use wasmtime::component::InstancePre;
+use wasmtime_wasi::preview2::in_tokio;
+use wasmtime_wasi_http::{
+    bindings::http::types::ErrorCode, body::HyperOutgoingBody, proxy::Proxy, WasiHttpView,
+};
+
+type ResponseResult = Result<hyper::Response<HyperOutgoingBody>, ErrorCode>;
+
+/// Instantiates the component of `pre` in `store`, and calls its
+/// +wasi:http/incoming-handler+ with a request built from its Ruby parts.
+/// Returns the response's status, headers and body.
+pub fn handle_request(
+    pre: &InstancePre<StoreData>,
+    store: Obj<Store>,
+    method: RString,
+    url: RString,
+    headers: RArray,
+    body: RString,
+) -> Result<(u16, RArray, RString), Error> {
+    let mut builder = hyper::Request::builder()
+        .method(unsafe { method.as_slice() })
+        .uri(unsafe { url.as_str()? });
+    for header in headers.each() {
+        let (name, value) = <(RString, RString)>::try_convert(header?)?;
+        builder = builder.header(unsafe { name.as_slice() }, unsafe { value.as_slice() });
+    }
+    let body = Full::new(Bytes::copy_from_slice(unsafe { body.as_slice() }))
+        .map_err(|never| match never {})
+        .boxed();
+    let request = builder
+        .body(body)
+        .map_err(|e| error!("invalid HTTP request: {}", e))?;
+
+    let store_context = StoreContextValue::from(store);
+    let mut context = store.context_mut();
+    let (proxy, _) = Proxy::instantiate_pre(&mut context, pre)
+        .map_err(|e| store_context.handle_wasm_error(e))?;
+    let data = context.data_mut();
+    let request = data
+        .new_incoming_request(request)
+        .map_err(|e| error!("{}", e))?;
+    let (sender, receiver) = oneshot::channel();
+    let out = data
+        .new_response_outparam(sender)
+        .map_err(|e| error!("{}", e))?;
+    let (done_sender, done) = oneshot::channel();
+
+    // The component runs on this thread, so that the store's Ruby callbacks
+    // can re-acquire the GVL, while its response is streamed on another one.
+    let (call, response) = nogvl(|| {
+        thread::scope(|scope| {
+            let response = scope.spawn(move || in_tokio(read_response(receiver, done)));
+            let call = proxy
+                .wasi_http_incoming_handler()
+                .call_handle(&mut context, request, out);
+            let _ = done_sender.send(call.is_ok());
+            (call, response.join())
+        })
+    });
+
+    call.map_err(|e| store_context.handle_wasm_error(e))?;
+    let (parts, body) = response
+        .map_err(|_| error!("failed to read the HTTP response"))?
+        .map_err(|e| error!("{}", e))?;
+
+    let headers = RArray::with_capacity(parts.headers.len());
+    for (name, value) in parts.headers.iter() {
+        headers.push((name.as_str(), RString::from_slice(value.as_bytes())))?;
+    }
+
+    Ok((parts.status.as_u16(), headers, RString::from_slice(&body)))
+}
+
+/// Waits for the response of a +wasi:http/incoming-handler+ call, and reads
+/// its body. `done` resolves once the call returns, to whether it succeeded.
+async fn read_response(
+    receiver: oneshot::Receiver<ResponseResult>,
+    mut done: oneshot::Receiver<bool>,
+) -> anyhow::Result<(hyper::http::response::Parts, Bytes)> {
+    let response = tokio::select! {
+        biased;
+        response = receiver => response,
+        _ = &mut done => anyhow::bail!("the component did not set a response"),
+    };
+    let (parts, body) = response
+        .map_err(|_| anyhow::anyhow!("the component did not set a response"))?
+        .map_err(|code| anyhow::anyhow!("the component failed to respond: {:?}", code))?
+        .into_parts();
+
+    let mut body = pin!(body.collect());
+    let mut returned = false;
+    loop {
+        tokio::select! {
+            body = &mut body => {
+                let body = body
+                    .map_err(|code| anyhow::anyhow!("failed to read the response body: {:?}", code))?;
+                return Ok((parts, body.to_bytes()));
+            }
+            succeeded = &mut done, if !returned => {
+                if !succeeded.unwrap_or(false) {
+                    anyhow::bail!("the component did not finish the response");
+                }
+                returned = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{err, error, helpers::with_gvl, ruby_api::store::StoreData};
+use magnus::{exception::arg_error, prelude::*, value::Opaque, Error, RString, Ruby, Value};
+use wasmtime::component::Resource;
+use wasmtime_wasi::preview2::WasiView;
+
+mod bindings {
+    wasmtime::component::bindgen!({
+        path: "wit/keyvalue",
+        world: "imports",
+        with: {
+            "wasi:keyvalue/store/bucket": super::Bucket,
+        },
+    });
+}
+
+use bindings::wasi::keyvalue::store::{Error as KeyValueError, Host, HostBucket, KeyResponse};
+
+/// The methods key-value backends must respond to.
+const REQUIRED_METHODS: [&str; 3] = ["get", "set", "delete"];
+
+/// A bucket opened by a guest.
+pub struct Bucket {
+    identifier: String,
+}
+
+/// Checks that `backend` can be used as a {Store}'s +wasi_keyvalue+.
+pub fn check_backend(backend: Value) -> Result<Opaque<Value>, Error> {
+    for method in REQUIRED_METHODS {
+        if !backend.respond_to(method, false)? {
+            return Err(Error::new(
+                arg_error(),
+                format!("wasi_keyvalue backend must respond to #{method}"),
+            ));
+        }
+    }
+
+    Ok(backend.into())
+}
+
+/// Calls the store's Ruby backend, with the GVL. Exceptions raised by the
+/// backend are returned to the guest as +error::other+.
+fn call_backend<T>(
+    data: &StoreData,
+    call: impl FnOnce(Value) -> Result<T, Error>,
+) -> wasmtime::Result<Result<T, KeyValueError>> {
+    let Some(backend) = data.wasi_keyvalue_backend() else {
+        anyhow::bail!("Store is missing a wasi_keyvalue backend");
+    };
+
+    Ok(with_gvl(|| {
+        let ruby = Ruby::get().unwrap();
+        call(ruby.get_inner(backend)).map_err(|e| KeyValueError::Other(e.to_string()))
+    }))
+}
+
+impl StoreData {
+    fn bucket_identifier(&mut self, bucket: &Resource<Bucket>) -> wasmtime::Result<String> {
+        Ok(WasiView::table(self).get(bucket)?.identifier.clone())
+    }
+}
+
+impl Host for StoreData {
+    fn open(
+        &mut self,
+        identifier: String,
+    ) -> wasmtime::Result<Result<Resource<Bucket>, KeyValueError>> {
+        let opened = call_backend(self, |backend| {
+            if !backend.respond_to("open", false)? {
+                return Ok(true);
+            }
+            let opened: Value = backend.funcall("open", (identifier.as_str(),))?;
+            Ok(opened.to_bool())
+        })?;
+
+        match opened {
+            Ok(true) => Ok(Ok(WasiView::table(self).push(Bucket { identifier })?)),
+            Ok(false) => Ok(Err(KeyValueError::NoSuchStore)),
+            Err(e) => Ok(Err(e)),
+        }
+    }
+}
+
+impl HostBucket for StoreData {
+    fn get(
+        &mut self,
+        bucket: Resource<Bucket>,
+        key: String,
+    ) -> wasmtime::Result<Result<Option<Vec<u8>>, KeyValueError>> {
+        let identifier = self.bucket_identifier(&bucket)?;
+        call_backend(self, |backend| {
+            let value: Option<RString> = backend.funcall("get", (identifier, key))?;
+            Ok(value.map(|value| unsafe { value.as_slice() }.to_vec()))
+        })
+    }
+
+    fn set(
+        &mut self,
+        bucket: Resource<Bucket>,
+        key: String,
+        value: Vec<u8>,
+    ) -> wasmtime::Result<Result<(), KeyValueError>> {
+        let identifier = self.bucket_identifier(&bucket)?;
+        call_backend(self, |backend| {
+            let value = RString::from_slice(&value);
+            backend.funcall::<_, _, Value>("set", (identifier, key, value))?;
+            Ok(())
+        })
+    }
+
+    fn delete(
+        &mut self,
+        bucket: Resource<Bucket>,
+        key: String,
+    ) -> wasmtime::Result<Result<(), KeyValueError>> {
+        let identifier = self.bucket_identifier(&bucket)?;
+        call_backend(self, |backend| {
+            backend.funcall::<_, _, Value>("delete", (identifier, key))?;
+            Ok(())
+        })
+    }
+
+    fn exists(
+        &mut self,
+        bucket: Resource<Bucket>,
+        key: String,
+    ) -> wasmtime::Result<Result<bool, KeyValueError>> {
+        let identifier = self.bucket_identifier(&bucket)?;
+        call_backend(self, |backend| {
+            if backend.respond_to("exists?", false)? {
+                let exists: Value = backend.funcall("exists?", (identifier, key))?;
+                return Ok(exists.to_bool());
+            }
+            let value: Value = backend.funcall("get", (identifier, key))?;
+            Ok(!value.is_nil())
+        })
+    }
+
+    fn list_keys(
+        &mut self,
+        bucket: Resource<Bucket>,
+        cursor: Option<u64>,
+    ) -> wasmtime::Result<Result<KeyResponse, KeyValueError>> {
+        let identifier = self.bucket_identifier(&bucket)?;
+        call_backend(self, |backend| {
+            if !backend.respond_to("keys", false)? {
+                return err!("wasi_keyvalue backend does not respond to #keys");
+            }
+            let (keys, cursor) = backend.funcall("keys", (identifier, cursor))?;
+            Ok(KeyResponse { keys, cursor })
+        })
+    }
+
+    fn drop(&mut self, bucket: Resource<Bucket>) -> wasmtime::Result<()> {
+        WasiView::table(self).delete(bucket)?;
+        Ok(())
+    }
+}
+
+/// Adds the +wasi:keyvalue/store+ imports to `linker`.
+pub fn add_to_linker(
+    linker: &mut wasmtime::component::Linker<StoreData>,
+    async_support: bool,
+) -> Result<(), Error> {
+    if async_support {
+        return err!("wasi_keyvalue is not supported in engines with async_support: true");
+    }
+
+    bindings::wasi::keyvalue::store::add_to_linker(linker, |s| s).map_err(|e| error!("{}", e))
+}
@@ -0,0 +1,72 @@
+use crate::error;
+use magnus::{Error, RArray, RHash, RString};
+use wit_parser::{Function, Resolve, WorldItem, WorldKey};
+
+/// @yard
+/// Describes the functions +world+ imports and exports, as parsed from the
+/// WIT package at +path+. Used by {Component.bindgen}.
+///
+/// @def wit_world(path, world)
+/// @param path [String] A WIT file or a directory of WIT files.
+/// @param world [String, nil] The name of the world, optional when the
+///   package has a single world.
+/// @return [Hash] The world's +name+, with its +imports+ and +exports+ as
+///   +Array+s of functions. Each function +Hash+ has a +name+, its +params+
+///   names, its number of +results+ and the +interface+ it belongs to, +nil+
+///   for the world's own functions.
+pub fn wit_world(path: RString, world: Option<RString>) -> Result<RHash, Error> {
+    let mut resolve = Resolve::new();
+    let (package, _files) = resolve
+        .push_path(path.to_string()?)
+        .map_err(|e| error!("Could not parse WIT: {:?}", e))?;
+    let world = match world {
+        Some(world) => Some(world.to_string()?),
+        None => None,
+    };
+    let world_id = resolve
+        .select_world(package, world.as_deref())
+        .map_err(|e| error!("{}", e))?;
+    let world = &resolve.worlds[world_id];
+
+    let description = RHash::new();
+    description.aset("name", world.name.as_str())?;
+    description.aset("imports", world_functions(&resolve, world.imports.iter())?)?;
+    description.aset("exports", world_functions(&resolve, world.exports.iter())?)?;
+    Ok(description)
+}
+
+fn world_functions<'a>(
+    resolve: &Resolve,
+    items: impl Iterator<Item = (&'a WorldKey, &'a WorldItem)>,
+) -> Result<RArray, Error> {
+    let functions = RArray::new();
+    for (key, item) in items {
+        match item {
+            WorldItem::Function(function) => {
+                functions.push(function_description(None, function)?)?;
+            }
+            WorldItem::Interface(id) => {
+                let interface = resolve.name_world_key(key);
+                for function in resolve.interfaces[*id].functions.values() {
+                    functions.push(function_description(Some(&interface), function)?)?;
+                }
+            }
+            WorldItem::Type(_) => {}
+        }
+    }
+    Ok(functions)
+}
+
+fn function_description(interface: Option<&str>, function: &Function) -> Result<RHash, Error> {
+    let params = RArray::with_capacity(function.params.len());
+    for (name, _ty) in function.params.iter() {
+        params.push(name.as_str())?;
+    }
+
+    let description = RHash::new();
+    description.aset("name", function.name.as_str())?;
+    description.aset("interface", interface)?;
+    description.aset("params", params)?;
+    description.aset("results", function.results.len())?;
+    Ok(description)
+}
@@ -1,12 +1,16 @@
 mod tracked_memory_creator;
 pub(crate) use self::tracked_memory_creator::TrackedMemoryCreator;
-use crate::{define_rb_intern, helpers::SymbolEnum};
+use crate::{
+    define_rb_intern, error,
+    helpers::{StaticId, SymbolEnum},
+};
 use lazy_static::lazy_static;
 use magnus::{
     exception::{arg_error, type_error},
     prelude::*,
     r_hash::ForEach,
-    Error, RHash, Symbol, TryConvert, Value,
+    value::{Qfalse, Qtrue},
+    Error, RArray, RHash, RString, Symbol, TryConvert, Value,
 };
 use std::{
     convert::{TryFrom, TryInto},
@@ -24,8 +28,17 @@ define_rb_intern!(
     WASM_THREADS => "wasm_threads",
     WASM_MULTI_MEMORY => "wasm_multi_memory",
     WASM_MEMORY64 => "wasm_memory64",
+    WASM_COMPONENT_MODEL => "wasm_component_model",
+    WASM_REFERENCE_TYPES => "wasm_reference_types",
+    WASM_BULK_MEMORY => "wasm_bulk_memory",
+    WASM_SIGN_EXTENSION => "wasm_sign_extension",
+    WASM_MULTI_VALUE => "wasm_multi_value",
+    WASM_SIMD => "wasm_simd",
+    WASM_RELAXED_SIMD => "wasm_relaxed_simd",
+    WASM_FUNCTION_REFERENCES => "wasm_function_references",
     PROFILER => "profiler",
     CRANELIFT_OPT_LEVEL => "cranelift_opt_level",
+    CRANELIFT_NAN_CANONICALIZATION => "cranelift_nan_canonicalization",
     STRATEGY => "strategy",
     PARALLEL_COMPILATION => "parallel_compilation",
     NONE => "none",
@@ -36,6 +49,15 @@ define_rb_intern!(
     SPEED_AND_SIZE => "speed_and_size",
     TARGET => "target",
     GENERATE_ADDRESS_MAP => "generate_address_map",
+    CACHE => "cache",
+    ASYNC_SUPPORT => "async_support",
+    ASYNC_STACK_SIZE => "async_stack_size",
+    MEMORY_RESERVATION => "memory_reservation",
+    MEMORY_GUARD_SIZE => "memory_guard_size",
+    GUARD_BEFORE_LINEAR_MEMORY => "guard_before_linear_memory",
+    MEMORY_INIT_COW => "memory_init_cow",
+    CRANELIFT_FLAGS => "cranelift_flags",
+    CRANELIFT_FLAG_ENABLE => "cranelift_flag_enable",
     AUTO => "auto",
     CRANELIFT => "cranelift",
     WINCH => "winch",
@@ -73,16 +95,32 @@ lazy_static! {
 }
 
 /// Default for [`wasmtime::Config`], which includes a [`TrackedMemoryCreator`]
-/// to report memory usage to Ruby.
+/// to report memory usage to Ruby and enables the component model.
 pub fn default_config() -> Config {
+    let mut config = base_config();
+    track_host_memory(&mut config);
+    config
+}
+
+fn base_config() -> Config {
     let mut config = Config::new();
+    config.wasm_component_model(true);
+    config
+}
+
+fn track_host_memory(config: &mut Config) {
+    // Memories of custom memory creators can't be initialized from
+    // copy-on-write images, see `memory_init_cow`.
+    config.memory_init_cow(false);
     let host_memory = TrackedMemoryCreator::new();
     config.with_host_memory(Arc::new(host_memory));
-    config
 }
 
 pub fn hash_to_config(hash: RHash) -> Result<Config, Error> {
-    let mut config = default_config();
+    let mut config = base_config();
+    let mut memory_init_cow = false;
+    let mut winch = false;
+    let mut target = None;
     hash.foreach(|name: Symbol, value: Value| {
         let id = magnus::value::Id::from(name);
         let entry = ConfigEntry(name, value);
@@ -105,24 +143,96 @@ pub fn hash_to_config(hash: RHash) -> Result<Config, Error> {
             config.wasm_multi_memory(entry.try_into()?);
         } else if *WASM_MEMORY64 == id {
             config.wasm_memory64(entry.try_into()?);
+        } else if *WASM_COMPONENT_MODEL == id {
+            config.wasm_component_model(entry.try_into()?);
+        } else if *WASM_REFERENCE_TYPES == id {
+            config.wasm_reference_types(entry.try_into()?);
+        } else if *WASM_BULK_MEMORY == id {
+            config.wasm_bulk_memory(entry.try_into()?);
+        } else if *WASM_SIGN_EXTENSION == id {
+            // Wasmtime doesn't allow disabling the sign-extension operators.
+            if !bool::try_from(entry)? {
+                return Err(Error::new(
+                    arg_error(),
+                    "The sign-extension operators cannot be disabled",
+                ));
+            }
+        } else if *WASM_MULTI_VALUE == id {
+            config.wasm_multi_value(entry.try_into()?);
+        } else if *WASM_SIMD == id {
+            config.wasm_simd(entry.try_into()?);
+        } else if *WASM_RELAXED_SIMD == id {
+            config.wasm_relaxed_simd(entry.try_into()?);
+        } else if *WASM_FUNCTION_REFERENCES == id {
+            config.wasm_function_references(entry.try_into()?);
         } else if *PARALLEL_COMPILATION == id {
             config.parallel_compilation(entry.try_into()?);
         } else if *PROFILER == id {
             config.profiler(entry.try_into()?);
         } else if *CRANELIFT_OPT_LEVEL == id {
             config.cranelift_opt_level(entry.try_into()?);
-        } else if *STRATEGY == id && cfg!(feature = "winch") {
-            config.strategy(entry.try_into()?);
+        } else if *CRANELIFT_NAN_CANONICALIZATION == id {
+            config.cranelift_nan_canonicalization(entry.try_into()?);
+        } else if *CRANELIFT_FLAGS == id {
+            let flags = RHash::try_convert(entry.1).map_err(|_| entry.invalid_type())?;
+            flags.foreach(|name: Value, value: Value| {
+                let name: String = name.funcall("to_s", ())?;
+                let value: String = value.funcall("to_s", ())?;
+                // SAFETY: before running code compiled with these flags,
+                // Wasmtime checks that the host supports them.
+                unsafe { config.cranelift_flag_set(&name, &value) };
+                Ok(ForEach::Continue)
+            })?;
+        } else if *CRANELIFT_FLAG_ENABLE == id {
+            let flags = RArray::try_convert(entry.1).map_err(|_| entry.invalid_type())?;
+            for flag in flags.each() {
+                let flag: String = flag?.funcall("to_s", ())?;
+                // SAFETY: see `cranelift_flags` above.
+                unsafe { config.cranelift_flag_enable(&flag) };
+            }
+        } else if *STRATEGY == id {
+            let strategy = entry.try_into()?;
+            winch = matches!(strategy, Strategy::Winch);
+            config.strategy(strategy);
         } else if *TARGET == id {
-            let target: Option<String> = entry.try_into()?;
+            let entry: Option<String> = entry.try_into()?;
 
-            if let Some(target) = target {
-                config.target(&target).map_err(|e| {
-                    Error::new(arg_error(), format!("Invalid target: {}: {}", target, e))
+            if let Some(entry) = entry {
+                config.target(&entry).map_err(|e| {
+                    Error::new(arg_error(), format!("Invalid target: {}: {}", entry, e))
                 })?;
+                target = Some(entry);
             }
         } else if *GENERATE_ADDRESS_MAP == id {
             config.generate_address_map(entry.try_into()?);
+        } else if *ASYNC_SUPPORT == id {
+            config.async_support(entry.try_into()?);
+        } else if *ASYNC_STACK_SIZE == id {
+            config.async_stack_size(entry.try_into()?);
+        } else if *MEMORY_RESERVATION == id {
+            config.static_memory_maximum_size(entry.try_into()?);
+        } else if *MEMORY_GUARD_SIZE == id {
+            let size: u64 = entry.try_into()?;
+            config.static_memory_guard_size(size);
+            config.dynamic_memory_guard_size(size);
+        } else if *GUARD_BEFORE_LINEAR_MEMORY == id {
+            config.guard_before_linear_memory(entry.try_into()?);
+        } else if *MEMORY_INIT_COW == id {
+            memory_init_cow = entry.try_into()?;
+        } else if *CACHE == id {
+            match entry.try_into()? {
+                CacheConfig::Disabled => {}
+                CacheConfig::Default => {
+                    config
+                        .cache_config_load_default()
+                        .map_err(|e| error!("Failed to load cache config: {}", e))?;
+                }
+                CacheConfig::File(path) => {
+                    config
+                        .cache_config_load(&path)
+                        .map_err(|e| error!("Failed to load cache config {}: {}", path, e))?;
+                }
+            }
         } else {
             return Err(Error::new(
                 arg_error(),
@@ -133,9 +243,62 @@ pub fn hash_to_config(hash: RHash) -> Result<Config, Error> {
         Ok(ForEach::Continue)
     })?;
 
+    if winch {
+        ensure_winch_supported(target.as_deref())?;
+    }
+
+    if memory_init_cow {
+        config.memory_init_cow(true);
+    } else {
+        track_host_memory(&mut config);
+    }
+
     Ok(config)
 }
 
+/// Winch is only compiled in with the `winch` feature, and only generates code
+/// for x86_64 as of Wasmtime 17.
+fn ensure_winch_supported(target: Option<&str>) -> Result<(), Error> {
+    if !cfg!(feature = "winch") {
+        return Err(Error::new(
+            arg_error(),
+            "The :winch strategy requires wasmtime-rb to be compiled with the winch feature",
+        ));
+    }
+
+    let arch = target
+        .and_then(|target| target.split('-').next())
+        .unwrap_or(std::env::consts::ARCH);
+    if arch != "x86_64" {
+        return Err(Error::new(
+            arg_error(),
+            format!("The :winch strategy is not supported on {arch}, only on x86_64"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Whether the config +hash+ enables +:async_support+.
+pub fn is_async(hash: RHash) -> bool {
+    is_enabled(hash, *ASYNC_SUPPORT)
+}
+
+/// Whether the config +hash+ enables +:epoch_interruption+.
+pub fn is_epoch_interruption(hash: RHash) -> bool {
+    is_enabled(hash, *EPOCH_INTERRUPTION)
+}
+
+/// Whether the config +hash+ enables +:memory_init_cow+.
+pub fn is_memory_init_cow(hash: RHash) -> bool {
+    is_enabled(hash, *MEMORY_INIT_COW)
+}
+
+fn is_enabled(hash: RHash, id: StaticId) -> bool {
+    hash.get(Symbol::from(id))
+        .map_or(false, |value| value.to_bool())
+}
+
 struct ConfigEntry(Symbol, Value);
 
 impl ConfigEntry {
@@ -161,6 +324,13 @@ impl TryFrom<ConfigEntry> for usize {
     }
 }
 
+impl TryFrom<ConfigEntry> for u64 {
+    type Error = magnus::Error;
+    fn try_from(value: ConfigEntry) -> Result<Self, Self::Error> {
+        Self::try_convert(value.1).map_err(|_| value.invalid_type())
+    }
+}
+
 impl TryFrom<ConfigEntry> for String {
     type Error = magnus::Error;
     fn try_from(value: ConfigEntry) -> Result<Self, Self::Error> {
@@ -175,6 +345,28 @@ impl TryFrom<ConfigEntry> for Option<String> {
     }
 }
 
+/// Value of the +:cache+ option.
+enum CacheConfig {
+    Disabled,
+    Default,
+    File(String),
+}
+
+impl TryFrom<ConfigEntry> for CacheConfig {
+    type Error = magnus::Error;
+    fn try_from(value: ConfigEntry) -> Result<Self, Self::Error> {
+        if let Some(path) = RString::from_value(value.1) {
+            Ok(CacheConfig::File(path.to_string()?))
+        } else if Qtrue::from_value(value.1).is_some() {
+            Ok(CacheConfig::Default)
+        } else if Qfalse::from_value(value.1).is_some() || value.1.is_nil() {
+            Ok(CacheConfig::Disabled)
+        } else {
+            Err(value.invalid_type())
+        }
+    }
+}
+
 impl TryFrom<ConfigEntry> for WasmBacktraceDetails {
     type Error = magnus::Error;
     fn try_from(value: ConfigEntry) -> Result<WasmBacktraceDetails, Error> {
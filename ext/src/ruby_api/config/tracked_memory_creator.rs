@@ -1,6 +1,6 @@
 use rb_sys::tracking_allocator::ManuallyTracked;
 use wasmtime::{LinearMemory, MemoryCreator};
-use wasmtime_environ::{Memory, MemoryPlan};
+use wasmtime_environ::{Memory, MemoryPlan, MemoryStyle, WASM_PAGE_SIZE};
 use wasmtime_runtime::{DefaultMemoryCreator, RuntimeLinearMemory, RuntimeMemoryCreator};
 
 pub(crate) struct TrackedLinearMemory {
@@ -59,8 +59,8 @@ unsafe impl MemoryCreator for TrackedMemoryCreator {
         ty: wasmtime::MemoryType,
         minimum: usize,
         maximum: Option<usize>,
-        _reserved_size_in_bytes: Option<usize>,
-        _guard_size_in_bytes: usize,
+        reserved_size_in_bytes: Option<usize>,
+        guard_size_in_bytes: usize,
     ) -> anyhow::Result<Box<dyn wasmtime::LinearMemory>, String> {
         let default_memory_creator = DefaultMemoryCreator {};
         let memory = Memory {
@@ -69,8 +69,20 @@ unsafe impl MemoryCreator for TrackedMemoryCreator {
             shared: ty.is_shared(),
             memory64: ty.is_64(),
         };
-        let tunables = wasmtime_environ::Tunables::default();
-        let plan = MemoryPlan::for_memory(memory, &tunables);
+        // The compiled code relies on the reservation and guard sizes the
+        // engine was configured with (e.g. `memory_reservation`).
+        let style = match reserved_size_in_bytes {
+            Some(reserved) => MemoryStyle::Static {
+                bound: reserved as u64 / u64::from(WASM_PAGE_SIZE),
+            },
+            None => MemoryStyle::Dynamic { reserve: 0 },
+        };
+        let plan = MemoryPlan {
+            memory,
+            style,
+            pre_guard_size: 0,
+            offset_guard_size: guard_size_in_bytes as u64,
+        };
         let base = default_memory_creator
             .new_memory(&plan, minimum, maximum, None)
             .map_err(|e| e.to_string())?;
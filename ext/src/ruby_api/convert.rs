@@ -1,9 +1,15 @@
-use crate::{define_rb_intern, err, error, helpers::SymbolEnum};
+use crate::{define_rb_intern, error, helpers::SymbolEnum};
 use lazy_static::lazy_static;
-use magnus::{prelude::*, Error, IntoValue, RArray, Ruby, Symbol, TryConvert, TypedData, Value};
+use magnus::{
+    exception::arg_error, gc::Marker, prelude::*, Error, IntoValue, RArray, RString, Ruby, Symbol,
+    TryConvert, TypedData, Value,
+};
 use wasmtime::{ExternRef, Val, ValType};
 
-use super::{func::Func, global::Global, memory::Memory, store::StoreContextValue, table::Table};
+use super::{
+    func::Func, global::Global, memory::Memory, shared_memory::SharedMemory,
+    store::StoreContextValue, table::Table,
+};
 
 define_rb_intern!(
     I32 => "i32",
@@ -54,7 +60,7 @@ impl ToRubyValue for Val {
                 None => Ok(().into_value()),
                 Some(funcref) => Ok(Func::from_inner(*store, *funcref).into_value()),
             },
-            Val::V128(_) => err!("converting from v128 to Ruby unsupported"),
+            Val::V128(v128) => Ok(RString::from_slice(&v128.as_u128().to_le_bytes()).as_value()),
         }
     }
 }
@@ -80,11 +86,24 @@ impl ToWasmVal for Value {
             ValType::FuncRef => {
                 let func_ref_value = match self.is_nil() {
                     true => None,
-                    false => Some(*<&Func>::try_convert(*self)?.inner()),
+                    false => Some(<&Func>::try_convert(*self)?.escape()),
                 };
                 Ok(Val::FuncRef(func_ref_value))
             }
-            ValType::V128 => err!("converting from Ruby to v128 not supported"),
+            ValType::V128 => {
+                let string = RString::try_convert(*self)?;
+                // SAFETY: the bytes are copied before calling into Ruby again.
+                let bytes: [u8; 16] = unsafe { string.as_slice() }.try_into().map_err(|_| {
+                    Error::new(
+                        arg_error(),
+                        format!(
+                            "expected a 16-byte String for v128, got {} bytes",
+                            string.len()
+                        ),
+                    )
+                })?;
+                Ok(Val::V128(u128::from_le_bytes(bytes).into()))
+            }
         }
     }
 }
@@ -98,6 +117,14 @@ impl From<Value> for ExternRefValue {
 unsafe impl Send for ExternRefValue {}
 unsafe impl Sync for ExternRefValue {}
 
+/// Marks the Ruby object wrapped by an externref created by [`ToWasmVal`].
+/// The object is pinned, as the externref can't be updated on compaction.
+pub fn mark_externref(externref: &ExternRef, marker: &Marker) {
+    if let Some(value) = externref.data().downcast_ref::<ExternRefValue>() {
+        marker.mark(value.0);
+    }
+}
+
 pub trait ToExtern {
     fn to_extern(&self, ruby: &Ruby) -> Result<wasmtime::Extern, Error>;
 }
@@ -108,6 +135,8 @@ impl ToExtern for Value {
             Ok(<&Func>::try_convert(*self)?.into())
         } else if self.is_kind_of(Memory::class(ruby)) {
             Ok(<&Memory>::try_convert(*self)?.into())
+        } else if self.is_kind_of(SharedMemory::class(ruby)) {
+            Ok(<&SharedMemory>::try_convert(*self)?.into())
         } else if self.is_kind_of(Table::class(ruby)) {
             Ok(<&Table>::try_convert(*self)?.into())
         } else if self.is_kind_of(Global::class(ruby)) {
@@ -1,5 +1,7 @@
 use super::{
-    config::{default_config, hash_to_config},
+    config::{default_config, hash_to_config, is_async, is_epoch_interruption, is_memory_init_cow},
+    epoch_timer,
+    errors::compile_error,
     root,
 };
 use crate::{
@@ -7,24 +9,178 @@ use crate::{
     helpers::{nogvl, Tmplock},
 };
 use magnus::{
-    class, function, method, prelude::*, scan_args, typed_data::Obj, value::LazyId, Error, Module,
-    Object, RHash, RString, Ruby, TryConvert, Value,
+    class, exception::arg_error, function, method, prelude::*, scan_args, typed_data::Obj,
+    value::LazyId, Error, Module, Object, RHash, RString, Ruby, TryConvert, Value,
 };
 use std::{
     collections::hash_map::DefaultHasher,
     hash::{Hash, Hasher},
-    sync::Mutex,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, Weak,
+    },
 };
 use wasmtime::Engine as EngineImpl;
 
 #[cfg(feature = "tokio")]
 lazy_static::lazy_static! {
-    static ref TOKIO_RT: tokio::runtime::Runtime = tokio::runtime::Builder::new_multi_thread()
+    /// The runtime of the epoch timers, with the process it was started in.
+    static ref TOKIO_RT: Mutex<(u32, &'static tokio::runtime::Runtime)> =
+        Mutex::new((std::process::id(), new_runtime()));
+}
+
+#[cfg(feature = "tokio")]
+fn new_runtime() -> &'static tokio::runtime::Runtime {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
         .thread_name("wasmtime-engine-timers")
         .worker_threads(1)
         .enable_io()
         .build()
         .unwrap();
+    Box::leak(Box::new(runtime))
+}
+
+/// The runtime of the epoch timers, started again in forked processes, which
+/// don't inherit its thread. The parent's runtime is leaked: dropping it would
+/// wait for its thread.
+#[cfg(feature = "tokio")]
+fn runtime() -> &'static tokio::runtime::Runtime {
+    let mut runtime = TOKIO_RT.lock().unwrap();
+    if runtime.0 != std::process::id() {
+        *runtime = (std::process::id(), new_runtime());
+    }
+    runtime.1
+}
+
+lazy_static::lazy_static! {
+    /// The engines, closed by +Wasmtime.shutdown+ and paused around forks.
+    static ref ENGINES: Mutex<Vec<Weak<Mutex<EngineState>>>> = Mutex::new(Vec::new());
+}
+
+/// An engine's epoch, counted alongside Wasmtime's (which can't be read) for
+/// stores to compare with their epoch deadlines. All increments go through
+/// [`Epoch::increment`].
+#[derive(Clone)]
+pub struct Epoch {
+    engine: EngineImpl,
+    count: Arc<AtomicU64>,
+}
+
+impl Epoch {
+    pub fn current(&self) -> u64 {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    pub fn increment(&self) {
+        // Counted first, for stores seeing the new epoch to count it too.
+        self.count.fetch_add(1, Ordering::SeqCst);
+        self.engine.increment_epoch();
+    }
+}
+
+/// What an [`Engine`] releases when closed.
+#[derive(Default)]
+struct EngineState {
+    inner: Option<EngineImpl>,
+    epoch_count: Arc<AtomicU64>,
+    /// The epoch timer, with its interval in milliseconds.
+    #[cfg(feature = "tokio")]
+    timer: Option<(tokio::task::JoinHandle<()>, u64)>,
+    /// The interval of the timer paused by [`before_fork`].
+    #[cfg(feature = "tokio")]
+    paused_timer: Option<u64>,
+}
+
+impl EngineState {
+    fn engine(&self) -> Result<EngineImpl, Error> {
+        self.inner.clone().ok_or_else(|| error!("engine is closed"))
+    }
+
+    fn epoch(&self) -> Result<Epoch, Error> {
+        Ok(Epoch {
+            engine: self.engine()?,
+            count: self.epoch_count.clone(),
+        })
+    }
+
+    #[cfg(feature = "tokio")]
+    fn start_timer(&mut self, milliseconds: u64) -> Result<(), Error> {
+        let epoch = self.epoch()?;
+        self.stop_timer();
+
+        let handle = runtime().spawn(async move {
+            let tick_every = tokio::time::Duration::from_millis(milliseconds);
+            let mut interval = async_timer::Interval::platform_new(tick_every);
+
+            loop {
+                interval.wait().await;
+                epoch.increment();
+            }
+        });
+
+        self.timer = Some((handle, milliseconds));
+        Ok(())
+    }
+
+    /// Stops the timer, returning its interval.
+    #[cfg(feature = "tokio")]
+    fn stop_timer(&mut self) -> Option<u64> {
+        self.paused_timer = None;
+        let (handle, milliseconds) = self.timer.take()?;
+        handle.abort();
+        Some(milliseconds)
+    }
+
+    fn close(&mut self) {
+        #[cfg(feature = "tokio")]
+        self.stop_timer();
+        self.inner = None;
+    }
+}
+
+/// Calls `f` with the state of each open engine.
+#[cfg(feature = "tokio")]
+fn each_engine(mut f: impl FnMut(&mut EngineState) -> Result<(), Error>) -> Result<(), Error> {
+    for state in ENGINES.lock().unwrap().iter() {
+        if let Some(state) = state.upgrade() {
+            f(&mut state.lock().unwrap())?;
+        }
+    }
+    Ok(())
+}
+
+/// Closes all open engines, see [`Engine::close`].
+pub fn close_all() {
+    for state in ENGINES.lock().unwrap().drain(..) {
+        if let Some(state) = state.upgrade() {
+            state.lock().unwrap().close();
+        }
+    }
+}
+
+/// Pauses the epoch timers of all engines, and the epoch timer of time
+/// limits and timeouts.
+pub fn before_fork() -> Result<(), Error> {
+    epoch_timer::before_fork();
+    #[cfg(feature = "tokio")]
+    each_engine(|state| {
+        if let Some(milliseconds) = state.stop_timer() {
+            state.paused_timer = Some(milliseconds);
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Resumes the epoch timers paused by [`before_fork`].
+pub fn after_fork() -> Result<(), Error> {
+    epoch_timer::after_fork()?;
+    #[cfg(feature = "tokio")]
+    each_engine(|state| match state.paused_timer.take() {
+        Some(milliseconds) => state.start_timer(milliseconds),
+        None => Ok(()),
+    })?;
+    Ok(())
 }
 
 /// @yard
@@ -48,19 +204,45 @@ lazy_static::lazy_static! {
 ///      # ...
 ///    end
 ///
+/// @example Sharing an engine and a module with Ractors
+///    # Engines and modules can be made shareable to be compiled once and
+///    # used by many Ractors, each running Wasm in its own {Store}.
+///    engine = Ractor.make_shareable(Wasmtime::Engine.new)
+///    wasm_module = Ractor.make_shareable(Wasmtime::Module.new(engine, wat))
+///
+///    ractors = 4.times.map do
+///      Ractor.new(engine, wasm_module) do |engine, wasm_module|
+///        store = Wasmtime::Store.new(engine)
+///        Wasmtime::Instance.new(store, wasm_module).invoke("run")
+///      end
+///    end
+///    ractors.map(&:take)
+///
+/// @example Releasing engines before forking
+///    engine = Wasmtime::Engine.new(epoch_interruption: true)
+///    engine.start_epoch_interval(10)
+///    # ...
+///
+///    # Stops the epoch timer and closes the engine, as would `engine.close`.
+///    Wasmtime.shutdown
+///
+///    fork do
+///      engine = Wasmtime::Engine.new
+///      # ...
+///    end
+///
 /// @see https://docs.rs/wasmtime/latest/wasmtime/struct.Engine.html Wasmtime's Rust doc
 #[magnus::wrap(class = "Wasmtime::Engine", free_immediately, frozen_shareable)]
 pub struct Engine {
-    inner: EngineImpl,
-
-    #[cfg(feature = "tokio")]
-    timer_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    state: Arc<Mutex<EngineState>>,
+    async_support: bool,
+    epoch_interruption: bool,
+    memory_init_cow: bool,
 }
 
-#[cfg(feature = "tokio")]
 impl Drop for Engine {
     fn drop(&mut self) {
-        self.stop_epoch_interval()
+        self.close()
     }
 }
 
@@ -70,21 +252,78 @@ impl Engine {
     /// @param config [Hash] The engine's config.
     ///   See the {https://docs.rs/wasmtime/latest/wasmtime/struct.Engine.html +Config+‘s Rust doc} for detailed description of
     ///   the different options and the defaults.
-    /// @option config [Boolean] :debug_info
+    /// @option config [Boolean] :debug_info (false) Whether the DWARF debug info of modules is translated
+    ///   to native debug info and registered with the GDB JIT interface, so that +gdb+ and +lldb+ can set
+    ///   breakpoints in and step through the guest's source.
     /// @option config [Boolean] :wasm_backtrace_details
     /// @option config [Boolean] :native_unwind_info
     /// @option config [Boolean] :consume_fuel
     /// @option config [Boolean] :epoch_interruption
-    /// @option config [Integer] :max_wasm_stack
+    /// @option config [Integer] :max_wasm_stack (512 KiB) The maximum number of bytes of native stack
+    ///   Wasm can use, past which it traps with a stack overflow. Raise it for deeply recursive
+    ///   guests, lower it to bound each call's stack usage. In engines with +async_support+, it must
+    ///   be lower than +async_stack_size+.
     /// @option config [Boolean] :wasm_threads
     /// @option config [Boolean] :wasm_multi_memory
     /// @option config [Boolean] :wasm_memory64
-    /// @option config [Boolean] :parallel_compilation (true) Whether compile WASM using multiple threads
+    /// @option config [Boolean] :wasm_reference_types (true) Requires +wasm_bulk_memory+.
+    /// @option config [Boolean] :wasm_bulk_memory (true)
+    /// @option config [Boolean] :wasm_sign_extension (true) Always enabled, can only be set to +true+.
+    /// @option config [Boolean] :wasm_multi_value (true)
+    /// @option config [Boolean] :wasm_simd (true) +v128+ values are represented as 16-byte binary
+    ///   +String+s, in little-endian order.
+    /// @option config [Boolean] :wasm_relaxed_simd (false) Requires +wasm_simd+.
+    /// @option config [Boolean] :wasm_function_references (false) Allows typed function references
+    ///   and +call_ref+ inside modules. Exported functions and tables can't use typed references in
+    ///   their types yet, {Func} and {Table} only support +funcref+.
+    /// @option config [Boolean] :wasm_component_model (true) Whether {Component::Component}s can be compiled.
+    /// @option config [Boolean] :parallel_compilation (true) Whether compile WASM using multiple threads.
+    ///   The number of threads is set with {Wasmtime.compilation_threads=}.
     /// @option config [Boolean] :generate_address_map Configures whether compiled artifacts will contain information to map native program addresses back to the original wasm module. This configuration option is `true` by default. Disabling this feature can result in considerably smaller serialized modules.
     /// @option config [Symbol] :cranelift_opt_level One of +none+, +speed+, +speed_and_size+.
-    /// @option config [Symbol] :profiler One of +none+, +jitdump+, +vtune+.
-    /// @option config [Symbol] :strategy One of +auto+, +cranelift+, +winch+ (requires crate feature `winch` to be enabled)
-    /// @option config [String] :target
+    /// @option config [Boolean] :cranelift_nan_canonicalization Whether floating point NaN values are canonicalized, for deterministic execution across platforms.
+    /// @option config [Symbol] :profiler One of +none+, +perfmap+, +jitdump+, +vtune+. Lets native
+    ///   profilers symbolize the JIT-compiled Wasm frames: +perfmap+ writes +/tmp/perf-<pid>.map+, read
+    ///   by +perf report+; +jitdump+ writes +jit-<pid>.dump+, to be merged with +perf inject --jit+.
+    ///   +perfmap+ and +jitdump+ are Linux only.
+    /// @option config [Symbol] :strategy One of +auto+, +cranelift+, +winch+. Winch, the baseline compiler,
+    ///   compiles faster but generates slower code; it requires crate feature `winch` to be enabled and
+    ///   only supports x86_64.
+    /// @option config [String] :target The target triple to compile for, e.g.
+    ///   +aarch64-unknown-linux-gnu+. Engines targeting another architecture than the host's can
+    ///   only {#precompile_module}, for {Module.deserialize} on hosts of that architecture.
+    /// @option config [Hash{String => Boolean, String}] :cranelift_flags *Unsafe*: Cranelift
+    ///   settings, including ISA extensions of the target (e.g. +{"has_avx2" => true}+), e.g. to pin
+    ///   the SIMD level of artifacts built for heterogeneous hosts. Unknown settings raise when
+    ///   creating the engine, ISA extensions the host doesn't support raise when loading a {Module},
+    ///   but other settings can make Cranelift generate incorrect code: only set the ones you
+    ///   understand.
+    /// @option config [Array<String>] :cranelift_flag_enable *Unsafe*: Cranelift boolean settings to
+    ///   enable, e.g. +["has_avx512f"]+. Same as +cranelift_flags+ with +true+ values.
+    /// @option config [Boolean] :async_support (false) Whether Wasm runs on separate stacks so that
+    ///   host functions defined with {Linker#func_new_async} can call Ruby code that switches Fibers.
+    ///   WASI calls of such engines are made without holding the GVL.
+    /// @option config [Integer] :memory_reservation (4 GiB on 64-bit hosts) The number of bytes of
+    ///   address space reserved up front for each linear memory, which can then grow in place up to
+    ///   it. Memories needing more are moved when they grow. Lower it, along with
+    ///   +memory_guard_size+, where virtual memory is limited (e.g. +ulimit -v+ in containers), at the
+    ///   cost of bounds checks in the compiled code.
+    /// @option config [Integer] :memory_guard_size (2 GiB on 64-bit hosts) The number of bytes of
+    ///   inaccessible address space reserved after each linear memory, to elide bounds checks.
+    /// @option config [Boolean] :guard_before_linear_memory (true) Whether a guard region is also
+    ///   reserved before each linear memory, as a defense in depth.
+    /// @option config [Boolean] :memory_init_cow (false) Whether linear memories are initialized by
+    ///   mapping a copy-on-write image of their data segments, built once per {Module}, instead of
+    ///   copying the segments on each instantiation. Speeds up instantiation (in particular through
+    ///   {InstancePre}) of modules with large data segments. Memories are then allocated by Wasmtime
+    ///   directly and not reported to Ruby's GC. See {#memory_init_cow?}.
+    /// @option config [Integer] :async_stack_size (2 MiB) The number of bytes of each stack Wasm runs
+    ///   on in engines with +async_support+, including the space host functions use. Must be greater
+    ///   than +max_wasm_stack+. Lower it to run many concurrent calls with less memory.
+    /// @option config [Boolean, String] :cache (false) Whether to cache compiled code on disk, across
+    ///   processes. +true+ loads the cache configuration from Wasmtime's default location, a +String+
+    ///   loads it from the given TOML file.
+    ///   See {https://docs.wasmtime.dev/cli-cache.html Wasmtime's cache configuration doc}.
     ///
     /// @see https://docs.rs/wasmtime/latest/wasmtime/struct.Engine.html
     ///     Wasmtime's Rust doc for details of the configuration options.
@@ -92,19 +331,39 @@ impl Engine {
         let args = scan_args::scan_args::<(), (Option<Value>,), (), (), (), ()>(args)?;
         let (config,) = args.optional;
         let config = config.and_then(|v| if v.is_nil() { None } else { Some(v) });
-        let inner = match config {
+        let (inner, async_support, epoch_interruption, memory_init_cow) = match config {
             Some(config) => {
-                let config = RHash::try_convert(config).and_then(hash_to_config)?;
+                let hash = RHash::try_convert(config)?;
+                let config = hash_to_config(hash)?;
 
-                EngineImpl::new(&config).map_err(|e| error!("{}", e))?
+                (
+                    EngineImpl::new(&config).map_err(|e| error!("{:#}", e))?,
+                    is_async(hash),
+                    is_epoch_interruption(hash),
+                    is_memory_init_cow(hash),
+                )
             }
-            None => EngineImpl::new(&default_config()).map_err(|e| error!("{}", e))?,
+            None => (
+                EngineImpl::new(&default_config()).map_err(|e| error!("{}", e))?,
+                false,
+                false,
+                false,
+            ),
         };
 
+        let state = Arc::new(Mutex::new(EngineState {
+            inner: Some(inner),
+            ..Default::default()
+        }));
+        let mut engines = ENGINES.lock().unwrap();
+        engines.retain(|state| state.strong_count() > 0);
+        engines.push(Arc::downgrade(&state));
+
         Ok(Self {
-            inner,
-            #[cfg(feature = "tokio")]
-            timer_task: Default::default(),
+            state,
+            async_support,
+            epoch_interruption,
+            memory_init_cow,
         })
     }
 
@@ -113,25 +372,22 @@ impl Engine {
     /// Waits +milliseconds+ before incrementing for the first time.
     ///
     /// If a prior timer was started, it will be stopped.
+    ///
+    /// The timer runs on a native thread, without the GVL, so the epoch
+    /// advances even while Ruby threads compete for it.
     /// @def start_epoch_interval(milliseconds)
-    /// @param milliseconds [Integer]
+    /// @param milliseconds [Integer] Must be positive.
     /// @return [nil]
     #[cfg(feature = "tokio")]
-    pub fn start_epoch_interval(&self, milliseconds: u64) {
-        self.stop_epoch_interval();
-        let engine = self.inner.clone();
-
-        let handle = TOKIO_RT.spawn(async move {
-            let tick_every = tokio::time::Duration::from_millis(milliseconds);
-            let mut interval = async_timer::Interval::platform_new(tick_every);
-
-            loop {
-                interval.wait().await;
-                engine.increment_epoch();
-            }
-        });
+    pub fn start_epoch_interval(&self, milliseconds: u64) -> Result<(), Error> {
+        if milliseconds == 0 {
+            return Err(Error::new(
+                arg_error(),
+                "epoch interval must be at least 1 millisecond",
+            ));
+        }
 
-        *self.timer_task.lock().unwrap() = Some(handle);
+        self.state.lock().unwrap().start_timer(milliseconds)
     }
 
     /// @yard
@@ -140,31 +396,59 @@ impl Engine {
     /// @return [nil]
     #[cfg(feature = "tokio")]
     pub fn stop_epoch_interval(&self) {
-        let maybe_handle = self.timer_task.lock().unwrap().take();
+        self.state.lock().unwrap().stop_timer();
+    }
 
-        if let Some(handle) = maybe_handle {
-            handle.abort();
-        }
+    /// @yard
+    /// Manually increment the engine's epoch, e.g. from another Ruby thread
+    /// while WebAssembly runs, since Wasm runs with the Global VM lock (GVL)
+    /// released.
+    /// {#start_epoch_interval} increments it without involving Ruby threads.
+    /// @return [nil]
+    pub fn increment_epoch(&self) -> Result<(), Error> {
+        self.epoch()?.increment();
+        Ok(())
     }
 
     /// @yard
-    /// Manually increment the engine's epoch.
-    /// Note: this cannot be used from a different thread while WebAssembly is
-    /// running because the Global VM lock (GVL) is not released.
-    /// Using {#start_epoch_interval} is recommended because it sidesteps the GVL.
+    /// Closes the engine: stops the timer started with {#start_epoch_interval},
+    /// e.g. before forking, and makes the engine raise when creating modules,
+    /// components, linkers or stores.
+    ///
+    /// This doesn't free the engine's compiled code, allocator or cache while
+    /// modules, components, linkers or stores created with it are alive: each
+    /// keeps a reference to the engine, and keeps working. They're released
+    /// once all of them are garbage collected.
     /// @return [nil]
-    pub fn increment_epoch(&self) {
-        self.inner.increment_epoch();
+    pub fn close(&self) {
+        self.state.lock().unwrap().close();
+    }
+
+    /// @yard
+    /// Whether the engine was closed with {#close} or {Wasmtime.shutdown}.
+    /// @return [Boolean]
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().inner.is_none()
+    }
+
+    /// @yard
+    /// Whether linear memories are initialized from copy-on-write images,
+    /// i.e. whether the engine was created with +memory_init_cow: true+.
+    /// @return [Boolean]
+    pub fn is_memory_init_cow(&self) -> bool {
+        self.memory_init_cow
     }
 
     pub fn is_equal(&self, other: &Engine) -> bool {
-        EngineImpl::same(self.get(), other.get())
+        Arc::ptr_eq(&self.state, &other.state)
     }
 
     /// @yard
     /// AoT compile a WebAssembly text or WebAssembly binary module for later use.
     ///
     /// The compiled module can be instantiated using {Module.deserialize}.
+    /// Engines created with a +target+ compile for that target, e.g. to build
+    /// arm64 artifacts on an x86_64 host.
     ///
     /// @def precompile_module(wat_or_wasm)
     /// @param wat_or_wasm [String] The String of WAT or Wasm.
@@ -173,9 +457,30 @@ impl Engine {
     pub fn precompile_module(&self, wat_or_wasm: RString) -> Result<RString, Error> {
         let (wat_or_wasm, _guard) = wat_or_wasm.as_locked_slice()?;
 
-        nogvl(|| self.inner.precompile_module(wat_or_wasm))
+        let engine = self.get()?;
+        nogvl(|| engine.precompile_module(wat_or_wasm))
+            .map(|bytes| RString::from_slice(&bytes))
+            .map_err(|e| Error::new(compile_error(), e.to_string()))
+    }
+
+    /// @yard
+    /// AoT compile a WebAssembly text or WebAssembly binary component for later use.
+    ///
+    /// The compiled component can be instantiated using
+    /// {Component::Component.deserialize}, e.g. to compile components at build
+    /// time rather than in every worker booting.
+    ///
+    /// @def precompile_component(wat_or_wasm)
+    /// @param wat_or_wasm [String] The String of WAT or Wasm.
+    /// @return [String] Binary String of the compiled component.
+    /// @see Component::Component.deserialize
+    pub fn precompile_component(&self, wat_or_wasm: RString) -> Result<RString, Error> {
+        let (wat_or_wasm, _guard) = wat_or_wasm.as_locked_slice()?;
+
+        let engine = self.get()?;
+        nogvl(|| engine.precompile_component(wat_or_wasm))
             .map(|bytes| RString::from_slice(&bytes))
-            .map_err(|e| error!("{}", e.to_string()))
+            .map_err(|e| Error::new(compile_error(), e.to_string()))
     }
 
     /// @yard
@@ -192,19 +497,36 @@ impl Engine {
         }
 
         let mut hasher = DefaultHasher::new();
-        let engine = rb_self.inner.clone();
+        let engine = Engine::get(&rb_self)?;
         engine.precompile_compatibility_hash().hash(&mut hasher);
         let hex_encoded = format!("{:x}", hasher.finish());
         let key = RString::new(&hex_encoded);
         key.freeze();
 
-        rb_self.ivar_set(ivar_id, key)?;
+        // Engines shared with Ractors are frozen and can't cache the key.
+        if !rb_self.is_frozen() {
+            rb_self.ivar_set(ivar_id, key)?;
+        }
 
         Ok(key)
     }
 
-    pub fn get(&self) -> &EngineImpl {
-        &self.inner
+    /// The Wasmtime engine, raising if the engine is closed.
+    pub fn get(&self) -> Result<EngineImpl, Error> {
+        self.state.lock().unwrap().engine()
+    }
+
+    /// The engine's epoch, raising if the engine is closed.
+    pub fn epoch(&self) -> Result<Epoch, Error> {
+        self.state.lock().unwrap().epoch()
+    }
+
+    pub fn is_async(&self) -> bool {
+        self.async_support
+    }
+
+    pub fn has_epoch_interruption(&self) -> bool {
+        self.epoch_interruption
     }
 }
 
@@ -225,8 +547,15 @@ pub fn init() -> Result<(), Error> {
         method!(Engine::stop_epoch_interval, 0),
     )?;
     class.define_method("increment_epoch", method!(Engine::increment_epoch, 0))?;
+    class.define_method("close", method!(Engine::close, 0))?;
+    class.define_method("closed?", method!(Engine::is_closed, 0))?;
     class.define_method("==", method!(Engine::is_equal, 1))?;
+    class.define_method("memory_init_cow?", method!(Engine::is_memory_init_cow, 0))?;
     class.define_method("precompile_module", method!(Engine::precompile_module, 1))?;
+    class.define_method(
+        "precompile_component",
+        method!(Engine::precompile_component, 1),
+    )?;
     class.define_method(
         "precompile_compatibility_key",
         method!(Engine::precompile_compatibility_key, 0),
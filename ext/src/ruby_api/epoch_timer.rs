@@ -0,0 +1,117 @@
+use super::engine::Epoch;
+use crate::error;
+use lazy_static::lazy_static;
+use magnus::Error;
+use std::collections::BTreeMap;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Instant;
+
+lazy_static! {
+    static ref TIMERS: Mutex<Timers> = Mutex::new(Timers::default());
+    static ref WAKEUP: Condvar = Condvar::new();
+    static ref PARKED: Condvar = Condvar::new();
+}
+
+/// The epoch increments scheduled with [`schedule`], by deadline.
+#[derive(Default)]
+struct Timers {
+    /// The process the timer thread runs in, 0 until it's started.
+    pid: u32,
+    next_id: u64,
+    entries: BTreeMap<(Instant, u64), Epoch>,
+    /// Set by [`before_fork`] for the timer thread to wait, not holding the
+    /// lock, which the forked process inherits.
+    paused: bool,
+    parked: bool,
+}
+
+/// An epoch increment scheduled with [`schedule`], cancelled when dropped.
+pub struct Timer {
+    key: (Instant, u64),
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        TIMERS.lock().unwrap().entries.remove(&self.key);
+    }
+}
+
+/// Increments `epoch` at `deadline`, from a single thread shared by all
+/// engines, e.g. for the store's epoch deadline callback to raise once a time
+/// limit is exceeded.
+pub fn schedule(epoch: Epoch, deadline: Instant) -> Result<Timer, Error> {
+    let mut timers = TIMERS.lock().unwrap();
+    start_thread(&mut timers)?;
+
+    timers.next_id += 1;
+    let key = (deadline, timers.next_id);
+    timers.entries.insert(key, epoch);
+    WAKEUP.notify_all();
+    Ok(Timer { key })
+}
+
+/// Starts the timer thread, unless it runs in this process already.
+fn start_thread(timers: &mut Timers) -> Result<(), Error> {
+    let pid = std::process::id();
+    if timers.pid == pid {
+        return Ok(());
+    }
+
+    thread::Builder::new()
+        .name("wasmtime-epoch-timer".into())
+        .spawn(run)
+        .map_err(|e| error!("Could not start the epoch timer: {}", e))?;
+    timers.pid = pid;
+    timers.parked = false;
+    Ok(())
+}
+
+fn run() {
+    let mut timers = TIMERS.lock().unwrap();
+    loop {
+        if timers.paused {
+            timers.parked = true;
+            PARKED.notify_all();
+            timers = WAKEUP.wait(timers).unwrap();
+            continue;
+        }
+        timers.parked = false;
+
+        let now = Instant::now();
+        timers = match timers.entries.first_key_value() {
+            None => WAKEUP.wait(timers).unwrap(),
+            Some((&(deadline, _), _)) if deadline > now => {
+                WAKEUP.wait_timeout(timers, deadline - now).unwrap().0
+            }
+            Some(_) => {
+                let (_, epoch) = timers.entries.pop_first().unwrap();
+                epoch.increment();
+                timers
+            }
+        };
+    }
+}
+
+/// Pauses the timer thread until [`after_fork`], waiting for it to release
+/// the lock.
+pub fn before_fork() {
+    let mut timers = TIMERS.lock().unwrap();
+    timers.paused = true;
+    WAKEUP.notify_all();
+    while timers.pid == std::process::id() && !timers.parked {
+        timers = PARKED.wait(timers).unwrap();
+    }
+}
+
+/// Resumes the timer thread, starting it again in forked processes, which
+/// don't inherit it, for the timers scheduled before the fork to fire.
+pub fn after_fork() -> Result<(), Error> {
+    let mut timers = TIMERS.lock().unwrap();
+    timers.paused = false;
+    if timers.pid != 0 && !timers.entries.is_empty() {
+        start_thread(&mut timers)?;
+    }
+    WAKEUP.notify_all();
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use crate::ruby_api::root;
-use magnus::{value::Lazy, Error, ExceptionClass, Module, Ruby};
+use magnus::{prelude::*, value::Lazy, Error, ExceptionClass, Ruby, Symbol};
 
 /// Base error class for all Wasmtime errors.
 pub fn base_error() -> ExceptionClass {
@@ -30,6 +30,111 @@ pub fn wasi_exit_error() -> ExceptionClass {
     ruby.get_inner(&ERR)
 }
 
+/// Raised when a call to a Wasm function exceeds its +timeout+.
+pub fn timeout_error() -> ExceptionClass {
+    static ERR: Lazy<ExceptionClass> = Lazy::new(|_| root().const_get("Timeout").unwrap());
+    let ruby = Ruby::get().unwrap();
+    ruby.get_inner(&ERR)
+}
+
+/// Raised when a Wasm call exceeds its store's time limit.
+pub fn deadline_exceeded_error() -> ExceptionClass {
+    static ERR: Lazy<ExceptionClass> = Lazy::new(|_| root().const_get("DeadlineExceeded").unwrap());
+    let ruby = Ruby::get().unwrap();
+    ruby.get_inner(&ERR)
+}
+
+/// Raised when validating an invalid module.
+pub fn validation_error() -> ExceptionClass {
+    static ERR: Lazy<ExceptionClass> = Lazy::new(|_| root().const_get("ValidationError").unwrap());
+    let ruby = Ruby::get().unwrap();
+    ruby.get_inner(&ERR)
+}
+
+/// Raised when a module or a component fails to compile.
+pub fn compile_error() -> ExceptionClass {
+    static ERR: Lazy<ExceptionClass> = Lazy::new(|_| root().const_get("CompileError").unwrap());
+    let ruby = Ruby::get().unwrap();
+    ruby.get_inner(&ERR)
+}
+
+/// Raised when a module's imports can't be satisfied.
+pub fn link_error() -> ExceptionClass {
+    static ERR: Lazy<ExceptionClass> = Lazy::new(|_| root().const_get("LinkError").unwrap());
+    let ruby = Ruby::get().unwrap();
+    ruby.get_inner(&ERR)
+}
+
+/// Raised when a store exceeds the number of instances, tables or memories
+/// it may create.
+pub fn resource_limit_exceeded_error() -> ExceptionClass {
+    static ERR: Lazy<ExceptionClass> =
+        Lazy::new(|_| root().const_get("ResourceLimitExceeded").unwrap());
+    let ruby = Ruby::get().unwrap();
+    ruby.get_inner(&ERR)
+}
+
+/// Raised when an instance doesn't export a function.
+pub fn export_not_found_error() -> ExceptionClass {
+    static ERR: Lazy<ExceptionClass> = Lazy::new(|_| root().const_get("ExportNotFound").unwrap());
+    let ruby = Ruby::get().unwrap();
+    ruby.get_inner(&ERR)
+}
+
+/// Raised when a function's type doesn't match the expected signature.
+pub fn signature_mismatch_error() -> ExceptionClass {
+    static ERR: Lazy<ExceptionClass> =
+        Lazy::new(|_| root().const_get("SignatureMismatch").unwrap());
+    let ruby = Ruby::get().unwrap();
+    ruby.get_inner(&ERR)
+}
+
+/// Converts a Wasmtime error that isn't a trap to the most specific Ruby
+/// error. Wasmtime doesn't expose the kinds of these errors, which are
+/// recognized by their messages.
+pub fn wasmtime_error(error: anyhow::Error) -> Error {
+    let message = error.to_string();
+
+    if let Some(resource) = exceeded_resource(&message) {
+        return resource_limit_exceeded_error()
+            .new_instance((message, Symbol::new(resource)))
+            .map_or_else(|e| e, Into::into);
+    }
+
+    if message.starts_with("unknown import: ")
+        || message.starts_with("incompatible import type for ")
+        || (message.starts_with("expected ") && message.contains(" imports, found "))
+    {
+        let (import_module, import_name) = import_path(&message).unzip();
+        return link_error()
+            .new_instance((message, import_module, import_name))
+            .map_or_else(|e| e, Into::into);
+    }
+
+    Error::new(base_error(), message)
+}
+
+/// Parses the resource out of e.g. "resource limit exceeded: instance count
+/// too high at 10".
+fn exceeded_resource(message: &str) -> Option<&'static str> {
+    let message = message.strip_prefix("resource limit exceeded: ")?;
+    match message.split(' ').next()? {
+        "instance" => Some("instances"),
+        "table" => Some("tables"),
+        "memory" => Some("memories"),
+        _ => None,
+    }
+}
+
+/// Parses the import out of e.g. "unknown import: `env::log` has not been
+/// defined".
+fn import_path(message: &str) -> Option<(String, String)> {
+    let (_, rest) = message.split_once('`')?;
+    let (path, _) = rest.split_once('`')?;
+    let (module, name) = path.split_once("::")?;
+    Some((module.to_string(), name.to_string()))
+}
+
 #[macro_export]
 macro_rules! err {
     ($($arg:expr),*) => {
@@ -69,6 +174,13 @@ pub fn init() -> Result<(), Error> {
     let _ = result_error();
     let _ = conversion_error();
     let _ = wasi_exit_error();
+    let _ = timeout_error();
+    let _ = deadline_exceeded_error();
+    let _ = validation_error();
+    let _ = compile_error();
+    let _ = link_error();
+    let _ = resource_limit_exceeded_error();
+    let _ = export_not_found_error();
 
     Ok(())
 }
@@ -1,8 +1,8 @@
 use super::{
     convert::WrapWasmtimeType, func::Func, global::Global, memory::Memory, root,
-    store::StoreContextValue, table::Table,
+    shared_memory::SharedMemory, store::StoreContextValue, table::Table,
 };
-use crate::{conversion_err, not_implemented};
+use crate::conversion_err;
 use magnus::{
     class, gc::Marker, method, prelude::*, rb_sys::AsRawValue, typed_data::Obj, DataTypeFunctions,
     Error, Module, RClass, Ruby, TypedData, Value,
@@ -24,6 +24,7 @@ pub enum Extern<'a> {
     Func(Obj<Func<'a>>),
     Global(Obj<Global<'a>>),
     Memory(Obj<Memory<'a>>),
+    SharedMemory(Obj<SharedMemory>),
     Table(Obj<Table<'a>>),
 }
 
@@ -33,6 +34,7 @@ impl DataTypeFunctions for Extern<'_> {
             Extern::Func(f) => marker.mark(*f),
             Extern::Global(g) => marker.mark(*g),
             Extern::Memory(m) => marker.mark(*m),
+            Extern::SharedMemory(m) => marker.mark(*m),
             Extern::Table(t) => marker.mark(*t),
         }
     }
@@ -72,6 +74,17 @@ impl Extern<'_> {
         }
     }
 
+    /// @yard
+    /// Returns the exported shared memory or raises a `{ConversionError}` when the export is not a
+    /// shared memory.
+    /// @return [SharedMemory] The exported shared memory.
+    pub fn to_shared_memory(ruby: &Ruby, rb_self: Obj<Self>) -> Result<Value, Error> {
+        match *rb_self {
+            Extern::SharedMemory(m) => Ok(m.as_value()),
+            _ => conversion_err!(Self::inner_class(rb_self), SharedMemory::class(ruby)),
+        }
+    }
+
     /// @yard
     /// Returns the exported table or raises a `{ConversionError}` when the export is not a table.
     /// @return [Table] The exported table.
@@ -87,6 +100,7 @@ impl Extern<'_> {
             Extern::Func(f) => f.inspect(),
             Extern::Global(g) => g.inspect(),
             Extern::Memory(m) => m.inspect(),
+            Extern::SharedMemory(m) => m.inspect(),
             Extern::Table(t) => t.inspect(),
         };
 
@@ -102,6 +116,7 @@ impl Extern<'_> {
             Extern::Func(f) => f.class(),
             Extern::Global(g) => g.class(),
             Extern::Memory(m) => m.class(),
+            Extern::SharedMemory(m) => m.class(),
             Extern::Table(t) => t.class(),
         }
     }
@@ -122,7 +137,9 @@ impl<'a> WrapWasmtimeType<'a, Extern<'a>> for wasmtime::Extern {
             wasmtime::Extern::Table(table) => {
                 Ok(Extern::Table(Obj::wrap(Table::from_inner(store, *table))))
             }
-            wasmtime::Extern::SharedMemory(_) => not_implemented!("shared memory not supported"),
+            wasmtime::Extern::SharedMemory(mem) => Ok(Extern::SharedMemory(Obj::wrap(
+                SharedMemory::from_inner(mem.clone()),
+            ))),
         }
     }
 }
@@ -133,6 +150,7 @@ pub fn init() -> Result<(), Error> {
     class.define_method("to_func", method!(Extern::to_func, 0))?;
     class.define_method("to_global", method!(Extern::to_global, 0))?;
     class.define_method("to_memory", method!(Extern::to_memory, 0))?;
+    class.define_method("to_shared_memory", method!(Extern::to_shared_memory, 0))?;
     class.define_method("to_table", method!(Extern::to_table, 0))?;
     class.define_method("inspect", method!(Extern::inspect, 0))?;
 
@@ -1,17 +1,65 @@
 use super::{
     convert::{ToRubyValue, ToSym, ToValTypeVec, ToWasmVal},
+    engine::Epoch,
     errors::result_error,
     params::Params,
     root,
-    store::{Store, StoreContextValue, StoreData},
+    store::{CallTimeout, HostBlockHandle, Store, StoreContextValue, StoreData},
+    typed_func::TypedFunc,
 };
-use crate::Caller;
+use crate::{
+    define_rb_intern, err,
+    helpers::{block_on, is_polling, nogvl, nogvl_interruptible, with_gvl, SymbolEnum},
+    Caller,
+};
+use lazy_static::lazy_static;
 use magnus::{
-    block::Proc, class, function, gc::Marker, method, prelude::*, scan_args::scan_args,
-    typed_data::Obj, value::Opaque, DataTypeFunctions, Error, IntoValue, Object, RArray, Ruby,
-    TypedData, Value,
+    block::Proc,
+    class,
+    exception::{self, arg_error},
+    function,
+    gc::Marker,
+    method,
+    prelude::*,
+    scan_args::{get_kwargs, scan_args},
+    typed_data::Obj,
+    value::Opaque,
+    DataTypeFunctions, Error, IntoValue, Object, RArray, RClass, Ruby, TypedData, Value,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use wasmtime::{
+    AsContext, Caller as CallerImpl, Func as FuncImpl, FuncType as FuncTypeImpl, StoreContext, Val,
+    ValType,
 };
-use wasmtime::{Caller as CallerImpl, Func as FuncImpl, Val};
+use wasmtime_wasi::I32Exit;
+
+define_rb_intern!(
+    TIMEOUT => "timeout",
+    RESULTS => "results",
+    AUTO => "auto",
+    ARRAY => "array",
+);
+
+/// How [`Func::call`] returns results, see its +results+ keyword argument.
+#[derive(Clone, Copy)]
+enum ResultShape {
+    Auto,
+    Array,
+}
+
+lazy_static! {
+    static ref RESULT_SHAPE_MAPPING: SymbolEnum<'static, ResultShape> = {
+        let mapping = vec![(*AUTO, ResultShape::Auto), (*ARRAY, ResultShape::Array)];
+
+        SymbolEnum::new(":results", mapping)
+    };
+}
 
 /// @yard
 /// @rename Wasmtime::Func
@@ -28,6 +76,8 @@ use wasmtime::{Caller as CallerImpl, Func as FuncImpl, Val};
 pub struct Func<'a> {
     store: StoreContextValue<'a>,
     inner: FuncImpl,
+    /// Keeps the block of a function created by [`Func::new`] alive.
+    host_block: Option<HostBlockHandle>,
 }
 
 impl DataTypeFunctions for Func<'_> {
@@ -44,6 +94,10 @@ impl<'a> Func<'a> {
     /// valid WebAssembly type represented as a symbol. The valid symbols are:
     /// +:i32+, +:i64+, +:f32+, +:f64+, +:v128+, +:funcref+, +:externref+.
     ///
+    /// The {Store} keeps the block alive while the returned {Func} (or a
+    /// {TypedFunc} of it) is, and for the rest of its lifetime once the
+    /// function is handed to Wasm, e.g. as an import or in a table.
+    ///
     /// @def new(store, params, results, &block)
     /// @param store [Store]
     /// @param params [Array<Symbol>] The function's parameters.
@@ -75,8 +129,9 @@ impl<'a> Func<'a> {
         let args = scan_args::<(Obj<Store>, RArray, RArray), (), (), (), (), Proc>(args)?;
         let (store, params, results) = args.required;
         let callable = args.block;
+        store.check_thread()?;
 
-        store.retain(callable.as_value());
+        let host_block = store.retain_host_block(callable.as_value());
 
         let context = store.context_mut();
         let ty = wasmtime::FuncType::new(params.to_val_type_vec()?, results.to_val_type_vec()?);
@@ -86,11 +141,16 @@ impl<'a> Func<'a> {
         Ok(Self {
             store: store.into(),
             inner,
+            host_block: Some(host_block),
         })
     }
 
     pub fn from_inner(store: StoreContextValue<'a>, inner: FuncImpl) -> Self {
-        Self { store, inner }
+        Self {
+            store,
+            inner,
+            host_block: None,
+        }
     }
 
     pub fn get(&self) -> FuncImpl {
@@ -98,15 +158,54 @@ impl<'a> Func<'a> {
         self.inner
     }
 
+    /// Returns the function to hand to Wasm, which may call it for the
+    /// lifetime of the store: its block, if any, is no longer released once
+    /// its Ruby objects are garbage collected.
+    pub fn escape(&self) -> FuncImpl {
+        if let Some(host_block) = &self.host_block {
+            host_block.escape();
+        }
+        self.inner
+    }
+
     /// @yard
     /// Calls a Wasm function.
     ///
-    /// @def call(*args)
+    /// The GVL is released while the Wasm function runs, allowing other Ruby
+    /// threads to make progress. It is re-acquired whenever a host function
+    /// (a {Func} defined in Ruby) is called.
+    ///
+    /// When the {Engine} is created with +epoch_interruption: true+, Ruby
+    /// interrupts (such as Ctrl-C, +Thread#raise+ or +Thread#kill+) make the
+    /// Wasm function trap so that Ruby can handle them. The interrupt bumps
+    /// the engine's epoch once, which counts as a tick towards the epoch
+    /// deadlines of the engine's other stores, without interrupting their
+    /// calls. Without epoch interruption, interrupts are only handled once the
+    /// function returns.
+    ///
+    /// When +timeout+ is given, the call is interrupted once it has run for
+    /// +timeout+ seconds of wall-clock time, raising {Timeout}. This relies on
+    /// epoch interruption: the {Engine} must be created with
+    /// +epoch_interruption: true+. The store's epoch deadline doesn't apply
+    /// during the call, and is kept for later calls. The engine's epoch is
+    /// incremented when the timeout elapses, which counts as a tick towards
+    /// the epoch deadlines of the engine's other stores. Time spent in
+    /// host functions counts towards the timeout, but host functions themselves
+    /// are not interrupted.
+    ///
+    /// @def call(*args, timeout: nil, results: :auto)
     /// @param args [Object]
     ///   The arguments to send to the Wasm function. Raises if the arguments do
     ///   not conform to the Wasm function's parameters.
+    /// @param timeout [Numeric, nil] The maximum duration of the call, in seconds.
+    /// @param results [Symbol, Class] How results are returned: +:auto+
+    ///   depending on the function's results arity, +:array+ for an +Array+
+    ///   whatever the arity, or a class (e.g. a +Struct+) instantiated with the
+    ///   results as positional arguments.
     ///
-    /// @return [nil, Object, Array<Object>] The return type depends on the function's results arity:
+    /// @raise [Timeout] when the call exceeds +timeout+.
+    /// @return [nil, Object, Array<Object>] With +results: :auto+, the return
+    ///   type depends on the function's results arity:
     ///   * 0 => +nil+
     ///   * 1 => +Object+
     ///   * > 1 => +Array<Object>+
@@ -116,8 +215,51 @@ impl<'a> Func<'a> {
     ///     [arg1.succ, arg2.succ]
     ///   end
     ///   func.call(1, 2) # => [2, 3]
+    ///
+    /// @example Bounding the duration of a call:
+    ///   engine = Wasmtime::Engine.new(epoch_interruption: true)
+    ///   # ...
+    ///   instance.export("run").to_func.call(timeout: 0.5)
+    ///
+    /// @example Naming results:
+    ///   DivMod = Struct.new(:quotient, :remainder)
+    ///   instance.export("divmod").to_func.call(7, 2, results: DivMod)
+    ///   # => #<struct DivMod quotient=3, remainder=1>
     pub fn call(&self, args: &[Value]) -> Result<Value, Error> {
-        Self::invoke(&self.store, &self.inner, args)
+        let args = scan_args::<(), (), RArray, (), _, ()>(args)?;
+        let kw = get_kwargs::<_, (), (Option<f64>, Option<Value>), ()>(
+            args.keywords,
+            &[],
+            &[*TIMEOUT, *RESULTS],
+        )?;
+        let params = args.splat.to_vec::<Value>()?;
+        let (shape, class) = match kw.optional.1 {
+            None => (ResultShape::Auto, None),
+            Some(results) => match RClass::from_value(results) {
+                Some(class) => (ResultShape::Array, Some(class)),
+                None => (RESULT_SHAPE_MAPPING.get(results)?, None),
+            },
+        };
+
+        let result = match kw.optional.0 {
+            None => Self::invoke(&self.store, &self.inner, &params),
+            Some(timeout) => with_timeout(&self.store, timeout, || {
+                Self::invoke(&self.store, &self.inner, &params)
+            }),
+        }?;
+
+        let result = match shape {
+            ResultShape::Auto => return Ok(result),
+            ResultShape::Array => {
+                let len = self.inner.ty(self.store.context()?).results().len();
+                results_array(result, len)
+            }
+        };
+        match class {
+            // SAFETY: the array is not mutated while instantiating the class.
+            Some(class) => class.new_instance(unsafe { result.as_slice() }),
+            None => Ok(result.as_value()),
+        }
     }
 
     pub fn inner(&self) -> &FuncImpl {
@@ -148,36 +290,319 @@ impl<'a> Func<'a> {
         Ok(results)
     }
 
+    /// @yard
+    /// @return [FuncType] The function's type, with its parameter and result types.
+    pub fn type_(&self) -> Result<FuncType, Error> {
+        Ok(FuncType::from_inner(self.inner.ty(self.store.context()?)))
+    }
+
+    /// @yard
+    /// Returns a {TypedFunc} for this function, checking once that the
+    /// function's type matches +params+ and +results+. Calling the returned
+    /// {TypedFunc} skips the per-call type lookup done by {#call}.
+    ///
+    /// @def typed(params, results)
+    /// @param params [Array<Symbol>] The expected parameter types.
+    /// @param results [Array<Symbol>] The expected result types.
+    /// @return [TypedFunc]
+    /// @raise [Error] if the function's type doesn't match.
+    ///
+    /// @example
+    ///   add = instance.export("add").to_func.typed([:i32, :i32], [:i32])
+    ///   add.call(1, 2) # => 3
+    pub fn typed(&self, params: RArray, results: RArray) -> Result<TypedFunc<'a>, Error> {
+        TypedFunc::new(
+            self.store,
+            self.inner,
+            self.host_block.clone(),
+            params,
+            results,
+        )
+    }
+
     pub fn invoke(
         store: &StoreContextValue,
         func: &wasmtime::Func,
         args: &[Value],
     ) -> Result<Value, Error> {
-        let mut context = store.context_mut()?;
-        let func_ty = func.ty(&mut context);
-        let params = Params::new(&func_ty, args)?.to_vec()?;
+        let func_ty = func.ty(store.context()?);
+        Self::invoke_with_type(store, func, &func_ty, args)
+    }
+
+    pub fn invoke_with_type(
+        store: &StoreContextValue,
+        func: &wasmtime::Func,
+        func_ty: &FuncTypeImpl,
+        args: &[Value],
+    ) -> Result<Value, Error> {
+        let _lock = store.lock()?;
+        let params = Params::new(func_ty, args)?.to_vec()?;
         let mut results = vec![Val::null(); func_ty.results().len()];
 
-        func.call(context, &params, &mut results)
-            .map_err(|e| store.handle_wasm_error(e))?;
+        Self::call_vals(store, func, &params, &mut results)
+    }
+
+    /// Like [`Self::invoke_with_type`], for functions of a type for which
+    /// [`is_primitive_type`] holds: the params and results are converted in
+    /// buffers on the stack, without allocating.
+    pub fn invoke_primitive(
+        store: &StoreContextValue,
+        func: &wasmtime::Func,
+        func_ty: &FuncTypeImpl,
+        args: &[Value],
+    ) -> Result<Value, Error> {
+        let _lock = store.lock()?;
+        let mut params: [Val; PRIMITIVE_FUNC_MAX_PARAMS] = std::array::from_fn(|_| Val::I32(0));
+        let mut results: [Val; PRIMITIVE_FUNC_MAX_RESULTS] = std::array::from_fn(|_| Val::I32(0));
+        let params = &mut params[..func_ty.params().len()];
+        Params::new(func_ty, args)?.write_to(params)?;
+
+        Self::call_vals(store, func, params, &mut results[..func_ty.results().len()])
+    }
+
+    /// Calls `func` with converted `params`, with the store locked.
+    fn call_vals(
+        store: &StoreContextValue,
+        func: &wasmtime::Func,
+        params: &[Val],
+        results: &mut [Val],
+    ) -> Result<Value, Error> {
+        let mut context = store.context_mut()?;
+
+        // The guest may hold on to externrefs (e.g. by storing them in a
+        // table), so they must outlive this call.
+        for param in params.iter() {
+            context.data_mut().retain_externref(param);
+        }
+
+        let result = if context.data().is_async() {
+            if let StoreContextValue::Caller(_) = store {
+                return err!("calling Wasm from a host function is not supported in async engines");
+            }
+            block_on(func.call_async(context, params, results))
+        } else if context.data().has_epoch_interruption() {
+            // Lets Ruby interrupt (e.g. on Ctrl-C) a guest stuck in a loop.
+            let interrupter = EpochInterrupter::new(context.as_context());
+            nogvl_interruptible(
+                || func.call(context, params, results),
+                || interrupter.interrupt(),
+            )?
+        } else {
+            nogvl(|| func.call(context, params, results))
+        };
+        if let Err(error) = result {
+            // Nested calls (from a host function) must unwind up to the host.
+            let returns_exit_code = matches!(store, StoreContextValue::Store(_))
+                && store.context()?.data().returns_exit_code();
+            if returns_exit_code && matches!(error.downcast_ref::<I32Exit>(), Some(I32Exit(0))) {
+                return Ok(0.into_value());
+            }
+            return Err(store.handle_wasm_error(error));
+        }
 
-        match results.as_slice() {
-            [] => Ok(().into_value()),
-            [result] => result.to_ruby_value(store),
-            _ => {
-                let array = RArray::with_capacity(results.len());
-                for result in results {
-                    array.push(result.to_ruby_value(store)?)?;
+        results_to_ruby(store, results)
+    }
+
+    /// Calls `func` once per element of `batch`, with the GVL released for
+    /// the whole batch rather than for each call.
+    pub fn invoke_batch_with_type(
+        store: &StoreContextValue,
+        func: &wasmtime::Func,
+        func_ty: &FuncTypeImpl,
+        batch: RArray,
+    ) -> Result<RArray, Error> {
+        let _lock = store.lock()?;
+        let mut context = store.context_mut()?;
+        let mut calls = Vec::with_capacity(batch.len());
+        for args in batch.each() {
+            let args = RArray::try_convert(args?)?.to_vec::<Value>()?;
+            let params = Params::new(func_ty, &args)?.to_vec()?;
+            for param in params.iter() {
+                context.data_mut().retain_externref(param);
+            }
+            let results = vec![Val::null(); func_ty.results().len()];
+            calls.push((params, results));
+        }
+
+        let result = if context.data().is_async() {
+            if let StoreContextValue::Caller(_) = store {
+                return err!("calling Wasm from a host function is not supported in async engines");
+            }
+            block_on(async {
+                for (params, results) in calls.iter_mut() {
+                    func.call_async(&mut context, params, results).await?;
+                }
+                Ok::<_, anyhow::Error>(())
+            })
+        } else {
+            let interrupter = context
+                .data()
+                .has_epoch_interruption()
+                .then(|| EpochInterrupter::new(context.as_context()));
+            let call_all = || {
+                for (params, results) in calls.iter_mut() {
+                    func.call(&mut context, params, results)?;
                 }
-                Ok(array.as_value())
+                Ok::<_, anyhow::Error>(())
+            };
+
+            match interrupter {
+                Some(interrupter) => nogvl_interruptible(call_all, || interrupter.interrupt())?,
+                None => nogvl(call_all),
+            }
+        };
+        result.map_err(|error| store.handle_wasm_error(error))?;
+
+        let array = RArray::with_capacity(calls.len());
+        for (_, results) in calls {
+            array.push(results_to_ruby(store, &results)?)?;
+        }
+        Ok(array)
+    }
+
+    /// @yard
+    /// Calls the Wasm function once for each +Array+ of arguments in
+    /// +batch+, converting all the arguments up front and releasing the GVL
+    /// once for the whole batch. For small functions called in a loop, this
+    /// avoids most of the cost of crossing from Ruby to Wasm on each call.
+    ///
+    /// The batch stops at the first call that traps, raising its error:
+    /// results of the previous calls are lost, but their side effects (e.g.
+    /// on memory) are not undone.
+    ///
+    /// @def call_batch(batch)
+    /// @param batch [Array<Array<Object>>] The arguments of each call.
+    /// @return [Array<nil, Object, Array<Object>>] The results of each call,
+    ///   in the same format as {#call}.
+    ///
+    /// @example
+    ///   add = instance.export("add").to_func
+    ///   add.call_batch([[1, 2], [3, 4]]) # => [3, 7]
+    pub fn call_batch(&self, batch: RArray) -> Result<RArray, Error> {
+        let func_ty = self.inner.ty(self.store.context()?);
+        Self::invoke_batch_with_type(&self.store, &self.inner, &func_ty, batch)
+    }
+}
+
+/// Wraps the result of a function with `len` results, as returned by
+/// [`results_to_ruby`], in an +Array+.
+fn results_array(result: Value, len: usize) -> RArray {
+    match (len, RArray::from_value(result)) {
+        (0, _) => RArray::new(),
+        (1, _) | (_, None) => RArray::from_slice(&[result]),
+        (_, Some(array)) => array,
+    }
+}
+
+/// Converts the results of a call to the value returned to Ruby: +nil+,
+/// the single result, or an +Array+ of results.
+fn results_to_ruby(store: &StoreContextValue, results: &[Val]) -> Result<Value, Error> {
+    match results {
+        [] => Ok(().into_value()),
+        [result] => result.to_ruby_value(store),
+        _ => {
+            let array = RArray::with_capacity(results.len());
+            for result in results {
+                array.push(result.to_ruby_value(store)?)?;
             }
+            Ok(array.as_value())
+        }
+    }
+}
+
+/// @yard
+/// @rename Wasmtime::FuncType
+/// Represents the type of a WebAssembly function.
+/// @see https://docs.rs/wasmtime/latest/wasmtime/struct.FuncType.html Wasmtime's Rust doc
+#[magnus::wrap(class = "Wasmtime::FuncType", free_immediately, frozen_shareable)]
+pub struct FuncType {
+    inner: FuncTypeImpl,
+}
+
+impl FuncType {
+    pub fn from_inner(inner: FuncTypeImpl) -> Self {
+        Self { inner }
+    }
+
+    /// @yard
+    /// @return [Array<Symbol>] The function's parameter types.
+    pub fn params(&self) -> RArray {
+        self.inner.params().map(ToSym::to_sym).collect()
+    }
+
+    /// @yard
+    /// @return [Array<Symbol>] The function's result types.
+    pub fn results(&self) -> RArray {
+        self.inner.results().map(ToSym::to_sym).collect()
+    }
+}
+
+/// Runs `call` with a timeout, checked by the store's epoch deadline
+/// callback, which the epoch timer calls once `timeout` seconds elapsed.
+fn with_timeout(
+    store: &StoreContextValue,
+    timeout: f64,
+    call: impl FnOnce() -> Result<Value, Error>,
+) -> Result<Value, Error> {
+    let timeout = Duration::try_from_secs_f64(timeout)
+        .map_err(|e| Error::new(arg_error(), format!("invalid timeout: {e}")))?;
+
+    let previous = {
+        let mut context = store.context_mut()?;
+        if !context.data().has_epoch_interruption() {
+            return err!("timeout requires an engine with epoch_interruption: true");
+        }
+        let call_timeout = CallTimeout::new(context.data().epoch(), timeout)?;
+        context.set_epoch_deadline(1);
+        context.data_mut().replace_call_timeout(Some(call_timeout))
+    };
+
+    let result = call();
+    store
+        .context_mut()?
+        .data_mut()
+        .replace_call_timeout(previous);
+    result
+}
+
+/// Interrupts a Wasm call by flagging its store and incrementing the
+/// engine's epoch once, for the store's epoch deadline callback to trap. The
+/// engine's other stores see a single tick.
+struct EpochInterrupter {
+    epoch: Epoch,
+    started: AtomicBool,
+    requested: Arc<AtomicBool>,
+}
+
+impl EpochInterrupter {
+    fn new(context: StoreContext<'_, StoreData>) -> Self {
+        Self {
+            epoch: context.data().epoch().clone(),
+            started: AtomicBool::new(false),
+            requested: context.data().interrupt_requested(),
+        }
+    }
+
+    fn interrupt(&self) {
+        if self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        self.requested.store(true, Ordering::SeqCst);
+        self.epoch.increment();
+    }
+}
+
+impl Drop for EpochInterrupter {
+    fn drop(&mut self) {
+        if self.started.load(Ordering::SeqCst) {
+            self.requested.store(false, Ordering::SeqCst);
         }
     }
 }
 
 impl From<&Func<'_>> for wasmtime::Extern {
     fn from(func: &Func) -> Self {
-        Self::Func(func.get())
+        Self::Func(func.escape())
     }
 }
 
@@ -196,97 +621,237 @@ macro_rules! result_error {
     }};
 }
 
+/// Functions with at most this many params and results, see
+/// [`is_primitive_type`].
+const PRIMITIVE_FUNC_MAX_PARAMS: usize = 8;
+const PRIMITIVE_FUNC_MAX_RESULTS: usize = 8;
+
+/// Whether functions of type `ty` can be called with their params and results
+/// in buffers on the stack: they're all numbers, and there are few of them.
+pub fn is_primitive_type(ty: &wasmtime::FuncType) -> bool {
+    let is_number = |ty: ValType| {
+        matches!(
+            ty,
+            ValType::I32 | ValType::I64 | ValType::F32 | ValType::F64
+        )
+    };
+
+    ty.params().len() <= PRIMITIVE_FUNC_MAX_PARAMS
+        && ty.results().len() <= PRIMITIVE_FUNC_MAX_RESULTS
+        && ty.params().all(is_number)
+        && ty.results().all(is_number)
+}
+
+/// Whether the host function of type `ty` can be called without allocating
+/// an +Array+ of arguments: its type is primitive, and the block takes the
+/// caller and each param.
+fn is_primitive_func(ty: &wasmtime::FuncType, callable: Proc) -> bool {
+    is_primitive_type(ty) && callable.arity() == ty.params().len() as i64 + 1
+}
+
 pub fn make_func_closure(
     ty: &wasmtime::FuncType,
     callable: Opaque<Proc>,
 ) -> impl Fn(CallerImpl<'_, StoreData>, &[Val], &mut [Val]) -> anyhow::Result<()> + Send + Sync + 'static
 {
     let ty = ty.to_owned();
+    let primitive = is_primitive_func(&ty, Ruby::get().unwrap().get_inner(callable));
 
     // The error handling here is a bit tricky. We want to return a Ruby exception,
     // but doing so directly can easily cause an early Ruby GC and segfault. So to
     // be safe, we store all Ruby errors on the store context so it can be marked.
     // We then return a generic error here. The caller will check for a stored error
     // and raise it if it exists.
+    //
+    // Wasm runs without the GVL (see `Func::invoke_with_type`), so it must be
+    // re-acquired before calling into Ruby.
     move |caller_impl: CallerImpl<'_, StoreData>, params: &[Val], results: &mut [Val]| {
-        let wrapped_caller = Obj::wrap(Caller::new(caller_impl));
-        let store_context = StoreContextValue::from(wrapped_caller);
-
-        let rparams = RArray::with_capacity(params.len() + 1);
-        rparams.push(wrapped_caller.as_value()).unwrap();
+        // Ruby can't run on the stacks async Wasm runs on: only functions
+        // deferred by `Linker#func_new_async` can call into Ruby.
+        if is_polling() {
+            return Err(anyhow::anyhow!(
+                "host functions of async engines must be defined with Linker#func_new_async"
+            ));
+        }
 
-        for (i, param) in params.iter().enumerate() {
-            let rparam = param
-                .to_ruby_value(&store_context)
-                .map_err(|e| anyhow::anyhow!(format!("invalid argument at index {i}: {e}")))?;
-            rparams.push(rparam).unwrap();
+        if primitive {
+            return with_gvl(|| call_primitive_func(&ty, callable, caller_impl, params, results));
         }
 
-        let ruby = Ruby::get().unwrap();
-        let callable = ruby.get_inner(callable);
+        with_gvl(|| {
+            let wrapped_caller = Obj::wrap(Caller::new(caller_impl));
+            let store_context = StoreContextValue::from(wrapped_caller);
+
+            let rparams = RArray::with_capacity(params.len() + 1);
+            rparams.push(wrapped_caller.as_value()).unwrap();
 
-        match (callable.call(rparams), results.len()) {
-            (Ok(_proc_result), 0) => {
-                wrapped_caller.expire();
-                Ok(())
+            for (i, param) in params.iter().enumerate() {
+                let rparam = param
+                    .to_ruby_value(&store_context)
+                    .map_err(|e| anyhow::anyhow!(format!("invalid argument at index {i}: {e}")))?;
+                rparams.push(rparam).unwrap();
             }
-            (Ok(proc_result), n) => {
-                // For len=1, accept both `val` and `[val]`
-                let Ok(proc_result) = RArray::to_ary(proc_result) else {
-                    return result_error!(
-                        store_context,
-                        wrapped_caller,
-                        format!("could not convert {} to results array", callable)
-                    );
-                };
-
-                if proc_result.len() != results.len() {
-                    return result_error!(
-                        store_context,
-                        wrapped_caller,
-                        format!(
-                            "wrong number of results (given {}, expected {}) in {}",
-                            proc_result.len(),
-                            n,
-                            callable
-                        )
-                    );
-                }
 
-                for (i, ((rb_val, wasm_val), ty)) in unsafe { proc_result.as_slice() }
-                    .iter()
-                    .zip(results.iter_mut())
-                    .zip(ty.results())
-                    .enumerate()
-                {
-                    match rb_val.to_wasm_val(ty) {
-                        Ok(val) => *wasm_val = val,
-                        Err(e) => {
-                            return result_error!(
-                                store_context,
-                                wrapped_caller,
-                                format!("invalid result at index {i}: {e} in {callable}")
-                            );
-                        }
-                    }
-                }
+            let ruby = Ruby::get().unwrap();
+            let callable = ruby.get_inner(callable);
 
-                wrapped_caller.expire();
-                Ok(())
-            }
-            (Err(e), _) => {
-                caller_error!(store_context, wrapped_caller, e)
+            let proc_result = callable
+                .call::<_, Value>(rparams)
+                .or_else(|e| rescue_host_error(&store_context, &ty, e));
+            set_results(
+                &store_context,
+                wrapped_caller,
+                &ty,
+                callable,
+                proc_result,
+                results,
+            )
+        })
+    }
+}
+
+/// Converts the value returned by the host function `callable` of type `ty`
+/// to its `results`, accepting a single result with or without an +Array+.
+///
+/// Ruby errors are stored on the store context to be raised by the caller,
+/// rather than returned directly, which could easily cause an early Ruby GC
+/// and segfault.
+fn set_results(
+    store_context: &StoreContextValue,
+    wrapped_caller: Obj<Caller<'_>>,
+    ty: &wasmtime::FuncType,
+    callable: Proc,
+    proc_result: Result<Value, Error>,
+    results: &mut [Val],
+) -> anyhow::Result<()> {
+    let proc_result = match (proc_result, results.len()) {
+        (Ok(_), 0) => {
+            wrapped_caller.expire();
+            return Ok(());
+        }
+        (Ok(proc_result), _) => proc_result,
+        (Err(e), _) => return caller_error!(store_context, wrapped_caller, e),
+    };
+
+    // For len=1, accept both `val` and `[val]`
+    let Ok(proc_result) = RArray::to_ary(proc_result) else {
+        return result_error!(
+            store_context,
+            wrapped_caller,
+            format!("could not convert {} to results array", callable)
+        );
+    };
+
+    if proc_result.len() != results.len() {
+        return result_error!(
+            store_context,
+            wrapped_caller,
+            format!(
+                "wrong number of results (given {}, expected {}) in {}",
+                proc_result.len(),
+                results.len(),
+                callable
+            )
+        );
+    }
+
+    for (i, ((rb_val, wasm_val), ty)) in unsafe { proc_result.as_slice() }
+        .iter()
+        .zip(results.iter_mut())
+        .zip(ty.results())
+        .enumerate()
+    {
+        match rb_val.to_wasm_val(ty) {
+            Ok(val) => *wasm_val = val,
+            Err(e) => {
+                return result_error!(
+                    store_context,
+                    wrapped_caller,
+                    format!("invalid result at index {i}: {e} in {callable}")
+                );
             }
         }
+
+        if let Err(e) = store_context.retain_externref(wasm_val) {
+            return caller_error!(store_context, wrapped_caller, e);
+        }
     }
+
+    wrapped_caller.expire();
+    Ok(())
+}
+
+/// Converts the exception raised by a host function of type `ty` to its
+/// results with the store's [`Store::on_host_error`] block, if any.
+fn rescue_host_error(
+    store: &StoreContextValue,
+    ty: &wasmtime::FuncType,
+    error: Error,
+) -> Result<Value, Error> {
+    let handler = match store.context()?.data().on_host_error() {
+        Some(handler) if error.is_kind_of(exception::standard_error()) => handler,
+        _ => return Err(error),
+    };
+    let Some(raised) = error.value() else {
+        return Err(error);
+    };
+
+    let results: RArray = ty.results().map(ToSym::to_sym).collect();
+    Ruby::get()
+        .unwrap()
+        .get_inner(handler)
+        .call((raised, results))
+}
+
+/// Calls a host function for which [`is_primitive_func`] holds, passing the
+/// arguments on the stack. Results are converted like in [`make_func_closure`].
+fn call_primitive_func(
+    ty: &wasmtime::FuncType,
+    callable: Opaque<Proc>,
+    caller_impl: CallerImpl<'_, StoreData>,
+    params: &[Val],
+    results: &mut [Val],
+) -> anyhow::Result<()> {
+    let ruby = Ruby::get().unwrap();
+    let wrapped_caller = Obj::wrap(Caller::new(caller_impl));
+    let store_context = StoreContextValue::from(wrapped_caller);
+    let callable = ruby.get_inner(callable);
+
+    let mut args = [ruby.qnil().as_value(); PRIMITIVE_FUNC_MAX_PARAMS + 1];
+    args[0] = wrapped_caller.as_value();
+    for (i, (arg, param)) in args[1..].iter_mut().zip(params).enumerate() {
+        *arg = param
+            .to_ruby_value(&store_context)
+            .map_err(|e| anyhow::anyhow!(format!("invalid argument at index {i}: {e}")))?;
+    }
+
+    let proc_result = callable
+        .call::<_, Value>(&args[..=params.len()])
+        .or_else(|e| rescue_host_error(&store_context, ty, e));
+    set_results(
+        &store_context,
+        wrapped_caller,
+        ty,
+        callable,
+        proc_result,
+        results,
+    )
 }
 
 pub fn init() -> Result<(), Error> {
     let func = root().define_class("Func", class::object())?;
     func.define_singleton_method("new", function!(Func::new, -1))?;
     func.define_method("call", method!(Func::call, -1))?;
+    func.define_method("call_batch", method!(Func::call_batch, 1))?;
     func.define_method("params", method!(Func::params, 0))?;
     func.define_method("results", method!(Func::results, 0))?;
+    func.define_method("type", method!(Func::type_, 0))?;
+    func.define_alias("signature", "type")?;
+    func.define_method("typed", method!(Func::typed, 2))?;
+
+    let func_type = root().define_class("FuncType", class::object())?;
+    func_type.define_method("params", method!(FuncType::params, 0))?;
+    func_type.define_method("results", method!(FuncType::results, 0))?;
 
     Ok(())
 }
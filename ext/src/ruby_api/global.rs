@@ -3,12 +3,16 @@ use super::{
     root,
     store::{Store, StoreContextValue},
 };
-use crate::error;
+use crate::{define_rb_intern, error};
 use magnus::{
-    class, function, gc::Marker, method, prelude::*, typed_data::Obj, DataTypeFunctions, Error,
-    Object, Symbol, TypedData, Value,
+    class, function, gc::Marker, method, prelude::*, scan_args, typed_data::Obj, DataTypeFunctions,
+    Error, Object, Symbol, TypedData, Value,
 };
-use wasmtime::{Extern, Global as GlobalImpl, GlobalType, Mutability};
+use wasmtime::{Extern, Global as GlobalImpl, GlobalType as GlobalTypeImpl, Mutability};
+
+define_rb_intern!(
+    MUTABLE => "mutable",
+);
 
 /// @yard
 /// @rename Wasmtime::Global
@@ -28,6 +32,28 @@ impl DataTypeFunctions for Global<'_> {
 }
 
 impl<'a> Global<'a> {
+    /// @yard
+    /// @def new(store, type, default, mutable: false)
+    /// @param store [Store]
+    /// @param type [Symbol] The WebAssembly type of the value held by this global.
+    /// @param default [Object] The default value of this global.
+    /// @param mutable [Boolean] Whether the global can be changed with {#set}.
+    /// @return [Global] A variable global when +mutable+ is true, a constant global otherwise.
+    /// @see .const
+    /// @see .var
+    pub fn new(args: &[Value]) -> Result<Self, Error> {
+        let args = scan_args::scan_args::<(Obj<Store>, Symbol, Value), (), (), (), _, ()>(args)?;
+        let kw =
+            scan_args::get_kwargs::<_, (), (Option<bool>,), ()>(args.keywords, &[], &[*MUTABLE])?;
+        let (store, value_type, default) = args.required;
+        let mutability = match kw.optional.0.unwrap_or(false) {
+            true => Mutability::Var,
+            false => Mutability::Const,
+        };
+
+        Self::with_mutability(store, value_type, default, mutability)
+    }
+
     /// @yard
     /// @def const(store, type, default)
     /// @param store [Store]
@@ -35,7 +61,7 @@ impl<'a> Global<'a> {
     /// @param default [Object] The default value of this global.
     /// @return [Global] A constant global.
     pub fn const_(store: Obj<Store>, value_type: Symbol, default: Value) -> Result<Self, Error> {
-        Self::new(store, value_type, default, Mutability::Const)
+        Self::with_mutability(store, value_type, default, Mutability::Const)
     }
 
     /// @yard
@@ -45,21 +71,22 @@ impl<'a> Global<'a> {
     /// @param default [Object] The default value of this global.
     /// @return [Global] A variable global.
     pub fn var(store: Obj<Store>, value_type: Symbol, default: Value) -> Result<Self, Error> {
-        Self::new(store, value_type, default, Mutability::Var)
+        Self::with_mutability(store, value_type, default, Mutability::Var)
     }
 
-    fn new(
+    fn with_mutability(
         store: Obj<Store>,
         value_type: Symbol,
         default: Value,
         mutability: Mutability,
     ) -> Result<Self, Error> {
+        store.check_thread()?;
         let wasm_type = value_type.to_val_type()?;
         let wasm_default = default.to_wasm_val(wasm_type.clone())?;
         let inner = GlobalImpl::new(
             store.context_mut(),
-            GlobalType::new(wasm_type, mutability),
-            wasm_default,
+            GlobalTypeImpl::new(wasm_type, mutability),
+            wasm_default.clone(),
         )
         .map_err(|e| error!("{}", e))?;
 
@@ -68,7 +95,7 @@ impl<'a> Global<'a> {
             inner,
         };
 
-        global.retain_non_nil_extern_ref(default)?;
+        global.store.retain_externref(&wasm_default)?;
 
         Ok(global)
     }
@@ -112,19 +139,14 @@ impl<'a> Global<'a> {
     /// @param value [Object] An object that can be converted to the global's type.
     /// @return [nil]
     pub fn set(&self, value: Value) -> Result<(), Error> {
+        let value = value.to_wasm_val(self.value_type()?)?;
         self.inner
-            .set(
-                self.store.context_mut()?,
-                value.to_wasm_val(self.value_type()?)?,
-            )
-            .map_err(|e| error!("{}", e))
-            .and_then(|result| {
-                self.retain_non_nil_extern_ref(value)?;
-                Ok(result)
-            })
-    }
-
-    fn ty(&self) -> Result<GlobalType, Error> {
+            .set(self.store.context_mut()?, value.clone())
+            .map_err(|e| error!("{}", e))?;
+        self.store.retain_externref(&value)
+    }
+
+    fn ty(&self) -> Result<GlobalTypeImpl, Error> {
         Ok(self.inner.ty(self.store.context()?))
     }
 
@@ -132,18 +154,47 @@ impl<'a> Global<'a> {
         self.ty().map(|ty| ty.content().clone())
     }
 
-    fn retain_non_nil_extern_ref(&self, value: Value) -> Result<(), Error> {
-        if wasmtime::ValType::ExternRef == self.value_type()? && !value.is_nil() {
-            self.store.retain(value)?;
-        }
-        Ok(())
-    }
-
     pub fn inner(&self) -> GlobalImpl {
         self.inner
     }
 }
 
+/// @yard
+/// @rename Wasmtime::GlobalType
+/// Represents the type of a WebAssembly global.
+/// @see https://docs.rs/wasmtime/latest/wasmtime/struct.GlobalType.html Wasmtime's Rust doc
+#[magnus::wrap(class = "Wasmtime::GlobalType", free_immediately, frozen_shareable)]
+pub struct GlobalType {
+    inner: GlobalTypeImpl,
+}
+
+impl GlobalType {
+    pub fn from_inner(inner: GlobalTypeImpl) -> Self {
+        Self { inner }
+    }
+
+    /// @yard
+    /// @def const?
+    /// @return [Boolean]
+    pub fn is_const(&self) -> bool {
+        self.inner.mutability() == Mutability::Const
+    }
+
+    /// @yard
+    /// @def var?
+    /// @return [Boolean]
+    pub fn is_var(&self) -> bool {
+        self.inner.mutability() == Mutability::Var
+    }
+
+    /// @yard
+    /// @def type
+    /// @return [Symbol] The Wasm type of the global‘s content.
+    pub fn type_(&self) -> Symbol {
+        self.inner.content().clone().to_sym()
+    }
+}
+
 impl From<&Global<'_>> for Extern {
     fn from(global: &Global) -> Self {
         Self::Global(global.inner())
@@ -152,6 +203,7 @@ impl From<&Global<'_>> for Extern {
 
 pub fn init() -> Result<(), Error> {
     let class = root().define_class("Global", class::object())?;
+    class.define_singleton_method("new", function!(Global::new, -1))?;
     class.define_singleton_method("var", function!(Global::var, 3))?;
     class.define_singleton_method("const", function!(Global::const_, 3))?;
 
@@ -162,5 +214,10 @@ pub fn init() -> Result<(), Error> {
     class.define_method("get", method!(Global::get, 0))?;
     class.define_method("set", method!(Global::set, 1))?;
 
+    let class = root().define_class("GlobalType", class::object())?;
+    class.define_method("const?", method!(GlobalType::is_const, 0))?;
+    class.define_method("var?", method!(GlobalType::is_var, 0))?;
+    class.define_method("type", method!(GlobalType::type_, 0))?;
+
     Ok(())
 }
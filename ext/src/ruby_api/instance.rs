@@ -1,16 +1,22 @@
 use super::{
     convert::{ToExtern, WrapWasmtimeType},
-    func::Func,
+    errors::export_not_found_error,
+    func::{make_func_closure, Func},
     module::Module,
     root,
     store::{Store, StoreContextValue, StoreData},
 };
-use crate::err;
+use crate::{
+    error,
+    helpers::{block_on, nogvl},
+};
 use magnus::{
-    class, function, gc::Marker, method, prelude::*, scan_args, typed_data::Obj, DataTypeFunctions,
-    Error, Object, RArray, RHash, RString, Ruby, TryConvert, TypedData, Value,
+    block::Proc, class, function, gc::Marker, method, prelude::*, scan_args, typed_data::Obj,
+    DataTypeFunctions, Error, Object, RArray, RHash, RString, Ruby, TryConvert, TypedData, Value,
+};
+use wasmtime::{
+    Extern, ExternType, Instance as InstanceImpl, Module as ModuleImpl, StoreContextMut,
 };
-use wasmtime::{Extern, Instance as InstanceImpl, StoreContextMut};
 
 /// @yard
 /// Represents a WebAssembly instance.
@@ -35,13 +41,25 @@ impl Instance {
     /// @def new(store, mod, imports = [])
     /// @param store [Store] The store to instantiate the module in.
     /// @param mod [Module] The module to instantiate.
-    /// @param imports [Array<Func, Memory>]
+    /// @param imports [Array<Func, Memory, SharedMemory, Table, Global>, Hash{String => Hash{String => Object}}]
     ///   The module's import, in orders that that they show up in the module.
+    ///   Or a +Hash+ of import module names to +Hash+es of import names to
+    ///   imports, in which +Proc+s are wrapped in {Func}s with the signature
+    ///   the module expects (the +Proc+ is called like {Func.new}'s block).
     /// @return [Instance]
+    ///
+    /// @example
+    ///   Wasmtime::Instance.new(store, mod, {
+    ///     "env" => {
+    ///       "log" => ->(_caller, value) { puts value },
+    ///       "memory" => Wasmtime::Memory.new(store, min_size: 1)
+    ///     }
+    ///   })
     pub fn new(ruby: &Ruby, args: &[Value]) -> Result<Self, Error> {
         let args =
             scan_args::scan_args::<(Obj<Store>, &Module), (Option<Value>,), (), (), (), ()>(args)?;
         let (wrapped_store, module) = args.required;
+        let _lock = wrapped_store.lock()?;
         let mut context = wrapped_store.context_mut();
         let imports = args
             .optional
@@ -49,6 +67,12 @@ impl Instance {
             .and_then(|v| if v.is_nil() { None } else { Some(v) });
 
         let imports: Vec<Extern> = match imports {
+            Some(hash) if hash.is_kind_of(class::hash()) => Self::imports_from_hash(
+                ruby,
+                &mut context,
+                module.get(),
+                RHash::try_convert(hash)?,
+            )?,
             Some(arr) => {
                 let arr = RArray::try_convert(arr)?;
                 let mut imports = Vec::with_capacity(arr.len());
@@ -63,20 +87,58 @@ impl Instance {
         };
 
         let module = module.get();
-        let inner = InstanceImpl::new(context, module, &imports)
-            .map_err(|e| StoreContextValue::from(wrapped_store).handle_wasm_error(e))?;
+        let result = if context.data().is_async() {
+            block_on(InstanceImpl::new_async(context, module, &imports))
+        } else {
+            nogvl(|| InstanceImpl::new(context, module, &imports))
+        };
+        let inner =
+            result.map_err(|e| StoreContextValue::from(wrapped_store).handle_wasm_error(e))?;
+
+        Ok(Self::from_inner(wrapped_store, inner))
+    }
+
+    fn imports_from_hash(
+        ruby: &Ruby,
+        context: &mut StoreContextMut<StoreData>,
+        module: &ModuleImpl,
+        hash: RHash,
+    ) -> Result<Vec<Extern>, Error> {
+        let mut imports = Vec::with_capacity(module.imports().len());
+
+        for import in module.imports() {
+            let missing = || error!("missing import {}::{}", import.module(), import.name());
+            let namespace = hash.get(import.module()).ok_or_else(missing)?;
+            let value = RHash::try_convert(namespace)?
+                .get(import.name())
+                .ok_or_else(missing)?;
+            context.data_mut().retain(value);
+
+            let import = match (Proc::from_value(value), import.ty()) {
+                (Some(callable), ExternType::Func(ty)) => {
+                    let func_closure = make_func_closure(&ty, callable.into());
+                    wasmtime::Func::new(&mut *context, ty, func_closure).into()
+                }
+                _ => value.to_extern(ruby)?,
+            };
+            imports.push(import);
+        }
 
-        Ok(Self {
-            inner,
-            store: wrapped_store,
-        })
+        Ok(imports)
     }
 
     pub fn get(&self) -> InstanceImpl {
         self.inner
     }
 
+    /// The store the instance was created in.
+    pub fn store(&self) -> Obj<Store> {
+        self.store
+    }
+
+    /// Wraps an instance just created in `store`.
     pub fn from_inner(store: Obj<Store>, inner: InstanceImpl) -> Self {
+        store.context_mut().data_mut().count_instance();
         Self { inner, store }
     }
 
@@ -87,6 +149,7 @@ impl Instance {
     /// @def exports
     /// @return [Hash{String => Extern}]
     pub fn exports(&self) -> Result<RHash, Error> {
+        self.store.check_thread()?;
         let mut ctx = self.store.context_mut();
         let hash = RHash::new();
 
@@ -109,6 +172,7 @@ impl Instance {
     /// @param name [String]
     /// @return [Extern, nil] The export if it exists, nil otherwise.
     pub fn export(&self, str: RString) -> Result<Option<super::externals::Extern>, Error> {
+        self.store.check_thread()?;
         let export = self
             .inner
             .get_export(self.store.context_mut(), unsafe { str.as_str()? });
@@ -126,6 +190,7 @@ impl Instance {
     /// @param name [String] The name of function  to run.
     /// @param (see Func#call)
     /// @return (see Func#call)
+    /// @raise [ExportNotFound] if the instance doesn't export a function named +name+.
     /// @see Func#call
     pub fn invoke(&self, args: &[Value]) -> Result<Value, Error> {
         let name = RString::try_convert(*args.first().ok_or_else(|| {
@@ -135,22 +200,45 @@ impl Instance {
             )
         })?)?;
 
+        self.store.check_thread()?;
         let func = self.get_func(self.store.context_mut(), unsafe { name.as_str()? })?;
         Func::invoke(&self.store.into(), &func, &args[1..])
     }
 
     fn get_func(
         &self,
-        context: StoreContextMut<'_, StoreData>,
+        mut context: StoreContextMut<'_, StoreData>,
         name: &str,
     ) -> Result<wasmtime::Func, Error> {
         let instance = self.inner;
 
-        if let Some(func) = instance.get_func(context, name) {
-            Ok(func)
-        } else {
-            err!("function \"{}\" not found", name)
+        if let Some(func) = instance.get_func(&mut context, name) {
+            return Ok(func);
         }
+
+        let available: Vec<String> = instance
+            .exports(&mut context)
+            .filter_map(|export| {
+                let name = export.name().to_string();
+                export.into_func().map(|_| name)
+            })
+            .collect();
+        let message = if available.is_empty() {
+            format!(
+                "function \"{}\" not found (no functions are exported)",
+                name
+            )
+        } else {
+            format!(
+                "function \"{}\" not found (exported functions: {})",
+                name,
+                available.join(", ")
+            )
+        };
+
+        Err(export_not_found_error()
+            .new_instance((message, name, available))
+            .map_or_else(|e| e, Into::into))
     }
 }
 
@@ -0,0 +1,76 @@
+use super::{
+    instance::Instance,
+    linker::ensure_wasi_ctx,
+    root,
+    store::{Store, StoreContextValue, StoreData},
+};
+use crate::helpers::{block_on, nogvl};
+use magnus::{
+    class, gc::Marker, method, prelude::*, typed_data::Obj, DataTypeFunctions, Error, TypedData,
+    Value,
+};
+use wasmtime::InstancePre as InstancePreImpl;
+
+/// @yard
+/// A {Module} whose imports were resolved by {Linker#instantiate_pre}, ready
+/// to be instantiated in any number of {Store}s.
+///
+/// In engines created with +memory_init_cow: true+, each instantiation maps
+/// the module's copy-on-write memory image instead of copying its data
+/// segments, making instantiation cost independent of their size.
+/// @see https://docs.rs/wasmtime/latest/wasmtime/struct.InstancePre.html Wasmtime's Rust doc
+#[derive(TypedData)]
+#[magnus(class = "Wasmtime::InstancePre", size, mark, free_immediately)]
+pub struct InstancePre {
+    inner: InstancePreImpl<StoreData>,
+    refs: Vec<Value>,
+    has_wasi: bool,
+}
+
+unsafe impl Send for InstancePre {}
+
+impl DataTypeFunctions for InstancePre {
+    fn mark(&self, marker: &Marker) {
+        marker.mark_slice(self.refs.as_slice());
+    }
+}
+
+impl InstancePre {
+    pub fn new(inner: InstancePreImpl<StoreData>, refs: Vec<Value>, has_wasi: bool) -> Self {
+        Self {
+            inner,
+            refs,
+            has_wasi,
+        }
+    }
+
+    /// @yard
+    /// Instantiates the pre-linked {Module} in a {Store}.
+    /// @def instantiate(store)
+    /// @param store [Store]
+    /// @return [Instance]
+    pub fn instantiate(&self, store: Obj<Store>) -> Result<Instance, Error> {
+        ensure_wasi_ctx(self.has_wasi, &store, "InstancePre#instantiate")?;
+
+        let _lock = store.lock()?;
+        let context = store.context_mut();
+        let result = if context.data().is_async() {
+            block_on(self.inner.instantiate_async(context))
+        } else {
+            nogvl(|| self.inner.instantiate(context))
+        };
+        result
+            .map_err(|e| StoreContextValue::from(store).handle_wasm_error(e))
+            .map(|instance| {
+                self.refs.iter().for_each(|val| store.retain(*val));
+                Instance::from_inner(store, instance)
+            })
+    }
+}
+
+pub fn init() -> Result<(), Error> {
+    let class = root().define_class("InstancePre", class::object())?;
+    class.define_method("instantiate", method!(InstancePre::instantiate, 1))?;
+
+    Ok(())
+}
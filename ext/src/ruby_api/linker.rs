@@ -5,21 +5,29 @@ use super::{
     externals::Extern,
     func::{self, Func},
     instance::Instance,
+    instance_pre::InstancePre,
     module::Module,
     root,
     store::{Store, StoreContextValue, StoreData},
 };
-use crate::{define_rb_intern, err, error};
+use crate::{
+    define_rb_intern, err, error,
+    helpers::{block_on, defer, nogvl},
+};
 use magnus::{
     block::Proc, class, function, gc::Marker, method, prelude::*, scan_args, scan_args::scan_args,
     typed_data::Obj, DataTypeFunctions, Error, Object, RArray, RHash, RString, Ruby, TypedData,
     Value,
 };
-use std::cell::RefCell;
+use std::{
+    cell::{Cell, RefCell, RefMut},
+    sync::Arc,
+};
 use wasmtime::Linker as LinkerImpl;
 
 define_rb_intern!(
     WASI=> "wasi",
+    WASI_NN => "wasi_nn",
 );
 
 /// @yard
@@ -28,8 +36,11 @@ define_rb_intern!(
 #[magnus(class = "Wasmtime::Linker", size, mark, free_immediately)]
 pub struct Linker {
     inner: RefCell<LinkerImpl<StoreData>>,
+    /// Incremented when items are defined, see [`Linker::module`].
+    generation: Cell<u64>,
     refs: RefCell<Vec<Value>>,
     has_wasi: bool,
+    async_support: bool,
 }
 
 unsafe impl Send for Linker {}
@@ -42,34 +53,64 @@ impl DataTypeFunctions for Linker {
 
 impl Linker {
     /// @yard
-    /// @def new(engine, wasi: false)
+    /// @def new(engine, wasi: false, wasi_nn: false)
     /// @param engine [Engine]
     /// @param wasi [Boolean] Whether WASI should be defined in this Linker. Defaults to false.
+    /// @param wasi_nn [Boolean] Whether wasi-nn, for machine learning
+    ///   inference, should be defined in this Linker. Its backends and graphs
+    ///   are configured by the Store's +wasi_nn_ctx+ (see {WasiNnCtxBuilder}).
+    ///   Requires the gem to be built with the +wasi-nn+ feature.
     /// @return [Linker]
     pub fn new(args: &[Value]) -> Result<Self, Error> {
         let args = scan_args::scan_args::<(&Engine,), (), (), (), _, ()>(args)?;
-        let kw = scan_args::get_kwargs::<_, (), (Option<bool>,), ()>(args.keywords, &[], &[*WASI])?;
+        let kw = scan_args::get_kwargs::<_, (), (Option<bool>, Option<bool>), ()>(
+            args.keywords,
+            &[],
+            &[*WASI, *WASI_NN],
+        )?;
         let (engine,) = args.required;
         let wasi = kw.optional.0.unwrap_or(false);
+        let wasi_nn = kw.optional.1.unwrap_or(false);
 
-        let mut inner: LinkerImpl<StoreData> = LinkerImpl::new(engine.get());
+        let mut inner: LinkerImpl<StoreData> = LinkerImpl::new(&engine.get()?);
         if wasi {
-            wasmtime_wasi::add_to_linker(&mut inner, |s| s.wasi_ctx_mut())
-                .map_err(|e| error!("{}", e))?
+            // Async engines can only call async host functions. Their WASI
+            // calls block the thread polling the Wasm, which doesn't hold the
+            // GVL (see `block_on`).
+            let result = if engine.is_async() {
+                wasmtime_wasi::tokio::add_to_linker(&mut inner, |s| s.wasi_ctx_mut())
+            } else {
+                wasmtime_wasi::add_to_linker(&mut inner, |s| s.wasi_ctx_mut())
+            };
+            result.map_err(|e| error!("{}", e))?
+        }
+        if wasi_nn {
+            #[cfg(feature = "wasi-nn")]
+            super::wasi_nn::add_to_linker(&mut inner, engine.is_async())?;
+            #[cfg(not(feature = "wasi-nn"))]
+            return err!("wasi_nn: true requires the gem to be built with the wasi-nn feature");
         }
         Ok(Self {
             inner: RefCell::new(inner),
+            generation: Default::default(),
             refs: Default::default(),
             has_wasi: wasi,
+            async_support: engine.is_async(),
         })
     }
 
+    /// Borrows the linker to define items in it.
+    fn inner_mut(&self) -> RefMut<'_, LinkerImpl<StoreData>> {
+        self.generation.set(self.generation.get() + 1);
+        self.inner.borrow_mut()
+    }
+
     /// @yard
     /// Allow shadowing.
     /// @def allow_shadowing=(val)
     /// @param val [Boolean]
     pub fn set_allow_shadowing(&self, val: bool) {
-        self.inner.borrow_mut().allow_shadowing(val);
+        self.inner_mut().allow_shadowing(val);
     }
 
     /// @yard
@@ -77,7 +118,7 @@ impl Linker {
     /// @def allow_unknown_exports=(val)
     /// @param val [Boolean]
     pub fn set_allow_unknown_exports(&self, val: bool) {
-        self.inner.borrow_mut().allow_unknown_exports(val);
+        self.inner_mut().allow_unknown_exports(val);
     }
 
     /// @yard
@@ -86,19 +127,31 @@ impl Linker {
     /// @param mod [Module]
     /// @return [void]
     pub fn define_unknown_imports_as_traps(&self, module: &Module) -> Result<(), Error> {
-        self.inner
-            .borrow_mut()
+        self.inner_mut()
             .define_unknown_imports_as_traps(module.get())
             .map_err(|e| error!("{}", e))
     }
 
+    /// @yard
+    /// Define unknown (unresolved) function imports as functions which
+    /// do nothing and return the default value of their results (zero, or
+    /// +nil+ for references).
+    /// @def define_unknown_imports_as_default_values(mod)
+    /// @param mod [Module]
+    /// @return [void]
+    pub fn define_unknown_imports_as_default_values(&self, module: &Module) -> Result<(), Error> {
+        self.inner_mut()
+            .define_unknown_imports_as_default_values(module.get())
+            .map_err(|e| error!("{}", e))
+    }
+
     /// @yard
     /// Define an item in this linker.
     /// @def define(store, mod, name, item)
     /// @param store [Store]
     /// @param mod [String] Module name
     /// @param name [String] Import name
-    /// @param item [Func, Memory] The item to define.
+    /// @param item [Func, Memory, SharedMemory, Table, Global] The item to define.
     /// @return [void]
     pub fn define(
         ruby: &Ruby,
@@ -145,8 +198,7 @@ impl Linker {
 
         self.refs.borrow_mut().push(callable.as_value());
 
-        self.inner
-            .borrow_mut()
+        self.inner_mut()
             .func_new(
                 unsafe { module.as_str() }?,
                 unsafe { name.as_str() }?,
@@ -157,6 +209,59 @@ impl Linker {
             .map(|_| ())
     }
 
+    /// @yard
+    /// Define a function in this linker whose implementation can switch
+    /// Fibers, e.g. to wait for non-blocking IO through a +Fiber.scheduler+.
+    /// Requires an {Engine} created with +async_support: true+, in which
+    /// host functions must be defined with this method.
+    ///
+    /// While the block runs, the calling Wasm is suspended, and other Fibers
+    /// may call Wasm, in other {Store}s. Calling Wasm from the block (e.g.
+    /// through the {Caller}) is not supported.
+    ///
+    /// @def func_new_async(mod, name, params, results, &block)
+    /// @param mod [String] Module name
+    /// @param name [String] Import name
+    /// @param params [Array<Symbol>] The function's parameters.
+    /// @param results [Array<Symbol>] The function's results.
+    /// @param block [Block] See {Func.new} for block argument details.
+    /// @return [void]
+    /// @see Func.new
+    ///
+    /// @example
+    ///   engine = Wasmtime::Engine.new(async_support: true)
+    ///   linker = Wasmtime::Linker.new(engine)
+    ///   linker.func_new_async("env", "fetch", [:i32], [:i32]) do |_caller, id|
+    ///     # Other Fibers run while waiting for the response.
+    ///     http.get("/items/#{id}").status
+    ///   end
+    pub fn func_new_async(&self, args: &[Value]) -> Result<(), Error> {
+        if !self.async_support {
+            return err!("Linker#func_new_async requires an Engine with async_support: true");
+        }
+
+        let args = scan_args::<(RString, RString, RArray, RArray), (), (), (), RHash, Proc>(args)?;
+        let (module, name, params, results) = args.required;
+        let callable = args.block;
+        let ty = wasmtime::FuncType::new(params.to_val_type_vec()?, results.to_val_type_vec()?);
+        let func_closure = Arc::new(func::make_func_closure(&ty, callable.into()));
+
+        self.refs.borrow_mut().push(callable.as_value());
+
+        self.inner_mut()
+            .func_new_async(
+                unsafe { module.as_str() }?,
+                unsafe { name.as_str() }?,
+                ty,
+                move |caller, params, results| {
+                    let func_closure = func_closure.clone();
+                    Box::new(defer(move || func_closure(caller, params, results)))
+                },
+            )
+            .map_err(|e| error!("{}", e))
+            .map(|_| ())
+    }
+
     /// @yard
     /// Looks up a previously defined item in this linker.
     ///
@@ -185,21 +290,31 @@ impl Linker {
     }
 
     /// @yard
-    /// Defines an entire {Instance} in this linker.
+    /// Defines an entire {Instance} in this linker: its exports become
+    /// importable under +mod+ by the modules instantiated later in +store+.
+    ///
+    /// @example Linking an application module against a libc module
+    ///   libc = linker.instantiate(store, libc_module)
+    ///   linker.instance(store, "libc", libc)
+    ///   app = linker.instantiate(store, app_module)
     ///
     /// @def instance(store, mod, instance)
-    /// @param store [Store]
+    /// @param store [Store] The store +instance+ was created in.
     /// @param mod [String] Module name
     /// @param instance [Instance]
     /// @return [void]
     pub fn instance(
         &self,
-        store: &Store,
+        store: Obj<Store>,
         module: RString,
         instance: &Instance,
     ) -> Result<(), Error> {
-        self.inner
-            .borrow_mut()
+        if instance.store().as_raw() != store.as_raw() {
+            return err!("Linker#instance: the instance belongs to a different store");
+        }
+        store.check_thread()?;
+
+        self.inner_mut()
             .instance(
                 store.context_mut(),
                 unsafe { module.as_str() }?,
@@ -210,19 +325,45 @@ impl Linker {
     }
 
     /// @yard
-    /// Defines automatic instantiation of a {Module} in this linker.
+    /// Defines the exports of a {Module} in this linker under +name+,
+    /// following the WASI application ABI like Wasmtime's Rust API:
+    ///
+    /// - Commands (modules exporting +_start+) are instantiated anew each
+    ///   time one of their exports is called, as commands expect to run once
+    ///   per instance.
+    /// - Reactors (other modules) are instantiated once, in +store+, calling
+    ///   their +_initialize+ export if any. Their exports can then be used by
+    ///   modules instantiated later in the same store, e.g. a language runtime
+    ///   shared by user code.
     ///
     /// @def module(store, name, mod)
     /// @param store [Store]
     /// @param name [String] Module name
     /// @param mod [Module]
     /// @return [void]
-    pub fn module(&self, store: &Store, name: RString, module: &Module) -> Result<(), Error> {
-        self.inner
-            .borrow_mut()
-            .module(store.context_mut(), unsafe { name.as_str()? }, module.get())
-            .map(|_| ())
-            .map_err(|e| error!("{}", e))
+    pub fn module(&self, store: Obj<Store>, name: RString, module: &Module) -> Result<(), Error> {
+        ensure_wasi_ctx(self.has_wasi, &store, "Linker#module")?;
+
+        let name = name.to_string()?;
+        // Defined in a copy, not to borrow the linker while other threads may
+        // use it, as reactors are instantiated with the GVL released.
+        let mut inner = self.inner.borrow().clone();
+        let generation = self.generation.get();
+        let _lock = store.lock()?;
+        let context = store.context_mut();
+        let result = if context.data().is_async() {
+            block_on(inner.module_async(context, &name, module.get())).map(|_| ())
+        } else {
+            nogvl(|| inner.module(context, &name, module.get()).map(|_| ()))
+        };
+        result.map_err(|e| StoreContextValue::from(store).handle_wasm_error(e))?;
+
+        if self.generation.get() != generation {
+            return err!("Linker was changed by another thread during Linker#module");
+        }
+        *self.inner_mut() = inner;
+        self.refs.borrow().iter().for_each(|val| store.retain(*val));
+        Ok(())
     }
 
     /// @yard
@@ -241,8 +382,7 @@ impl Linker {
         as_module: RString,
         as_name: RString,
     ) -> Result<(), Error> {
-        self.inner
-            .borrow_mut()
+        self.inner_mut()
             .alias(
                 unsafe { module.as_str() }?,
                 unsafe { name.as_str() }?,
@@ -256,13 +396,12 @@ impl Linker {
     /// @yard
     /// Aliases one module’s name as another.
     ///
-    /// @def alias(mod, as_mod)
+    /// @def alias_module(mod, as_mod)
     /// @param mod [String] Source module name
     /// @param as_mod [String] Destination module name
     /// @return [void]
     pub fn alias_module(&self, module: RString, as_module: RString) -> Result<(), Error> {
-        self.inner
-            .borrow_mut()
+        self.inner_mut()
             .alias_module(unsafe { module.as_str() }?, unsafe { as_module.as_str() }?)
             .map_err(|e| error!("{}", e))
             .map(|_| ())
@@ -275,19 +414,23 @@ impl Linker {
     /// @param mod [Module]
     /// @return [Instance]
     pub fn instantiate(&self, store: Obj<Store>, module: &Module) -> Result<Instance, Error> {
-        if self.has_wasi && !store.context().data().has_wasi_ctx() {
-            return err!(
-                "Store is missing WASI configuration.\n\n\
-                When using `wasi: true`, the Store given to\n\
-                `Linker#instantiate` must have a WASI configuration.\n\
-                To fix this, provide the `wasi_ctx` when creating the Store:\n\
-                    Wasmtime::Store.new(engine, wasi_ctx: WasiCtxBuilder.new)"
-            );
-        }
+        ensure_wasi_ctx(self.has_wasi, &store, "Linker#instantiate")?;
 
-        self.inner
-            .borrow_mut()
-            .instantiate(store.context_mut(), module.get())
+        // Resolved with the GVL held, not to borrow the linker while other
+        // threads may define items in it.
+        let pre = self
+            .inner
+            .borrow()
+            .instantiate_pre(module.get())
+            .map_err(|e| StoreContextValue::from(store).handle_wasm_error(e))?;
+        let _lock = store.lock()?;
+        let context = store.context_mut();
+        let result = if context.data().is_async() {
+            block_on(pre.instantiate_async(context))
+        } else {
+            nogvl(|| pre.instantiate(context))
+        };
+        result
             .map_err(|e| StoreContextValue::from(store).handle_wasm_error(e))
             .map(|instance| {
                 self.refs.borrow().iter().for_each(|val| store.retain(*val));
@@ -295,6 +438,25 @@ impl Linker {
             })
     }
 
+    /// @yard
+    /// Resolves a {Module}'s imports against the items defined in this linker
+    /// once, so that the module can then be instantiated repeatedly through
+    /// {InstancePre#instantiate} without redoing the resolution.
+    ///
+    /// Items defined in the linker after this call are not seen by the
+    /// returned {InstancePre}.
+    ///
+    /// @def instantiate_pre(mod)
+    /// @param mod [Module]
+    /// @return [InstancePre]
+    pub fn instantiate_pre(&self, module: &Module) -> Result<InstancePre, Error> {
+        self.inner
+            .borrow()
+            .instantiate_pre(module.get())
+            .map(|inner| InstancePre::new(inner, self.refs.borrow().clone(), self.has_wasi))
+            .map_err(|e| error!("{}", e))
+    }
+
     /// @yard
     /// Returns the “default export” of a module.
     /// @def get_default(store, mod)
@@ -310,6 +472,21 @@ impl Linker {
     }
 }
 
+pub fn ensure_wasi_ctx(has_wasi: bool, store: &Store, method: &str) -> Result<(), Error> {
+    if has_wasi && !store.context().data().has_wasi_ctx() {
+        return err!(
+            "Store is missing WASI configuration.\n\n\
+            When using `wasi: true`, the Store given to\n\
+            `{}` must have a WASI configuration.\n\
+            To fix this, provide the `wasi_ctx` when creating the Store:\n\
+                Wasmtime::Store.new(engine, wasi_ctx: WasiCtxBuilder.new)",
+            method
+        );
+    }
+
+    Ok(())
+}
+
 pub fn init() -> Result<(), Error> {
     let class = root().define_class("Linker", class::object())?;
     class.define_singleton_method("new", function!(Linker::new, -1))?;
@@ -322,14 +499,20 @@ pub fn init() -> Result<(), Error> {
         "define_unknown_imports_as_traps",
         method!(Linker::define_unknown_imports_as_traps, 1),
     )?;
+    class.define_method(
+        "define_unknown_imports_as_default_values",
+        method!(Linker::define_unknown_imports_as_default_values, 1),
+    )?;
     class.define_method("define", method!(Linker::define, 4))?;
     class.define_method("func_new", method!(Linker::func_new, -1))?;
+    class.define_method("func_new_async", method!(Linker::func_new_async, -1))?;
     class.define_method("get", method!(Linker::get, 3))?;
     class.define_method("instance", method!(Linker::instance, 3))?;
     class.define_method("module", method!(Linker::module, 3))?;
     class.define_method("alias", method!(Linker::alias, 4))?;
     class.define_method("alias_module", method!(Linker::alias_module, 2))?;
     class.define_method("instantiate", method!(Linker::instantiate, 2))?;
+    class.define_method("instantiate_pre", method!(Linker::instantiate_pre, 1))?;
     class.define_method("get_default", method!(Linker::get_default, 2))?;
 
     Ok(())
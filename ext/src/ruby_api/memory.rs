@@ -5,21 +5,66 @@ use super::{
     root,
     store::{Store, StoreContextValue},
 };
-use crate::{define_rb_intern, error};
+use crate::{define_rb_intern, err, error, helpers::SymbolEnum};
+use lazy_static::lazy_static;
 use magnus::{
     class, function, gc::Marker, method, r_string::RString, scan_args, typed_data::Obj,
     DataTypeFunctions, Error, Module as _, Object, Ruby, TypedData, Value,
 };
 
+#[cfg(ruby_gte_3_1)]
+use magnus::{
+    rb_sys::{protect, AsRawValue},
+    value::ReprValue,
+};
 use rb_sys::tracking_allocator::ManuallyTracked;
-use wasmtime::{Extern, Memory as MemoryImpl};
+#[cfg(ruby_gte_3_1)]
+use std::ffi::c_void;
+use wasmtime::{Extern, Memory as MemoryImpl, MemoryType as MemoryTypeImpl};
 use wasmtime_environ::WASM_PAGE_SIZE;
 
 define_rb_intern!(
     MIN_SIZE => "min_size",
     MAX_SIZE => "max_size",
+    MEMORY64 => "memory64",
+    INVALID => "invalid",
+    MAX => "max",
+    RAISE => "raise",
+    REPLACE => "replace",
 );
 
+#[cfg(ruby_gte_3_1)]
+extern "C" {
+    /// Named `rb_io_buffer_get_immutable` before Ruby 3.2.
+    #[cfg_attr(not(ruby_gte_3_2), link_name = "rb_io_buffer_get_immutable")]
+    fn rb_io_buffer_get_bytes_for_reading(
+        buffer: rb_sys::VALUE,
+        base: *mut *const c_void,
+        size: *mut usize,
+    );
+}
+
+/// How string readers handle invalid encodings.
+#[derive(Clone, Copy)]
+enum Invalid {
+    Raise,
+    Replace,
+}
+
+lazy_static! {
+    static ref INVALID_MAPPING: SymbolEnum<'static, Invalid> = {
+        let mapping = vec![(*RAISE, Invalid::Raise), (*REPLACE, Invalid::Replace)];
+
+        SymbolEnum::new(":invalid", mapping)
+    };
+}
+
+impl Invalid {
+    fn get(value: Option<Value>) -> Result<Self, Error> {
+        value.map_or(Ok(Self::Raise), |value| INVALID_MAPPING.get(value))
+    }
+}
+
 /// @yard
 /// @rename Wasmtime::Memory
 /// Represents a WebAssembly memory.
@@ -40,22 +85,34 @@ unsafe impl Send for Memory<'_> {}
 
 impl<'a> Memory<'a> {
     /// @yard
-    /// @def new(store, min_size:, max_size: nil)
+    /// @def new(store, min_size:, max_size: nil, memory64: false)
     /// @param store [Store]
     /// @param min_size [Integer] The minimum memory pages.
     /// @param max_size [Integer, nil] The maximum memory pages.
+    /// @param memory64 [Boolean] Whether the memory is indexed with 64-bit
+    ///   addresses, allowing more than 4GiB of memory. Requires the {Engine}
+    ///   to be created with +wasm_memory64: true+.
     pub fn new(args: &[Value]) -> Result<Self, Error> {
         let args = scan_args::scan_args::<(Obj<Store>,), (), (), (), _, ()>(args)?;
-        let kw = scan_args::get_kwargs::<_, (u32,), (Option<u32>,), ()>(
+        let kw = scan_args::get_kwargs::<_, (u64,), (Option<u64>, Option<bool>), ()>(
             args.keywords,
             &[*MIN_SIZE],
-            &[*MAX_SIZE],
+            &[*MAX_SIZE, *MEMORY64],
         )?;
         let (store,) = args.required;
+        store.check_thread()?;
         let (min,) = kw.required;
-        let (max,) = kw.optional;
+        let (max, memory64) = kw.optional;
 
-        let memtype = wasmtime::MemoryType::new(min, max);
+        let memtype = if memory64.unwrap_or(false) {
+            MemoryTypeImpl::new64(min, max)
+        } else {
+            let to_u32 = |pages: u64| {
+                u32::try_from(pages)
+                    .map_err(|_| error!("{} pages is too large for a 32-bit memory", pages))
+            };
+            MemoryTypeImpl::new(to_u32(min)?, max.map(to_u32).transpose()?)
+        };
 
         let inner = MemoryImpl::new(store.context_mut(), memtype).map_err(|e| error!("{}", e))?;
         let memsize = inner.data_size(store.context_mut());
@@ -93,6 +150,13 @@ impl<'a> Memory<'a> {
             .maximum())
     }
 
+    /// @yard
+    /// @return [MemoryType] The memory's type, with its limits.
+    pub fn type_(&self) -> Result<MemoryType, Error> {
+        let ty = self.get_wasmtime_memory().ty(self.store.context()?);
+        Ok(MemoryType::from_inner(ty))
+    }
+
     /// @yard
     /// Read +size+ bytes starting at +offset+. Result is a ASCII-8BIT encoded string.
     ///
@@ -112,18 +176,94 @@ impl<'a> Memory<'a> {
     /// @yard
     /// Read +size+ bytes starting at +offset+. Result is a UTF-8 encoded string.
     ///
-    /// @def read_utf8(offset, size)
+    /// @def read_utf8(offset, size, invalid: :raise)
     /// @param offset [Integer]
     /// @param size [Integer]
+    /// @param invalid [Symbol] +:raise+ to raise on invalid UTF-8, +:replace+
+    ///   to replace invalid bytes with U+FFFD.
     /// @return [String] UTF-8 +String+ of the memory.
-    pub fn read_utf8(&self, offset: usize, size: usize) -> Result<RString, Error> {
-        self.get_wasmtime_memory()
-            .data(self.store.context()?)
+    pub fn read_utf8(&self, args: &[Value]) -> Result<RString, Error> {
+        let args = scan_args::scan_args::<(usize, usize), (), (), (), _, ()>(args)?;
+        let kw =
+            scan_args::get_kwargs::<_, (), (Option<Value>,), ()>(args.keywords, &[], &[*INVALID])?;
+        let (offset, size) = args.required;
+        let invalid = Invalid::get(kw.optional.0)?;
+
+        utf8_string(self.slice(offset, size)?, invalid)
+    }
+
+    /// @yard
+    /// Read +size+ bytes of UTF-16LE starting at +offset+, as used by C# and
+    /// JavaScript toolchains. Result is transcoded to a UTF-8 string.
+    ///
+    /// @def read_utf16le(offset, size, invalid: :raise)
+    /// @param offset [Integer]
+    /// @param size [Integer] The size in bytes, twice the number of code units.
+    /// @param invalid [Symbol] +:raise+ to raise on unpaired surrogates,
+    ///   +:replace+ to replace them with U+FFFD.
+    /// @return [String] UTF-8 +String+ of the memory.
+    pub fn read_utf16le(&self, args: &[Value]) -> Result<RString, Error> {
+        let args = scan_args::scan_args::<(usize, usize), (), (), (), _, ()>(args)?;
+        let kw =
+            scan_args::get_kwargs::<_, (), (Option<Value>,), ()>(args.keywords, &[], &[*INVALID])?;
+        let (offset, size) = args.required;
+        let invalid = Invalid::get(kw.optional.0)?;
+
+        if size % 2 != 0 {
+            return err!("UTF-16 size must be even, got {}", size);
+        }
+        let units = self
+            .slice(offset, size)?
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]));
+        let decoded = char::decode_utf16(units);
+        let string = match invalid {
+            Invalid::Raise => decoded
+                .collect::<Result<String, _>>()
+                .map_err(|e| error!("invalid utf-16: {}", e))?,
+            Invalid::Replace => decoded
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect(),
+        };
+        Ok(RString::new(&string))
+    }
+
+    /// @yard
+    /// Read a NUL-terminated string starting at +offset+, as used by C
+    /// toolchains. Result is a UTF-8 encoded string, without the NUL.
+    ///
+    /// @def read_cstring(offset, max:, invalid: :raise)
+    /// @param offset [Integer]
+    /// @param max [Integer] The maximum number of bytes to scan for the NUL,
+    ///   raising when none is found.
+    /// @param invalid [Symbol] +:raise+ to raise on invalid UTF-8, +:replace+
+    ///   to replace invalid bytes with U+FFFD.
+    /// @return [String] UTF-8 +String+ of the memory.
+    pub fn read_cstring(&self, args: &[Value]) -> Result<RString, Error> {
+        let args = scan_args::scan_args::<(usize,), (), (), (), _, ()>(args)?;
+        let kw = scan_args::get_kwargs::<_, (usize,), (Option<Value>,), ()>(
+            args.keywords,
+            &[*MAX],
+            &[*INVALID],
+        )?;
+        let (offset,) = args.required;
+        let (max,) = kw.required;
+        let invalid = Invalid::get(kw.optional.0)?;
+
+        let data = self
+            .data()?
             .get(offset..)
-            .and_then(|s| s.get(..size))
-            .ok_or_else(|| error!("out of bounds memory access"))
-            .and_then(|s| std::str::from_utf8(s).map_err(|e| error!("{}", e)))
-            .map(RString::new)
+            .ok_or_else(|| error!("out of bounds memory access"))?;
+        let scanned = &data[..max.min(data.len())];
+        let len = scanned.iter().position(|byte| *byte == 0).ok_or_else(|| {
+            if scanned.len() < max {
+                error!("out of bounds memory access")
+            } else {
+                error!("no NUL terminator within {} bytes", max)
+            }
+        })?;
+
+        utf8_string(&scanned[..len], invalid)
     }
 
     /// @yard
@@ -161,6 +301,79 @@ impl<'a> Memory<'a> {
         )?))
     }
 
+    /// @yard
+    /// Get the whole memory as a read-only +IO::Buffer+, without copying it.
+    /// As with {#read_unsafe_slice}, the buffer must not be used after the
+    /// memory is resized.
+    ///
+    /// @def to_io_buffer
+    /// @return [IO::Buffer] Read-only buffer of the memory.
+    #[cfg(ruby_gte_3_1)]
+    pub fn to_io_buffer(rb_self: Obj<Self>) -> Result<Value, Error> {
+        let size = rb_self.data_size()?;
+        UnsafeSlice::to_io_buffer(Obj::wrap(UnsafeSlice::new(rb_self, 0..size)?))
+    }
+
+    /// @yard
+    /// Write the contents of +buffer+ starting at +offset+, copying them only
+    /// once.
+    ///
+    /// @def write_from(buffer, offset)
+    /// @param buffer [IO::Buffer]
+    /// @param offset [Integer]
+    /// @return [void]
+    /// @raise [Error] if the contents don't fit in the memory.
+    #[cfg(ruby_gte_3_1)]
+    pub fn write_from(&self, buffer: Value, offset: usize) -> Result<(), Error> {
+        let mut base = std::ptr::null();
+        let mut size = 0;
+        // Raises if `buffer` is not a valid IO::Buffer.
+        protect(|| {
+            unsafe { rb_io_buffer_get_bytes_for_reading(buffer.as_raw(), &mut base, &mut size) };
+            Ruby::get().unwrap().qnil().as_raw()
+        })?;
+
+        let memory = self.get_wasmtime_memory();
+        let mut context = self.store.context_mut()?;
+        let end = offset
+            .checked_add(size)
+            .filter(|end| *end <= memory.data_size(&context))
+            .ok_or_else(|| error!("out of bounds memory access"))?;
+
+        // SAFETY: the range is in bounds, and Ruby doesn't run during the
+        // copy. `ptr::copy` allows the buffer to be a view of this memory
+        // (see #to_io_buffer).
+        unsafe {
+            let dest = memory.data_ptr(&mut context).add(offset);
+            std::ptr::copy(base as *const u8, dest, end - offset);
+        }
+        Ok(())
+    }
+
+    /// @yard
+    /// Read +size+ bytes starting at +offset+ into the beginning of +buffer+,
+    /// copying them only once.
+    ///
+    /// @def read_into(buffer, offset, size)
+    /// @param buffer [IO::Buffer] A writable buffer of at least +size+ bytes.
+    /// @param offset [Integer]
+    /// @param size [Integer]
+    /// @return [Integer] The number of bytes read.
+    #[cfg(ruby_gte_3_1)]
+    pub fn read_into(
+        rb_self: Obj<Self>,
+        buffer: Value,
+        offset: usize,
+        size: usize,
+    ) -> Result<usize, Error> {
+        let end = offset
+            .checked_add(size)
+            .ok_or_else(|| error!("out of bounds memory access"))?;
+        // The slice's string points to the memory, `set_string` copies it.
+        let slice = Obj::wrap(UnsafeSlice::new(rb_self, offset..end)?);
+        buffer.funcall("set_string", (UnsafeSlice::to_str(slice)?,))
+    }
+
     /// @yard
     /// Write +value+ starting at +offset+.
     ///
@@ -171,9 +384,133 @@ impl<'a> Memory<'a> {
     pub fn write(&self, offset: usize, value: RString) -> Result<(), Error> {
         let slice = unsafe { value.as_slice() };
 
-        self.get_wasmtime_memory()
-            .write(self.store.context_mut()?, offset, slice)
-            .map_err(|e| error!("{}", e))
+        self.write_bytes(offset, slice)
+    }
+
+    /// @yard
+    /// Read a little-endian signed 32-bit integer at +offset+.
+    ///
+    /// @def read_i32(offset)
+    /// @param offset [Integer]
+    /// @return [Integer]
+    pub fn read_i32(&self, offset: usize) -> Result<i32, Error> {
+        self.read_bytes(offset).map(i32::from_le_bytes)
+    }
+
+    /// @yard
+    /// Write +value+ as a little-endian signed 32-bit integer at +offset+.
+    ///
+    /// @def write_i32(offset, value)
+    /// @param offset [Integer]
+    /// @param value [Integer]
+    /// @return [void]
+    pub fn write_i32(&self, offset: usize, value: i32) -> Result<(), Error> {
+        self.write_bytes(offset, &value.to_le_bytes())
+    }
+
+    /// @yard
+    /// Read a little-endian unsigned 32-bit integer at +offset+.
+    ///
+    /// @def read_u32(offset)
+    /// @param offset [Integer]
+    /// @return [Integer]
+    pub fn read_u32(&self, offset: usize) -> Result<u32, Error> {
+        self.read_bytes(offset).map(u32::from_le_bytes)
+    }
+
+    /// @yard
+    /// Write +value+ as a little-endian unsigned 32-bit integer at +offset+.
+    ///
+    /// @def write_u32(offset, value)
+    /// @param offset [Integer]
+    /// @param value [Integer]
+    /// @return [void]
+    pub fn write_u32(&self, offset: usize, value: u32) -> Result<(), Error> {
+        self.write_bytes(offset, &value.to_le_bytes())
+    }
+
+    /// @yard
+    /// Read a little-endian signed 64-bit integer at +offset+.
+    ///
+    /// @def read_i64(offset)
+    /// @param offset [Integer]
+    /// @return [Integer]
+    pub fn read_i64(&self, offset: usize) -> Result<i64, Error> {
+        self.read_bytes(offset).map(i64::from_le_bytes)
+    }
+
+    /// @yard
+    /// Write +value+ as a little-endian signed 64-bit integer at +offset+.
+    ///
+    /// @def write_i64(offset, value)
+    /// @param offset [Integer]
+    /// @param value [Integer]
+    /// @return [void]
+    pub fn write_i64(&self, offset: usize, value: i64) -> Result<(), Error> {
+        self.write_bytes(offset, &value.to_le_bytes())
+    }
+
+    /// @yard
+    /// Read a little-endian unsigned 64-bit integer at +offset+.
+    ///
+    /// @def read_u64(offset)
+    /// @param offset [Integer]
+    /// @return [Integer]
+    pub fn read_u64(&self, offset: usize) -> Result<u64, Error> {
+        self.read_bytes(offset).map(u64::from_le_bytes)
+    }
+
+    /// @yard
+    /// Write +value+ as a little-endian unsigned 64-bit integer at +offset+.
+    ///
+    /// @def write_u64(offset, value)
+    /// @param offset [Integer]
+    /// @param value [Integer]
+    /// @return [void]
+    pub fn write_u64(&self, offset: usize, value: u64) -> Result<(), Error> {
+        self.write_bytes(offset, &value.to_le_bytes())
+    }
+
+    /// @yard
+    /// Read a little-endian 32-bit float at +offset+.
+    ///
+    /// @def read_f32(offset)
+    /// @param offset [Integer]
+    /// @return [Float]
+    pub fn read_f32(&self, offset: usize) -> Result<f32, Error> {
+        self.read_bytes(offset).map(f32::from_le_bytes)
+    }
+
+    /// @yard
+    /// Write +value+ as a little-endian 32-bit float at +offset+.
+    ///
+    /// @def write_f32(offset, value)
+    /// @param offset [Integer]
+    /// @param value [Float]
+    /// @return [void]
+    pub fn write_f32(&self, offset: usize, value: f32) -> Result<(), Error> {
+        self.write_bytes(offset, &value.to_le_bytes())
+    }
+
+    /// @yard
+    /// Read a little-endian 64-bit float at +offset+.
+    ///
+    /// @def read_f64(offset)
+    /// @param offset [Integer]
+    /// @return [Float]
+    pub fn read_f64(&self, offset: usize) -> Result<f64, Error> {
+        self.read_bytes(offset).map(f64::from_le_bytes)
+    }
+
+    /// @yard
+    /// Write +value+ as a little-endian 64-bit float at +offset+.
+    ///
+    /// @def write_f64(offset, value)
+    /// @param offset [Integer]
+    /// @param value [Float]
+    /// @return [void]
+    pub fn write_f64(&self, offset: usize, value: f64) -> Result<(), Error> {
+        self.write_bytes(offset, &value.to_le_bytes())
     }
 
     /// @yard
@@ -183,16 +520,16 @@ impl<'a> Memory<'a> {
     /// @def grow(delta)
     /// @param delta [Integer] The number of pages to grow by.
     /// @return [Integer] The number of pages the memory had before being resized.
-    pub fn grow(&self, delta: usize) -> Result<u64, Error> {
+    pub fn grow(&self, delta: u64) -> Result<u64, Error> {
         let ret = self
             .get_wasmtime_memory()
-            .grow(self.store.context_mut()?, delta as _)
-            .map_err(|e| error!("{}", e));
+            .grow(self.store.context_mut()?, delta)
+            .map_err(|e| self.store.handle_wasm_error(e))?;
 
         self.inner
-            .increase_memory_usage(delta * (WASM_PAGE_SIZE as usize));
+            .increase_memory_usage(delta as usize * (WASM_PAGE_SIZE as usize));
 
-        ret
+        Ok(ret)
     }
 
     /// @yard
@@ -214,6 +551,77 @@ impl<'a> Memory<'a> {
     fn data(&self) -> Result<&[u8], Error> {
         Ok(self.get_wasmtime_memory().data(self.store.context()?))
     }
+
+    fn slice(&self, offset: usize, size: usize) -> Result<&[u8], Error> {
+        self.data()?
+            .get(offset..)
+            .and_then(|s| s.get(..size))
+            .ok_or_else(|| error!("out of bounds memory access"))
+    }
+
+    fn read_bytes<const N: usize>(&self, offset: usize) -> Result<[u8; N], Error> {
+        self.data()?
+            .get(offset..)
+            .and_then(|s| s.get(..N))
+            .and_then(|s| s.try_into().ok())
+            .ok_or_else(|| error!("out of bounds memory access"))
+    }
+
+    fn write_bytes(&self, offset: usize, bytes: &[u8]) -> Result<(), Error> {
+        self.get_wasmtime_memory()
+            .write(self.store.context_mut()?, offset, bytes)
+            .map_err(|e| error!("{}", e))
+    }
+}
+
+fn utf8_string(bytes: &[u8], invalid: Invalid) -> Result<RString, Error> {
+    match invalid {
+        Invalid::Raise => std::str::from_utf8(bytes)
+            .map(RString::new)
+            .map_err(|e| error!("{}", e)),
+        Invalid::Replace => Ok(RString::new(&String::from_utf8_lossy(bytes))),
+    }
+}
+
+/// @yard
+/// @rename Wasmtime::MemoryType
+/// Represents the type of a WebAssembly memory.
+/// @see https://docs.rs/wasmtime/latest/wasmtime/struct.MemoryType.html Wasmtime's Rust doc
+#[magnus::wrap(class = "Wasmtime::MemoryType", free_immediately, frozen_shareable)]
+pub struct MemoryType {
+    inner: MemoryTypeImpl,
+}
+
+impl MemoryType {
+    pub fn from_inner(inner: MemoryTypeImpl) -> Self {
+        Self { inner }
+    }
+
+    /// @yard
+    /// @return [Integer] The minimum number of memory pages.
+    pub fn min_size(&self) -> u64 {
+        self.inner.minimum()
+    }
+
+    /// @yard
+    /// @return [Integer, nil] The maximum number of memory pages.
+    pub fn max_size(&self) -> Option<u64> {
+        self.inner.maximum()
+    }
+
+    /// @yard
+    /// @def shared?
+    /// @return [Boolean] Whether the memory is shared between threads.
+    pub fn is_shared(&self) -> bool {
+        self.inner.is_shared()
+    }
+
+    /// @yard
+    /// @def memory64?
+    /// @return [Boolean] Whether the memory is indexed with 64-bit integers.
+    pub fn is_memory64(&self) -> bool {
+        self.inner.is_64()
+    }
 }
 
 impl From<&Memory<'_>> for Extern {
@@ -227,14 +635,42 @@ pub fn init(ruby: &Ruby) -> Result<(), Error> {
     class.define_singleton_method("new", function!(Memory::new, -1))?;
     class.define_method("min_size", method!(Memory::min_size, 0))?;
     class.define_method("max_size", method!(Memory::max_size, 0))?;
+    class.define_method("type", method!(Memory::type_, 0))?;
     class.define_method("read", method!(Memory::read, 2))?;
-    class.define_method("read_utf8", method!(Memory::read_utf8, 2))?;
+    class.define_method("read_utf8", method!(Memory::read_utf8, -1))?;
+    class.define_method("read_utf16le", method!(Memory::read_utf16le, -1))?;
+    class.define_method("read_cstring", method!(Memory::read_cstring, -1))?;
     class.define_method("write", method!(Memory::write, 2))?;
+    class.define_method("read_i32", method!(Memory::read_i32, 1))?;
+    class.define_method("write_i32", method!(Memory::write_i32, 2))?;
+    class.define_method("read_u32", method!(Memory::read_u32, 1))?;
+    class.define_method("write_u32", method!(Memory::write_u32, 2))?;
+    class.define_method("read_i64", method!(Memory::read_i64, 1))?;
+    class.define_method("write_i64", method!(Memory::write_i64, 2))?;
+    class.define_method("read_u64", method!(Memory::read_u64, 1))?;
+    class.define_method("write_u64", method!(Memory::write_u64, 2))?;
+    class.define_method("read_f32", method!(Memory::read_f32, 1))?;
+    class.define_method("write_f32", method!(Memory::write_f32, 2))?;
+    class.define_method("read_f64", method!(Memory::read_f64, 1))?;
+    class.define_method("write_f64", method!(Memory::write_f64, 2))?;
     class.define_method("grow", method!(Memory::grow, 1))?;
     class.define_method("size", method!(Memory::size, 0))?;
     class.define_method("data_size", method!(Memory::data_size, 0))?;
     class.define_method("read_unsafe_slice", method!(Memory::read_unsafe_slice, 2))?;
 
+    #[cfg(ruby_gte_3_1)]
+    {
+        class.define_method("to_io_buffer", method!(Memory::to_io_buffer, 0))?;
+        class.define_method("write_from", method!(Memory::write_from, 2))?;
+        class.define_method("read_into", method!(Memory::read_into, 3))?;
+    }
+
+    let class = root().define_class("MemoryType", class::object())?;
+    class.define_method("min_size", method!(MemoryType::min_size, 0))?;
+    class.define_method("max_size", method!(MemoryType::max_size, 0))?;
+    class.define_method("shared?", method!(MemoryType::is_shared, 0))?;
+    class.define_method("memory64?", method!(MemoryType::is_memory64, 0))?;
+
     unsafe_slice::init(ruby)?;
 
     Ok(())
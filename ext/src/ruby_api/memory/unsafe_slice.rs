@@ -1,4 +1,6 @@
 use crate::{define_rb_intern, error, root, Memory};
+#[cfg(ruby_gte_3_1)]
+use magnus::value::ReprValue;
 use magnus::{
     class,
     gc::Marker,
@@ -73,6 +75,26 @@ impl<'a> UnsafeSlice<'a> {
         ruby.get_inner(&CLASS).new_instance((rb_self,))
     }
 
+    /// @yard
+    /// Get this slice as a read-only +IO::Buffer+ over the memory, without
+    /// copying it. The same caveats as {#to_str} apply to the buffer.
+    ///
+    /// @def to_io_buffer
+    /// @return [IO::Buffer] Read-only buffer of the slice.
+    #[cfg(ruby_gte_3_1)]
+    pub fn to_io_buffer(rb_self: Obj<Self>) -> Result<Value, Error> {
+        static CLASS: Lazy<RClass> = Lazy::new(|_| {
+            object()
+                .const_get::<_, RClass>("IO")
+                .and_then(|io| io.const_get("Buffer"))
+                .unwrap()
+        });
+        let ruby = Ruby::get().unwrap();
+        // `IO::Buffer.for` wraps frozen strings without copying them.
+        let string = Self::to_str(rb_self)?;
+        ruby.get_inner(&CLASS).funcall("for", (string,))
+    }
+
     /// @yard
     /// Gets the memory slice as a Ruby string without copying the underlying buffer.
     ///
@@ -182,6 +204,9 @@ pub fn init(ruby: &Ruby) -> Result<(), Error> {
     let class = parent.define_class("UnsafeSlice", class::object())?;
     class.define_method("to_str", method!(UnsafeSlice::to_str, 0))?;
 
+    #[cfg(ruby_gte_3_1)]
+    class.define_method("to_io_buffer", method!(UnsafeSlice::to_io_buffer, 0))?;
+
     #[cfg(ruby_gte_3_0)]
     if require("fiddle").is_ok() && fiddle_memory_view_class().is_some() {
         UnsafeSlice::register_memory_view(ruby)?;
@@ -7,39 +7,56 @@
 // (`pub(crate) use`). Allowing unused imports is easier and less repetitive.
 // Also the feature is already correctly gated in lib.rs.
 #![allow(unused_imports)]
-use magnus::{function, value::Lazy, Error, RModule, RString, Ruby};
+use crate::helpers::set_log_level;
+use log::LevelFilter;
+use magnus::{
+    exception::arg_error, function, prelude::*, value::Lazy, Error, RModule, RString, Ruby, Value,
+};
 
 mod caller;
+mod component;
 mod config;
 mod convert;
 mod engine;
+mod epoch_timer;
 mod errors;
 mod externals;
 mod func;
 mod global;
 mod instance;
+mod instance_pre;
 mod linker;
 mod memory;
 mod module;
 mod params;
+mod shared_memory;
 mod store;
 mod table;
 mod trap;
+mod typed_func;
 mod wasi_ctx;
 mod wasi_ctx_builder;
+#[cfg(feature = "wasi-nn")]
+mod wasi_nn;
+mod wasi_p2_ctx_builder;
 
 pub use caller::Caller;
+pub use component::Component;
 pub use engine::Engine;
 pub use func::Func;
 pub use instance::Instance;
+pub use instance_pre::InstancePre;
 pub use linker::Linker;
 pub use memory::Memory;
 pub use module::Module;
 pub use params::Params;
+pub use shared_memory::SharedMemory;
 pub use store::Store;
 pub use trap::Trap;
+pub use typed_func::TypedFunc;
 pub use wasi_ctx::WasiCtx;
 pub use wasi_ctx_builder::WasiCtxBuilder;
+pub use wasi_p2_ctx_builder::{WasiP2CtxBuilder, WasiP2State};
 
 /// The "Wasmtime" Ruby module.
 pub fn root() -> RModule {
@@ -64,12 +81,143 @@ impl Wasmtime {
             .map(|bytes| RString::from_slice(bytes.as_slice()))
             .map_err(|e| crate::error!("{}", e))
     }
+
+    /// @yard
+    /// Converts Wasm into its WAT text representation.
+    /// @param wasm [String]
+    /// @def wasm2wat(wasm)
+    /// @return [String] The WAT as a UTF-8 +String+.
+    pub fn wasm2wat(wasm: RString) -> Result<RString, Error> {
+        wasmprinter::print_bytes(unsafe { wasm.as_slice() })
+            .map(|wat| RString::new(&wat))
+            .map_err(|e| crate::error!("{}", e))
+    }
+
+    /// @yard
+    /// Routes the log records of Wasmtime, Cranelift and WASI to +logger+,
+    /// mapping their levels to +Logger+ severities (trace records are logged
+    /// as debug). Records below the level of +logger+ at the time of the
+    /// assignment are not collected: re-assign the logger after changing its
+    /// level.
+    ///
+    /// Records logged by Wasmtime's own threads (e.g. during parallel
+    /// compilation) are delivered from the Ruby thread that started the
+    /// work, once it's done.
+    ///
+    /// @example
+    ///   Wasmtime.logger = Logger.new($stderr, level: :info)
+    ///
+    /// @def logger=(logger)
+    /// @param logger [Logger, nil] Any object responding to +level+ and
+    ///   +add(severity, message, progname)+, or +nil+ to stop logging.
+    /// @return [Logger, nil]
+    pub fn set_logger(logger: Value) -> Result<Value, Error> {
+        let filter = if logger.is_nil() {
+            LevelFilter::Off
+        } else {
+            match logger.funcall::<_, _, i64>("level", ())? {
+                i64::MIN..=0 => LevelFilter::Trace,
+                1 => LevelFilter::Info,
+                2 => LevelFilter::Warn,
+                3 => LevelFilter::Error,
+                _ => LevelFilter::Off,
+            }
+        };
+
+        root().ivar_set("@logger", logger)?;
+        set_log_level(filter);
+        Ok(logger)
+    }
+
+    /// @yard
+    /// Sets the number of threads compiling modules and components in
+    /// parallel, for all engines created with +parallel_compilation: true+
+    /// (the default). Defaults to the number of CPUs, which oversubscribes
+    /// containers whose CPU quota is lower.
+    ///
+    /// The compilation threads are started by the first parallel compilation:
+    /// this must be called before it.
+    ///
+    /// @example
+    ///   Wasmtime.compilation_threads = Integer(ENV.fetch("CPU_QUOTA", 2))
+    ///
+    /// @def compilation_threads=(count)
+    /// @param count [Integer]
+    /// @return [Integer]
+    /// @raise [Error] if parallel compilation already started.
+    pub fn set_compilation_threads(count: usize) -> Result<usize, Error> {
+        if count == 0 {
+            return Err(Error::new(
+                arg_error(),
+                "compilation_threads must be at least 1",
+            ));
+        }
+
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(count)
+            .thread_name(|i| format!("wasmtime-compile-{i}"))
+            .build_global()
+            .map(|()| count)
+            .map_err(|e| crate::error!("Could not set compilation_threads: {}", e))
+    }
+
+    /// @yard
+    /// Closes all engines (see {Engine#close}), stopping their epoch timers,
+    /// e.g. before forking. Existing stores keep working.
+    /// Threads started by parallel compilation keep running, see
+    /// {Wasmtime.compilation_threads=}.
+    /// @return [nil]
+    pub fn shutdown() {
+        engine::close_all()
+    }
+
+    /// @yard
+    /// Pauses the epoch timers started with {Engine#start_epoch_interval},
+    /// and the one enforcing {Store#set_time_limit} and {Func#call}'s
+    /// +timeout+, whose threads are not inherited by forked processes. Call
+    /// {Wasmtime.after_fork} in both processes after forking to resume them.
+    ///
+    /// Both are called around +Process.fork+ on Ruby 3.1 and later, e.g. by
+    /// Puma and Unicorn with +preload_app+. Wasm memories and compiled code
+    /// are copied to the child like the rest of the process's memory, but
+    /// engines with +parallel_compilation+ must not compile before forking,
+    /// see {Engine}.
+    /// @return [nil]
+    pub fn before_fork() -> Result<(), Error> {
+        engine::before_fork()
+    }
+
+    /// @yard
+    /// Resumes the epoch timers paused by {Wasmtime.before_fork}, starting
+    /// them on a new thread in forked processes.
+    /// @return [nil]
+    pub fn after_fork() -> Result<(), Error> {
+        engine::after_fork()
+    }
+
+    /// @yard
+    /// The logger set with {Wasmtime.logger=}, if any.
+    /// @def logger
+    /// @return [Logger, nil]
+    pub fn logger() -> Result<Value, Error> {
+        root().ivar_get("@logger")
+    }
 }
 
 pub fn init(ruby: &Ruby) -> Result<(), Error> {
     let wasmtime = root();
 
     wasmtime.define_module_function("wat2wasm", function!(Wasmtime::wat2wasm, 1))?;
+    wasmtime.define_module_function("wasm2wat", function!(Wasmtime::wasm2wat, 1))?;
+    wasmtime.define_module_function("logger=", function!(Wasmtime::set_logger, 1))?;
+    wasmtime.define_module_function("logger", function!(Wasmtime::logger, 0))?;
+    wasmtime.define_module_function("shutdown", function!(Wasmtime::shutdown, 0))?;
+    wasmtime.define_module_function("before_fork", function!(Wasmtime::before_fork, 0))?;
+    wasmtime.define_module_function("after_fork", function!(Wasmtime::after_fork, 0))?;
+    wasmtime.define_module_function(
+        "compilation_threads=",
+        function!(Wasmtime::set_compilation_threads, 1),
+    )?;
 
     errors::init()?;
     trap::init()?;
@@ -77,15 +225,22 @@ pub fn init(ruby: &Ruby) -> Result<(), Error> {
     module::init()?;
     store::init()?;
     instance::init()?;
+    instance_pre::init()?;
     func::init()?;
+    typed_func::init()?;
     caller::init()?;
     memory::init(ruby)?;
+    shared_memory::init()?;
     linker::init()?;
     externals::init()?;
     wasi_ctx_builder::init()?;
+    wasi_p2_ctx_builder::init()?;
+    #[cfg(feature = "wasi-nn")]
+    wasi_nn::init()?;
     table::init()?;
     global::init()?;
     wasi_ctx::init()?;
+    component::init()?;
 
     Ok(())
 }
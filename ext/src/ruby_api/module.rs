@@ -2,26 +2,59 @@ use std::{
     mem::{transmute, MaybeUninit},
     ops::Deref,
     os::raw::c_void,
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
 };
 
-use super::{engine::Engine, root};
+use super::{
+    engine::Engine,
+    errors::{compile_error, validation_error},
+    func::FuncType,
+    global::GlobalType,
+    memory::MemoryType,
+    root,
+    table::TableType,
+};
 use crate::{
-    error,
+    define_rb_intern, err, error,
     helpers::{nogvl, Tmplock},
 };
-use magnus::{class, function, method, rb_sys::AsRawValue, Error, Module as _, Object, RString};
+use magnus::{
+    class, function, method, rb_sys::AsRawValue, scan_args, typed_data::Obj, Error, IntoValue,
+    Module as _, Object, RArray, RHash, RString, Value,
+};
 use rb_sys::{
     rb_str_locktmp, rb_str_unlocktmp, tracking_allocator::ManuallyTracked, RSTRING_LEN, RSTRING_PTR,
 };
-use wasmtime::Module as ModuleImpl;
+use wasmparser::{Name, NameSectionReader, Parser, Payload};
+use wasmtime::{Engine as EngineImpl, ExternType, Module as ModuleImpl};
+
+define_rb_intern!(
+    KEEP_CUSTOM_SECTIONS => "keep_custom_sections",
+);
+
+/// What's known of a module compiled from its source, which Wasmtime doesn't
+/// keep.
+struct Source {
+    /// The name and contents of the module's custom sections: its name
+    /// section, and the others when kept.
+    custom_sections: Vec<(String, Vec<u8>)>,
+    /// The index of the module's start function, if any.
+    start_function: Option<u32>,
+}
 
 /// @yard
 /// Represents a WebAssembly module.
+///
+/// Modules are immutable: once frozen (e.g. with +Ractor.make_shareable+),
+/// they can be shared with other Ractors, see {Engine}.
 /// @see https://docs.rs/wasmtime/latest/wasmtime/struct.Module.html Wasmtime's Rust doc
 #[derive(Clone)]
 #[magnus::wrap(class = "Wasmtime::Module", size, free_immediately, frozen_shareable)]
 pub struct Module {
     inner: ModuleImpl,
+    /// Only known for modules compiled from their source, not deserialized.
+    source: Option<Arc<Source>>,
     _track_memory_usage: ManuallyTracked<()>,
 }
 
@@ -30,32 +63,108 @@ unsafe impl Send for Module {}
 
 impl Module {
     /// @yard
-    /// @def new(engine, wat_or_wasm)
+    /// @def new(engine, wat_or_wasm, keep_custom_sections: false)
     /// @param engine [Wasmtime::Engine]
     /// @param wat_or_wasm [String] The String of WAT or Wasm.
+    /// @param keep_custom_sections [Boolean] Whether to keep the module's
+    ///   custom sections, other than its name section, for {#custom_sections}.
     /// @return [Wasmtime::Module]
-    pub fn new(engine: &Engine, wat_or_wasm: RString) -> Result<Self, Error> {
-        let eng = engine.get();
+    pub fn new(args: &[Value]) -> Result<Self, Error> {
+        let args = scan_args::scan_args::<(Obj<Engine>, RString), (), (), (), _, ()>(args)?;
+        let keep = keep_custom_sections(args.keywords)?;
+        let (engine, wat_or_wasm) = args.required;
+        let eng = &engine.get()?;
         let (locked_slice, _locked_slice_guard) = wat_or_wasm.as_locked_slice()?;
-        let module = nogvl(|| ModuleImpl::new(eng, locked_slice))
-            .map_err(|e| error!("Could not build module: {}", e))?;
+        let (module, source) = nogvl(|| compile(eng, locked_slice, keep))
+            .map_err(|e| Error::new(compile_error(), format!("Could not build module: {}", e)))?;
 
-        Ok(module.into())
+        Ok(Self::from(module).with_source(source))
     }
 
     /// @yard
-    /// @def from_file(engine, path)
+    /// Starts compiling +wat_or_wasm+ on a background thread and returns
+    /// right away, so that the calling thread can keep running (e.g. serve
+    /// requests) while a large module compiles.
+    ///
+    /// @def compile_async(engine, wat_or_wasm, keep_custom_sections: false)
+    /// @param engine [Wasmtime::Engine]
+    /// @param wat_or_wasm [String] The String of WAT or Wasm.
+    /// @param keep_custom_sections [Boolean] See {.new}.
+    /// @return [ModuleCompilation]
+    ///
+    /// @example
+    ///   compilation = Wasmtime::Module.compile_async(engine, File.binread("app.wasm"))
+    ///   # ...
+    ///   mod = compilation.value
+    pub fn compile_async(args: &[Value]) -> Result<ModuleCompilation, Error> {
+        let args = scan_args::scan_args::<(Obj<Engine>, RString), (), (), (), _, ()>(args)?;
+        let keep = keep_custom_sections(args.keywords)?;
+        let (engine, wat_or_wasm) = args.required;
+        let engine = engine.get()?;
+        let wat_or_wasm = unsafe { wat_or_wasm.as_slice() }.to_vec();
+        let handle = thread::Builder::new()
+            .name("wasmtime-compile".into())
+            .spawn(move || compile(&engine, &wat_or_wasm, keep))
+            .map_err(|e| error!("Could not start compilation: {}", e))?;
+
+        Ok(ModuleCompilation {
+            state: Mutex::new(CompilationState::Running(Some(handle))),
+        })
+    }
+
+    /// @yard
+    /// Validates +wat_or_wasm+ against the {Engine}'s enabled features,
+    /// without compiling it.
+    ///
+    /// @def validate(engine, wat_or_wasm)
+    /// @param engine [Wasmtime::Engine]
+    /// @param wat_or_wasm [String] The String of WAT or Wasm.
+    /// @return [true]
+    /// @raise [ValidationError] if the module is invalid, with the offset of
+    ///   the invalid item in the Wasm binary when known.
+    ///
+    /// @example
+    ///   Wasmtime::Module.validate(engine, user_upload)
+    /// rescue Wasmtime::ValidationError => e
+    ///   puts "invalid module at offset #{e.offset}: #{e.message}"
+    pub fn validate(engine: &Engine, wat_or_wasm: RString) -> Result<bool, Error> {
+        let eng = &engine.get()?;
+        let (locked_slice, _locked_slice_guard) = wat_or_wasm.as_locked_slice()?;
+        let result = nogvl(|| {
+            let wasm = wat::parse_bytes(locked_slice).map_err(|e| (e.to_string(), None))?;
+            ModuleImpl::validate(eng, &wasm).map_err(|e| split_offset(format!("{:#}", e)))
+        });
+
+        match result {
+            Ok(()) => Ok(true),
+            Err((message, offset)) => Err(validation_error()
+                .new_instance((message, offset))
+                .map_or_else(|e| e, Into::into)),
+        }
+    }
+
+    /// @yard
+    /// @def from_file(engine, path, keep_custom_sections: false)
     /// @param engine [Wasmtime::Engine]
     /// @param path [String]
+    /// @param keep_custom_sections [Boolean] See {.new}.
     /// @return [Wasmtime::Module]
-    pub fn from_file(engine: &Engine, path: RString) -> Result<Self, Error> {
-        let eng = engine.get();
+    pub fn from_file(args: &[Value]) -> Result<Self, Error> {
+        let args = scan_args::scan_args::<(Obj<Engine>, RString), (), (), (), _, ()>(args)?;
+        let keep = keep_custom_sections(args.keywords)?;
+        let (engine, path) = args.required;
+        let eng = &engine.get()?;
         let (path, _locked_str_guard) = path.as_locked_str()?;
         // SAFETY: this string is immediately copied and never moved off the stack
-        let module = nogvl(|| ModuleImpl::from_file(eng, path))
-            .map_err(|e| error!("Could not build module from file: {}", e))?;
+        let (module, source) =
+            nogvl(|| compile(eng, &std::fs::read(path)?, keep)).map_err(|e| {
+                Error::new(
+                    compile_error(),
+                    format!("Could not build module from file: {}", e),
+                )
+            })?;
 
-        Ok(module.into())
+        Ok(Self::from(module).with_source(source))
     }
 
     /// @yard
@@ -71,7 +180,7 @@ impl Module {
     /// @return [Wasmtime::Module]
     pub fn deserialize(engine: &Engine, compiled: RString) -> Result<Self, Error> {
         // SAFETY: this string is immediately copied and never moved off the stack
-        unsafe { ModuleImpl::deserialize(engine.get(), compiled.as_slice()) }
+        unsafe { ModuleImpl::deserialize(&engine.get()?, compiled.as_slice()) }
             .map(Into::into)
             .map_err(|e| error!("Could not deserialize module: {}", e))
     }
@@ -85,7 +194,7 @@ impl Module {
     /// @return [Wasmtime::Module]
     /// @see .deserialize
     pub fn deserialize_file(engine: &Engine, path: RString) -> Result<Self, Error> {
-        unsafe { ModuleImpl::deserialize_file(engine.get(), path.as_str()?) }
+        unsafe { ModuleImpl::deserialize_file(&engine.get()?, path.as_str()?) }
             .map(Into::into)
             .map_err(|e| error!("Could not deserialize module from file: {}", e))
     }
@@ -103,9 +212,272 @@ impl Module {
             .map_err(|e| error!("{:?}", e))
     }
 
+    /// @yard
+    /// Returns the module's imports, in the order they're declared.
+    /// Each import is a +Hash+ with the following keys:
+    /// * +"module"+: the +String+ name of the module the item is imported from,
+    /// * +"name"+: the +String+ name of the imported item,
+    /// * +"type"+: the type of the imported item, one of {FuncType},
+    ///   {MemoryType}, {TableType} or {GlobalType}.
+    ///
+    /// @return [Array<Hash{String => Object}>]
+    pub fn imports(&self) -> Result<RArray, Error> {
+        let imports = self.inner.imports();
+        let array = RArray::with_capacity(imports.len());
+        for import in imports {
+            let hash = RHash::new();
+            hash.aset("module", import.module())?;
+            hash.aset("name", import.name())?;
+            hash.aset("type", extern_type_to_value(import.ty()))?;
+            array.push(hash)?;
+        }
+
+        Ok(array)
+    }
+
+    /// @yard
+    /// Returns the module's exports, in the order they're declared.
+    /// Each export is a +Hash+ with the following keys:
+    /// * +"name"+: the +String+ name of the exported item,
+    /// * +"type"+: the type of the exported item, one of {FuncType},
+    ///   {MemoryType}, {TableType} or {GlobalType}.
+    ///
+    /// @return [Array<Hash{String => Object}>]
+    pub fn exports(&self) -> Result<RArray, Error> {
+        let exports = self.inner.exports();
+        let array = RArray::with_capacity(exports.len());
+        for export in exports {
+            let hash = RHash::new();
+            hash.aset("name", export.name())?;
+            hash.aset("type", extern_type_to_value(export.ty()))?;
+            array.push(hash)?;
+        }
+
+        Ok(array)
+    }
+
+    /// @yard
+    /// Returns the resources the module's own (not imported) memories and
+    /// tables require at instantiation, to check them against limits before
+    /// instantiating. A +Hash+ with the following keys:
+    /// * +"num_memories"+: the +Integer+ number of memories defined,
+    /// * +"max_initial_memory_size"+: the largest initial size of a defined
+    ///   memory in Wasm pages, or +nil+ if there's no memory,
+    /// * +"num_tables"+: the +Integer+ number of tables defined,
+    /// * +"max_initial_table_size"+: the largest initial element count of a
+    ///   defined table, or +nil+ if there's no table.
+    ///
+    /// @return [Hash{String => Integer, nil}]
+    /// @see https://docs.rs/wasmtime/latest/wasmtime/struct.Module.html#method.resources_required
+    ///   Wasmtime's Rust doc
+    pub fn resources_required(&self) -> Result<RHash, Error> {
+        let resources = self.inner.resources_required();
+        let hash = RHash::new();
+        hash.aset("num_memories", resources.num_memories)?;
+        hash.aset("max_initial_memory_size", resources.max_initial_memory_size)?;
+        hash.aset("num_tables", resources.num_tables)?;
+        hash.aset("max_initial_table_size", resources.max_initial_table_size)?;
+
+        Ok(hash)
+    }
+
+    /// @yard
+    /// Returns the contents of the module's custom sections named +name+,
+    /// in the order they appear in the module.
+    ///
+    /// Only the name section is kept by default, not to retain large debug
+    /// info: others require compiling with +keep_custom_sections: true+.
+    ///
+    /// @def custom_sections(name)
+    /// @param name [String]
+    /// @return [Array<String>] Binary +String+s of the sections' contents.
+    /// @raise [Error] for modules created with {.deserialize} or
+    ///   {.deserialize_file}, which don't keep their custom sections.
+    ///
+    /// @example
+    ///   mod = Wasmtime::Module.new(engine, wasm, keep_custom_sections: true)
+    ///   mod.custom_sections("version") #=> ["1.2.3"]
+    pub fn custom_sections(&self, name: RString) -> Result<RArray, Error> {
+        let source = match &self.source {
+            Some(source) => source,
+            None => return err!("custom sections are not available on deserialized modules"),
+        };
+        let name = name.to_string()?;
+
+        Ok(source
+            .custom_sections
+            .iter()
+            .filter(|(section_name, _)| *section_name == name)
+            .map(|(_, data)| RString::from_slice(data))
+            .collect())
+    }
+
+    /// @yard
+    /// Returns the module's name, from its name section (e.g. +(module $name)+
+    /// in WAT).
+    ///
+    /// @return [String, nil]
+    pub fn name(&self) -> Option<String> {
+        self.inner.name().map(ToString::to_string)
+    }
+
+    /// @yard
+    /// Returns the name of the function at +index+ in the module's function
+    /// index space (imported functions first), from the module's name section.
+    ///
+    /// @def function_name(index)
+    /// @param index [Integer]
+    /// @return [String, nil] +nil+ if the function has no name.
+    /// @raise [Error] for modules created with {.deserialize} or
+    ///   {.deserialize_file}, which don't keep their name section.
+    ///
+    /// @example
+    ///   mod = Wasmtime::Module.new(engine, "(module (func $add))")
+    ///   mod.function_name(0) #=> "add"
+    pub fn function_name(&self, index: u32) -> Result<Option<String>, Error> {
+        let source = match &self.source {
+            Some(source) => source,
+            None => return err!("function names are not available on deserialized modules"),
+        };
+
+        // Like Wasmtime, ignore malformed name sections.
+        let name = source
+            .custom_sections
+            .iter()
+            .filter(|(section_name, _)| section_name == "name")
+            .flat_map(|(_, data)| NameSectionReader::new(data, 0))
+            .filter_map(|subsection| match subsection {
+                Ok(Name::Function(names)) => Some(names),
+                _ => None,
+            })
+            .flat_map(|names| names.into_iter().map_while(Result::ok))
+            .find(|naming| naming.index == index)
+            .map(|naming| naming.name.to_string());
+
+        Ok(name)
+    }
+
+    /// @yard
+    /// Whether the module has a start function, which runs when it's
+    /// instantiated.
+    ///
+    /// @return [Boolean]
+    /// @raise [Error] for modules created with {.deserialize} or
+    ///   {.deserialize_file}, which don't keep their start section.
+    pub fn has_start_function(&self) -> Result<bool, Error> {
+        match &self.source {
+            Some(source) => Ok(source.start_function.is_some()),
+            None => err!("the start function is not available on deserialized modules"),
+        }
+    }
+
     pub fn get(&self) -> &ModuleImpl {
         &self.inner
     }
+
+    fn with_source(mut self, source: Arc<Source>) -> Self {
+        let size = source
+            .custom_sections
+            .iter()
+            .map(|(_, data)| data.len())
+            .sum();
+        self._track_memory_usage.increase_memory_usage(size);
+        self.source = Some(source);
+        self
+    }
+}
+
+enum CompilationState {
+    Running(Option<JoinHandle<anyhow::Result<(ModuleImpl, Arc<Source>)>>>),
+    Done(Result<Module, String>),
+}
+
+/// @yard
+/// A {Module} compiling on a background thread, returned by
+/// {Module.compile_async}.
+#[magnus::wrap(class = "Wasmtime::ModuleCompilation", free_immediately)]
+pub struct ModuleCompilation {
+    state: Mutex<CompilationState>,
+}
+
+impl ModuleCompilation {
+    /// @yard
+    /// Waits for the compilation to finish, with the GVL released.
+    ///
+    /// @return [Module]
+    /// @raise [CompileError] if the module could not be compiled.
+    pub fn value(&self) -> Result<Module, Error> {
+        let mut state = nogvl(|| self.state.lock().unwrap());
+        if let CompilationState::Running(handle) = &mut *state {
+            let handle = handle.take().expect("compilation thread joined");
+            let result = match nogvl(|| handle.join()) {
+                Ok(Ok((module, source))) => Ok(Module::from(module).with_source(source)),
+                Ok(Err(e)) => Err(e.to_string()),
+                Err(_) => Err("compilation panicked".to_string()),
+            };
+            *state = CompilationState::Done(result);
+        }
+
+        match &*state {
+            CompilationState::Done(Ok(module)) => Ok(module.clone()),
+            CompilationState::Done(Err(e)) => Err(Error::new(
+                compile_error(),
+                format!("Could not build module: {}", e),
+            )),
+            CompilationState::Running(_) => unreachable!(),
+        }
+    }
+
+    /// @yard
+    /// Whether the compilation finished, i.e. {#value} won't wait.
+    /// @return [Boolean]
+    pub fn is_done(&self) -> bool {
+        match self.state.try_lock().as_deref() {
+            Ok(CompilationState::Running(Some(handle))) => handle.is_finished(),
+            Ok(_) => true,
+            // Another thread is waiting in `value`.
+            Err(_) => false,
+        }
+    }
+}
+
+/// Parses the +keep_custom_sections:+ option of the methods compiling modules.
+fn keep_custom_sections(keywords: RHash) -> Result<bool, Error> {
+    let kw = scan_args::get_kwargs::<_, (), (Option<bool>,), ()>(
+        keywords,
+        &[],
+        &[*KEEP_CUSTOM_SECTIONS],
+    )?;
+    Ok(kw.optional.0.unwrap_or(false))
+}
+
+/// Compiles a module from WAT or Wasm, collecting its name section (and
+/// other custom sections if `keep_custom_sections`) and start function.
+fn compile(
+    engine: &EngineImpl,
+    wat_or_wasm: &[u8],
+    keep_custom_sections: bool,
+) -> anyhow::Result<(ModuleImpl, Arc<Source>)> {
+    let wasm = wat::parse_bytes(wat_or_wasm)?;
+    let module = ModuleImpl::from_binary(engine, &wasm)?;
+
+    let mut source = Source {
+        custom_sections: vec![],
+        start_function: None,
+    };
+    for payload in Parser::new(0).parse_all(&wasm) {
+        match payload? {
+            Payload::CustomSection(reader) if keep_custom_sections || reader.name() == "name" => {
+                source
+                    .custom_sections
+                    .push((reader.name().to_string(), reader.data().to_vec()))
+            }
+            Payload::StartSection { func, .. } => source.start_function = Some(func),
+            _ => {}
+        }
+    }
+
+    Ok((module, Arc::new(source)))
 }
 
 impl From<ModuleImpl> for Module {
@@ -114,19 +486,56 @@ impl From<ModuleImpl> for Module {
 
         Self {
             inner,
+            source: None,
             _track_memory_usage: ManuallyTracked::new(size),
         }
     }
 }
 
+fn extern_type_to_value(ty: ExternType) -> Value {
+    match ty {
+        ExternType::Func(ty) => FuncType::from_inner(ty).into_value(),
+        ExternType::Memory(ty) => MemoryType::from_inner(ty).into_value(),
+        ExternType::Table(ty) => TableType::from_inner(ty).into_value(),
+        ExternType::Global(ty) => GlobalType::from_inner(ty).into_value(),
+    }
+}
+
+/// Splits the offset out of a `wasmparser` error, which are formatted as
+/// "<message> (at offset 0x<offset>)".
+fn split_offset(message: String) -> (String, Option<usize>) {
+    let offset = message
+        .strip_suffix(')')
+        .and_then(|message| message.rsplit_once(" (at offset 0x"))
+        .and_then(|(message, offset)| Some((message, usize::from_str_radix(offset, 16).ok()?)));
+
+    match offset {
+        Some((message, offset)) => (message.to_string(), Some(offset)),
+        None => (message, None),
+    }
+}
+
 pub fn init() -> Result<(), Error> {
     let class = root().define_class("Module", class::object())?;
 
-    class.define_singleton_method("new", function!(Module::new, 2))?;
-    class.define_singleton_method("from_file", function!(Module::from_file, 2))?;
+    class.define_singleton_method("new", function!(Module::new, -1))?;
+    class.define_singleton_method("from_file", function!(Module::from_file, -1))?;
+    class.define_singleton_method("compile_async", function!(Module::compile_async, -1))?;
+    class.define_singleton_method("validate", function!(Module::validate, 2))?;
     class.define_singleton_method("deserialize", function!(Module::deserialize, 2))?;
     class.define_singleton_method("deserialize_file", function!(Module::deserialize_file, 2))?;
     class.define_method("serialize", method!(Module::serialize, 0))?;
+    class.define_method("imports", method!(Module::imports, 0))?;
+    class.define_method("exports", method!(Module::exports, 0))?;
+    class.define_method("resources_required", method!(Module::resources_required, 0))?;
+    class.define_method("custom_sections", method!(Module::custom_sections, 1))?;
+    class.define_method("name", method!(Module::name, 0))?;
+    class.define_method("function_name", method!(Module::function_name, 1))?;
+    class.define_method("start_function?", method!(Module::has_start_function, 0))?;
+
+    let class = root().define_class("ModuleCompilation", class::object())?;
+    class.define_method("value", method!(ModuleCompilation::value, 0))?;
+    class.define_method("done?", method!(ModuleCompilation::is_done, 0))?;
 
     Ok(())
 }
@@ -53,15 +53,21 @@ impl<'a> Params<'a> {
     }
 
     pub fn to_vec(&self) -> Result<Vec<wasmtime::Val>, Error> {
-        let mut vals = Vec::with_capacity(self.0.params().len());
-        for (i, (param, value)) in self.0.params().zip(self.1.iter()).enumerate() {
+        let mut vals = vec![wasmtime::Val::I32(0); self.0.params().len()];
+        self.write_to(&mut vals)?;
+
+        Ok(vals)
+    }
+
+    /// Converts the params into `vals`, which must be as long as the params.
+    pub fn write_to(&self, vals: &mut [wasmtime::Val]) -> Result<(), Error> {
+        for (i, ((param, value), val)) in self.0.params().zip(self.1.iter()).zip(vals).enumerate() {
             let i: u32 = i
                 .try_into()
                 .map_err(|_| Error::new(arg_error(), "too many params"))?;
-            let param = Param::new(i, param, *value);
-            vals.push(param.to_wasmtime_val()?);
+            *val = Param::new(i, param, *value).to_wasmtime_val()?;
         }
 
-        Ok(vals)
+        Ok(())
     }
 }
@@ -0,0 +1,263 @@
+use super::{engine::Engine, root};
+use crate::{define_rb_intern, err, error};
+use magnus::{
+    class, function, method, r_string::RString, scan_args, DataTypeFunctions, Error, Module as _,
+    Object, TypedData, Value,
+};
+use std::sync::atomic::{AtomicI32, AtomicI64, Ordering};
+use wasmtime::{Extern, MemoryType, SharedMemory as SharedMemoryImpl};
+
+define_rb_intern!(
+    MIN_SIZE => "min_size",
+    MAX_SIZE => "max_size",
+);
+
+/// @yard
+/// @rename Wasmtime::SharedMemory
+/// Represents a WebAssembly shared memory, which can be accessed concurrently
+/// by multiple threads. Unlike {Memory}, it belongs to an {Engine} rather than
+/// to a {Store}, and can be imported by instances of different stores.
+///
+/// Requires the {Engine} to be created with +wasm_threads: true+.
+/// @see https://docs.rs/wasmtime/latest/wasmtime/struct.SharedMemory.html Wasmtime's Rust doc
+#[derive(TypedData)]
+#[magnus(class = "Wasmtime::SharedMemory", size, free_immediately)]
+pub struct SharedMemory {
+    inner: SharedMemoryImpl,
+}
+
+impl DataTypeFunctions for SharedMemory {}
+
+impl SharedMemory {
+    /// @yard
+    /// @def new(engine, min_size:, max_size:)
+    /// @param engine [Engine]
+    /// @param min_size [Integer] The minimum memory pages.
+    /// @param max_size [Integer] The maximum memory pages.
+    pub fn new(args: &[Value]) -> Result<Self, Error> {
+        let args = scan_args::scan_args::<(&Engine,), (), (), (), _, ()>(args)?;
+        let kw = scan_args::get_kwargs::<_, (u32, u32), (), ()>(
+            args.keywords,
+            &[*MIN_SIZE, *MAX_SIZE],
+            &[],
+        )?;
+        let (engine,) = args.required;
+        let (min, max) = kw.required;
+
+        let inner = SharedMemoryImpl::new(&engine.get()?, MemoryType::shared(min, max))
+            .map_err(|e| error!("{}", e))?;
+
+        Ok(Self { inner })
+    }
+
+    pub fn from_inner(inner: SharedMemoryImpl) -> Self {
+        Self { inner }
+    }
+
+    pub fn get(&self) -> &SharedMemoryImpl {
+        &self.inner
+    }
+
+    /// @yard
+    /// @return [Integer] The minimum number of memory pages.
+    pub fn min_size(&self) -> u64 {
+        self.inner.ty().minimum()
+    }
+
+    /// @yard
+    /// @return [Integer] The maximum number of memory pages.
+    pub fn max_size(&self) -> Option<u64> {
+        self.inner.ty().maximum()
+    }
+
+    /// @yard
+    /// @return [Integer] The number of pages of the memory.
+    pub fn size(&self) -> u64 {
+        self.inner.size()
+    }
+
+    /// @yard
+    /// @return [Integer] The number of bytes of the memory.
+    pub fn data_size(&self) -> usize {
+        self.inner.data_size()
+    }
+
+    /// @yard
+    /// Grows a memory by +delta+ pages.
+    /// Raises if the memory grows beyond its limit.
+    ///
+    /// @def grow(delta)
+    /// @param delta [Integer] The number of pages to grow by.
+    /// @return [Integer] The number of pages the memory had before being resized.
+    pub fn grow(&self, delta: u64) -> Result<u64, Error> {
+        self.inner.grow(delta).map_err(|e| error!("{}", e))
+    }
+
+    /// @yard
+    /// Read +size+ bytes starting at +offset+. Result is a ASCII-8BIT encoded string.
+    /// The read is not atomic: other threads may write to the memory concurrently.
+    ///
+    /// @def read(offset, size)
+    /// @param offset [Integer]
+    /// @param size [Integer]
+    /// @return [String] Binary +String+ of the memory.
+    pub fn read(&self, offset: usize, size: usize) -> Result<RString, Error> {
+        let data = self.checked_range(offset, size)?;
+        let mut bytes = vec![0u8; size];
+        // SAFETY: `checked_range` ensures the range is within the memory.
+        unsafe { std::ptr::copy_nonoverlapping(data, bytes.as_mut_ptr(), size) };
+
+        Ok(RString::from_slice(&bytes))
+    }
+
+    /// @yard
+    /// Write +value+ starting at +offset+.
+    /// The write is not atomic: other threads may access the memory concurrently.
+    ///
+    /// @def write(offset, value)
+    /// @param offset [Integer]
+    /// @param value [String]
+    /// @return [void]
+    pub fn write(&self, offset: usize, value: RString) -> Result<(), Error> {
+        // SAFETY: the slice is copied before calling into Ruby again.
+        let bytes = unsafe { value.as_slice() };
+        let data = self.checked_range(offset, bytes.len())?;
+        // SAFETY: `checked_range` ensures the range is within the memory.
+        unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), data, bytes.len()) };
+
+        Ok(())
+    }
+
+    /// @yard
+    /// Atomically reads a 32-bit integer at +offset+, which must be 4-byte aligned.
+    /// @def atomic_load_i32(offset)
+    /// @param offset [Integer]
+    /// @return [Integer]
+    pub fn atomic_load_i32(&self, offset: usize) -> Result<i32, Error> {
+        Ok(self.atomic_i32(offset)?.load(Ordering::SeqCst))
+    }
+
+    /// @yard
+    /// Atomically writes a 32-bit integer at +offset+, which must be 4-byte aligned.
+    /// @def atomic_store_i32(offset, value)
+    /// @param offset [Integer]
+    /// @param value [Integer]
+    /// @return [void]
+    pub fn atomic_store_i32(&self, offset: usize, value: i32) -> Result<(), Error> {
+        self.atomic_i32(offset)?.store(value, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// @yard
+    /// Atomically adds +value+ to the 32-bit integer at +offset+, which must be 4-byte aligned.
+    /// @def atomic_add_i32(offset, value)
+    /// @param offset [Integer]
+    /// @param value [Integer]
+    /// @return [Integer] The previous value.
+    pub fn atomic_add_i32(&self, offset: usize, value: i32) -> Result<i32, Error> {
+        Ok(self.atomic_i32(offset)?.fetch_add(value, Ordering::SeqCst))
+    }
+
+    /// @yard
+    /// Atomically reads a 64-bit integer at +offset+, which must be 8-byte aligned.
+    /// @def atomic_load_i64(offset)
+    /// @param offset [Integer]
+    /// @return [Integer]
+    pub fn atomic_load_i64(&self, offset: usize) -> Result<i64, Error> {
+        Ok(self.atomic_i64(offset)?.load(Ordering::SeqCst))
+    }
+
+    /// @yard
+    /// Atomically writes a 64-bit integer at +offset+, which must be 8-byte aligned.
+    /// @def atomic_store_i64(offset, value)
+    /// @param offset [Integer]
+    /// @param value [Integer]
+    /// @return [void]
+    pub fn atomic_store_i64(&self, offset: usize, value: i64) -> Result<(), Error> {
+        self.atomic_i64(offset)?.store(value, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// @yard
+    /// Atomically adds +value+ to the 64-bit integer at +offset+, which must be 8-byte aligned.
+    /// @def atomic_add_i64(offset, value)
+    /// @param offset [Integer]
+    /// @param value [Integer]
+    /// @return [Integer] The previous value.
+    pub fn atomic_add_i64(&self, offset: usize, value: i64) -> Result<i64, Error> {
+        Ok(self.atomic_i64(offset)?.fetch_add(value, Ordering::SeqCst))
+    }
+
+    /// @yard
+    /// Wakes up to +count+ threads waiting on +offset+ (through +memory.atomic.wait32+
+    /// or +memory.atomic.wait64+).
+    /// @def atomic_notify(offset, count)
+    /// @param offset [Integer]
+    /// @param count [Integer]
+    /// @return [Integer] The number of threads woken up.
+    pub fn atomic_notify(&self, offset: u64, count: u32) -> Result<u32, Error> {
+        self.inner
+            .atomic_notify(offset, count)
+            .map_err(|e| error!("{}", e))
+    }
+
+    fn checked_range(&self, offset: usize, size: usize) -> Result<*mut u8, Error> {
+        let data = self.inner.data();
+        match offset.checked_add(size) {
+            Some(end) if end <= data.len() => Ok(data[offset..].as_ptr() as *mut u8),
+            _ => err!("out of bounds memory access"),
+        }
+    }
+
+    fn atomic_i32(&self, offset: usize) -> Result<&AtomicI32, Error> {
+        if offset % std::mem::align_of::<AtomicI32>() != 0 {
+            return err!("unaligned atomic access");
+        }
+        let ptr = self.checked_range(offset, std::mem::size_of::<AtomicI32>())?;
+        // SAFETY: the memory's base is page-aligned and `offset` is aligned, and
+        // the memory is never moved nor shrunk.
+        Ok(unsafe { &*(ptr as *const AtomicI32) })
+    }
+
+    fn atomic_i64(&self, offset: usize) -> Result<&AtomicI64, Error> {
+        if offset % std::mem::align_of::<AtomicI64>() != 0 {
+            return err!("unaligned atomic access");
+        }
+        let ptr = self.checked_range(offset, std::mem::size_of::<AtomicI64>())?;
+        // SAFETY: see `atomic_i32`.
+        Ok(unsafe { &*(ptr as *const AtomicI64) })
+    }
+}
+
+impl From<&SharedMemory> for Extern {
+    fn from(memory: &SharedMemory) -> Self {
+        Self::SharedMemory(memory.get().clone())
+    }
+}
+
+pub fn init() -> Result<(), Error> {
+    let class = root().define_class("SharedMemory", class::object())?;
+    class.define_singleton_method("new", function!(SharedMemory::new, -1))?;
+    class.define_method("min_size", method!(SharedMemory::min_size, 0))?;
+    class.define_method("max_size", method!(SharedMemory::max_size, 0))?;
+    class.define_method("size", method!(SharedMemory::size, 0))?;
+    class.define_method("data_size", method!(SharedMemory::data_size, 0))?;
+    class.define_method("grow", method!(SharedMemory::grow, 1))?;
+    class.define_method("read", method!(SharedMemory::read, 2))?;
+    class.define_method("write", method!(SharedMemory::write, 2))?;
+    class.define_method("atomic_load_i32", method!(SharedMemory::atomic_load_i32, 1))?;
+    class.define_method(
+        "atomic_store_i32",
+        method!(SharedMemory::atomic_store_i32, 2),
+    )?;
+    class.define_method("atomic_add_i32", method!(SharedMemory::atomic_add_i32, 2))?;
+    class.define_method("atomic_load_i64", method!(SharedMemory::atomic_load_i64, 1))?;
+    class.define_method(
+        "atomic_store_i64",
+        method!(SharedMemory::atomic_store_i64, 2),
+    )?;
+    class.define_method("atomic_add_i64", method!(SharedMemory::atomic_add_i64, 2))?;
+    class.define_method("atomic_notify", method!(SharedMemory::atomic_notify, 2))?;
+
+    Ok(())
+}
@@ -1,36 +1,191 @@
-use super::errors::wasi_exit_error;
-use super::{caller::Caller, engine::Engine, root, trap::Trap, wasi_ctx::WasiCtx};
-use crate::{define_rb_intern, error};
+use super::component::{HostResources, WasiHttpCtxBuilder, WasiHttpState};
+use super::errors::{deadline_exceeded_error, timeout_error, wasi_exit_error, wasmtime_error};
+use super::{
+    caller::Caller,
+    convert::mark_externref,
+    engine::{Engine, Epoch},
+    epoch_timer::{self, Timer},
+    module::Module as ModuleObj,
+    root,
+    trap::Trap,
+    wasi_ctx::WasiCtx,
+    WasiCtxBuilder, WasiP2CtxBuilder, WasiP2State,
+};
+use crate::{
+    define_rb_intern, err, error,
+    helpers::{check_interrupts, nogvl_interruptible, with_gvl},
+};
 use magnus::value::StaticSymbol;
 use magnus::{
     class, function,
     gc::{Compactor, Marker},
-    method, scan_args,
+    method,
+    rb_sys::AsRawValue,
+    scan_args,
     typed_data::Obj,
-    value::Opaque,
-    DataTypeFunctions, Error, IntoValue, Module, Object, Ruby, TypedData, Value,
+    value::{Opaque, ReprValue},
+    DataTypeFunctions, Error, Exception, IntoValue, Module, Object, Ruby, TypedData, Value,
 };
-use magnus::{Class, RHash};
+use magnus::{Class, RArray, RHash, RProc, RString, TryConvert};
 use std::borrow::Borrow;
 use std::cell::UnsafeCell;
+use std::collections::HashSet;
 use std::convert::TryFrom;
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
 use wasmtime::{
-    AsContext, AsContextMut, Store as StoreImpl, StoreContext, StoreContextMut, StoreLimits,
-    StoreLimitsBuilder,
+    AsContext, AsContextMut, ExternRef, GuestProfiler, ResourceLimiter, Store as StoreImpl,
+    StoreContext, StoreContextMut, StoreLimits, StoreLimitsBuilder, UpdateDeadline, Val,
+    WasmBacktrace,
 };
-use wasmtime_wasi::{I32Exit, WasiCtx as WasiCtxImpl};
+use wasmtime_wasi::{preview2::I32Exit as P2I32Exit, I32Exit, WasiCtx as WasiCtxImpl};
 
 define_rb_intern!(
     WASI_CTX => "wasi_ctx",
+    WASI_HTTP_CTX => "wasi_http_ctx",
+    WASI_P2_CTX => "wasi_p2_ctx",
+    WASI_NN_CTX => "wasi_nn_ctx",
+    WASI_KEYVALUE => "wasi_keyvalue",
     LIMITS => "limits",
+    RETURN_EXIT_CODE => "return_exit_code",
+    INTERVAL => "interval",
+    MODULES => "modules",
 );
 
+/// The wall-clock budget of a store's Wasm calls, see [`Store::set_time_limit`].
+struct TimeLimit {
+    limit: Duration,
+    deadline: Instant,
+    /// Dropping it cancels the epoch increment at the deadline.
+    _timer: Timer,
+}
+
+/// The timeout of the Wasm call in progress, see [`Func::call`].
+pub struct CallTimeout {
+    timeout: Duration,
+    deadline: Instant,
+    _timer: Timer,
+}
+
+impl CallTimeout {
+    pub fn new(epoch: &Epoch, timeout: Duration) -> Result<Self, Error> {
+        let deadline = Instant::now() + timeout;
+        Ok(Self {
+            timeout,
+            deadline,
+            _timer: epoch_timer::schedule(epoch.clone(), deadline)?,
+        })
+    }
+}
+
+/// The error of Wasm calls exceeding their timeout.
+#[derive(Debug)]
+pub struct TimeoutExceeded(Duration);
+
+impl std::fmt::Display for TimeoutExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Wasm call exceeded its timeout of {:?}", self.0)
+    }
+}
+
+impl std::error::Error for TimeoutExceeded {}
+
+/// The error of Wasm calls once their store's time limit is exceeded.
+#[derive(Debug)]
+pub struct TimeLimitExceeded(Duration);
+
+impl std::fmt::Display for TimeLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Store exceeded its time limit of {:?}", self.0)
+    }
+}
+
+impl std::error::Error for TimeLimitExceeded {}
+
+/// A [`GuestProfiler`] along with the time of its last sample.
+struct Profiler {
+    inner: GuestProfiler,
+    last_sample: Instant,
+}
+
+/// The resources used by the guests of a store, tracked by its limiter.
+#[derive(Default)]
+struct ResourceUsage {
+    memory_bytes: usize,
+    table_elements: usize,
+    instances: usize,
+    /// The growths last allowed, undone if they fail.
+    pending_memory_growth: usize,
+    pending_table_growth: usize,
+}
+
+/// The number of `Func.new` blocks a store retains before first pruning the
+/// dead ones.
+const MIN_HOST_BLOCKS_PRUNE_AT: usize = 64;
+
+/// Shared by a store and the Ruby objects ([`super::func::Func`] and
+/// [`super::typed_func::TypedFunc`]) of a host function, whose block the
+/// store keeps alive while any of them is.
+#[derive(Clone, Default)]
+pub struct HostBlockHandle(Arc<AtomicBool>);
+
+impl HostBlockHandle {
+    /// Keeps the block alive for the lifetime of the store, once the function
+    /// is handed to Wasm (e.g. as an import or in a table).
+    pub fn escape(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the block may still be called. Dead blocks can't come back to
+    /// life: nothing but the store holds their handle.
+    fn is_live(&self) -> bool {
+        self.0.load(Ordering::Relaxed) || Arc::strong_count(&self.0) > 1
+    }
+}
+
 pub struct StoreData {
     user_data: Value,
     wasi: Option<WasiCtxImpl>,
+    wasi_p2: Option<WasiP2State>,
+    wasi_http: Option<WasiHttpState>,
+    #[cfg(feature = "wasi-nn")]
+    wasi_nn: Option<wasmtime_wasi_nn::WasiNnCtx>,
+    /// The Ruby object backing +wasi:keyvalue+, pinned by `mark`.
+    wasi_keyvalue: Option<Opaque<Value>>,
+    host_resources: HostResources,
     refs: Vec<Value>,
+    /// The raw values of `refs`, to retain each value once.
+    ref_set: HashSet<rb_sys::VALUE>,
+    /// The blocks of `Func.new`, see [`StoreData::retain_host_block`].
+    host_blocks: Vec<(Value, HostBlockHandle)>,
+    /// The length of `host_blocks` at which dead blocks are pruned.
+    host_blocks_prune_at: usize,
+    externrefs: Vec<ExternRef>,
     last_error: Option<Error>,
     store_limits: StoreLimits,
+    on_limit_exceeded: Option<Opaque<RProc>>,
+    on_memory_grow: Option<Opaque<RProc>>,
+    on_table_grow: Option<Opaque<RProc>>,
+    on_host_error: Option<Opaque<RProc>>,
+    fuel_granted: u64,
+    usage: ResourceUsage,
+    async_support: bool,
+    epoch_interruption: bool,
+    profiler: Option<Profiler>,
+    time_limit: Option<TimeLimit>,
+    call_timeout: Option<CallTimeout>,
+    epoch: Epoch,
+    /// The epoch set with [`Store::set_epoch_deadline`], see
+    /// [`on_epoch_deadline`].
+    epoch_deadline: u64,
+    /// Set when incrementing the epoch to interrupt a call, for the epoch
+    /// deadline callback to trap.
+    interrupt_requested: Arc<AtomicBool>,
+    return_exit_code: bool,
 }
 
 impl StoreData {
@@ -38,6 +193,55 @@ impl StoreData {
         self.user_data
     }
 
+    pub fn set_user_data(&mut self, user_data: Value) {
+        self.user_data = user_data;
+    }
+
+    /// Whether the store's engine has +async_support+ enabled, in which case
+    /// Wasm must be called with [`crate::helpers::block_on`].
+    pub fn is_async(&self) -> bool {
+        self.async_support
+    }
+
+    /// Whether the store's engine has +epoch_interruption+ enabled.
+    pub fn has_epoch_interruption(&self) -> bool {
+        self.epoch_interruption
+    }
+
+    /// Whether Wasm calls exiting through WASI's +proc_exit+ with code 0
+    /// return the exit code instead of raising.
+    pub fn returns_exit_code(&self) -> bool {
+        self.return_exit_code
+    }
+
+    /// The epoch of the store's engine.
+    pub fn epoch(&self) -> &Epoch {
+        &self.epoch
+    }
+
+    /// Replaces the timeout of the call in progress, returning the previous
+    /// one, e.g. of the call a host function runs in.
+    pub fn replace_call_timeout(&mut self, timeout: Option<CallTimeout>) -> Option<CallTimeout> {
+        std::mem::replace(&mut self.call_timeout, timeout)
+    }
+
+    /// The flag to set before incrementing the epoch to interrupt a call in
+    /// this store, see [`on_epoch_deadline`].
+    pub fn interrupt_requested(&self) -> Arc<AtomicBool> {
+        self.interrupt_requested.clone()
+    }
+
+    /// The block converting host function errors to results, see
+    /// [`Store::on_host_error`].
+    pub fn on_host_error(&self) -> Option<Opaque<RProc>> {
+        self.on_host_error
+    }
+
+    /// Counts an instance created in the store, see [`Store::resource_usage`].
+    pub fn count_instance(&mut self) {
+        self.usage.instances += 1;
+    }
+
     pub fn has_wasi_ctx(&self) -> bool {
         self.wasi.is_some()
     }
@@ -46,8 +250,90 @@ impl StoreData {
         self.wasi.as_mut().expect("Store must have a WASI context")
     }
 
+    pub fn has_wasi_p2_ctx(&self) -> bool {
+        self.wasi_p2.is_some()
+    }
+
+    /// The WASI preview 2 state, which WASI HTTP also relies on: stores
+    /// created without +wasi_p2_ctx+ get an empty one on first use.
+    pub fn wasi_p2_mut(&mut self) -> &mut WasiP2State {
+        self.wasi_p2.get_or_insert_with(WasiP2State::default)
+    }
+
+    /// The wasi-nn context: stores created without +wasi_nn_ctx+ get one
+    /// without backends on first use.
+    #[cfg(feature = "wasi-nn")]
+    pub fn wasi_nn_mut(&mut self) -> &mut wasmtime_wasi_nn::WasiNnCtx {
+        self.wasi_nn.get_or_insert_with(super::wasi_nn::empty_ctx)
+    }
+
+    pub fn wasi_keyvalue_backend(&self) -> Option<Opaque<Value>> {
+        self.wasi_keyvalue
+    }
+
+    /// The Ruby objects of the resources handed to components.
+    pub fn host_resources(&self) -> &HostResources {
+        &self.host_resources
+    }
+
+    pub fn host_resources_mut(&mut self) -> &mut HostResources {
+        &mut self.host_resources
+    }
+
+    pub fn has_wasi_http_ctx(&self) -> bool {
+        self.wasi_http.is_some()
+    }
+
+    pub fn wasi_http_mut(&mut self) -> &mut WasiHttpState {
+        self.wasi_http
+            .as_mut()
+            .expect("Store must have a WASI HTTP context")
+    }
+
+    /// Keeps `value` alive for the lifetime of the store. Values are only
+    /// retained once, as some are retained on each use (e.g. the blocks of a
+    /// `Linker`'s functions on every instantiation).
     pub fn retain(&mut self, value: Value) {
-        self.refs.push(value);
+        if self.ref_set.insert(value.as_raw()) {
+            self.refs.push(value);
+        }
+    }
+
+    /// Keeps the block of a host function alive while the returned handle
+    /// is held by the function's Ruby objects, or for the lifetime of the
+    /// store once the function was handed to Wasm (see
+    /// [`HostBlockHandle::escape`]), which may call it whatever happens to
+    /// its Ruby objects.
+    pub fn retain_host_block(&mut self, block: Value) -> HostBlockHandle {
+        if self.host_blocks.len() >= self.host_blocks_prune_at {
+            self.prune_host_blocks();
+        }
+        let handle = HostBlockHandle::default();
+        self.host_blocks.push((block, handle.clone()));
+        handle
+    }
+
+    /// Forgets the blocks that are no longer marked, see
+    /// [`StoreData::retain_host_block`].
+    fn prune_host_blocks(&mut self) {
+        self.host_blocks.retain(|(_, handle)| handle.is_live());
+        self.host_blocks_prune_at = (self.host_blocks.len() * 2).max(MIN_HOST_BLOCKS_PRUNE_AT);
+    }
+
+    /// Keeps the Ruby object wrapped by `val` alive for as long as Wasm may
+    /// reference it, when `val` is a non-null externref.
+    pub fn retain_externref(&mut self, val: &Val) {
+        if let Val::ExternRef(Some(externref)) = val {
+            self.externrefs.push(externref.clone());
+        }
+    }
+
+    /// Releases the externrefs only referenced by this store. Must be called
+    /// right after a GC, which releases the ones only referenced from
+    /// previous Wasm calls.
+    fn prune_externrefs(&mut self) {
+        self.externrefs
+            .retain(|externref| externref.strong_count() > 1);
     }
 
     pub fn set_error(&mut self, error: Error) {
@@ -67,9 +353,38 @@ impl StoreData {
             }
         }
 
+        for callback in [
+            self.on_limit_exceeded,
+            self.on_memory_grow,
+            self.on_table_grow,
+            self.on_host_error,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            marker.mark(callback);
+        }
+
+        // Pinned: unlike `refs`, not updated by `compact`.
+        if let Some(backend) = self.wasi_keyvalue {
+            marker.mark(backend);
+        }
+
         for value in self.refs.iter() {
             marker.mark_movable(*value);
         }
+
+        for (block, handle) in self.host_blocks.iter() {
+            if handle.is_live() {
+                marker.mark_movable(*block);
+            }
+        }
+
+        self.host_resources.mark(marker);
+
+        for externref in self.externrefs.iter() {
+            mark_externref(externref, marker);
+        }
     }
 
     pub fn compact(&mut self, compactor: &Compactor) {
@@ -78,16 +393,236 @@ impl StoreData {
         for value in self.refs.iter_mut() {
             *value = compactor.location(*value);
         }
+        self.ref_set = self.refs.iter().map(|value| value.as_raw()).collect();
+
+        for (block, handle) in self.host_blocks.iter_mut() {
+            if handle.is_live() {
+                *block = compactor.location(*block);
+            }
+        }
+    }
+
+    fn limit_exceeded(
+        &mut self,
+        resource: &'static str,
+        current: usize,
+        desired: usize,
+    ) -> anyhow::Result<()> {
+        let callback = match self.on_limit_exceeded {
+            Some(callback) => callback,
+            None => return Ok(()),
+        };
+
+        let result = with_gvl(|| {
+            let ruby = Ruby::get().unwrap();
+            ruby.get_inner(callback)
+                .call::<_, Value>((StaticSymbol::new(resource), current, desired))
+                .map(|_| ())
+        });
+
+        result.map_err(|e| {
+            self.set_error(e);
+            anyhow::anyhow!("")
+        })
+    }
+
+    /// Asks `callback`, if any, whether a growth allowed by the limits
+    /// should happen.
+    fn growing(
+        &mut self,
+        callback: Option<Opaque<RProc>>,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        let callback = match callback {
+            Some(callback) => callback,
+            None => return Ok(true),
+        };
+
+        let result = with_gvl(|| {
+            let ruby = Ruby::get().unwrap();
+            ruby.get_inner(callback)
+                .call::<_, Value>((current, desired, maximum))
+                .map(|allowed| allowed.to_bool())
+        });
+
+        result.map_err(|e| {
+            self.set_error(e);
+            anyhow::anyhow!("")
+        })
+    }
+}
+
+impl ResourceLimiter for StoreData {
+    fn memory_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        let allowed = self
+            .store_limits
+            .memory_growing(current, desired, maximum)?;
+        if !allowed {
+            self.limit_exceeded("memory", current, desired)?;
+            return Ok(false);
+        }
+        let allowed = self.growing(self.on_memory_grow, current, desired, maximum)?;
+        if allowed {
+            let growth = desired.saturating_sub(current);
+            self.usage.memory_bytes += growth;
+            self.usage.pending_memory_growth = growth;
+        }
+        Ok(allowed)
+    }
+
+    fn memory_grow_failed(&mut self, error: anyhow::Error) -> anyhow::Result<()> {
+        self.usage.memory_bytes -= std::mem::take(&mut self.usage.pending_memory_growth);
+        self.store_limits.memory_grow_failed(error)
+    }
+
+    fn table_growing(
+        &mut self,
+        current: u32,
+        desired: u32,
+        maximum: Option<u32>,
+    ) -> anyhow::Result<bool> {
+        let allowed = self.store_limits.table_growing(current, desired, maximum)?;
+        if !allowed {
+            self.limit_exceeded("table", current as _, desired as _)?;
+            return Ok(false);
+        }
+        let allowed = self.growing(
+            self.on_table_grow,
+            current as _,
+            desired as _,
+            maximum.map(|max| max as _),
+        )?;
+        if allowed {
+            let growth = desired.saturating_sub(current) as usize;
+            self.usage.table_elements += growth;
+            self.usage.pending_table_growth = growth;
+        }
+        Ok(allowed)
+    }
+
+    fn table_grow_failed(&mut self, error: anyhow::Error) -> anyhow::Result<()> {
+        self.usage.table_elements -= std::mem::take(&mut self.usage.pending_table_growth);
+        self.store_limits.table_grow_failed(error)
+    }
+
+    fn instances(&self) -> usize {
+        self.store_limits.instances()
+    }
+
+    fn tables(&self) -> usize {
+        self.store_limits.tables()
+    }
+
+    fn memories(&self) -> usize {
+        self.store_limits.memories()
+    }
+}
+
+/// The Ruby thread using a [`Store`] while Wasm runs with the GVL released,
+/// during which other threads must not access the store.
+#[derive(Debug, Default)]
+struct StoreLock {
+    state: Mutex<LockState>,
+    released: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct LockState {
+    owner: Option<ThreadId>,
+    depth: usize,
+}
+
+impl StoreLock {
+    fn is_held_elsewhere(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state
+            .owner
+            .is_some_and(|owner| owner != thread::current().id())
+    }
+
+    /// Acquires the lock, re-entrantly. When another thread holds it, waits
+    /// for it with the GVL released if `wait` is true, raises otherwise.
+    ///
+    /// The wait is interruptible: e.g. +Thread#raise+ or +SIGINT+ stop it and
+    /// are raised.
+    fn acquire(&self, wait: bool) -> Result<StoreLockGuard<'_>, Error> {
+        let current = thread::current().id();
+        let mut state = self.state.lock().unwrap();
+        while state.owner.is_some_and(|owner| owner != current) {
+            if !wait {
+                return Err(in_use_error());
+            }
+            let cancelled = AtomicBool::new(false);
+            state = nogvl_interruptible(
+                || {
+                    self.released
+                        .wait_while(state, |state| {
+                            state.owner.is_some() && !cancelled.load(Ordering::SeqCst)
+                        })
+                        .unwrap()
+                },
+                || {
+                    // Under the lock, not to notify between the waiter's check
+                    // of `cancelled` and its wait.
+                    let _state = self.state.lock().unwrap();
+                    cancelled.store(true, Ordering::SeqCst);
+                    self.released.notify_all();
+                },
+            )?;
+            if cancelled.load(Ordering::SeqCst) {
+                // Raise the interrupt, or wait again if it was handled
+                // without raising (e.g. a trapped signal).
+                drop(state);
+                check_interrupts()?;
+                state = self.state.lock().unwrap();
+            }
+        }
+
+        state.owner = Some(current);
+        state.depth += 1;
+        Ok(StoreLockGuard(self))
     }
 }
 
+/// Releases a [`StoreLock`] when dropped.
+pub struct StoreLockGuard<'a>(&'a StoreLock);
+
+impl Drop for StoreLockGuard<'_> {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap();
+        state.depth -= 1;
+        if state.depth == 0 {
+            state.owner = None;
+            self.0.released.notify_all();
+        }
+    }
+}
+
+fn in_use_error() -> Error {
+    error!("Store is in use by another thread, see Store#with_lock to share it between threads")
+}
+
 /// @yard
 /// Represents a WebAssembly store.
+///
+/// A store can be used by one Ruby thread at a time. Wasm runs with the GVL
+/// released, and using the store from another thread in the meantime (e.g.
+/// calling one of its functions or reading one of its memories) raises an
+/// {Error}. Threads sharing a store on purpose must use it within
+/// {#with_lock}.
 /// @see https://docs.rs/wasmtime/latest/wasmtime/struct.Store.html Wasmtime's Rust doc
 #[derive(Debug, TypedData)]
 #[magnus(class = "Wasmtime::Store", size, mark, compact, free_immediately)]
 pub struct Store {
     inner: UnsafeCell<StoreImpl<StoreData>>,
+    lock: StoreLock,
 }
 
 impl DataTypeFunctions for Store {
@@ -106,13 +641,33 @@ unsafe impl Send for StoreData {}
 impl Store {
     /// @yard
     ///
-    /// @def new(engine, data = nil, wasi_ctx: nil)
+    /// @def new(engine, data = nil, wasi_ctx: nil, wasi_p2_ctx: nil, wasi_http_ctx: nil, wasi_nn_ctx: nil, wasi_keyvalue: nil, limits: nil, return_exit_code: false)
     /// @param engine [Wasmtime::Engine]
     ///   The engine for this store.
     /// @param data [Object]
     ///   The data attached to the store. Can be retrieved through {Wasmtime::Store#data} and {Wasmtime::Caller#data}.
-    /// @param wasi_ctx [Wasmtime::WasiCtxBuilder]
-    ///   The WASI context to use in this store.
+    /// @param wasi_ctx [Wasmtime::WasiCtxBuilder, Wasmtime::WasiCtx]
+    ///   The WASI context to use in this store. A new context is built for
+    ///   each store created with a {Wasmtime::WasiCtxBuilder}, while stores
+    ///   given the same {Wasmtime::WasiCtx} share its state (e.g. open files
+    ///   and stdin).
+    /// @param wasi_p2_ctx [Wasmtime::WasiP2CtxBuilder]
+    ///   The WASI preview 2 context of components instantiated in this store.
+    /// @param wasi_http_ctx [Wasmtime::Component::WasiHttpCtxBuilder]
+    ///   The outbound HTTP configuration of components instantiated in this store.
+    /// @param wasi_nn_ctx [Wasmtime::WasiNnCtxBuilder]
+    ///   The wasi-nn backends and graphs of the modules and components
+    ///   instantiated in this store. Requires the +wasi-nn+ feature.
+    /// @param wasi_keyvalue [Object]
+    ///   The key-value store of the components instantiated in this store
+    ///   through +wasi:keyvalue+ (see {Component::Linker.new}), e.g. backed by
+    ///   Redis. Must respond to +get(bucket, key)+ (returning a +String+ or
+    ///   +nil+), +set(bucket, key, value)+ and +delete(bucket, key)+, and can
+    ///   respond to +open(bucket)+ (returning whether the guest may use the
+    ///   bucket), +exists?(bucket, key)+ and +keys(bucket, cursor)+. +keys+
+    ///   returns a page of keys and the +Integer+ cursor of the next page, or
+    ///   +nil+ for the last page, as +[keys, next_cursor]+; +cursor+ is +nil+
+    ///   for the first page.
     /// @param limits [Hash]
     ///   See the {https://docs.rs/wasmtime/latest/wasmtime/struct.StoreLimitsBuilder.html +StoreLimitsBuilder+‘s Rust doc}
     ///   for detailed description of the different options and the defaults.
@@ -126,6 +681,10 @@ impl Store {
     ///   The maximum number of tables that can be created for a Store.
     /// @option limits memories [Integer]
     ///   The maximum number of linear memories that can be created for a Store.
+    /// @param return_exit_code [Boolean]
+    ///   Whether calling a Wasm function that exits with code 0 through WASI's
+    ///   +proc_exit+ (e.g. a command's +_start+) returns +0+ instead of raising
+    ///   {Wasmtime::WasiExit}. Other exit codes still raise.
     /// @return [Wasmtime::Store]
     ///
     /// @example
@@ -135,39 +694,263 @@ impl Store {
     ///   store = Wasmtime::Store.new(Wasmtime::Engine.new, {})
     pub fn new(args: &[Value]) -> Result<Self, Error> {
         let args = scan_args::scan_args::<(&Engine,), (Option<Value>,), (), (), _, ()>(args)?;
-        let kw = scan_args::get_kwargs::<_, (), (Option<&WasiCtx>, Option<RHash>), ()>(
+        let kw = scan_args::get_kwargs::<
+            _,
+            (),
+            (
+                Option<Value>,
+                Option<RHash>,
+                Option<bool>,
+                Option<&WasiHttpCtxBuilder>,
+                Option<&WasiP2CtxBuilder>,
+                Option<Value>,
+                Option<Value>,
+            ),
+            (),
+        >(
             args.keywords,
             &[],
-            &[*WASI_CTX, *LIMITS],
+            &[
+                *WASI_CTX,
+                *LIMITS,
+                *RETURN_EXIT_CODE,
+                *WASI_HTTP_CTX,
+                *WASI_P2_CTX,
+                *WASI_NN_CTX,
+                *WASI_KEYVALUE,
+            ],
         )?;
 
         let (engine,) = args.required;
         let (user_data,) = args.optional;
         let user_data = user_data.unwrap_or_else(|| ().into_value());
-        let wasi = kw.optional.0.map(|wasi_ctx| wasi_ctx.get_inner());
+        let (wasi, mut refs) = match kw.optional.0 {
+            Some(wasi_ctx) => {
+                let (ctx, refs) = wasi_ctx_state(wasi_ctx)?;
+                (Some(ctx), refs)
+            }
+            None => (None, vec![]),
+        };
+        let wasi_p2 = match kw.optional.4 {
+            Some(builder) => {
+                let (state, p2_refs) = builder.build_state(&Ruby::get().unwrap())?;
+                refs.extend(p2_refs);
+                Some(state)
+            }
+            None => None,
+        };
+        #[cfg(feature = "wasi-nn")]
+        let wasi_nn = match kw.optional.5 {
+            Some(builder) => {
+                Some(<&super::wasi_nn::WasiNnCtxBuilder>::try_convert(builder)?.build_ctx()?)
+            }
+            None => None,
+        };
+        #[cfg(not(feature = "wasi-nn"))]
+        if kw.optional.5.is_some() {
+            return err!("wasi_nn_ctx requires the gem to be built with the wasi-nn feature");
+        }
+
+        let wasi_keyvalue = match kw.optional.6 {
+            Some(backend) => Some(super::component::check_keyvalue_backend(backend)?),
+            None => None,
+        };
 
         let limiter = match kw.optional.1 {
             None => StoreLimitsBuilder::new(),
             Some(limits) => hash_to_store_limits_builder(limits)?,
         };
 
-        let eng = engine.get();
+        let eng = &engine.get()?;
         let store_data = StoreData {
             user_data,
             wasi,
-            refs: Default::default(),
+            wasi_p2,
+            wasi_http: kw.optional.3.map(|builder| builder.build_state()),
+            #[cfg(feature = "wasi-nn")]
+            wasi_nn,
+            wasi_keyvalue,
+            host_resources: Default::default(),
+            ref_set: refs.iter().map(|value| value.as_raw()).collect(),
+            refs,
+            host_blocks: Default::default(),
+            host_blocks_prune_at: MIN_HOST_BLOCKS_PRUNE_AT,
+            externrefs: Default::default(),
             last_error: Default::default(),
             store_limits: limiter.build(),
+            on_limit_exceeded: None,
+            on_memory_grow: None,
+            on_table_grow: None,
+            on_host_error: None,
+            fuel_granted: 0,
+            usage: Default::default(),
+            async_support: engine.is_async(),
+            epoch_interruption: engine.has_epoch_interruption(),
+            profiler: None,
+            time_limit: None,
+            call_timeout: None,
+            epoch: engine.epoch()?,
+            epoch_deadline: 0,
+            interrupt_requested: Default::default(),
+            return_exit_code: kw.optional.2.unwrap_or(false),
         };
         let store = Self {
             inner: UnsafeCell::new(StoreImpl::new(eng, store_data)),
+            lock: StoreLock::default(),
         };
 
-        unsafe { &mut *store.inner.get() }.limiter(|data| &mut data.store_limits);
+        let inner = unsafe { &mut *store.inner.get() };
+        inner.limiter(|data| data);
+        if engine.has_epoch_interruption() {
+            inner.epoch_deadline_callback(on_epoch_deadline);
+        }
 
         Ok(store)
     }
 
+    /// @yard
+    /// Replaces the {Store}'s resource limits. Limits not given are reset to
+    /// their defaults, see {.new} for the available options.
+    ///
+    /// When a block is given, it is called every time a memory or a table
+    /// growth is denied because of a limit. Exceptions raised from the block
+    /// are propagated to the caller of the operation that attempted to grow.
+    ///
+    /// @def set_limits(memory_size: nil, table_elements: nil, instances: nil, tables: nil, memories: nil, &block)
+    /// @param memory_size [Integer]
+    ///   The maximum number of bytes a linear memory can grow to.
+    /// @param table_elements [Integer]
+    ///   The maximum number of elements in a table.
+    /// @param instances [Integer]
+    ///   The maximum number of instances that can be created for a Store.
+    /// @param tables [Integer]
+    ///   The maximum number of tables that can be created for a Store.
+    /// @param memories [Integer]
+    ///   The maximum number of linear memories that can be created for a Store.
+    /// @yield [resource, current, desired] Called when a limit is hit.
+    /// @yieldparam resource [Symbol] +:memory+ (sizes in bytes) or +:table+ (sizes in elements).
+    /// @yieldparam current [Integer] The current size.
+    /// @yieldparam desired [Integer] The size that was requested.
+    /// @return [nil]
+    ///
+    /// @example
+    ///   store.set_limits(memory_size: 10 * 2**20) do |resource, current, desired|
+    ///     logger.warn("tenant #{tenant_id} hit its #{resource} limit (#{current} -> #{desired})")
+    ///   end
+    pub fn set_limits(&self, args: &[Value]) -> Result<(), Error> {
+        self.check_thread()?;
+        let args = scan_args::scan_args::<(), (), (), (), RHash, Option<RProc>>(args)?;
+        let limits = hash_to_store_limits_builder(args.keywords)?.build();
+
+        let data = self.context_mut().data_mut();
+        data.store_limits = limits;
+        data.on_limit_exceeded = args.block.map(Opaque::from);
+
+        Ok(())
+    }
+
+    /// @yard
+    /// Registers a block called every time a memory of the store is about to
+    /// grow, including its initial allocation at instantiation, to allow or
+    /// veto the growth. Growths denied by {#set_limits} don't call the block.
+    ///
+    /// A vetoed growth makes the guest's +memory.grow+ return -1, makes
+    /// {Memory#grow} raise, and makes instantiation fail. Exceptions raised
+    /// from the block are propagated to the caller of the operation that
+    /// attempted to grow.
+    ///
+    /// Replaces the previously registered block; call without a block to
+    /// remove it. Not supported in engines with +async_support: true+.
+    ///
+    /// @def on_memory_grow(&block)
+    /// @yield [current, desired, maximum]
+    /// @yieldparam current [Integer] The current size in bytes.
+    /// @yieldparam desired [Integer] The requested size in bytes.
+    /// @yieldparam maximum [Integer, nil] The maximum size of the memory
+    ///   in bytes, if any.
+    /// @yieldreturn [Boolean] Whether to allow the growth.
+    /// @return [nil]
+    ///
+    /// @example
+    ///   store.on_memory_grow do |_current, desired, _maximum|
+    ///     tenant.memory_budget >= desired
+    ///   end
+    pub fn on_memory_grow(&self, args: &[Value]) -> Result<(), Error> {
+        self.check_thread()?;
+        let callback = self.growth_callback(args, "Store#on_memory_grow")?;
+        self.context_mut().data_mut().on_memory_grow = callback;
+        Ok(())
+    }
+
+    /// @yard
+    /// Like {#on_memory_grow}, for the tables of the store. Sizes are in
+    /// elements.
+    ///
+    /// @def on_table_grow(&block)
+    /// @yield [current, desired, maximum]
+    /// @yieldparam current [Integer] The current number of elements.
+    /// @yieldparam desired [Integer] The requested number of elements.
+    /// @yieldparam maximum [Integer, nil] The maximum number of elements of
+    ///   the table, if any.
+    /// @yieldreturn [Boolean] Whether to allow the growth.
+    /// @return [nil]
+    pub fn on_table_grow(&self, args: &[Value]) -> Result<(), Error> {
+        self.check_thread()?;
+        let callback = self.growth_callback(args, "Store#on_table_grow")?;
+        self.context_mut().data_mut().on_table_grow = callback;
+        Ok(())
+    }
+
+    /// @yard
+    /// Registers a block converting the exceptions raised by the host
+    /// functions of the store (see {Func.new} and {Linker#func_new}) to the
+    /// results returned to the guest, e.g. an error code, for ABIs that
+    /// report errors through return values. Without it, exceptions abort the
+    /// guest and are raised to the caller of the Wasm function.
+    ///
+    /// Only +StandardError+s are converted. Exceptions raised from the block,
+    /// including re-raising the one given, abort the guest as without it.
+    ///
+    /// Replaces the previously registered block; call without a block to
+    /// remove it.
+    ///
+    /// @def on_host_error(&block)
+    /// @yield [error, results]
+    /// @yieldparam error [StandardError] The exception raised by the host function.
+    /// @yieldparam results [Array<Symbol>] The types of the host function's results.
+    /// @yieldreturn [nil, Object, Array<Object>] The results, as returned by
+    ///   a {Func.new} block.
+    /// @return [nil]
+    ///
+    /// @example Returning -1 from failing functions:
+    ///   store.on_host_error do |error, results|
+    ///     raise error unless results == [:i32]
+    ///     logger.warn("host function failed: #{error.message}")
+    ///     -1
+    ///   end
+    pub fn on_host_error(&self, args: &[Value]) -> Result<(), Error> {
+        self.check_thread()?;
+        let args = scan_args::scan_args::<(), (), (), (), (), Option<RProc>>(args)?;
+        self.context_mut().data_mut().on_host_error = args.block.map(Opaque::from);
+        Ok(())
+    }
+
+    fn growth_callback(
+        &self,
+        args: &[Value],
+        method: &str,
+    ) -> Result<Option<Opaque<RProc>>, Error> {
+        let args = scan_args::scan_args::<(), (), (), (), (), Option<RProc>>(args)?;
+        if args.block.is_some() && self.context().data().is_async() {
+            return err!(
+                "{} is not supported in engines with async_support: true",
+                method
+            );
+        }
+
+        Ok(args.block.map(Opaque::from))
+    }
+
     /// @yard
     /// @return [Object] The passed in value in {.new}
     pub fn data(&self) -> Value {
@@ -175,11 +958,25 @@ impl Store {
     }
 
     /// @yard
-    /// Returns the amount of fuel in the {Store}.
+    /// Replaces the store's data, e.g. to attach a new request context to a
+    /// long-lived store. The previous data is no longer retained by the store.
+    /// @def data=(data)
+    /// @param data [Object]
+    /// @return [Object] +data+
+    pub fn set_data(&self, data: Value) -> Result<Value, Error> {
+        self.check_thread()?;
+        self.context_mut().data_mut().set_user_data(data);
+        Ok(data)
+    }
+
+    /// @yard
+    /// Returns the amount of fuel in the {Store}. Also available as
+    /// +fuel_remaining+.
     ///
     /// @return [Integer]
     /// @raise [Error] if fuel consumption is not enabled via {Wasmtime::Engine#new}
     pub fn get_fuel(&self) -> Result<u64, Error> {
+        self.check_thread()?;
         self.inner_ref().get_fuel().map_err(|e| error!("{}", e))
     }
 
@@ -188,26 +985,370 @@ impl Store {
     /// @param fuel [Integer] The new fuel amount.
     /// @def set_fuel(fuel)
     /// @raise [Error] if fuel consumption is not enabled via {Wasmtime::Engine#new}
+    /// Sets the cause of the exception a host function raised, unless it already
+    /// has one, to a [`Trap`] carrying the Wasm backtrace of the call.
+    fn chain_to_trap(raised: Error, error: anyhow::Error) -> Error {
+        let Some(exception) = raised.value().and_then(Exception::from_value) else {
+            return raised;
+        };
+        let has_cause = exception
+            .funcall::<_, _, Value>("cause", ())
+            .map_or(true, |cause| !cause.is_nil());
+        if has_cause || exception.is_frozen() {
+            return raised;
+        }
+
+        let trap = Obj::wrap(Trap::host(error.downcast::<WasmBacktrace>().ok()));
+        // `cause` is the (hidden) instance variable Ruby's `raise` sets.
+        let _ = exception.ivar_set("cause", trap);
+        raised
+    }
+
     pub fn set_fuel(&self, fuel: u64) -> Result<(), Error> {
-        unsafe { &mut *self.inner.get() }
-            .set_fuel(fuel)
-            .map_err(|e| error!("{}", e))?;
+        self.check_thread()?;
+        set_fuel(self.context_mut(), fuel)
+    }
 
-        Ok(())
+    /// @yard
+    /// Adds fuel to the {Store}, on top of the fuel it has left.
+    /// @param fuel [Integer] The amount of fuel to add.
+    /// @def add_fuel(fuel)
+    /// @raise [Error] if fuel consumption is not enabled via {Wasmtime::Engine#new}
+    pub fn add_fuel(&self, fuel: u64) -> Result<(), Error> {
+        self.check_thread()?;
+        add_fuel(self.context_mut(), fuel)
+    }
+
+    /// @yard
+    /// Returns the number of bytes of the linear memories created in the
+    /// {Store}, whether by Wasm or through {Memory.new}. Memories are only
+    /// freed with the {Store}, so this never decreases.
+    ///
+    /// @return [Integer]
+    pub fn memory_consumed(&self) -> Result<usize, Error> {
+        self.check_thread()?;
+        Ok(self.context().data().usage.memory_bytes)
+    }
+
+    /// @yard
+    /// Returns the resources used by the {Store}'s guests, e.g. to export
+    /// them as metrics. A +Hash+ with the following keys:
+    /// * +"memory_bytes"+: see {#memory_consumed},
+    /// * +"table_elements"+: the +Integer+ number of elements of the tables
+    ///   created in the store,
+    /// * +"instances"+: the +Integer+ number of instances created in the
+    ///   store through {Instance.new}, {Linker#instantiate},
+    ///   {InstancePre#instantiate} or {Component::Linker#instantiate}.
+    ///
+    /// Shared memories ({SharedMemory}) don't belong to a store and are not
+    /// included.
+    ///
+    /// @return [Hash{String => Integer}]
+    pub fn resource_usage(&self) -> Result<RHash, Error> {
+        self.check_thread()?;
+        let usage = &self.context().data().usage;
+        let hash = RHash::new();
+        hash.aset("memory_bytes", usage.memory_bytes)?;
+        hash.aset("table_elements", usage.table_elements)?;
+        hash.aset("instances", usage.instances)?;
+
+        Ok(hash)
+    }
+
+    /// @yard
+    /// Returns the amount of fuel consumed by the {Store}'s execution so far,
+    /// i.e. the fuel given through {#set_fuel} and {#add_fuel} minus the fuel left.
+    /// Also available as +instructions_consumed+, see {#set_instruction_limit}.
+    ///
+    /// @return [Integer]
+    /// @raise [Error] if fuel consumption is not enabled via {Wasmtime::Engine#new}
+    pub fn fuel_consumed(&self) -> Result<u64, Error> {
+        self.check_thread()?;
+        fuel_consumed(self.context())
+    }
+
+    /// @yard
+    /// Limits the number of Wasm instructions the {Store} may execute over its
+    /// lifetime, including the ones already executed (see
+    /// +instructions_consumed+). Unlike {#set_time_limit}, this measures the
+    /// work done deterministically, e.g. to bill tenants. Exceeding the limit
+    /// raises a {Trap} with code {Trap::OUT_OF_FUEL}.
+    ///
+    /// Instructions are counted with fuel, replacing the fuel given through
+    /// {#set_fuel} and {#add_fuel}: most instructions consume one unit of
+    /// fuel, while a few (e.g. +nop+, +drop+ and +block+) are free.
+    ///
+    /// @def set_instruction_limit(limit)
+    /// @param limit [Integer] The total number of instructions.
+    /// @return [nil]
+    /// @raise [Error] if fuel consumption is not enabled via {Wasmtime::Engine#new}
+    ///
+    /// @example
+    ///   store = Wasmtime::Store.new(Wasmtime::Engine.new(consume_fuel: true))
+    ///   store.set_instruction_limit(1_000_000)
+    ///   Wasmtime::Instance.new(store, mod).invoke("run")
+    ///   store.instructions_consumed # => 1234
+    pub fn set_instruction_limit(&self, limit: u64) -> Result<(), Error> {
+        self.check_thread()?;
+        let consumed = fuel_consumed(self.context())?;
+        set_fuel(self.context_mut(), limit.saturating_sub(consumed))
     }
 
     /// @yard
     /// Sets the epoch deadline to a certain number of ticks in the future.
     ///
-    /// Raises if there isn't enough fuel left in the {Store}, or
-    /// when the {Engine}’s config does not have fuel enabled.
+    /// When the deadline is reached, Wasm execution in this {Store} raises a
+    /// {Trap} with code {Trap::INTERRUPT}. Has no effect unless the
+    /// {Engine} was created with +epoch_interruption: true+.
     ///
-    /// @see ttps://docs.rs/wasmtime/latest/wasmtime/struct.Store.html#method.set_epoch_deadline Rust's doc on +set_epoch_deadline_ for more details.
+    /// @see https://docs.rs/wasmtime/latest/wasmtime/struct.Store.html#method.set_epoch_deadline Rust's doc on +set_epoch_deadline+ for more details.
     /// @def set_epoch_deadline(ticks_beyond_current)
     /// @param ticks_beyond_current [Integer] The number of ticks before this store reaches the deadline.
     /// @return [nil]
-    pub fn set_epoch_deadline(&self, ticks_beyond_current: u64) {
-        unsafe { &mut *self.inner.get() }.set_epoch_deadline(ticks_beyond_current);
+    pub fn set_epoch_deadline(&self, ticks_beyond_current: u64) -> Result<(), Error> {
+        self.check_thread()?;
+        let store = unsafe { &mut *self.inner.get() };
+        let data = store.data_mut();
+        data.epoch_deadline = data.epoch.current().saturating_add(ticks_beyond_current);
+        // The deadline is checked by `on_epoch_deadline`, on the next tick.
+        store.set_epoch_deadline(ticks_beyond_current.min(1));
+        Ok(())
+    }
+
+    /// @yard
+    /// Limits the wall-clock time of all the Wasm calls in this {Store},
+    /// together, to +seconds+ from now. Once the time limit is exceeded, Wasm
+    /// calls raise {DeadlineExceeded}, until the limit is reset.
+    ///
+    /// This relies on epoch interruption: the {Engine} must be created with
+    /// +epoch_interruption: true+, the store's epoch deadline is overridden,
+    /// and the engine's epoch is incremented when the time limit elapses.
+    /// Time spent in host functions counts towards the limit, but host
+    /// functions themselves are not interrupted.
+    ///
+    /// @def set_time_limit(seconds)
+    /// @param seconds [Numeric, nil] The time limit, +nil+ to remove it.
+    /// @return [nil]
+    /// @raise [Error] if epoch interruption is not enabled or the store is
+    ///   being profiled.
+    ///
+    /// @example Bounding the time spent handling a request:
+    ///   engine = Wasmtime::Engine.new(epoch_interruption: true)
+    ///   store = Wasmtime::Store.new(engine)
+    ///   store.set_time_limit(2)
+    ///   instance = Wasmtime::Instance.new(store, mod)
+    ///   instance.invoke("parse")
+    ///   instance.invoke("render") # raises Wasmtime::DeadlineExceeded past 2 seconds
+    pub fn set_time_limit(&self, seconds: Option<f64>) -> Result<(), Error> {
+        self.check_thread()?;
+        let store = unsafe { &mut *self.inner.get() };
+        if !store.data().has_epoch_interruption() {
+            return err!("set_time_limit requires an engine with epoch_interruption: true");
+        }
+        if store.data().profiler.is_some() {
+            return err!("set_time_limit is not supported while profiling");
+        }
+
+        let Some(seconds) = seconds else {
+            store.data_mut().time_limit = None;
+            return Ok(());
+        };
+        let limit = Duration::try_from_secs_f64(seconds)
+            .map_err(|e| error!("invalid time limit: {}", e))?;
+
+        let deadline = Instant::now() + limit;
+        let timer = epoch_timer::schedule(store.data().epoch.clone(), deadline)?;
+        store.data_mut().time_limit = Some(TimeLimit {
+            limit,
+            deadline,
+            _timer: timer,
+        });
+        store
+            .data()
+            .interrupt_requested
+            .store(false, Ordering::SeqCst);
+        store.set_epoch_deadline(1);
+
+        Ok(())
+    }
+
+    /// @yard
+    /// Starts sampling the stack of the Wasm code running in this {Store}, to
+    /// be written as a Firefox Profiler profile by {#finish_profiling}.
+    ///
+    /// A sample is taken on every epoch tick, so the {Engine} must be created
+    /// with +epoch_interruption: true+ and its epoch incremented regularly,
+    /// e.g. with {Engine#start_epoch_interval}. While profiling, reaching the
+    /// epoch deadline no longer raises a {Trap}.
+    ///
+    /// @see https://docs.rs/wasmtime/latest/wasmtime/struct.GuestProfiler.html Rust's doc on +GuestProfiler+ for more details.
+    /// @def start_profiling(interval: 0.001, modules: [])
+    /// @param interval [Float] The expected time between samples, in seconds.
+    /// @param modules [Array<Module>] The modules whose functions are named in the profile.
+    /// @return [nil]
+    /// @raise [Error] if epoch interruption is not enabled or profiling was already started.
+    ///
+    /// @example
+    ///   engine = Wasmtime::Engine.new(epoch_interruption: true)
+    ///   store = Wasmtime::Store.new(engine)
+    ///   store.start_profiling(interval: 0.001, modules: [mod])
+    ///   engine.start_epoch_interval(1)
+    ///   Wasmtime::Instance.new(store, mod).invoke("run")
+    ///   store.finish_profiling("profile.json")
+    pub fn start_profiling(&self, args: &[Value]) -> Result<(), Error> {
+        self.check_thread()?;
+        let args = scan_args::scan_args::<(), (), (), (), _, ()>(args)?;
+        let kw = scan_args::get_kwargs::<_, (), (Option<f64>, Option<RArray>), ()>(
+            args.keywords,
+            &[],
+            &[*INTERVAL, *MODULES],
+        )?;
+
+        let interval = Duration::try_from_secs_f64(kw.optional.0.unwrap_or(0.001))
+            .map_err(|e| error!("invalid interval: {}", e))?;
+        let mut modules = vec![];
+        if let Some(array) = kw.optional.1 {
+            for (i, module) in array.each().enumerate() {
+                let module = <&ModuleObj>::try_convert(module?)?.get();
+                let name = module
+                    .name()
+                    .map(ToString::to_string)
+                    .unwrap_or_else(|| format!("module{}", i));
+                modules.push((name, module.clone()));
+            }
+        }
+
+        let store = unsafe { &mut *self.inner.get() };
+        if !store.data().has_epoch_interruption() {
+            return err!("profiling requires an engine with epoch_interruption: true");
+        }
+        if store.data().profiler.is_some() {
+            return err!("profiling was already started");
+        }
+        if store.data().time_limit.is_some() {
+            return err!("profiling is not supported in stores with a time limit");
+        }
+
+        let name = modules
+            .first()
+            .map(|(name, _)| name.clone())
+            .unwrap_or_else(|| "wasm".to_string());
+        store.data_mut().profiler = Some(Profiler {
+            inner: GuestProfiler::new(&name, interval, modules),
+            last_sample: Instant::now(),
+        });
+        store.set_epoch_deadline(1);
+
+        Ok(())
+    }
+
+    /// @yard
+    /// Stops the profiling started with {#start_profiling} and writes the
+    /// profile in the Firefox Profiler's JSON format, which can be opened at
+    /// {https://profiler.firefox.com}.
+    ///
+    /// @def finish_profiling(path = nil)
+    /// @param path [String, nil] The file to write the profile to.
+    /// @return [String, nil] The profile's JSON when no +path+ is given, +nil+ otherwise.
+    /// @raise [Error] if profiling was not started.
+    pub fn finish_profiling(&self, args: &[Value]) -> Result<Option<RString>, Error> {
+        self.check_thread()?;
+        let args = scan_args::scan_args::<(), (Option<RString>,), (), (), (), ()>(args)?;
+        let (path,) = args.optional;
+
+        let store = unsafe { &mut *self.inner.get() };
+        let profiler = match store.data_mut().profiler.take() {
+            Some(profiler) => profiler,
+            None => return err!("profiling was not started"),
+        };
+
+        match path {
+            Some(path) => {
+                let file = File::create(path.to_string()?).map_err(|e| error!("{}", e))?;
+                profiler
+                    .inner
+                    .finish(BufWriter::new(file))
+                    .map_err(|e| error!("{}", e))?;
+                Ok(None)
+            }
+            None => {
+                let mut json = vec![];
+                profiler
+                    .inner
+                    .finish(&mut json)
+                    .map_err(|e| error!("{}", e))?;
+                Ok(Some(RString::from_slice(&json)))
+            }
+        }
+    }
+
+    /// @yard
+    /// Runs Wasmtime's garbage collection, releasing the Ruby objects passed
+    /// to Wasm as externrefs that are no longer referenced by Wasm (from
+    /// globals, tables or running functions).
+    ///
+    /// @see https://docs.rs/wasmtime/latest/wasmtime/struct.Store.html#method.gc Rust's doc on +gc+ for more details.
+    /// @return [nil]
+    pub fn gc(&self) -> Result<(), Error> {
+        self.check_thread()?;
+        let store = unsafe { &mut *self.inner.get() };
+        store.gc();
+        store.data_mut().prune_externrefs();
+        store.data_mut().prune_host_blocks();
+        Ok(())
+    }
+
+    /// @yard
+    /// Runs the block while holding the store's lock, so that other Ruby
+    /// threads can use the store within {#with_lock} too. Threads wait for
+    /// each other (with the GVL released) rather than raising an {Error}
+    /// when the store is in use.
+    ///
+    /// The lock is re-entrant, and is held by the thread running Wasm
+    /// (including its host functions) for the duration of the call.
+    ///
+    /// @yield The block using the store.
+    /// @return [Object] The block's result.
+    ///
+    /// @example Calling a store's functions from many threads
+    ///   threads = 4.times.map do
+    ///     Thread.new { store.with_lock { instance.invoke("run") } }
+    ///   end
+    ///   threads.each(&:join)
+    pub fn with_lock(&self, args: &[Value]) -> Result<Value, Error> {
+        let args = scan_args::scan_args::<(), (), (), (), (), RProc>(args)?;
+        let _guard = self.lock.acquire(true)?;
+        args.block.call(())
+    }
+
+    /// @yard
+    /// Returns the number of Ruby objects kept alive by the {Store} on behalf
+    /// of Wasm, such as the blocks of host functions and externrefs (see {#gc}).
+    /// @return [Integer]
+    pub fn retained_count(&self) -> Result<usize, Error> {
+        self.check_thread()?;
+        let data = self.context().data();
+        let host_blocks = data
+            .host_blocks
+            .iter()
+            .filter(|(_, handle)| handle.is_live())
+            .count();
+        Ok(data.refs.len() + host_blocks + data.externrefs.len())
+    }
+
+    /// Locks the store for the duration of a Wasm call, raising if another
+    /// thread holds the lock.
+    pub fn lock(&self) -> Result<StoreLockGuard<'_>, Error> {
+        self.lock.acquire(false)
+    }
+
+    /// Raises if another thread holds the store's lock, i.e. may be running
+    /// Wasm in the store.
+    pub fn check_thread(&self) -> Result<(), Error> {
+        if self.lock.is_held_elsewhere() {
+            return Err(in_use_error());
+        }
+        Ok(())
     }
 
     pub fn context(&self) -> StoreContext<StoreData> {
@@ -222,6 +1363,10 @@ impl Store {
         self.context_mut().data_mut().retain(value);
     }
 
+    pub fn retain_host_block(&self, block: Value) -> HostBlockHandle {
+        self.context_mut().data_mut().retain_host_block(block)
+    }
+
     pub fn take_last_error(&self) -> Option<Error> {
         self.context_mut().data_mut().take_error()
     }
@@ -265,7 +1410,11 @@ impl<'a> StoreContextValue<'a> {
     pub fn context(&self) -> Result<StoreContext<StoreData>, Error> {
         let ruby = Ruby::get().unwrap();
         match self {
-            Self::Store(store) => Ok(ruby.get_inner_ref(store).context()),
+            Self::Store(store) => {
+                let store = ruby.get_inner_ref(store);
+                store.check_thread()?;
+                Ok(store.context())
+            }
             Self::Caller(caller) => ruby.get_inner_ref(caller).context(),
         }
     }
@@ -273,11 +1422,25 @@ impl<'a> StoreContextValue<'a> {
     pub fn context_mut(&self) -> Result<StoreContextMut<StoreData>, Error> {
         let ruby = Ruby::get().unwrap();
         match self {
-            Self::Store(store) => Ok(ruby.get_inner_ref(store).context_mut()),
+            Self::Store(store) => {
+                let store = ruby.get_inner_ref(store);
+                store.check_thread()?;
+                Ok(store.context_mut())
+            }
             Self::Caller(caller) => ruby.get_inner_ref(caller).context_mut(),
         }
     }
 
+    /// Locks the store for the duration of a Wasm call. Callers are only
+    /// live on the thread holding the lock already.
+    pub fn lock(&self) -> Result<Option<StoreLockGuard<'_>>, Error> {
+        let ruby = Ruby::get().unwrap();
+        match self {
+            Self::Store(store) => ruby.get_inner_ref(store).lock().map(Some),
+            Self::Caller(_) => Ok(None),
+        }
+    }
+
     pub fn set_last_error(&self, error: Error) {
         let ruby = Ruby::get().unwrap();
         match self {
@@ -295,14 +1458,20 @@ impl<'a> StoreContextValue<'a> {
     }
 
     pub fn handle_wasm_error(&self, error: anyhow::Error) -> Error {
-        if let Ok(Some(error)) = self.take_last_error() {
-            error
+        if let Ok(Some(raised)) = self.take_last_error() {
+            chain_to_trap(raised, error)
         } else if let Some(exit) = error.downcast_ref::<I32Exit>() {
             wasi_exit_error().new_instance((exit.0,)).unwrap().into()
+        } else if let Some(exit) = error.downcast_ref::<P2I32Exit>() {
+            wasi_exit_error().new_instance((exit.0,)).unwrap().into()
+        } else if let Some(exceeded) = error.downcast_ref::<TimeLimitExceeded>() {
+            Error::new(deadline_exceeded_error(), exceeded.to_string())
+        } else if let Some(exceeded) = error.downcast_ref::<TimeoutExceeded>() {
+            Error::new(timeout_error(), exceeded.to_string())
         } else {
             Trap::try_from(error)
                 .map(|trap| trap.into())
-                .unwrap_or_else(|e| error!("{}", e))
+                .unwrap_or_else(wasmtime_error)
         }
     }
 
@@ -311,6 +1480,11 @@ impl<'a> StoreContextValue<'a> {
         Ok(())
     }
 
+    pub fn retain_externref(&self, val: &Val) -> Result<(), Error> {
+        self.context_mut()?.data_mut().retain_externref(val);
+        Ok(())
+    }
+
     fn take_last_error(&self) -> Result<Option<Error>, Error> {
         let ruby = Ruby::get().unwrap();
         match self {
@@ -324,6 +1498,93 @@ impl<'a> StoreContextValue<'a> {
     }
 }
 
+pub fn set_fuel(mut context: StoreContextMut<StoreData>, fuel: u64) -> Result<(), Error> {
+    let consumed = fuel_consumed(context.as_context())?;
+    context.set_fuel(fuel).map_err(|e| error!("{}", e))?;
+    context.data_mut().fuel_granted = consumed.saturating_add(fuel);
+
+    Ok(())
+}
+
+pub fn add_fuel(mut context: StoreContextMut<StoreData>, fuel: u64) -> Result<(), Error> {
+    let remaining = context.get_fuel().map_err(|e| error!("{}", e))?;
+    context
+        .set_fuel(remaining.saturating_add(fuel))
+        .map_err(|e| error!("{}", e))?;
+
+    let data = context.data_mut();
+    data.fuel_granted = data.fuel_granted.saturating_add(fuel);
+
+    Ok(())
+}
+
+pub fn fuel_consumed(context: StoreContext<StoreData>) -> Result<u64, Error> {
+    let remaining = context.get_fuel().map_err(|e| error!("{}", e))?;
+
+    Ok(context.data().fuel_granted.saturating_sub(remaining))
+}
+
+/// The epoch deadline callback of stores in engines with epoch
+/// interruption. The deadline is always the next epoch, so that interrupts,
+/// time limits and profiling are handled on every tick, and the deadline of
+/// [`Store::set_epoch_deadline`] is checked here.
+fn on_epoch_deadline(
+    mut context: StoreContextMut<'_, StoreData>,
+) -> anyhow::Result<UpdateDeadline> {
+    if context
+        .data()
+        .interrupt_requested
+        .swap(false, Ordering::SeqCst)
+    {
+        return Err(wasmtime::Trap::Interrupt.into());
+    }
+
+    // Calls with a timeout run past the store's epoch deadline.
+    let call_timeout = context.data().call_timeout.as_ref();
+    if let Some(call_timeout) = call_timeout {
+        if Instant::now() >= call_timeout.deadline {
+            return Err(TimeoutExceeded(call_timeout.timeout).into());
+        }
+    }
+    let has_call_timeout = call_timeout.is_some();
+
+    if let Some(mut profiler) = context.data_mut().profiler.take() {
+        let now = Instant::now();
+        profiler
+            .inner
+            .sample(&context, now.duration_since(profiler.last_sample));
+        profiler.last_sample = now;
+        context.data_mut().profiler = Some(profiler);
+        return Ok(UpdateDeadline::Continue(1));
+    }
+
+    let data = context.data_mut();
+    // Other stores' timers increment the engine's epoch too, so reaching the
+    // epoch deadline doesn't mean the time limit was exceeded.
+    if let Some(time_limit) = data.time_limit.as_ref() {
+        if Instant::now() < time_limit.deadline {
+            return Ok(UpdateDeadline::Continue(1));
+        }
+        return Err(TimeLimitExceeded(time_limit.limit).into());
+    }
+
+    if !has_call_timeout && data.epoch.current() >= data.epoch_deadline {
+        return Err(wasmtime::Trap::Interrupt.into());
+    }
+    Ok(UpdateDeadline::Continue(1))
+}
+
+/// The WASI context of a new store, with the Ruby objects its streams use, from
+/// either a [`WasiCtxBuilder`] or a [`WasiCtx`].
+fn wasi_ctx_state(value: Value) -> Result<(WasiCtxImpl, Vec<Value>), Error> {
+    if let Ok(builder) = Obj::<WasiCtxBuilder>::try_convert(value) {
+        let ctx = WasiCtxBuilder::build(&Ruby::get().unwrap(), builder)?;
+        return Ok((ctx.get_inner(), ctx.refs().to_vec()));
+    }
+    let ctx = <&WasiCtx>::try_convert(value)?;
+    Ok((ctx.get_inner(), ctx.refs().to_vec()))
+}
+
 fn hash_to_store_limits_builder(limits: RHash) -> Result<StoreLimitsBuilder, Error> {
     let mut limiter: StoreLimitsBuilder = StoreLimitsBuilder::new();
 
@@ -357,9 +1618,30 @@ pub fn init() -> Result<(), Error> {
 
     class.define_singleton_method("new", function!(Store::new, -1))?;
     class.define_method("data", method!(Store::data, 0))?;
+    class.define_method("data=", method!(Store::set_data, 1))?;
+    class.define_method("set_limits", method!(Store::set_limits, -1))?;
+    class.define_method("on_memory_grow", method!(Store::on_memory_grow, -1))?;
+    class.define_method("on_table_grow", method!(Store::on_table_grow, -1))?;
+    class.define_method("on_host_error", method!(Store::on_host_error, -1))?;
     class.define_method("get_fuel", method!(Store::get_fuel, 0))?;
+    class.define_alias("fuel_remaining", "get_fuel")?;
     class.define_method("set_fuel", method!(Store::set_fuel, 1))?;
+    class.define_method("add_fuel", method!(Store::add_fuel, 1))?;
+    class.define_method("fuel_consumed", method!(Store::fuel_consumed, 0))?;
+    class.define_alias("instructions_consumed", "fuel_consumed")?;
+    class.define_method(
+        "set_instruction_limit",
+        method!(Store::set_instruction_limit, 1),
+    )?;
+    class.define_method("memory_consumed", method!(Store::memory_consumed, 0))?;
+    class.define_method("resource_usage", method!(Store::resource_usage, 0))?;
     class.define_method("set_epoch_deadline", method!(Store::set_epoch_deadline, 1))?;
+    class.define_method("set_time_limit", method!(Store::set_time_limit, 1))?;
+    class.define_method("gc", method!(Store::gc, 0))?;
+    class.define_method("retained_count", method!(Store::retained_count, 0))?;
+    class.define_method("with_lock", method!(Store::with_lock, -1))?;
+    class.define_method("start_profiling", method!(Store::start_profiling, -1))?;
+    class.define_method("finish_profiling", method!(Store::finish_profiling, -1))?;
 
     Ok(())
 }
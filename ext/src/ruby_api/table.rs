@@ -8,7 +8,7 @@ use magnus::{
     class, function, gc::Marker, method, prelude::*, scan_args, typed_data::Obj, DataTypeFunctions,
     Error, IntoValue, Object, Symbol, TypedData, Value,
 };
-use wasmtime::{Extern, Table as TableImpl, TableType};
+use wasmtime::{Extern, Table as TableImpl, TableType as TableTypeImpl};
 
 define_rb_intern!(
     MIN_SIZE => "min_size",
@@ -48,6 +48,7 @@ impl<'a> Table<'a> {
             &[*MAX_SIZE],
         )?;
         let (store, value_type, default) = args.required;
+        store.check_thread()?;
         let (min,) = kw.required;
         let (max,) = kw.optional;
         let wasm_type = value_type.to_val_type()?;
@@ -55,8 +56,8 @@ impl<'a> Table<'a> {
 
         let inner = TableImpl::new(
             store.context_mut(),
-            TableType::new(wasm_type, min, max),
-            wasm_default,
+            TableTypeImpl::new(wasm_type, min, max),
+            wasm_default.clone(),
         )
         .map_err(|e| error!("{}", e))?;
 
@@ -65,7 +66,7 @@ impl<'a> Table<'a> {
             inner,
         };
 
-        table.retain_non_nil_extern_ref(default)?;
+        table.store.retain_externref(&wasm_default)?;
 
         Ok(table)
     }
@@ -108,23 +109,18 @@ impl<'a> Table<'a> {
 
     /// @yard
     /// Sets the table entry at +index+ to +value+.
+    /// Raises if +index+ is out of bound.
     ///
     /// @def set(index, value)
     /// @param index [Integer]
     /// @param value [Object]
     /// @return [void]
     pub fn set(&self, index: u32, value: Value) -> Result<(), Error> {
+        let value = value.to_wasm_val(self.value_type()?)?;
         self.inner
-            .set(
-                self.store.context_mut()?,
-                index,
-                value.to_wasm_val(self.value_type()?)?,
-            )
-            .map_err(|e| error!("{}", e))
-            .and_then(|result| {
-                self.retain_non_nil_extern_ref(value)?;
-                Ok(result)
-            })
+            .set(self.store.context_mut()?, index, value.clone())
+            .map_err(|e| error!("{}", e))?;
+        self.store.retain_externref(&value)
     }
 
     /// @yard
@@ -134,19 +130,15 @@ impl<'a> Table<'a> {
     /// @def grow(delta, initial)
     /// @param delta [Integer] The number of elements to add to the table.
     /// @param initial [Object] The initial value for newly added table slots.
-    /// @return [void]
+    /// @return [Integer] The size of the table before being grown.
     pub fn grow(&self, delta: u32, initial: Value) -> Result<u32, Error> {
-        self.inner
-            .grow(
-                self.store.context_mut()?,
-                delta,
-                initial.to_wasm_val(self.value_type()?)?,
-            )
-            .map_err(|e| error!("{}", e))
-            .and_then(|result| {
-                self.retain_non_nil_extern_ref(initial)?;
-                Ok(result)
-            })
+        let initial = initial.to_wasm_val(self.value_type()?)?;
+        let size = self
+            .inner
+            .grow(self.store.context_mut()?, delta, initial.clone())
+            .map_err(|e| self.store.handle_wasm_error(e))?;
+        self.store.retain_externref(&initial)?;
+        Ok(size)
     }
 
     /// @yard
@@ -155,7 +147,7 @@ impl<'a> Table<'a> {
         Ok(self.inner.size(self.store.context()?))
     }
 
-    fn ty(&self) -> Result<TableType, Error> {
+    fn ty(&self) -> Result<TableTypeImpl, Error> {
         Ok(self.inner.ty(self.store.context()?))
     }
 
@@ -163,18 +155,45 @@ impl<'a> Table<'a> {
         Ok(self.inner.ty(self.store.context()?).element())
     }
 
-    fn retain_non_nil_extern_ref(&self, value: Value) -> Result<(), Error> {
-        if wasmtime::ValType::ExternRef == self.value_type()? && !value.is_nil() {
-            self.store.retain(value)?;
-        }
-        Ok(())
-    }
-
     pub fn inner(&self) -> TableImpl {
         self.inner
     }
 }
 
+/// @yard
+/// @rename Wasmtime::TableType
+/// Represents the type of a WebAssembly table.
+/// @see https://docs.rs/wasmtime/latest/wasmtime/struct.TableType.html Wasmtime's Rust doc
+#[magnus::wrap(class = "Wasmtime::TableType", free_immediately, frozen_shareable)]
+pub struct TableType {
+    inner: TableTypeImpl,
+}
+
+impl TableType {
+    pub fn from_inner(inner: TableTypeImpl) -> Self {
+        Self { inner }
+    }
+
+    /// @yard
+    /// @def type
+    /// @return [Symbol] The Wasm type of the elements of the table.
+    pub fn type_(&self) -> Symbol {
+        self.inner.element().to_sym()
+    }
+
+    /// @yard
+    /// @return [Integer] The minimum size of the table.
+    pub fn min_size(&self) -> u32 {
+        self.inner.minimum()
+    }
+
+    /// @yard
+    /// @return [Integer, nil] The maximum size of the table.
+    pub fn max_size(&self) -> Option<u32> {
+        self.inner.maximum()
+    }
+}
+
 impl From<&Table<'_>> for Extern {
     fn from(table: &Table) -> Self {
         Self::Table(table.inner())
@@ -194,5 +213,10 @@ pub fn init() -> Result<(), Error> {
     class.define_method("grow", method!(Table::grow, 2))?;
     class.define_method("size", method!(Table::size, 0))?;
 
+    let class = root().define_class("TableType", class::object())?;
+    class.define_method("type", method!(TableType::type_, 0))?;
+    class.define_method("min_size", method!(TableType::min_size, 0))?;
+    class.define_method("max_size", method!(TableType::max_size, 0))?;
+
     Ok(())
 }
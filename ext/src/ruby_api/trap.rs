@@ -4,7 +4,7 @@ use crate::ruby_api::{errors::base_error, root};
 use magnus::Error;
 use magnus::{
     method, prelude::*, rb_sys::AsRawValue, typed_data::Obj, value::Lazy, DataTypeFunctions,
-    ExceptionClass, IntoValue, Ruby, Symbol, TypedData,
+    ExceptionClass, IntoValue, RArray, RClass, Ruby, Symbol, TypedData,
 };
 
 pub fn trap_error() -> ExceptionClass {
@@ -24,7 +24,8 @@ macro_rules! trap_const {
 #[magnus(class = "Wasmtime::Trap", size, free_immediately)]
 /// @yard
 pub struct Trap {
-    trap: wasmtime::Trap,
+    /// `None` for the trap a host exception is chained to, see [`Trap::host`].
+    trap: Option<wasmtime::Trap>,
     wasm_backtrace: Option<wasmtime::WasmBacktrace>,
 }
 impl DataTypeFunctions for Trap {}
@@ -32,7 +33,16 @@ impl DataTypeFunctions for Trap {}
 impl Trap {
     pub fn new(trap: wasmtime::Trap, wasm_backtrace: Option<wasmtime::WasmBacktrace>) -> Self {
         Self {
-            trap,
+            trap: Some(trap),
+            wasm_backtrace,
+        }
+    }
+
+    /// The trap set as the cause of an exception raised by a host function,
+    /// carrying the Wasm backtrace of the call.
+    pub fn host(wasm_backtrace: Option<wasmtime::WasmBacktrace>) -> Self {
+        Self {
+            trap: None,
             wasm_backtrace,
         }
     }
@@ -42,7 +52,10 @@ impl Trap {
     ///     wasm trap: wasm `unreachable` instruction executed
     /// @return [String]
     pub fn message(&self) -> String {
-        self.trap.to_string()
+        match self.trap {
+            Some(trap) => trap.to_string(),
+            None => "host function raised an exception".to_string(),
+        }
     }
 
     /// @yard
@@ -55,12 +68,39 @@ impl Trap {
         self.wasm_backtrace.as_ref().map(|bt| format!("{bt}"))
     }
 
+    /// @yard
+    /// Returns the frames of the Wasm backtrace, innermost first. Empty if the
+    /// backtrace was not captured.
+    /// @return [Array<Trap::Frame>]
+    pub fn wasm_backtrace(&self) -> Result<RArray, Error> {
+        let frames = RArray::new();
+        let Some(backtrace) = self.wasm_backtrace.as_ref() else {
+            return Ok(frames);
+        };
+
+        let frame_class = trap_error().const_get::<_, RClass>("Frame")?;
+        for frame in backtrace.frames() {
+            frames.push(frame_class.new_instance((
+                frame.module().name(),
+                frame.func_index(),
+                frame.func_name(),
+                frame.func_offset(),
+                frame.module_offset(),
+            ))?)?;
+        }
+
+        Ok(frames)
+    }
+
     /// @yard
     /// Returns the trap code as a Symbol, possibly nil if the trap did not
     /// origin from Wasm code. All possible trap codes are defined as constants on {Trap}.
     /// @return [Symbol, nil]
     pub fn code(&self) -> Result<Option<Symbol>, Error> {
-        match self.trap {
+        let Some(trap) = self.trap else {
+            return Ok(None);
+        };
+        match trap {
             wasmtime::Trap::StackOverflow => trap_const!(STACK_OVERFLOW),
             wasmtime::Trap::MemoryOutOfBounds => trap_const!(MEMORY_OUT_OF_BOUNDS),
             wasmtime::Trap::HeapMisaligned => trap_const!(HEAP_MISALIGNED),
@@ -118,6 +158,7 @@ pub fn init() -> Result<(), Error> {
         "wasm_backtrace_message",
         method!(Trap::wasm_backtrace_message, 0),
     )?;
+    class.define_method("wasm_backtrace", method!(Trap::wasm_backtrace, 0))?;
     class.define_method("code", method!(Trap::code, 0))?;
     class.define_method("inspect", method!(Trap::inspect, 0))?;
     class.define_alias("to_s", "message")?;
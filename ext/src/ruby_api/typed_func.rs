@@ -0,0 +1,142 @@
+use super::{
+    convert::{ToSym, ToValTypeVec},
+    errors::signature_mismatch_error,
+    func::{is_primitive_type, Func},
+    root,
+    store::{HostBlockHandle, StoreContextValue},
+};
+use magnus::{
+    class, gc::Marker, method, prelude::*, DataTypeFunctions, Error, RArray, RHash, Symbol,
+    TypedData, Value,
+};
+use wasmtime::{Func as FuncImpl, FuncType};
+
+/// @yard
+/// @rename Wasmtime::TypedFunc
+/// A WebAssembly function whose type was checked once, when it was created
+/// through {Func#typed}. Prefer it over {Func} when calling the same function
+/// repeatedly: calls to functions taking and returning at most 8 numbers
+/// don't allocate their params and results.
+/// @see https://docs.rs/wasmtime/latest/wasmtime/struct.TypedFunc.html Wasmtime's Rust doc
+#[derive(TypedData)]
+#[magnus(
+    class = "Wasmtime::TypedFunc",
+    size,
+    mark,
+    free_immediately,
+    unsafe_generics
+)]
+pub struct TypedFunc<'a> {
+    store: StoreContextValue<'a>,
+    inner: FuncImpl,
+    ty: FuncType,
+    /// Whether [`is_primitive_type`] holds for `ty`.
+    primitive: bool,
+    /// Keeps the block of a function created by [`Func::new`] alive.
+    host_block: Option<HostBlockHandle>,
+}
+
+impl DataTypeFunctions for TypedFunc<'_> {
+    fn mark(&self, marker: &Marker) {
+        self.store.mark(marker)
+    }
+}
+
+impl<'a> TypedFunc<'a> {
+    pub fn new(
+        store: StoreContextValue<'a>,
+        inner: FuncImpl,
+        host_block: Option<HostBlockHandle>,
+        params: RArray,
+        results: RArray,
+    ) -> Result<Self, Error> {
+        let ty = inner.ty(store.context()?);
+        let expected_params = params.to_val_type_vec()?;
+        let expected_results = results.to_val_type_vec()?;
+
+        if !ty.params().eq(expected_params.iter().cloned())
+            || !ty.results().eq(expected_results.iter().cloned())
+        {
+            let actual_params: RArray = ty.params().map(ToSym::to_sym).collect();
+            let actual_results: RArray = ty.results().map(ToSym::to_sym).collect();
+
+            let message = format!(
+                "type mismatch: expected params {} and results {}, got params {} and results {}",
+                params.inspect(),
+                results.inspect(),
+                actual_params.inspect(),
+                actual_results.inspect()
+            );
+            let expected = signature(params, results)?;
+            let actual = signature(actual_params, actual_results)?;
+            return Err(signature_mismatch_error()
+                .new_instance((message, expected, actual))
+                .map_or_else(|e| e, Into::into));
+        }
+
+        Ok(Self {
+            primitive: is_primitive_type(&ty),
+            store,
+            inner,
+            ty,
+            host_block,
+        })
+    }
+
+    /// @yard
+    /// Calls the Wasm function.
+    ///
+    /// @def call(*args)
+    /// @param args [Object]
+    ///   The arguments to send to the Wasm function. Raises if the arguments do
+    ///   not conform to the Wasm function's parameters.
+    ///
+    /// @return [nil, Object, Array<Object>] See {Func#call}.
+    pub fn call(&self, args: &[Value]) -> Result<Value, Error> {
+        if self.primitive {
+            return Func::invoke_primitive(&self.store, &self.inner, &self.ty, args);
+        }
+        Func::invoke_with_type(&self.store, &self.inner, &self.ty, args)
+    }
+
+    /// @yard
+    /// Calls the Wasm function once for each +Array+ of arguments in +batch+.
+    ///
+    /// @def call_batch(batch)
+    /// @param batch [Array<Array<Object>>] The arguments of each call.
+    /// @return [Array<nil, Object, Array<Object>>] See {Func#call_batch}.
+    pub fn call_batch(&self, batch: RArray) -> Result<RArray, Error> {
+        Func::invoke_batch_with_type(&self.store, &self.inner, &self.ty, batch)
+    }
+
+    /// @yard
+    /// @return [Array<Symbol>] The function's parameter types.
+    pub fn params(&self) -> RArray {
+        self.ty.params().map(ToSym::to_sym).collect()
+    }
+
+    /// @yard
+    /// @return [Array<Symbol>] The function's result types.
+    pub fn results(&self) -> RArray {
+        self.ty.results().map(ToSym::to_sym).collect()
+    }
+}
+
+/// A `{params: [...], results: [...]}` Hash, as carried by
+/// `Wasmtime::SignatureMismatch`.
+fn signature(params: RArray, results: RArray) -> Result<RHash, Error> {
+    let hash = RHash::new();
+    hash.aset(Symbol::new("params"), params)?;
+    hash.aset(Symbol::new("results"), results)?;
+    Ok(hash)
+}
+
+pub fn init() -> Result<(), Error> {
+    let class = root().define_class("TypedFunc", class::object())?;
+    class.define_method("call", method!(TypedFunc::call, -1))?;
+    class.define_method("call_batch", method!(TypedFunc::call_batch, 1))?;
+    class.define_method("params", method!(TypedFunc::params, 0))?;
+    class.define_method("results", method!(TypedFunc::results, 0))?;
+
+    Ok(())
+}
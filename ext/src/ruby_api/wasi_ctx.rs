@@ -6,8 +6,8 @@ use super::{
 use crate::error;
 use deterministic_wasi_ctx::build_wasi_ctx as wasi_deterministic_ctx;
 use magnus::{
-    class, function, gc::Marker, method, prelude::*, typed_data::Obj, Error, Object, RString,
-    RTypedData, Ruby, TypedData, Value,
+    class, function, gc::Marker, method, prelude::*, typed_data::Obj, DataTypeFunctions, Error,
+    Object, RString, RTypedData, Ruby, TypedData, Value,
 };
 use std::{borrow::Borrow, cell::RefCell, fs::File, path::PathBuf};
 use wasi_common::pipe::ReadPipe;
@@ -20,9 +20,19 @@ use wasmtime_wasi::WasiCtx as WasiCtxImpl;
 ///
 /// @see https://docs.rs/wasmtime-wasi/latest/wasmtime_wasi/struct.WasiCtx.html
 ///   Wasmtime's Rust doc
-#[magnus::wrap(class = "Wasmtime::WasiCtx", size, free_immediately)]
+#[derive(TypedData)]
+#[magnus(class = "Wasmtime::WasiCtx", size, mark, free_immediately)]
 pub struct WasiCtx {
     inner: RefCell<WasiCtxImpl>,
+    refs: Vec<Value>,
+}
+
+unsafe impl Send for WasiCtx {}
+
+impl DataTypeFunctions for WasiCtx {
+    fn mark(&self, marker: &Marker) {
+        marker.mark_slice(self.refs.as_slice());
+    }
 }
 
 type RbSelf = Obj<WasiCtx>;
@@ -34,6 +44,7 @@ impl WasiCtx {
     pub fn deterministic() -> Self {
         Self {
             inner: RefCell::new(wasi_deterministic_ctx()),
+            refs: Default::default(),
         }
     }
 
@@ -91,12 +102,24 @@ impl WasiCtx {
     pub fn from_inner(inner: WasiCtxImpl) -> Self {
         Self {
             inner: RefCell::new(inner),
+            refs: Default::default(),
         }
     }
 
+    /// Ruby objects the context's streams read from or write to. They must be
+    /// retained by any Store using this context.
+    pub fn with_refs(mut self, refs: Vec<Value>) -> Self {
+        self.refs = refs;
+        self
+    }
+
     pub fn get_inner(&self) -> WasiCtxImpl {
         return self.inner.borrow().clone();
     }
+
+    pub fn refs(&self) -> &[Value] {
+        &self.refs
+    }
 }
 
 pub fn init() -> Result<(), Error> {
@@ -1,17 +1,93 @@
 use super::{root, WasiCtx};
-use crate::error;
+use crate::{
+    define_rb_intern, error,
+    helpers::{
+        DirPerms, FilePerms, OutputLimitedBuffer, PermissionedDir, RubyIoReader, RubyIoWriter,
+        SymbolEnum,
+    },
+};
+use lazy_static::lazy_static;
 use magnus::{
-    class, function, gc::Marker, method, typed_data::Obj, value::Opaque, DataTypeFunctions, Error,
-    Module, Object, RArray, RHash, RString, Ruby, TryConvert, TypedData,
+    class, function, gc::Marker, method, prelude::*, scan_args, typed_data::Obj, value::Opaque,
+    DataTypeFunctions, Error, Module, Object, RArray, RHash, RString, Ruby, TryConvert, TypedData,
+    Value,
 };
+use rand::{rngs::StdRng, RngCore, SeedableRng};
 use std::cell::RefCell;
-use std::{fs::File, path::PathBuf};
-use wasi_common::pipe::ReadPipe;
+use std::{
+    fs::File,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use wasi_cap_std_sync::{clocks_ctx, random_ctx, sched_ctx, stdio};
+use wasi_common::{
+    clocks::{WasiMonotonicClock, WasiSystemClock},
+    pipe::{ReadPipe, WritePipe},
+    table::Table,
+    WasiDir, WasiFile,
+};
+use wasmtime_wasi::WasiCtx as WasiCtxImpl;
+
+define_rb_intern!(
+    DIR_PERMS => "dir_perms",
+    FILE_PERMS => "file_perms",
+    READ => "read",
+    WRITE => "write",
+    MUTATE => "mutate",
+    ALL => "all",
+);
+
+lazy_static! {
+    pub(super) static ref DIR_PERMS_MAPPING: SymbolEnum<'static, DirPerms> = {
+        let mapping = vec![
+            (
+                *READ,
+                DirPerms {
+                    read: true,
+                    mutate: false,
+                },
+            ),
+            (
+                *MUTATE,
+                DirPerms {
+                    read: false,
+                    mutate: true,
+                },
+            ),
+            (*ALL, DirPerms::ALL),
+        ];
+
+        SymbolEnum::new(":dir_perms", mapping)
+    };
+    pub(super) static ref FILE_PERMS_MAPPING: SymbolEnum<'static, FilePerms> = {
+        let mapping = vec![
+            (
+                *READ,
+                FilePerms {
+                    read: true,
+                    write: false,
+                },
+            ),
+            (
+                *WRITE,
+                FilePerms {
+                    read: false,
+                    write: true,
+                },
+            ),
+            (*ALL, FilePerms::ALL),
+        ];
+
+        SymbolEnum::new(":file_perms", mapping)
+    };
+}
 
+#[derive(Clone)]
 enum ReadStream {
     Inherit,
     Path(Opaque<RString>),
     String(Opaque<RString>),
+    Io(Opaque<Value>),
 }
 
 impl ReadStream {
@@ -20,30 +96,103 @@ impl ReadStream {
             Self::Inherit => (),
             Self::Path(s) => marker.mark(*s),
             Self::String(s) => marker.mark(*s),
+            Self::Io(v) => marker.mark(*v),
         }
     }
 }
 
+#[derive(Clone)]
 enum WriteStream {
     Inherit,
     Path(Opaque<RString>),
+    Buffer(Opaque<RString>, usize),
+    Io(Opaque<Value>),
 }
 impl WriteStream {
     pub fn mark(&self, marker: &Marker) {
         match self {
             Self::Inherit => (),
             Self::Path(v) => marker.mark(*v),
+            Self::Buffer(v, _) => marker.mark(*v),
+            Self::Io(v) => marker.mark(*v),
+        }
+    }
+
+    /// The Ruby object the built stream writes to, which must be kept alive
+    /// for as long as the WASI context is in use.
+    fn retained_value(&self, ruby: &Ruby) -> Option<Value> {
+        match self {
+            Self::Inherit | Self::Path(_) => None,
+            Self::Buffer(v, _) => Some(ruby.get_inner(*v).as_value()),
+            Self::Io(v) => Some(ruby.get_inner(*v)),
+        }
+    }
+
+    /// Builds the WASI file for this stream, `None` meaning the stream is inherited.
+    fn build(&self, ruby: &Ruby) -> Result<Option<Box<dyn WasiFile>>, Error> {
+        match self {
+            Self::Inherit => Ok(None),
+            Self::Path(path) => file_w(ruby.get_inner(*path))
+                .map(wasi_file)
+                .map(|file| Some(file as Box<dyn WasiFile>)),
+            Self::Buffer(buffer, capacity) => Ok(Some(Box::new(WritePipe::new(
+                OutputLimitedBuffer::new(*buffer, *capacity),
+            )))),
+            Self::Io(io) => Ok(Some(Box::new(WritePipe::new(RubyIoWriter::new(*io))))),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Env {
+    Inherit,
+    Hash(Opaque<RHash>),
+}
+
+impl Env {
+    pub fn mark(&self, marker: &Marker) {
+        match self {
+            Self::Inherit => (),
+            Self::Hash(v) => marker.mark(*v),
         }
     }
 }
 
-#[derive(Default)]
+#[derive(Clone)]
+enum Argv {
+    Inherit,
+    Array(Opaque<RArray>),
+}
+
+impl Argv {
+    pub fn mark(&self, marker: &Marker) {
+        match self {
+            Self::Inherit => (),
+            Self::Array(v) => marker.mark(*v),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct PreopenedDir {
+    host_path: String,
+    guest_path: String,
+    dir_perms: DirPerms,
+    file_perms: FilePerms,
+}
+
+#[derive(Clone, Default)]
 struct WasiCtxBuilderInner {
     stdin: Option<ReadStream>,
     stdout: Option<WriteStream>,
     stderr: Option<WriteStream>,
-    env: Option<Opaque<RHash>>,
-    args: Option<Opaque<RArray>>,
+    env: Option<Env>,
+    env_vars: Vec<(String, String)>,
+    args: Option<Argv>,
+    preopened_dirs: Vec<PreopenedDir>,
+    random_seed: Option<u64>,
+    wall_clock: Option<SystemTime>,
+    monotonic_clock: Option<Duration>,
 }
 
 impl WasiCtxBuilderInner {
@@ -58,10 +207,10 @@ impl WasiCtxBuilderInner {
             v.mark(marker);
         }
         if let Some(v) = self.env.as_ref() {
-            marker.mark(*v);
+            v.mark(marker);
         }
         if let Some(v) = self.args.as_ref() {
-            marker.mark(*v);
+            v.mark(marker);
         }
     }
 }
@@ -69,7 +218,8 @@ impl WasiCtxBuilderInner {
 /// @yard
 /// WASI context builder to be sent as {Store#new}’s +wasi_ctx+ keyword argument.
 ///
-/// Instance methods mutate the current object and return +self+.
+/// Instance methods mutate the current object and return +self+. A new
+/// context is built for each {Store} created with the builder.
 ///
 /// @see https://docs.rs/wasmtime-wasi/latest/wasmtime_wasi/sync/struct.WasiCtxBuilder.html
 ///   Wasmtime's Rust doc
@@ -128,6 +278,18 @@ impl WasiCtxBuilder {
         rb_self
     }
 
+    /// @yard
+    /// Set stdin to read from a Ruby +IO+ (or any object responding to
+    /// +readpartial+ or +read+), e.g. a +StringIO+ or a pipe.
+    /// @param io [IO]
+    /// @def set_stdin_io(io)
+    /// @return [WasiCtxBuilder] +self+
+    pub fn set_stdin_io(rb_self: RbSelf, io: Value) -> RbSelf {
+        let mut inner = rb_self.inner.borrow_mut();
+        inner.stdin = Some(ReadStream::Io(io.into()));
+        rb_self
+    }
+
     /// @yard
     /// Inherit stdout from the current Ruby process.
     /// @return [WasiCtxBuilder] +self+
@@ -149,6 +311,30 @@ impl WasiCtxBuilder {
         rb_self
     }
 
+    /// @yard
+    /// Set stdout to append to a +String+ buffer.
+    /// Output past +capacity+ bytes is discarded.
+    /// @param buffer [String] The string to append to; must not be frozen.
+    /// @param capacity [Integer] The maximum number of bytes +buffer+ may hold.
+    /// @def set_stdout_buffer(buffer, capacity)
+    /// @return [WasiCtxBuilder] +self+
+    pub fn set_stdout_buffer(rb_self: RbSelf, buffer: RString, capacity: usize) -> RbSelf {
+        let mut inner = rb_self.inner.borrow_mut();
+        inner.stdout = Some(WriteStream::Buffer(buffer.into(), capacity));
+        rb_self
+    }
+
+    /// @yard
+    /// Set stdout to write to a Ruby +IO+ (or any object responding to +write+).
+    /// @param io [IO]
+    /// @def set_stdout_io(io)
+    /// @return [WasiCtxBuilder] +self+
+    pub fn set_stdout_io(rb_self: RbSelf, io: Value) -> RbSelf {
+        let mut inner = rb_self.inner.borrow_mut();
+        inner.stdout = Some(WriteStream::Io(io.into()));
+        rb_self
+    }
+
     /// @yard
     /// Inherit stderr from the current Ruby process.
     /// @return [WasiCtxBuilder] +self+
@@ -170,14 +356,78 @@ impl WasiCtxBuilder {
         rb_self
     }
 
+    /// @yard
+    /// Set stderr to append to a +String+ buffer.
+    /// Output past +capacity+ bytes is discarded.
+    /// @param buffer [String] The string to append to; must not be frozen.
+    /// @param capacity [Integer] The maximum number of bytes +buffer+ may hold.
+    /// @def set_stderr_buffer(buffer, capacity)
+    /// @return [WasiCtxBuilder] +self+
+    pub fn set_stderr_buffer(rb_self: RbSelf, buffer: RString, capacity: usize) -> RbSelf {
+        let mut inner = rb_self.inner.borrow_mut();
+        inner.stderr = Some(WriteStream::Buffer(buffer.into(), capacity));
+        rb_self
+    }
+
+    /// @yard
+    /// Set stderr to write to a Ruby +IO+ (or any object responding to +write+).
+    /// @param io [IO]
+    /// @def set_stderr_io(io)
+    /// @return [WasiCtxBuilder] +self+
+    pub fn set_stderr_io(rb_self: RbSelf, io: Value) -> RbSelf {
+        let mut inner = rb_self.inner.borrow_mut();
+        inner.stderr = Some(WriteStream::Io(io.into()));
+        rb_self
+    }
+
+    /// @yard
+    /// Inherit the environment variables of the current Ruby process.
+    /// Replaces the variables previously set.
+    /// @return [WasiCtxBuilder] +self+
+    pub fn inherit_env(rb_self: RbSelf) -> RbSelf {
+        let mut inner = rb_self.inner.borrow_mut();
+        inner.env = Some(Env::Inherit);
+        inner.env_vars.clear();
+        rb_self
+    }
+
     /// @yard
     /// Set env to the specified +Hash+.
+    /// Replaces the variables previously set.
     /// @param env [Hash<String, String>]
     /// @def set_env(env)
     /// @return [WasiCtxBuilder] +self+
     pub fn set_env(rb_self: RbSelf, env: RHash) -> RbSelf {
         let mut inner = rb_self.inner.borrow_mut();
-        inner.env = Some(env.into());
+        inner.env = Some(Env::Hash(env.into()));
+        inner.env_vars.clear();
+        rb_self
+    }
+
+    /// @yard
+    /// Set a single environment variable, overriding the value from
+    /// {#inherit_env} or {#set_env} if any.
+    /// @param key [String]
+    /// @param value [String]
+    /// @def env(key, value)
+    /// @return [WasiCtxBuilder] +self+
+    pub fn env(rb_self: RbSelf, key: RString, value: RString) -> Result<RbSelf, Error> {
+        let (key, value) = (key.to_string()?, value.to_string()?);
+        let mut inner = rb_self.inner.borrow_mut();
+        inner.env_vars.retain(|(k, _)| *k != key);
+        inner.env_vars.push((key, value));
+        drop(inner);
+
+        Ok(rb_self)
+    }
+
+    /// @yard
+    /// Inherit the arguments (argv) of the current process. Note that they
+    /// include the +ruby+ executable and its options, unlike +ARGV+.
+    /// @return [WasiCtxBuilder] +self+
+    pub fn inherit_argv(rb_self: RbSelf) -> RbSelf {
+        let mut inner = rb_self.inner.borrow_mut();
+        inner.args = Some(Argv::Inherit);
         rb_self
     }
 
@@ -188,66 +438,244 @@ impl WasiCtxBuilder {
     /// @return [WasiCtxBuilder] +self+
     pub fn set_argv(rb_self: RbSelf, argv: RArray) -> RbSelf {
         let mut inner = rb_self.inner.borrow_mut();
-        inner.args = Some(argv.into());
+        inner.args = Some(Argv::Array(argv.into()));
+        rb_self
+    }
+
+    /// @yard
+    /// Seeds the guest's source of randomness, making the random data it
+    /// reads reproducible across runs (with the same version of this gem).
+    /// Not cryptographically secure: only meant for tests.
+    /// @param seed [Integer]
+    /// @def set_random_seed(seed)
+    /// @return [WasiCtxBuilder] +self+
+    pub fn set_random_seed(rb_self: RbSelf, seed: u64) -> RbSelf {
+        let mut inner = rb_self.inner.borrow_mut();
+        inner.random_seed = Some(seed);
+        rb_self
+    }
+
+    /// @yard
+    /// Freezes the guest's wall clock (+CLOCK_REALTIME+) at +time+.
+    /// @param time [Time] A time after the Unix epoch.
+    /// @def set_wall_clock(time)
+    /// @return [WasiCtxBuilder] +self+
+    pub fn set_wall_clock(rb_self: RbSelf, time: Value) -> Result<RbSelf, Error> {
+        let secs: u64 = time.funcall("to_i", ())?;
+        let nanos: u32 = time.funcall("nsec", ())?;
+        let time = UNIX_EPOCH
+            .checked_add(Duration::new(secs, nanos))
+            .ok_or_else(|| error!("Wall clock time out of range: {}", time))?;
+
+        let mut inner = rb_self.inner.borrow_mut();
+        inner.wall_clock = Some(time);
+        drop(inner);
+
+        Ok(rb_self)
+    }
+
+    /// @yard
+    /// Freezes the guest's monotonic clock (+CLOCK_MONOTONIC+) at
+    /// +nanoseconds+ after the context's creation.
+    /// @param nanoseconds [Integer]
+    /// @def set_monotonic_clock(nanoseconds)
+    /// @return [WasiCtxBuilder] +self+
+    pub fn set_monotonic_clock(rb_self: RbSelf, nanoseconds: u64) -> RbSelf {
+        let mut inner = rb_self.inner.borrow_mut();
+        inner.monotonic_clock = Some(Duration::from_nanos(nanoseconds));
         rb_self
     }
 
+    /// @yard
+    /// Gives the guest access to the +host_path+ directory tree, mounted as
+    /// +guest_path+. Can be called multiple times to preopen several directories.
+    ///
+    /// @def preopen_dir(host_path, guest_path, dir_perms: :all, file_perms: :all)
+    /// @param host_path [String] The directory on the host.
+    /// @param guest_path [String] The path the guest sees the directory as.
+    /// @param dir_perms [Symbol] What the guest may do with the directories:
+    ///   +:read+ (list, stat, open entries), +:mutate+ (create, rename, remove entries) or +:all+.
+    /// @param file_perms [Symbol] What the guest may do with the files:
+    ///   +:read+, +:write+ or +:all+.
+    /// @return [WasiCtxBuilder] +self+
+    ///
+    /// @example Read-only access to +./assets+, seen as +/assets+ by the guest
+    ///   WasiCtxBuilder.new.preopen_dir("./assets", "/assets", dir_perms: :read, file_perms: :read)
+    pub fn preopen_dir(rb_self: RbSelf, args: &[Value]) -> Result<RbSelf, Error> {
+        let args = scan_args::scan_args::<(RString, RString), (), (), (), _, ()>(args)?;
+        let kw = scan_args::get_kwargs::<_, (), (Option<Value>, Option<Value>), ()>(
+            args.keywords,
+            &[],
+            &[*DIR_PERMS, *FILE_PERMS],
+        )?;
+        let (host_path, guest_path) = args.required;
+        let dir_perms = match kw.optional.0 {
+            Some(perms) => DIR_PERMS_MAPPING.get(perms)?,
+            None => DirPerms::ALL,
+        };
+        let file_perms = match kw.optional.1 {
+            Some(perms) => FILE_PERMS_MAPPING.get(perms)?,
+            None => FilePerms::ALL,
+        };
+
+        let mut inner = rb_self.inner.borrow_mut();
+        inner.preopened_dirs.push(PreopenedDir {
+            host_path: host_path.to_string()?,
+            guest_path: guest_path.to_string()?,
+            dir_perms,
+            file_perms,
+        });
+        drop(inner);
+
+        Ok(rb_self)
+    }
+
+    /// @yard
+    /// Copy the builder's settings, to derive variants of a shared builder
+    /// (e.g. with per-request stdin) without changing it. The Ruby objects
+    /// the settings refer to, such as output buffers, are shared by the copy.
+    /// @return [WasiCtxBuilder]
+    pub fn dup(&self) -> Self {
+        Self {
+            inner: RefCell::new(self.inner.borrow().clone()),
+        }
+    }
+
     pub fn build(ruby: &Ruby, rb_self: RbSelf) -> Result<WasiCtx, Error> {
-        let mut builder = wasmtime_wasi::WasiCtxBuilder::new();
         let inner = rb_self.inner.borrow();
+        let mut refs = vec![];
+
+        // Built by hand rather than with `wasmtime_wasi::WasiCtxBuilder`, which
+        // doesn't allow replacing the source of randomness nor the clocks.
+        let random: Box<dyn RngCore + Send + Sync> = match inner.random_seed {
+            None => random_ctx(),
+            Some(seed) => Box::new(StdRng::seed_from_u64(seed)),
+        };
+        let mut clocks = clocks_ctx();
+        if let Some(time) = inner.wall_clock {
+            clocks.system = Some(Box::new(FixedSystemClock(time)));
+        }
+        if let Some(elapsed) = inner.monotonic_clock {
+            let now = clocks.creation_time + elapsed;
+            clocks.monotonic = Some(Box::new(FixedMonotonicClock(now)));
+        }
+        let mut ctx = WasiCtxImpl::new(random, clocks, sched_ctx(), Table::new());
 
         if let Some(stdin) = inner.stdin.as_ref() {
             match stdin {
-                ReadStream::Inherit => builder.inherit_stdin(),
+                ReadStream::Inherit => ctx.set_stdin(Box::new(stdio::stdin())),
                 ReadStream::Path(path) => {
-                    builder.stdin(file_r(ruby.get_inner(*path)).map(wasi_file)?)
+                    ctx.set_stdin(file_r(ruby.get_inner(*path)).map(wasi_file)?)
                 }
                 ReadStream::String(input) => {
                     // SAFETY: &[u8] copied before calling in to Ruby, no GC can happen before.
                     let pipe = ReadPipe::from(unsafe { ruby.get_inner(*input).as_slice() });
-                    builder.stdin(Box::new(pipe))
+                    ctx.set_stdin(Box::new(pipe))
+                }
+                ReadStream::Io(io) => {
+                    refs.push(ruby.get_inner(*io));
+                    ctx.set_stdin(Box::new(ReadPipe::new(RubyIoReader::new(*io))))
                 }
             };
         }
 
         if let Some(stdout) = inner.stdout.as_ref() {
-            match stdout {
-                WriteStream::Inherit => builder.inherit_stdout(),
-                WriteStream::Path(path) => {
-                    builder.stdout(file_w(ruby.get_inner(*path)).map(wasi_file)?)
-                }
+            match stdout.build(ruby)? {
+                None => ctx.set_stdout(Box::new(stdio::stdout())),
+                Some(file) => ctx.set_stdout(file),
             };
+            refs.extend(stdout.retained_value(ruby));
         }
 
         if let Some(stderr) = inner.stderr.as_ref() {
-            match stderr {
-                WriteStream::Inherit => builder.inherit_stderr(),
-                WriteStream::Path(path) => {
-                    builder.stderr(file_w(ruby.get_inner(*path)).map(wasi_file)?)
-                }
+            match stderr.build(ruby)? {
+                None => ctx.set_stderr(Box::new(stdio::stderr())),
+                Some(file) => ctx.set_stderr(file),
             };
+            refs.extend(stderr.retained_value(ruby));
         }
 
-        if let Some(args) = inner.args.as_ref() {
-            // SAFETY: no gc can happen nor do we write to `args`.
-            for item in unsafe { ruby.get_inner(*args).as_slice() } {
-                let arg = RString::try_convert(*item)?;
-                // SAFETY: &str copied before calling in to Ruby, no GC can happen before.
-                let arg = unsafe { arg.as_str() }?;
-                builder.arg(arg).map_err(|e| error!("{}", e))?;
+        match inner.args.as_ref() {
+            None => (),
+            Some(Argv::Inherit) => {
+                for arg in std::env::args() {
+                    ctx.push_arg(&arg).map_err(|e| error!("{}", e))?;
+                }
+            }
+            Some(Argv::Array(args)) => {
+                // SAFETY: no gc can happen nor do we write to `args`.
+                for item in unsafe { ruby.get_inner(*args).as_slice() } {
+                    let arg = RString::try_convert(*item)?;
+                    // SAFETY: &str copied before calling in to Ruby, no GC can happen before.
+                    let arg = unsafe { arg.as_str() }?;
+                    ctx.push_arg(arg).map_err(|e| error!("{}", e))?;
+                }
             }
         }
 
-        if let Some(env_hash) = inner.env.as_ref() {
-            let env_vec: Vec<(String, String)> = ruby.get_inner(*env_hash).to_vec()?;
-            builder.envs(&env_vec).map_err(|e| error!("{}", e))?;
+        let mut env_vec: Vec<(String, String)> = match inner.env.as_ref() {
+            None => vec![],
+            Some(Env::Inherit) => std::env::vars().collect(),
+            Some(Env::Hash(env_hash)) => ruby.get_inner(*env_hash).to_vec()?,
+        };
+        for (key, value) in inner.env_vars.iter() {
+            env_vec.retain(|(k, _)| k != key);
+            env_vec.push((key.clone(), value.clone()));
+        }
+        for (key, value) in env_vec.iter() {
+            ctx.push_env(key, value).map_err(|e| error!("{}", e))?;
         }
 
-        let ctx = WasiCtx::from_inner(builder.build());
+        for preopened_dir in inner.preopened_dirs.iter() {
+            ctx.push_preopened_dir(preopened_dir.open()?, &preopened_dir.guest_path)
+                .map_err(|e| error!("{}", e))?;
+        }
+
+        let ctx = WasiCtx::from_inner(ctx).with_refs(refs);
         Ok(ctx)
     }
 }
 
+/// A wall clock frozen at a given time.
+struct FixedSystemClock(SystemTime);
+
+impl WasiSystemClock for FixedSystemClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self, _precision: Duration) -> cap_std::time::SystemTime {
+        cap_std::time::SystemTime::from_std(self.0)
+    }
+}
+
+/// A monotonic clock frozen at a given instant.
+struct FixedMonotonicClock(cap_std::time::Instant);
+
+impl WasiMonotonicClock for FixedMonotonicClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self, _precision: Duration) -> cap_std::time::Instant {
+        self.0
+    }
+}
+
+impl PreopenedDir {
+    fn open(&self) -> Result<Box<dyn WasiDir>, Error> {
+        let dir = cap_std::fs::Dir::open_ambient_dir(&self.host_path, cap_std::ambient_authority())
+            .map_err(|e| error!("Failed to open directory {}\n{}", self.host_path, e))?;
+        let dir = Box::new(wasi_cap_std_sync::dir::Dir::from_cap_std(dir));
+
+        Ok(Box::new(PermissionedDir::new(
+            dir,
+            self.dir_perms,
+            self.file_perms,
+        )))
+    }
+}
+
 pub fn file_r(path: RString) -> Result<File, Error> {
     // SAFETY: &str copied before calling in to Ruby, no GC can happen before.
     File::open(PathBuf::from(unsafe { path.as_str()? }))
@@ -269,6 +697,7 @@ pub fn wasi_file(file: File) -> Box<wasi_cap_std_sync::file::File> {
 pub fn init() -> Result<(), Error> {
     let class = root().define_class("WasiCtxBuilder", class::object())?;
     class.define_singleton_method("new", function!(WasiCtxBuilder::new, 0))?;
+    class.define_method("dup", method!(WasiCtxBuilder::dup, 0))?;
 
     class.define_method("inherit_stdin", method!(WasiCtxBuilder::inherit_stdin, 0))?;
     class.define_method("set_stdin_file", method!(WasiCtxBuilder::set_stdin_file, 1))?;
@@ -276,6 +705,7 @@ pub fn init() -> Result<(), Error> {
         "set_stdin_string",
         method!(WasiCtxBuilder::set_stdin_string, 1),
     )?;
+    class.define_method("set_stdin_io", method!(WasiCtxBuilder::set_stdin_io, 1))?;
 
     class.define_method("inherit_stdout", method!(WasiCtxBuilder::inherit_stdout, 0))?;
     class.define_method(
@@ -283,16 +713,43 @@ pub fn init() -> Result<(), Error> {
         method!(WasiCtxBuilder::set_stdout_file, 1),
     )?;
 
+    class.define_method(
+        "set_stdout_buffer",
+        method!(WasiCtxBuilder::set_stdout_buffer, 2),
+    )?;
+    class.define_method("set_stdout_io", method!(WasiCtxBuilder::set_stdout_io, 1))?;
+
     class.define_method("inherit_stderr", method!(WasiCtxBuilder::inherit_stderr, 0))?;
     class.define_method(
         "set_stderr_file",
         method!(WasiCtxBuilder::set_stderr_file, 1),
     )?;
 
+    class.define_method(
+        "set_stderr_buffer",
+        method!(WasiCtxBuilder::set_stderr_buffer, 2),
+    )?;
+    class.define_method("set_stderr_io", method!(WasiCtxBuilder::set_stderr_io, 1))?;
+
+    class.define_method("inherit_env", method!(WasiCtxBuilder::inherit_env, 0))?;
     class.define_method("set_env", method!(WasiCtxBuilder::set_env, 1))?;
+    class.define_method("env", method!(WasiCtxBuilder::env, 2))?;
 
+    class.define_method("inherit_argv", method!(WasiCtxBuilder::inherit_argv, 0))?;
     class.define_method("set_argv", method!(WasiCtxBuilder::set_argv, 1))?;
 
+    class.define_method(
+        "set_random_seed",
+        method!(WasiCtxBuilder::set_random_seed, 1),
+    )?;
+    class.define_method("set_wall_clock", method!(WasiCtxBuilder::set_wall_clock, 1))?;
+    class.define_method(
+        "set_monotonic_clock",
+        method!(WasiCtxBuilder::set_monotonic_clock, 1),
+    )?;
+
+    class.define_method("preopen_dir", method!(WasiCtxBuilder::preopen_dir, -1))?;
+
     class.define_method("build", method!(WasiCtxBuilder::build, 0))?;
 
     Ok(())
@@ -0,0 +1,154 @@
+use super::{root, store::StoreData};
+use crate::{err, error};
+use magnus::{
+    class, exception::arg_error, function, method, typed_data::Obj, Error, Module as _, Object,
+    RString,
+};
+use std::cell::RefCell;
+use wasmtime_wasi_nn::{Backend, InMemoryRegistry, WasiNnCtx};
+
+/// The backends and graphs of a {Store}'s wasi-nn context.
+#[derive(Clone, Default)]
+struct WasiNnConfig {
+    /// All the backends compiled in when not restricted.
+    backends: Option<Vec<String>>,
+    /// The backend and directory of each graph to preload.
+    graphs: Vec<(String, String)>,
+}
+
+impl WasiNnConfig {
+    fn allows(&self, backend: &str) -> bool {
+        self.backends
+            .as_ref()
+            .map_or(true, |backends| backends.iter().any(|b| b == backend))
+    }
+}
+
+/// @yard
+/// wasi-nn configuration to be sent as {Store#new}’s +wasi_nn_ctx+ keyword
+/// argument, for modules and components instantiated by a {Linker} or a
+/// {Component::Linker} created with +wasi_nn: true+.
+///
+/// Only available when the gem is built with the +wasi-nn+ feature. The
+/// backends available (e.g. +openvino+, +onnx+) depend on the features
+/// +wasmtime-wasi-nn+ was built with.
+///
+/// Instance methods mutate the current object and return +self+.
+///
+/// @example
+///   wasi_nn_ctx = Wasmtime::WasiNnCtxBuilder.new
+///     .allow_backend("onnx")
+///     .preload_graph("onnx", "models/classifier")
+///   store = Wasmtime::Store.new(engine, wasi_nn_ctx: wasi_nn_ctx)
+///
+/// @see https://docs.rs/wasmtime-wasi-nn/latest/wasmtime_wasi_nn/ Wasmtime's Rust doc
+#[derive(Default)]
+#[magnus::wrap(class = "Wasmtime::WasiNnCtxBuilder", size, free_immediately)]
+pub struct WasiNnCtxBuilder {
+    inner: RefCell<WasiNnConfig>,
+}
+
+unsafe impl Send for WasiNnCtxBuilder {}
+
+type RbSelf = Obj<WasiNnCtxBuilder>;
+
+impl WasiNnCtxBuilder {
+    /// @yard
+    /// @return [WasiNnCtxBuilder]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// @yard
+    /// Restrict the backends guests can use, all of them are allowed unless
+    /// this is called.
+    /// @def allow_backend(backend)
+    /// @param backend [String] e.g. +openvino+ or +onnx+.
+    /// @return [WasiNnCtxBuilder] +self+
+    pub fn allow_backend(rb_self: RbSelf, backend: RString) -> Result<RbSelf, Error> {
+        let backend = backend.to_string()?.to_ascii_lowercase();
+        rb_self
+            .inner
+            .borrow_mut()
+            .backends
+            .get_or_insert_with(Vec::new)
+            .push(backend);
+        Ok(rb_self)
+    }
+
+    /// @yard
+    /// Load the graph (model) stored in +dir+ when creating the {Store}, for
+    /// guests to load by name (the directory's name).
+    /// @def preload_graph(backend, dir)
+    /// @param backend [String] The backend running the graph, e.g. +onnx+.
+    /// @param dir [String] The directory of the graph's files.
+    /// @return [WasiNnCtxBuilder] +self+
+    pub fn preload_graph(rb_self: RbSelf, backend: RString, dir: RString) -> Result<RbSelf, Error> {
+        let backend = backend.to_string()?.to_ascii_lowercase();
+        let dir = dir.to_string()?;
+        rb_self.inner.borrow_mut().graphs.push((backend, dir));
+        Ok(rb_self)
+    }
+
+    pub fn build_ctx(&self) -> Result<WasiNnCtx, Error> {
+        let config = self.inner.borrow();
+        if let Some((backend, _)) = config.graphs.iter().find(|(b, _)| !config.allows(b)) {
+            return Err(Error::new(
+                arg_error(),
+                format!("cannot preload a graph for disallowed backend {backend}"),
+            ));
+        }
+
+        let (backends, registry) = wasmtime_wasi_nn::preload(&config.graphs)
+            .map_err(|e| error!("Could not preload wasi-nn graphs: {}", e))?;
+        let backends = backends
+            .into_iter()
+            .filter(|backend| config.allows(&backend_name(backend)));
+
+        Ok(WasiNnCtx::new(backends, registry))
+    }
+}
+
+/// The backend's name, in the format of its graph encoding in WIT.
+fn backend_name(backend: &Backend) -> String {
+    format!("{:?}", backend.encoding()).to_ascii_lowercase()
+}
+
+/// An empty context, for stores created without +wasi_nn_ctx+.
+pub fn empty_ctx() -> WasiNnCtx {
+    WasiNnCtx::new(Vec::<Backend>::new(), InMemoryRegistry::new().into())
+}
+
+/// Adds the wasi-nn imports of modules to `linker`.
+pub fn add_to_linker(
+    linker: &mut wasmtime::Linker<StoreData>,
+    async_support: bool,
+) -> Result<(), Error> {
+    if async_support {
+        return err!("wasi-nn is not supported in engines with async_support: true");
+    }
+
+    wasmtime_wasi_nn::witx::add_to_linker(linker, |s| s.wasi_nn_mut()).map_err(|e| error!("{}", e))
+}
+
+/// Adds the wasi-nn imports of components to `linker`.
+pub fn add_to_component_linker(
+    linker: &mut wasmtime::component::Linker<StoreData>,
+    async_support: bool,
+) -> Result<(), Error> {
+    if async_support {
+        return err!("wasi-nn is not supported in engines with async_support: true");
+    }
+
+    wasmtime_wasi_nn::wit::ML::add_to_linker(linker, |s| s.wasi_nn_mut())
+        .map_err(|e| error!("{}", e))
+}
+
+pub fn init() -> Result<(), Error> {
+    let class = root().define_class("WasiNnCtxBuilder", class::object())?;
+    class.define_singleton_method("new", function!(WasiNnCtxBuilder::new, 0))?;
+    class.define_method("allow_backend", method!(WasiNnCtxBuilder::allow_backend, 1))?;
+    class.define_method("preload_graph", method!(WasiNnCtxBuilder::preload_graph, 2))?;
+
+    Ok(())
+}
@@ -0,0 +1,696 @@
+use super::{
+    root,
+    store::StoreData,
+    wasi_ctx_builder::{DIR_PERMS_MAPPING, FILE_PERMS_MAPPING},
+};
+use crate::{
+    define_rb_intern, err, error,
+    helpers::{DirPerms, FilePerms, OutputLimitedBuffer},
+};
+use bytes::Bytes;
+use magnus::{
+    class, exception::arg_error, function, gc::Marker, method, prelude::*, scan_args,
+    typed_data::Obj, value::Opaque, DataTypeFunctions, Error, Module, Object, RArray, RHash,
+    RString, Ruby, TypedData, Value,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use std::{
+    cell::RefCell,
+    io::Write,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use wasmtime::component::ResourceTable;
+use wasmtime_wasi::preview2::{
+    pipe::MemoryInputPipe, DirPerms as P2DirPerms, FilePerms as P2FilePerms, HostMonotonicClock,
+    HostOutputStream, HostWallClock, StdoutStream, StreamError, StreamResult, Subscribe,
+    WasiCtx as WasiP2Ctx, WasiCtxBuilder as WasiP2CtxBuilderImpl, WasiView,
+};
+
+define_rb_intern!(
+    DIR_PERMS => "dir_perms",
+    FILE_PERMS => "file_perms",
+);
+
+#[derive(Clone)]
+enum InputStream {
+    Inherit,
+    Bytes(Vec<u8>),
+}
+
+#[derive(Clone)]
+enum OutputStream {
+    Inherit,
+    Buffer(Opaque<RString>, usize),
+}
+
+impl OutputStream {
+    fn mark(&self, marker: &Marker) {
+        match self {
+            Self::Inherit => (),
+            Self::Buffer(v, _) => marker.mark(*v),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct PreopenedDir {
+    host_path: String,
+    guest_path: String,
+    dir_perms: DirPerms,
+    file_perms: FilePerms,
+}
+
+/// An address guests may bind or connect sockets to, on any port when `port`
+/// is `None`.
+#[derive(Clone, Copy)]
+struct AllowedAddr {
+    ip: IpAddr,
+    port: Option<u16>,
+}
+
+impl AllowedAddr {
+    fn parse(addr: &str) -> Option<Self> {
+        if let Ok(addr) = addr.parse::<SocketAddr>() {
+            return Some(Self {
+                ip: addr.ip(),
+                port: Some(addr.port()),
+            });
+        }
+        addr.parse::<IpAddr>()
+            .ok()
+            .map(|ip| Self { ip, port: None })
+    }
+
+    fn matches(&self, addr: &SocketAddr) -> bool {
+        self.ip == addr.ip() && self.port.map_or(true, |port| port == addr.port())
+    }
+}
+
+#[derive(Clone, Default)]
+enum NetworkAccess {
+    #[default]
+    Deny,
+    Inherit,
+    Allow(Vec<AllowedAddr>),
+}
+
+#[derive(Clone, Default)]
+struct WasiP2CtxBuilderInner {
+    stdin: Option<InputStream>,
+    stdout: Option<OutputStream>,
+    stderr: Option<OutputStream>,
+    env: Vec<(String, String)>,
+    args: Vec<String>,
+    preopened_dirs: Vec<PreopenedDir>,
+    random_seed: Option<u64>,
+    wall_clock: Option<SystemTime>,
+    monotonic_clock: Option<Duration>,
+    network: NetworkAccess,
+    ip_name_lookup: bool,
+}
+
+/// The per-{Store} state of WASI preview 2.
+pub struct WasiP2State {
+    ctx: WasiP2Ctx,
+    table: ResourceTable,
+}
+
+impl Default for WasiP2State {
+    /// A context with nothing: no stdio, no env, no args, no file access.
+    fn default() -> Self {
+        Self {
+            ctx: WasiP2CtxBuilderImpl::new().build(),
+            table: ResourceTable::new(),
+        }
+    }
+}
+
+/// @yard
+/// WASI preview 2 context builder to be sent as {Store#new}’s +wasi_p2_ctx+
+/// keyword argument, for {Component::Component}s instantiated by a
+/// {Component::Linker} created with +wasi: true+. Unlike {WasiCtxBuilder},
+/// which only supports WASI preview 1 modules, it supports the interfaces of
+/// the +wasi:cli+ worlds components target (clocks, filesystem, random,
+/// sockets, stdio). Networking is denied unless allowed with
+/// {#inherit_network} or {#allow_socket_addr}.
+///
+/// Instance methods mutate the current object and return +self+. A new
+/// context is built for each {Store} created with the builder.
+///
+/// @example
+///   wasi_p2_ctx = Wasmtime::WasiP2CtxBuilder.new
+///     .set_argv(["app", "--verbose"])
+///     .set_stdout_buffer(stdout = +"", 40_000)
+///   store = Wasmtime::Store.new(engine, wasi_p2_ctx: wasi_p2_ctx)
+///   linker = Wasmtime::Component::Linker.new(engine, wasi: true)
+///   linker.run_command(store, component)
+///
+/// @see https://docs.rs/wasmtime-wasi/latest/wasmtime_wasi/preview2/struct.WasiCtxBuilder.html
+///   Wasmtime's Rust doc
+#[derive(Default, TypedData)]
+#[magnus(class = "Wasmtime::WasiP2CtxBuilder", size, mark, free_immediately)]
+pub struct WasiP2CtxBuilder {
+    inner: RefCell<WasiP2CtxBuilderInner>,
+}
+
+impl DataTypeFunctions for WasiP2CtxBuilder {
+    fn mark(&self, marker: &Marker) {
+        let inner = self.inner.borrow();
+        if let Some(v) = inner.stdout.as_ref() {
+            v.mark(marker);
+        }
+        if let Some(v) = inner.stderr.as_ref() {
+            v.mark(marker);
+        }
+    }
+}
+
+type RbSelf = Obj<WasiP2CtxBuilder>;
+
+impl WasiP2CtxBuilder {
+    /// @yard
+    /// Create a new {WasiP2CtxBuilder}. By default, it has nothing: no
+    /// stdin/out/err, no env, no argv, no file nor network access.
+    /// @return [WasiP2CtxBuilder]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// @yard
+    /// Inherit stdin from the current Ruby process.
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn inherit_stdin(rb_self: RbSelf) -> RbSelf {
+        rb_self.inner.borrow_mut().stdin = Some(InputStream::Inherit);
+        rb_self
+    }
+
+    /// @yard
+    /// Set stdin to the specified String.
+    /// @param content [String]
+    /// @def set_stdin_string(content)
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn set_stdin_string(rb_self: RbSelf, content: RString) -> RbSelf {
+        let bytes = unsafe { content.as_slice() }.to_vec();
+        rb_self.inner.borrow_mut().stdin = Some(InputStream::Bytes(bytes));
+        rb_self
+    }
+
+    /// @yard
+    /// Inherit stdout from the current Ruby process.
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn inherit_stdout(rb_self: RbSelf) -> RbSelf {
+        rb_self.inner.borrow_mut().stdout = Some(OutputStream::Inherit);
+        rb_self
+    }
+
+    /// @yard
+    /// Set stdout to append to a +String+ buffer.
+    /// Output past +capacity+ bytes is discarded.
+    /// @param buffer [String] The string to append to; must not be frozen.
+    /// @param capacity [Integer] The maximum number of bytes +buffer+ may hold.
+    /// @def set_stdout_buffer(buffer, capacity)
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn set_stdout_buffer(rb_self: RbSelf, buffer: RString, capacity: usize) -> RbSelf {
+        rb_self.inner.borrow_mut().stdout = Some(OutputStream::Buffer(buffer.into(), capacity));
+        rb_self
+    }
+
+    /// @yard
+    /// Inherit stderr from the current Ruby process.
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn inherit_stderr(rb_self: RbSelf) -> RbSelf {
+        rb_self.inner.borrow_mut().stderr = Some(OutputStream::Inherit);
+        rb_self
+    }
+
+    /// @yard
+    /// Set stderr to append to a +String+ buffer.
+    /// Output past +capacity+ bytes is discarded.
+    /// @param buffer [String] The string to append to; must not be frozen.
+    /// @param capacity [Integer] The maximum number of bytes +buffer+ may hold.
+    /// @def set_stderr_buffer(buffer, capacity)
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn set_stderr_buffer(rb_self: RbSelf, buffer: RString, capacity: usize) -> RbSelf {
+        rb_self.inner.borrow_mut().stderr = Some(OutputStream::Buffer(buffer.into(), capacity));
+        rb_self
+    }
+
+    /// @yard
+    /// Inherit the environment variables of the current Ruby process.
+    /// Replaces the variables previously set.
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn inherit_env(rb_self: RbSelf) -> RbSelf {
+        rb_self.inner.borrow_mut().env = std::env::vars().collect();
+        rb_self
+    }
+
+    /// @yard
+    /// Set env to the specified +Hash+.
+    /// Replaces the variables previously set.
+    /// @param env [Hash<String, String>]
+    /// @def set_env(env)
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn set_env(rb_self: RbSelf, env: RHash) -> Result<RbSelf, Error> {
+        rb_self.inner.borrow_mut().env = env.to_vec()?;
+        Ok(rb_self)
+    }
+
+    /// @yard
+    /// Set a single environment variable, overriding the previous value if any.
+    /// @param key [String]
+    /// @param value [String]
+    /// @def env(key, value)
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn env(rb_self: RbSelf, key: RString, value: RString) -> Result<RbSelf, Error> {
+        let (key, value) = (key.to_string()?, value.to_string()?);
+        let mut inner = rb_self.inner.borrow_mut();
+        inner.env.retain(|(k, _)| *k != key);
+        inner.env.push((key, value));
+        drop(inner);
+
+        Ok(rb_self)
+    }
+
+    /// @yard
+    /// Inherit the arguments (argv) of the current process. Note that they
+    /// include the +ruby+ executable and its options, unlike +ARGV+.
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn inherit_argv(rb_self: RbSelf) -> RbSelf {
+        rb_self.inner.borrow_mut().args = std::env::args().collect();
+        rb_self
+    }
+
+    /// @yard
+    /// Set the arguments (argv) to the specified +Array+.
+    /// @param args [Array<String>]
+    /// @def set_argv(args)
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn set_argv(rb_self: RbSelf, argv: RArray) -> Result<RbSelf, Error> {
+        rb_self.inner.borrow_mut().args = argv.to_vec()?;
+        Ok(rb_self)
+    }
+
+    /// @yard
+    /// Gives the guest access to the +host_path+ directory tree, mounted as
+    /// +guest_path+. See {WasiCtxBuilder#preopen_dir} for the permissions.
+    ///
+    /// @def preopen_dir(host_path, guest_path, dir_perms: :all, file_perms: :all)
+    /// @param host_path [String] The directory on the host.
+    /// @param guest_path [String] The path the guest sees the directory as.
+    /// @param dir_perms [Symbol] +:read+, +:mutate+ or +:all+.
+    /// @param file_perms [Symbol] +:read+, +:write+ or +:all+.
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn preopen_dir(rb_self: RbSelf, args: &[Value]) -> Result<RbSelf, Error> {
+        let args = scan_args::scan_args::<(RString, RString), (), (), (), _, ()>(args)?;
+        let kw = scan_args::get_kwargs::<_, (), (Option<Value>, Option<Value>), ()>(
+            args.keywords,
+            &[],
+            &[*DIR_PERMS, *FILE_PERMS],
+        )?;
+        let (host_path, guest_path) = args.required;
+        let dir_perms = match kw.optional.0 {
+            Some(perms) => DIR_PERMS_MAPPING.get(perms)?,
+            None => DirPerms::ALL,
+        };
+        let file_perms = match kw.optional.1 {
+            Some(perms) => FILE_PERMS_MAPPING.get(perms)?,
+            None => FilePerms::ALL,
+        };
+
+        rb_self
+            .inner
+            .borrow_mut()
+            .preopened_dirs
+            .push(PreopenedDir {
+                host_path: host_path.to_string()?,
+                guest_path: guest_path.to_string()?,
+                dir_perms,
+                file_perms,
+            });
+
+        Ok(rb_self)
+    }
+
+    /// @yard
+    /// Seeds the guest's sources of randomness, making the random data it
+    /// reads reproducible across runs (with the same version of this gem).
+    /// Not cryptographically secure: only meant for tests.
+    /// @param seed [Integer]
+    /// @def set_random_seed(seed)
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn set_random_seed(rb_self: RbSelf, seed: u64) -> RbSelf {
+        rb_self.inner.borrow_mut().random_seed = Some(seed);
+        rb_self
+    }
+
+    /// @yard
+    /// Freezes the guest's wall clock at +time+.
+    /// @param time [Time] A time after the Unix epoch.
+    /// @def set_wall_clock(time)
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn set_wall_clock(rb_self: RbSelf, time: Value) -> Result<RbSelf, Error> {
+        let secs: u64 = time.funcall("to_i", ())?;
+        let nanos: u32 = time.funcall("nsec", ())?;
+        let time = UNIX_EPOCH
+            .checked_add(Duration::new(secs, nanos))
+            .ok_or_else(|| error!("Wall clock time out of range: {}", time))?;
+
+        rb_self.inner.borrow_mut().wall_clock = Some(time);
+        Ok(rb_self)
+    }
+
+    /// @yard
+    /// Freezes the guest's monotonic clock at +nanoseconds+.
+    /// @param nanoseconds [Integer]
+    /// @def set_monotonic_clock(nanoseconds)
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn set_monotonic_clock(rb_self: RbSelf, nanoseconds: u64) -> RbSelf {
+        rb_self.inner.borrow_mut().monotonic_clock = Some(Duration::from_nanos(nanoseconds));
+        rb_self
+    }
+
+    /// @yard
+    /// Lets the guest use the network of the host through +wasi:sockets+:
+    /// TCP and UDP sockets may bind or connect to any address.
+    /// Replaces the addresses previously allowed.
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn inherit_network(rb_self: RbSelf) -> RbSelf {
+        rb_self.inner.borrow_mut().network = NetworkAccess::Inherit;
+        rb_self
+    }
+
+    /// @yard
+    /// Lets the guest's sockets bind or connect to +addr+ through
+    /// +wasi:sockets+. Other addresses are denied.
+    /// @def allow_socket_addr(addr)
+    /// @param addr [String] An IP address, allowing all of its ports
+    ///   (e.g. +127.0.0.1+ or +::1+), or a socket address (e.g.
+    ///   +127.0.0.1:8080+ or +[::1]:8080+).
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn allow_socket_addr(rb_self: RbSelf, addr: RString) -> Result<RbSelf, Error> {
+        let addr_str = addr.to_string()?;
+        let addr = AllowedAddr::parse(&addr_str).ok_or_else(|| {
+            Error::new(
+                arg_error(),
+                format!("invalid socket address: {:?}", addr_str),
+            )
+        })?;
+
+        let mut inner = rb_self.inner.borrow_mut();
+        match &mut inner.network {
+            NetworkAccess::Allow(addrs) => addrs.push(addr),
+            network => *network = NetworkAccess::Allow(vec![addr]),
+        }
+        drop(inner);
+
+        Ok(rb_self)
+    }
+
+    /// @yard
+    /// Denies all networking, the default: the guest can't bind nor connect
+    /// sockets, nor look up names. Replaces the addresses previously allowed.
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn deny_network(rb_self: RbSelf) -> RbSelf {
+        let mut inner = rb_self.inner.borrow_mut();
+        inner.network = NetworkAccess::Deny;
+        inner.ip_name_lookup = false;
+        drop(inner);
+
+        rb_self
+    }
+
+    /// @yard
+    /// Lets the guest resolve names through +wasi:sockets/ip-name-lookup+,
+    /// when it has network access (see {#inherit_network} and
+    /// {#allow_socket_addr}).
+    /// @def allow_ip_name_lookup(enabled)
+    /// @param enabled [Boolean]
+    /// @return [WasiP2CtxBuilder] +self+
+    pub fn allow_ip_name_lookup(rb_self: RbSelf, enabled: bool) -> RbSelf {
+        rb_self.inner.borrow_mut().ip_name_lookup = enabled;
+        rb_self
+    }
+
+    /// @yard
+    /// Copy the builder's settings, to derive variants of a shared builder
+    /// (e.g. with per-request stdin) without changing it. The Ruby objects
+    /// the settings refer to, such as output buffers, are shared by the copy.
+    /// @return [WasiP2CtxBuilder]
+    pub fn dup(&self) -> Self {
+        Self {
+            inner: RefCell::new(self.inner.borrow().clone()),
+        }
+    }
+
+    /// Builds a new context, along with the Ruby objects it writes to, which
+    /// must be retained by the Store using it.
+    pub fn build_state(&self, ruby: &Ruby) -> Result<(WasiP2State, Vec<Value>), Error> {
+        let inner = self.inner.borrow();
+        let mut builder = WasiP2CtxBuilderImpl::new();
+        let mut refs = vec![];
+
+        match inner.stdin.as_ref() {
+            None => (),
+            Some(InputStream::Inherit) => {
+                builder.inherit_stdin();
+            }
+            Some(InputStream::Bytes(bytes)) => {
+                builder.stdin(MemoryInputPipe::new(Bytes::copy_from_slice(bytes)));
+            }
+        }
+
+        match inner.stdout.as_ref() {
+            None => (),
+            Some(OutputStream::Inherit) => {
+                builder.inherit_stdout();
+            }
+            Some(OutputStream::Buffer(buffer, capacity)) => {
+                builder.stdout(BufferStdout(*buffer, *capacity));
+                refs.push(ruby.get_inner(*buffer).as_value());
+            }
+        }
+
+        match inner.stderr.as_ref() {
+            None => (),
+            Some(OutputStream::Inherit) => {
+                builder.inherit_stderr();
+            }
+            Some(OutputStream::Buffer(buffer, capacity)) => {
+                builder.stderr(BufferStdout(*buffer, *capacity));
+                refs.push(ruby.get_inner(*buffer).as_value());
+            }
+        }
+
+        builder.envs(&inner.env);
+        builder.args(&inner.args);
+
+        for dir in inner.preopened_dirs.iter() {
+            let cap_dir =
+                cap_std::fs::Dir::open_ambient_dir(&dir.host_path, cap_std::ambient_authority())
+                    .map_err(|e| error!("Failed to open directory {}\n{}", dir.host_path, e))?;
+            builder.preopened_dir(
+                cap_dir,
+                p2_dir_perms(dir.dir_perms),
+                p2_file_perms(dir.file_perms),
+                &dir.guest_path,
+            );
+        }
+
+        if let Some(seed) = inner.random_seed {
+            builder.secure_random(StdRng::seed_from_u64(seed));
+            builder.insecure_random(StdRng::seed_from_u64(seed));
+            builder.insecure_random_seed(seed.into());
+        }
+        if let Some(time) = inner.wall_clock {
+            builder.wall_clock(FixedWallClock(time.duration_since(UNIX_EPOCH).unwrap()));
+        }
+        if let Some(elapsed) = inner.monotonic_clock {
+            builder.monotonic_clock(FixedMonotonicClock(elapsed.as_nanos() as u64));
+        }
+        match &inner.network {
+            NetworkAccess::Deny => (),
+            NetworkAccess::Inherit => {
+                builder.inherit_network();
+                builder.allow_ip_name_lookup(inner.ip_name_lookup);
+            }
+            NetworkAccess::Allow(addrs) => {
+                let addrs: Arc<[AllowedAddr]> = addrs.as_slice().into();
+                builder.inherit_network();
+                builder.allow_ip_name_lookup(inner.ip_name_lookup);
+                builder.socket_addr_check(move |addr| addrs.iter().any(|a| a.matches(addr)));
+            }
+        }
+
+        let state = WasiP2State {
+            ctx: builder.build(),
+            table: ResourceTable::new(),
+        };
+        Ok((state, refs))
+    }
+}
+
+fn p2_dir_perms(perms: DirPerms) -> P2DirPerms {
+    let mut p2_perms = P2DirPerms::empty();
+    p2_perms.set(P2DirPerms::READ, perms.read);
+    p2_perms.set(P2DirPerms::MUTATE, perms.mutate);
+    p2_perms
+}
+
+fn p2_file_perms(perms: FilePerms) -> P2FilePerms {
+    let mut p2_perms = P2FilePerms::empty();
+    p2_perms.set(P2FilePerms::READ, perms.read);
+    p2_perms.set(P2FilePerms::WRITE, perms.write);
+    p2_perms
+}
+
+/// A [`StdoutStream`] appending to a Ruby `String`, see [`OutputLimitedBuffer`].
+struct BufferStdout(Opaque<RString>, usize);
+
+impl StdoutStream for BufferStdout {
+    fn stream(&self) -> Box<dyn HostOutputStream> {
+        Box::new(BufferOutputStream(OutputLimitedBuffer::new(self.0, self.1)))
+    }
+
+    fn isatty(&self) -> bool {
+        false
+    }
+}
+
+struct BufferOutputStream(OutputLimitedBuffer);
+
+impl HostOutputStream for BufferOutputStream {
+    fn write(&mut self, bytes: Bytes) -> StreamResult<()> {
+        self.0
+            .write_all(&bytes)
+            .map_err(|e| StreamError::LastOperationFailed(e.into()))
+    }
+
+    fn flush(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    fn check_write(&mut self) -> StreamResult<usize> {
+        // Writes never block, the buffer drops what doesn't fit.
+        Ok(64 * 1024)
+    }
+}
+
+#[async_trait::async_trait]
+impl Subscribe for BufferOutputStream {
+    async fn ready(&mut self) {}
+}
+
+/// A wall clock frozen at a given duration since the Unix epoch.
+struct FixedWallClock(Duration);
+
+impl HostWallClock for FixedWallClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self) -> Duration {
+        self.0
+    }
+}
+
+/// A monotonic clock frozen at a given number of nanoseconds.
+struct FixedMonotonicClock(u64);
+
+impl HostMonotonicClock for FixedMonotonicClock {
+    fn resolution(&self) -> u64 {
+        1
+    }
+
+    fn now(&self) -> u64 {
+        self.0
+    }
+}
+
+impl WasiView for StoreData {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.wasi_p2_mut().table
+    }
+
+    fn ctx(&mut self) -> &mut WasiP2Ctx {
+        &mut self.wasi_p2_mut().ctx
+    }
+}
+
+/// Adds the WASI preview 2 imports of the +wasi:cli/command+ world to `linker`.
+pub fn add_to_linker(
+    linker: &mut wasmtime::component::Linker<StoreData>,
+    async_support: bool,
+) -> Result<(), Error> {
+    if async_support {
+        return err!("WASI preview 2 is not supported in engines with async_support: true");
+    }
+
+    wasmtime_wasi::preview2::command::sync::add_to_linker(linker).map_err(|e| error!("{}", e))
+}
+
+pub fn init() -> Result<(), Error> {
+    let class = root().define_class("WasiP2CtxBuilder", class::object())?;
+    class.define_singleton_method("new", function!(WasiP2CtxBuilder::new, 0))?;
+    class.define_method("dup", method!(WasiP2CtxBuilder::dup, 0))?;
+
+    class.define_method("inherit_stdin", method!(WasiP2CtxBuilder::inherit_stdin, 0))?;
+    class.define_method(
+        "set_stdin_string",
+        method!(WasiP2CtxBuilder::set_stdin_string, 1),
+    )?;
+    class.define_method(
+        "inherit_stdout",
+        method!(WasiP2CtxBuilder::inherit_stdout, 0),
+    )?;
+    class.define_method(
+        "set_stdout_buffer",
+        method!(WasiP2CtxBuilder::set_stdout_buffer, 2),
+    )?;
+    class.define_method(
+        "inherit_stderr",
+        method!(WasiP2CtxBuilder::inherit_stderr, 0),
+    )?;
+    class.define_method(
+        "set_stderr_buffer",
+        method!(WasiP2CtxBuilder::set_stderr_buffer, 2),
+    )?;
+
+    class.define_method("inherit_env", method!(WasiP2CtxBuilder::inherit_env, 0))?;
+    class.define_method("set_env", method!(WasiP2CtxBuilder::set_env, 1))?;
+    class.define_method("env", method!(WasiP2CtxBuilder::env, 2))?;
+
+    class.define_method("inherit_argv", method!(WasiP2CtxBuilder::inherit_argv, 0))?;
+    class.define_method("set_argv", method!(WasiP2CtxBuilder::set_argv, 1))?;
+
+    class.define_method("preopen_dir", method!(WasiP2CtxBuilder::preopen_dir, -1))?;
+
+    class.define_method(
+        "set_random_seed",
+        method!(WasiP2CtxBuilder::set_random_seed, 1),
+    )?;
+    class.define_method(
+        "set_wall_clock",
+        method!(WasiP2CtxBuilder::set_wall_clock, 1),
+    )?;
+    class.define_method(
+        "set_monotonic_clock",
+        method!(WasiP2CtxBuilder::set_monotonic_clock, 1),
+    )?;
+    class.define_method(
+        "inherit_network",
+        method!(WasiP2CtxBuilder::inherit_network, 0),
+    )?;
+    class.define_method(
+        "allow_socket_addr",
+        method!(WasiP2CtxBuilder::allow_socket_addr, 1),
+    )?;
+    class.define_method("deny_network", method!(WasiP2CtxBuilder::deny_network, 0))?;
+    class.define_method(
+        "allow_ip_name_lookup",
+        method!(WasiP2CtxBuilder::allow_ip_name_lookup, 1),
+    )?;
+
+    Ok(())
+}
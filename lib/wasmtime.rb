@@ -9,3 +9,7 @@ begin
 rescue LoadError
   require "wasmtime/wasmtime_rb"
 end
+
+require_relative "wasmtime/abi"
+require_relative "wasmtime/wasi/command"
+require_relative "wasmtime/fork"
@@ -0,0 +1,104 @@
+# frozen_string_literal: true
+
+module Wasmtime
+  # Helpers for the calling conventions of Wasm modules.
+  module ABI
+    # Passes strings to and from Wasm functions taking a +(ptr, len)+ pair of
+    # +i32+s, as compiled by most languages' toolchains.
+    #
+    # Strings are copied to memory allocated with the module's exported
+    # +cabi_realloc+ or +malloc+. Strings returned are read from either a
+    # +(ptr, len)+ pair of results, or a single result pointing to the pair in
+    # memory (as for the canonical ABI). Returned strings are then freed with
+    # the module's +cabi_post_<name>+ export, if any.
+    #
+    # @example
+    #   Wasmtime::ABI::CanonicalStrings.call(instance, "greet", "Ruby") # => "Hello, Ruby!"
+    module CanonicalStrings
+      class << self
+        # Calls +name+ with +string+ copied into +instance+'s memory.
+        #
+        # @param instance [Instance]
+        # @param name [String] The function, taking a +(ptr, len)+ pair.
+        # @param string [String]
+        # @param memory [String] The name of the memory export.
+        # @return [String, nil] The string returned by the function, +nil+
+        #   when it has no results.
+        def call(instance, name, string, memory: "memory")
+          func = instance.export(name)&.to_func || raise(Wasmtime::Error, "function \"#{name}\" not found")
+          results = func.call(*write(instance, string, memory: memory))
+
+          case func.results.size
+          when 0 then nil
+          when 1 then read_indirect(instance, name, results, memory: memory)
+          when 2 then read(instance, *results, memory: memory)
+          else raise Wasmtime::Error, "expected #{name} to return a string, got #{func.results.size} results"
+          end
+        end
+
+        # Copies +string+ into memory allocated in +instance+.
+        #
+        # @param instance [Instance]
+        # @param string [String]
+        # @param memory [String] The name of the memory export.
+        # @return [Array(Integer, Integer)] The pointer and length of the
+        #   string in memory.
+        def write(instance, string, memory: "memory")
+          bytes = string.b
+          ptr = allocate(instance, bytes.bytesize)
+          find_memory(instance, memory).write(ptr, bytes)
+          [ptr, bytes.bytesize]
+        end
+
+        # Reads a UTF-8 string from +instance+'s memory.
+        #
+        # @param instance [Instance]
+        # @param ptr [Integer]
+        # @param len [Integer]
+        # @param memory [String] The name of the memory export.
+        # @return [String]
+        def read(instance, ptr, len, memory: "memory")
+          find_memory(instance, memory).read_utf8(ptr, len)
+        end
+
+        private
+
+        def allocate(instance, size)
+          if (realloc = instance.export("cabi_realloc")&.to_func)
+            realloc.call(0, 0, 1, size)
+          elsif (malloc = instance.export("malloc")&.to_func)
+            malloc.call(size)
+          else
+            raise Wasmtime::Error, "expected the instance to export cabi_realloc or malloc"
+          end
+        end
+
+        def read_indirect(instance, name, retptr, memory:)
+          mem = find_memory(instance, memory)
+          string = read(instance, mem.read_u32(retptr), mem.read_u32(retptr + 4), memory: memory)
+          instance.export("cabi_post_#{name}")&.to_func&.call(retptr)
+          string
+        end
+
+        def find_memory(instance, name)
+          instance.export(name)&.to_memory || raise(Wasmtime::Error, "memory \"#{name}\" not found")
+        end
+      end
+    end
+  end
+
+  class Instance
+    # Calls +name+ with +string+ passed as a +(ptr, len)+ pair, returning the
+    # string it returns.
+    #
+    # @param name [String] The function, taking a +(ptr, len)+ pair.
+    # @param string [String]
+    # @param memory [String] The name of the memory export.
+    # @return [String, nil] The string returned by the function, +nil+ when
+    #   it has no results.
+    # @see ABI::CanonicalStrings
+    def call_with_string(name, string, memory: "memory")
+      ABI::CanonicalStrings.call(self, name, string, memory: memory)
+    end
+  end
+end
@@ -0,0 +1,105 @@
+# frozen_string_literal: true
+
+# To prevent double loading of this file when `ruby-api` is enabled
+return if defined?(Wasmtime::Component::Result)
+
+module Wasmtime
+  module Component
+    # Represents a component variant value: the name of the case and its
+    # payload, +nil+ when the case has none.
+    #
+    # @!attribute [r] name
+    #   @return [String, Symbol] The name of the case: a +String+ when
+    #     converted from Wasm, either when converted to Wasm.
+    # @!attribute [r] value
+    #   @return [Object, nil] The payload of the case.
+    Variant = Struct.new(:name, :value)
+
+    # Represents a component +result<O, E>+ value.
+    class Result
+      class << self
+        # @param ok [Object] The payload of the successful result.
+        # @return [Result]
+        def ok(ok = nil)
+          new(true, ok)
+        end
+
+        # @param error [Object] The payload of the failed result.
+        # @return [Result]
+        def error(error = nil)
+          new(false, error)
+        end
+
+        private :new
+      end
+
+      # Raised when accessing the payload of the wrong case, e.g. calling
+      # {#ok} on an error result.
+      class UncheckedResult < Wasmtime::Error; end
+
+      # Raised by {#unwrap} on error results, which {Func#call} does for the
+      # +result+ returned by functions.
+      class ErrorResult < Wasmtime::Error
+        # @return [Object] The payload of the error result.
+        attr_reader :error
+
+        def initialize(error)
+          @error = error
+          super("component returned an error result: #{error.inspect}")
+        end
+      end
+
+      def initialize(ok, value)
+        @ok = ok
+        @value = value
+      end
+
+      # @return [Boolean] Whether the result is successful.
+      def ok?
+        @ok
+      end
+
+      # @return [Boolean] Whether the result is an error.
+      def error?
+        !@ok
+      end
+
+      # @return [Object] The payload of a successful result.
+      # @raise [UncheckedResult] When the result is an error.
+      def ok
+        raise UncheckedResult, "expected ok, was error" unless ok?
+
+        @value
+      end
+
+      # @return [Object] The payload of an error result.
+      # @raise [UncheckedResult] When the result is successful.
+      def error
+        raise UncheckedResult, "expected error, was ok" unless error?
+
+        @value
+      end
+
+      # @return [Object] The payload of a successful result.
+      # @raise [ErrorResult] When the result is an error.
+      def unwrap
+        raise ErrorResult.new(@value) if error?
+
+        @value
+      end
+
+      def ==(other)
+        other.is_a?(Result) && other.ok? == ok? && other.value == value
+      end
+      alias_method :eql?, :==
+
+      def hash
+        [self.class, @ok, @value].hash
+      end
+
+      protected
+
+      attr_reader :value
+    end
+  end
+end
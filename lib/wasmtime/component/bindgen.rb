@@ -0,0 +1,108 @@
+# frozen_string_literal: true
+
+require "wasmtime"
+
+module Wasmtime
+  module Component
+    class << self
+      # Generates Ruby bindings for a WIT world: a module named after the
+      # world, with an +Exports+ class calling the world's exported functions
+      # on an {Instance}, and an +Imports+ mixin listing the functions the host
+      # must implement.
+      #
+      # Methods are named after the functions, with dashes replaced by
+      # underscores, and take their parameters as keyword arguments. Only the
+      # functions the world exports directly get +Exports+ methods.
+      #
+      # @example Generating bindings in a Rake task
+      #   require "wasmtime/component/bindgen"
+      #
+      #   file "lib/greeter.rb" => "wit/greeter.wit" do |t|
+      #     File.write(t.name, Wasmtime::Component.bindgen("wit", "greeter"))
+      #   end
+      #
+      # @example Using the generated bindings
+      #   require_relative "greeter"
+      #
+      #   greeter = Greeter::Exports.new(linker.instantiate(store, component))
+      #   greeter.greet(name: "Ruby")
+      #
+      # @param wit_path [String] A WIT file or a directory of WIT files.
+      # @param world [String, nil] The name of the world, optional when the
+      #   package has a single world.
+      # @param module_name [String] The name of the generated module.
+      # @return [String] The Ruby source of the bindings.
+      def bindgen(wit_path, world = nil, module_name: nil)
+        Bindgen.new(wit_world(wit_path, world), module_name).generate
+      end
+    end
+
+    # Generates the source of {Component.bindgen}.
+    # @api private
+    class Bindgen
+      def initialize(world, module_name)
+        @world = world
+        @module_name = module_name || camelize(world["name"])
+      end
+
+      def generate
+        exports = @world["exports"].select { |function| function["interface"].nil? }
+
+        <<~RUBY
+          # frozen_string_literal: true
+
+          # Generated by Wasmtime::Component.bindgen from the #{@world["name"]} world.
+          module #{@module_name}
+            # Calls the functions exported by the world.
+            class Exports
+              # @param instance [Wasmtime::Component::Instance]
+              def initialize(instance)
+                @instance = instance
+              end
+          #{exports.map { |function| export_method(function) }.join}  end
+
+            # The functions imported by the world, to implement in the including
+            # class.
+            module Imports
+          #{@world["imports"].map { |function| import_method(function) }.join("\n")}  end
+          end
+        RUBY
+      end
+
+      private
+
+      def export_method(function)
+        args = function["params"].map { |param| ruby_name(param) }
+        <<-RUBY
+
+    # Calls +#{function["name"]}+.
+    def #{ruby_name(function["name"])}(#{args.map { |arg| "#{arg}:" }.join(", ")})
+      @instance.invoke(#{function["name"].inspect}#{args.map { |arg| ", #{arg}" }.join})
+    end
+        RUBY
+      end
+
+      def import_method(function)
+        name = ruby_name(function["name"])
+        source = [function["interface"], function["name"]].compact.join("#")
+        args = function["params"].map { |param| "#{ruby_name(param)}:" }
+        <<-RUBY
+    # Implements +#{source}+.
+    def #{name}(#{args.join(", ")})
+      raise NotImplementedError, "\#{self.class} must implement ##{name}"
+    end
+        RUBY
+      end
+
+      # Turns WIT names into Ruby method names, e.g. +[method]counter.get-value+
+      # into +counter_get_value+.
+      def ruby_name(name)
+        name.sub(/\A\[\w+\]/, "").tr("-.", "__")
+      end
+
+      def camelize(name)
+        name.split(/[-_]/).map(&:capitalize).join
+      end
+    end
+  end
+end
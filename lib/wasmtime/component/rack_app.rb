@@ -0,0 +1,89 @@
+# frozen_string_literal: true
+
+require "wasmtime"
+
+module Wasmtime
+  module Component
+    # A Rack application serving requests with a {Component} exporting the
+    # +wasi:http/incoming-handler+ interface, as built for +wasmtime serve+.
+    #
+    # Each request is handled by a new instance of the component, in a new
+    # {Store}.
+    #
+    # @example config.ru
+    #   require "wasmtime/component/rack_app"
+    #
+    #   engine = Wasmtime::Engine.new
+    #   component = Wasmtime::Component::Component.from_file(engine, "proxy.wasm")
+    #   run Wasmtime::Component::RackApp.new(engine, component)
+    class RackApp
+      # Rack env keys of the request headers not prefixed with +HTTP_+.
+      UNPREFIXED_HEADERS = %w[CONTENT_TYPE CONTENT_LENGTH].freeze
+
+      # @param engine [Engine]
+      # @param component [Component]
+      # @yieldreturn [Store] The store to handle a request in, which must have
+      #   a +wasi_http_ctx+. Defaults to a store disallowing outbound requests.
+      def initialize(engine, component, &new_store)
+        @component = component
+        @linker = Linker.new(engine, wasi_http: true)
+        @new_store = new_store || -> { Store.new(engine, wasi_http_ctx: WasiHttpCtxBuilder.new) }
+      end
+
+      # @param env [Hash] The Rack env of the request.
+      # @return [Array(Integer, Hash, Array<String>)] The Rack response.
+      def call(env)
+        status, headers, body = @linker.handle_http_request(
+          @new_store.call,
+          @component,
+          env["REQUEST_METHOD"],
+          request_url(env),
+          request_headers(env),
+          request_body(env)
+        )
+
+        [status, response_headers(headers), [body]]
+      end
+
+      private
+
+      def request_url(env)
+        scheme = env["rack.url_scheme"] || "http"
+        host = env["HTTP_HOST"] || "#{env["SERVER_NAME"]}:#{env["SERVER_PORT"]}"
+        path = "#{env["SCRIPT_NAME"]}#{env["PATH_INFO"]}"
+        path = "/" if path.empty?
+        query = env["QUERY_STRING"]
+
+        url = "#{scheme}://#{host}#{path}"
+        (query.nil? || query.empty?) ? url : "#{url}?#{query}"
+      end
+
+      def request_headers(env)
+        env.filter_map do |key, value|
+          name = if UNPREFIXED_HEADERS.include?(key)
+            key
+          elsif key.start_with?("HTTP_")
+            key.delete_prefix("HTTP_")
+          end
+          [name.tr("_", "-").downcase, value.to_s] if name
+        end
+      end
+
+      def request_body(env)
+        input = env["rack.input"]
+        return +"" if input.nil?
+
+        body = input.read.to_s.b
+        input.rewind if input.respond_to?(:rewind)
+        body
+      end
+
+      # Rack 3 headers: repeated headers have an Array of values.
+      def response_headers(headers)
+        headers.each_with_object({}) do |(name, value), hash|
+          hash[name] = hash.key?(name) ? [*hash[name], value] : value
+        end
+      end
+    end
+  end
+end
@@ -29,8 +29,104 @@ module Wasmtime
     ALWAYS_TRAP_ADAPTER = :always_trap_adapter
     OUT_OF_FUEL = :out_of_fuel
     UNKNOWN = :unknown
+
+    # A frame of a trap's Wasm backtrace, see {Trap#wasm_backtrace}.
+    #
+    # @!attribute [r] module_name
+    #   @return [String, nil] The name of the module, if known.
+    # @!attribute [r] func_index
+    #   @return [Integer] The index of the function in its module.
+    # @!attribute [r] func_name
+    #   @return [String, nil] The name of the function, if known.
+    # @!attribute [r] func_offset
+    #   @return [Integer, nil] The offset of the instruction within its function.
+    # @!attribute [r] module_offset
+    #   @return [Integer, nil] The offset of the instruction within its module.
+    Frame = Struct.new(:module_name, :func_index, :func_name, :func_offset, :module_offset)
+  end
+
+  # Raised when a module or a component fails to compile, e.g. because it is
+  # invalid or uses a feature the {Engine} doesn't enable.
+  class CompileError < Error; end
+
+  # Raised by {Module.validate} when a module is invalid.
+  class ValidationError < CompileError
+    # @return [Integer, nil] The offset of the invalid item in the Wasm binary, if known.
+    attr_reader(:offset)
+
+    def initialize(message, offset = nil)
+      super(message)
+      @offset = offset
+    end
+  end
+
+  # Raised when instantiating a module whose imports are missing or don't
+  # have the expected types.
+  class LinkError < Error
+    # @return [String, nil] The module name of the offending import, if known.
+    attr_reader(:import_module)
+
+    # @return [String, nil] The name of the offending import, if known.
+    attr_reader(:import_name)
+
+    def initialize(message, import_module = nil, import_name = nil)
+      super(message)
+      @import_module = import_module
+      @import_name = import_name
+    end
   end
 
+  # Raised by {Instance#invoke} when the instance doesn't export a function
+  # with the given name.
+  class ExportNotFound < Error
+    # @return [String] The name that was looked up.
+    attr_reader(:name)
+
+    # @return [Array<String>] The names of the functions the instance exports.
+    attr_reader(:available)
+
+    def initialize(message, name, available)
+      super(message)
+      @name = name
+      @available = available
+    end
+  end
+
+  # Raised by {Func#typed} when the function's type doesn't match the given
+  # params and results.
+  class SignatureMismatch < Error
+    # @return [Hash{Symbol => Array<Symbol>}] The given +:params+ and +:results+.
+    attr_reader(:expected)
+
+    # @return [Hash{Symbol => Array<Symbol>}] The function's +:params+ and +:results+.
+    attr_reader(:actual)
+
+    def initialize(message, expected, actual)
+      super(message)
+      @expected = expected
+      @actual = actual
+    end
+  end
+
+  # Raised when a {Store} exceeds the number of instances, tables or memories
+  # it may create, see {Store#set_limits}.
+  class ResourceLimitExceeded < Error
+    # @return [Symbol] One of +:instances+, +:tables+, +:memories+.
+    attr_reader(:resource)
+
+    def initialize(message, resource)
+      super(message)
+      @resource = resource
+    end
+  end
+
+  # Raised when a call to a Wasm function exceeds its +timeout+, see {Func#call}.
+  class Timeout < Error; end
+
+  # Raised when the Wasm calls of a {Store} exceed its time limit, see
+  # {Store#set_time_limit}.
+  class DeadlineExceeded < Timeout; end
+
   # Raised when a WASI program terminates early by calling +exit+.
   class WasiExit < Error
     # @return [Integer] The system exit code.
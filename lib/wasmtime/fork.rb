@@ -0,0 +1,17 @@
+# frozen_string_literal: true
+
+module Wasmtime
+  # Calls {Wasmtime.before_fork} and {Wasmtime.after_fork} around forks, on
+  # Rubies with +Process._fork+ (3.1 and later).
+  # @api private
+  module ForkHooks
+    def _fork
+      Wasmtime.before_fork
+      super
+    ensure
+      Wasmtime.after_fork
+    end
+  end
+
+  Process.singleton_class.prepend(ForkHooks) if Process.respond_to?(:_fork)
+end
@@ -0,0 +1,115 @@
+# frozen_string_literal: true
+
+module Wasmtime
+  # Helpers to run WASI programs.
+  module Wasi
+    # Runs CLI-style WASI programs in one call, like +wasmtime run+: either a
+    # {Module} exporting +_start+ (WASI preview 1), or a {Component::Component}
+    # targeting the +wasi:cli/command+ world (WASI preview 2).
+    #
+    # The program is linked once, when the Command is created; each {#run}
+    # gets a new {Store} with its own WASI context.
+    #
+    # @example
+    #   result = Wasmtime::Wasi::Command.run(engine, mod, argv: ["cli", "--help"])
+    #   result.status # => 0
+    #   result.stdout # => "Usage: cli [OPTIONS]\n..."
+    class Command
+      # The default maximum number of bytes captured from stdout and stderr.
+      DEFAULT_OUTPUT_CAPACITY = 10 * 1024 * 1024
+
+      # The outcome of {Command#run}.
+      #
+      # @!attribute [r] status
+      #   @return [Integer] The exit code.
+      # @!attribute [r] stdout
+      #   @return [String] The captured stdout, as a binary +String+.
+      # @!attribute [r] stderr
+      #   @return [String] The captured stderr, as a binary +String+.
+      Result = Struct.new(:status, :stdout, :stderr) do
+        # @return [Boolean] Whether the command exited with code 0.
+        def success?
+          status.zero?
+        end
+      end
+
+      # Creates a Command and runs it once.
+      #
+      # @param engine [Engine]
+      # @param program [Module, Component::Component]
+      # @param options (see #run)
+      # @return [Result]
+      def self.run(engine, program, **options)
+        new(engine, program).run(**options)
+      end
+
+      # @param engine [Engine]
+      # @param program [Module, Component::Component]
+      def initialize(engine, program)
+        @engine = engine
+
+        case program
+        when Wasmtime::Module
+          @instance_pre = Linker.new(engine, wasi: true).instantiate_pre(program)
+        when Component::Component
+          @linker = Component::Linker.new(engine, wasi: true)
+          @component = program
+        else
+          raise ArgumentError, "expected a Wasmtime::Module or a Wasmtime::Component::Component, got #{program.inspect}"
+        end
+      end
+
+      # Runs the program in a new {Store}.
+      #
+      # @param argv [Array<String>] The arguments, including the program name
+      #   as the first one.
+      # @param env [Hash<String, String>] The environment variables.
+      # @param stdin [String, IO] The input, read whole before running.
+      # @param stdout [IO, nil] An IO to also copy the output to, once the
+      #   program exits.
+      # @param stderr [IO, nil] An IO to also copy the errors to, once the
+      #   program exits.
+      # @param dir_mappings [Hash<String, String>] The host directories the
+      #   program may access, by the path it sees them as, like +wasmtime run
+      #   --dir HOST::GUEST+ (e.g. +{"/data" => "./tmp/data"}+).
+      # @param output_capacity [Integer] The maximum number of bytes captured
+      #   from each of stdout and stderr, further output being dropped.
+      # @return [Result]
+      def run(argv: [], env: {}, stdin: "", stdout: nil, stderr: nil, dir_mappings: {}, output_capacity: DEFAULT_OUTPUT_CAPACITY)
+        captured_stdout = +""
+        captured_stderr = +""
+        builder = @component ? WasiP2CtxBuilder.new : WasiCtxBuilder.new
+        builder
+          .set_argv(argv)
+          .set_env(env)
+          .set_stdin_string(stdin.is_a?(String) ? stdin : stdin.read.to_s)
+          .set_stdout_buffer(captured_stdout, output_capacity)
+          .set_stderr_buffer(captured_stderr, output_capacity)
+        dir_mappings.each { |guest_path, host_path| builder.preopen_dir(host_path, guest_path) }
+
+        status = begin
+          @component ? run_component(builder) : run_module(builder)
+          0
+        rescue WasiExit => e
+          e.code
+        end
+
+        stdout&.write(captured_stdout)
+        stderr&.write(captured_stderr)
+        Result.new(status, captured_stdout, captured_stderr)
+      end
+
+      private
+
+      def run_module(builder)
+        store = Store.new(@engine, wasi_ctx: builder)
+        @instance_pre.instantiate(store).invoke("_start")
+      end
+
+      def run_component(builder)
+        store = Store.new(@engine, wasi_p2_ctx: builder)
+        @linker.run_command(store, @component)
+      end
+    end
+  end
+end
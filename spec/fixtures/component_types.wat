@@ -0,0 +1,100 @@
+(component
+  (core module $m
+    (memory (export "memory") 1)
+    (global $bump (mut i32) (i32.const 1024))
+
+    ;; Bump allocator, never frees.
+    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+      (local $ret i32)
+      global.get $bump
+      local.get 2
+      i32.const 1
+      i32.sub
+      i32.add
+      i32.const 0
+      local.get 2
+      i32.sub
+      i32.and
+      local.tee $ret
+      local.get 3
+      i32.add
+      global.set $bump
+      local.get $ret)
+
+    (func (export "noop"))
+    (func (export "trap") unreachable)
+    (func (export "id-i32") (param i32) (result i32) local.get 0)
+    (func (export "id-i64") (param i64) (result i64) local.get 0)
+    (func (export "id-f64") (param f64) (result f64) local.get 0)
+
+    ;; Identity for types flattened to 2 i32s and stored as 2 i32s in memory.
+    (func (export "id-pair") (param i32 i32) (result i32)
+      i32.const 0
+      local.get 0
+      i32.store
+      i32.const 4
+      local.get 1
+      i32.store
+      i32.const 0)
+
+    ;; Identity for types flattened to 3 i32s and stored as 3 i32s in memory.
+    (func (export "id-triple") (param i32 i32 i32) (result i32)
+      i32.const 0
+      local.get 0
+      i32.store
+      i32.const 4
+      local.get 1
+      i32.store
+      i32.const 8
+      local.get 2
+      i32.store
+      i32.const 0))
+  (core instance $i (instantiate $m))
+
+  (type $point' (record (field "x" s32) (field "y" s32)))
+  (export $point "point" (type $point'))
+  (type $person' (record (field "name" string) (field "age" u32)))
+  (export $person "person" (type $person'))
+  (type $filter' (variant (case "all") (case "limit" u32)))
+  (export $filter "filter" (type $filter'))
+  (type $color' (enum "red" "green" "blue"))
+  (export $color "color" (type $color'))
+  (type $perms' (flags "read" "write" "exec"))
+  (export $perms "perms" (type $perms'))
+
+  (func (export "noop") (canon lift (core func $i "noop")))
+  (func (export "trap") (canon lift (core func $i "trap")))
+  (func (export "id-bool") (param "v" bool) (result bool)
+    (canon lift (core func $i "id-i32")))
+  (func (export "id-s8") (param "v" s8) (result s8)
+    (canon lift (core func $i "id-i32")))
+  (func (export "id-u32") (param "v" u32) (result u32)
+    (canon lift (core func $i "id-i32")))
+  (func (export "id-s64") (param "v" s64) (result s64)
+    (canon lift (core func $i "id-i64")))
+  (func (export "id-float64") (param "v" float64) (result float64)
+    (canon lift (core func $i "id-f64")))
+  (func (export "id-char") (param "v" char) (result char)
+    (canon lift (core func $i "id-i32")))
+  (func (export "id-string") (param "v" string) (result string)
+    (canon lift (core func $i "id-pair") (memory $i "memory") (realloc (func $i "realloc"))))
+  (func (export "id-list") (param "v" (list u32)) (result (list u32))
+    (canon lift (core func $i "id-pair") (memory $i "memory") (realloc (func $i "realloc"))))
+  (func (export "id-list-string") (param "v" (list string)) (result (list string))
+    (canon lift (core func $i "id-pair") (memory $i "memory") (realloc (func $i "realloc"))))
+  (func (export "id-tuple") (param "v" (tuple s32 s32)) (result (tuple s32 s32))
+    (canon lift (core func $i "id-pair") (memory $i "memory") (realloc (func $i "realloc"))))
+  (func (export "id-point") (param "v" $point) (result $point)
+    (canon lift (core func $i "id-pair") (memory $i "memory") (realloc (func $i "realloc"))))
+  (func (export "id-person") (param "v" $person) (result $person)
+    (canon lift (core func $i "id-triple") (memory $i "memory") (realloc (func $i "realloc"))))
+  (func (export "id-filter") (param "v" $filter) (result $filter)
+    (canon lift (core func $i "id-pair") (memory $i "memory") (realloc (func $i "realloc"))))
+  (func (export "id-color") (param "v" $color) (result $color)
+    (canon lift (core func $i "id-i32")))
+  (func (export "id-perms") (param "v" $perms) (result $perms)
+    (canon lift (core func $i "id-i32")))
+  (func (export "id-option") (param "v" (option string)) (result (option string))
+    (canon lift (core func $i "id-triple") (memory $i "memory") (realloc (func $i "realloc"))))
+  (func (export "id-result") (param "v" (result u32 (error string))) (result (result u32 (error string)))
+    (canon lift (core func $i "id-triple") (memory $i "memory") (realloc (func $i "realloc")))))
@@ -0,0 +1,100 @@
+require "json"
+require "stringio"
+
+module Wasmtime
+  RSpec.describe "Async host functions" do
+    let(:engine) { Engine.new(async_support: true) }
+    let(:store) { Store.new(engine) }
+    let(:linker) { Linker.new(engine) }
+    let(:mod) do
+      Module.new(engine, <<~WAT)
+        (module
+          (import "host" "double" (func $double (param i32) (result i32)))
+          (func (export "run") (param i32) (result i32)
+            local.get 0
+            call $double
+            i32.const 1
+            i32.add))
+      WAT
+    end
+
+    it "calls the host function" do
+      linker.func_new_async("host", "double", [:i32], [:i32]) { |_caller, x| x * 2 }
+      instance = linker.instantiate(store, mod)
+
+      expect(instance.invoke("run", 20)).to eq(41)
+    end
+
+    it "passes the caller" do
+      store = Store.new(engine, :data)
+      linker.func_new_async("host", "double", [:i32], [:i32]) { |caller, _| caller.store_data == :data ? 1 : 0 }
+      instance = linker.instantiate(store, mod)
+
+      expect(instance.invoke("run", 0)).to eq(2)
+    end
+
+    it "lets the host function switch Fibers while Wasm is suspended" do
+      linker.func_new_async("host", "double", [:i32], [:i32]) { |_caller, x| Fiber.yield(x) }
+      instance = linker.instantiate(store, mod)
+
+      fiber = Fiber.new { instance.invoke("run", 20) }
+      expect(fiber.resume).to eq(20)
+
+      # Wasm can run in another store while the first call is suspended.
+      other_linker = Linker.new(engine)
+      other_linker.func_new_async("host", "double", [:i32], [:i32]) { |_caller, x| x * 2 }
+      expect(other_linker.instantiate(Store.new(engine), mod).invoke("run", 1)).to eq(3)
+
+      expect(fiber.resume(40)).to eq(41)
+      expect(fiber).not_to be_alive
+    end
+
+    it "propagates exceptions from the host function" do
+      linker.func_new_async("host", "double", [:i32], [:i32]) { raise "boom" }
+      instance = linker.instantiate(store, mod)
+
+      expect { instance.invoke("run", 1) }.to raise_error(RuntimeError, "boom")
+    end
+
+    it "rejects synchronous host functions" do
+      linker.func_new("host", "double", [:i32], [:i32]) { |_caller, x| x * 2 }
+      instance = linker.instantiate(store, mod)
+
+      expect { instance.invoke("run", 1) }
+        .to raise_error(Wasmtime::Error, /must be defined with Linker#func_new_async/)
+    end
+
+    it "supports Instance.new" do
+      linker.func_new_async("host", "double", [:i32], [:i32]) { |_caller, x| x * 2 }
+      double = linker.get(store, "host", "double")
+      instance = Instance.new(store, mod, [double])
+
+      expect(instance.invoke("run", 1)).to eq(3)
+    end
+
+    it "requires an async engine" do
+      expect { Linker.new(Engine.new).func_new_async("host", "double", [], []) {} }
+        .to raise_error(Wasmtime::Error, /async_support: true/)
+    end
+
+    describe "WASI" do
+      let(:wasi_mod) { Module.from_file(engine, "spec/fixtures/wasi-debug.wasm") }
+
+      it "runs WASI commands" do
+        stdout = +""
+        wasi_ctx = WasiCtxBuilder.new.set_stdin_string("stdin content").set_stdout_buffer(stdout, 40_000).build
+        instance = Linker.new(engine, wasi: true).instantiate(Store.new(engine, wasi_ctx: wasi_ctx), wasi_mod)
+        instance.invoke("_start")
+
+        expect(JSON.parse(stdout).dig("wasi", "stdin")).to eq("stdin content")
+      end
+
+      it "rejects Ruby IO streams" do
+        wasi_ctx = WasiCtxBuilder.new.set_stdout_io(StringIO.new).build
+        instance = Linker.new(engine, wasi: true).instantiate(Store.new(engine, wasi_ctx: wasi_ctx), wasi_mod)
+
+        expect { instance.invoke("_start") }.to raise_error(Wasmtime::Error)
+      end
+    end
+  end
+end
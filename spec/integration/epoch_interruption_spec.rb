@@ -49,6 +49,26 @@ module Wasmtime
       expect { instance.invoke("42") }.to raise_error(Trap)
     end
 
+    it "allows extending the deadline from a host call" do
+      mod = Module.new(engine, <<~WAT)
+        (module
+          (func $host_call (import "" ""))
+          (func $noop)
+          (func (export "f")
+            call $host_call
+            call $noop ;; new func call forces epoch check
+          )
+        )
+      WAT
+      f = Func.new(store_deadline_1, [], []) do |caller|
+        engine.increment_epoch
+        caller.set_epoch_deadline(1)
+      end
+
+      instance = Instance.new(store_deadline_1, mod, [f])
+      expect { instance.invoke("f") }.not_to raise_error
+    end
+
     describe "Engine timer" do
       it "prevents infinite loop from running forever" do
         instance = Instance.new(store_deadline_1, mod)
@@ -101,6 +121,52 @@ module Wasmtime
       end
     end
 
+    describe "Store#set_time_limit" do
+      let(:store) { Store.new(engine) }
+      let(:instance) { Instance.new(store, mod) }
+
+      it "raises DeadlineExceeded once the calls exceed the limit" do
+        store.set_time_limit(0.05)
+
+        expect(instance.invoke("42")).to eq(42)
+        expect { instance.invoke("loop_forever") }.to raise_error(DeadlineExceeded, /exceeded its time limit/)
+        expect { instance.invoke("42") }.to raise_error(DeadlineExceeded)
+      end
+
+      it "is a Timeout" do
+        expect(DeadlineExceeded.ancestors).to include(Timeout)
+      end
+
+      it "ignores the epoch increments of other stores" do
+        store.set_time_limit(5)
+        engine.increment_epoch
+
+        expect(instance.invoke("42")).to eq(42)
+      end
+
+      it "still honors call timeouts" do
+        store.set_time_limit(5)
+
+        expect { instance.export("loop_forever").to_func.call(timeout: 0.05) }
+          .to raise_error(Timeout, /exceeded its timeout/)
+        expect(instance.invoke("42")).to eq(42)
+      end
+
+      it "can be removed" do
+        store.set_time_limit(0.01)
+        sleep_ms(20)
+        store.set_time_limit(nil)
+        store.set_epoch_deadline(1)
+
+        expect(instance.invoke("42")).to eq(42)
+      end
+
+      it "requires epoch interruption" do
+        expect { Store.new(Engine.new).set_time_limit(1) }
+          .to raise_error(Wasmtime::Error, /epoch_interruption: true/)
+      end
+    end
+
     def sleep_ms(ms)
       sleep ms.to_f / 1000
     end
@@ -0,0 +1,83 @@
+require "spec_helper"
+require "timeout"
+
+module Wasmtime
+  RSpec.describe "Forking" do
+    let(:engine) { Engine.new(epoch_interruption: true) }
+
+    let(:mod) do
+      Module.new(engine, <<~WAT)
+        (module
+          (func (export "loop_forever")
+            (loop br 0)))
+      WAT
+    end
+
+    let(:autostart_mod) do
+      Module.new(engine, <<~WAT)
+        (module
+          (func nop)
+          (start 0))
+      WAT
+    end
+
+    after { engine.stop_epoch_interval }
+
+    it "pauses epoch timers until after_fork" do
+      store = Store.new(engine)
+      engine.start_epoch_interval(1)
+
+      Wasmtime.before_fork
+      sleep 0.005
+      store.set_epoch_deadline(1)
+      sleep 0.005
+      expect { Instance.new(store, autostart_mod) }.not_to raise_error
+
+      Wasmtime.after_fork
+      expect { Instance.new(store, mod).invoke("loop_forever") }.to raise_error(Trap)
+    end
+
+    it "restarts epoch timers in forked processes" do
+      skip "requires Process._fork" unless Process.respond_to?(:_fork)
+
+      store = Store.new(engine)
+      store.set_epoch_deadline(1)
+      instance = Instance.new(store, mod)
+      engine.start_epoch_interval(1)
+
+      pid = fork do
+        instance.invoke("loop_forever")
+        exit!(false)
+      rescue Trap
+        exit!(true)
+      end
+
+      expect(wait_for(pid)).to be_success
+    end
+
+    it "enforces time limits set before forking" do
+      skip "requires Process._fork" unless Process.respond_to?(:_fork)
+
+      store = Store.new(engine)
+      instance = Instance.new(store, mod)
+      store.set_time_limit(0.05)
+
+      pid = fork do
+        instance.invoke("loop_forever")
+        exit!(false)
+      rescue DeadlineExceeded
+        exit!(true)
+      end
+
+      expect(wait_for(pid)).to be_success
+    end
+
+    def wait_for(pid)
+      ::Timeout.timeout(5) { Process.wait(pid) }
+      $?
+    rescue ::Timeout::Error
+      Process.kill(:KILL, pid)
+      raise
+    end
+  end
+end
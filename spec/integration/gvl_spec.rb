@@ -0,0 +1,119 @@
+module Wasmtime
+  RSpec.describe "GVL" do
+    let(:engine) { Engine.new(epoch_interruption: true) }
+
+    let(:mod) do
+      Module.new(engine, <<~WAT)
+        (module
+          (func $host_call (import "" ""))
+          (func (export "call_host")
+            call $host_call)
+          (func (export "loop_forever")
+            (loop br 0)))
+      WAT
+    end
+
+    after { engine.stop_epoch_interval }
+
+    it "is released while Wasm runs" do
+      store = Store.new(engine)
+      store.set_epoch_deadline(100)
+      instance = Instance.new(store, mod, [Func.new(store, [], []) {}])
+
+      ticks = 0
+      ticker = Thread.new do
+        loop do
+          ticks += 1
+          sleep 0.001
+        end
+      end
+
+      engine.start_epoch_interval(2)
+      expect { instance.invoke("loop_forever") }.to raise_error(Trap)
+      ticker.kill
+
+      expect(ticks).to be > 10
+    end
+
+    it "is re-acquired for host calls" do
+      calls = Queue.new
+      threads = 4.times.map do |i|
+        Thread.new do
+          store = Store.new(engine)
+          store.set_epoch_deadline(1)
+          func = Func.new(store, [], []) { calls << i }
+          Instance.new(store, mod, [func]).invoke("call_host")
+        end
+      end
+      threads.each(&:join)
+
+      expect(Array.new(calls.size) { calls.pop }.sort).to eq([0, 1, 2, 3])
+    end
+
+    it "lets Ruby interrupt a running Wasm call" do
+      store = Store.new(engine)
+      store.set_epoch_deadline(1_000_000)
+      instance = Instance.new(store, mod, [Func.new(store, [], []) {}])
+
+      started = Queue.new
+      thread = Thread.new do
+        started << true
+        instance.invoke("loop_forever")
+      end
+      started.pop
+      sleep 0.05
+      thread.raise(Interrupt)
+
+      # Thread#join re-raises the exception the thread died with.
+      expect { thread.join(5) }.to raise_error(a_kind_of(Interrupt).or(a_kind_of(Trap)))
+    end
+
+    it "counts an interrupt as a single tick in the engine's other stores" do
+      store = Store.new(engine)
+      store.set_epoch_deadline(1_000_000)
+      instance = Instance.new(store, mod, [Func.new(store, [], []) {}])
+      other_store = Store.new(engine)
+      other_store.set_epoch_deadline(2)
+      other = Instance.new(other_store, mod, [Func.new(other_store, [], []) {}])
+
+      thread = Thread.new { instance.invoke("loop_forever") }
+      sleep 0.05
+      thread.raise(Interrupt)
+      expect { thread.join(5) }.to raise_error(a_kind_of(Interrupt).or(a_kind_of(Trap)))
+
+      expect { other.invoke("call_host") }.not_to raise_error
+    end
+
+    it "still traps at the epoch deadline" do
+      store = Store.new(engine)
+      store.set_epoch_deadline(3)
+      instance = Instance.new(store, mod, [Func.new(store, [], []) {}])
+
+      2.times { engine.increment_epoch }
+      expect { instance.invoke("call_host") }.not_to raise_error
+      engine.increment_epoch
+      expect { instance.invoke("call_host") }.to raise_error(Trap) do |trap|
+        expect(trap.code).to eq(:interrupt)
+      end
+    end
+
+    it "lets other threads define items in a linker while it instantiates" do
+      engine = Engine.new
+      started = Queue.new
+      release = Queue.new
+      linker = Linker.new(engine)
+      linker.func_new("env", "wait", [], []) do
+        started << true
+        release.pop
+      end
+      start_mod = Module.new(engine, '(module (import "env" "wait" (func $wait)) (start $wait))')
+
+      instantiating = Thread.new { linker.instantiate(Store.new(engine), start_mod) }
+      started.pop
+      linker.func_new("env", "other", [], []) {}
+      release << true
+
+      expect(instantiating.value).to be_a(Instance)
+    end
+  end
+end
@@ -0,0 +1,64 @@
+require "json"
+
+module Wasmtime
+  RSpec.describe "Guest profiling" do
+    include_context(:tmpdir)
+
+    let(:engine) { Engine.new(epoch_interruption: true) }
+    let(:store) { Store.new(engine) }
+
+    let(:mod) do
+      Module.new(engine, <<~WAT)
+        (module $profiled
+          (func $spin (export "spin") (param $n i32)
+            (loop $l
+              (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+              (br_if $l (local.get $n)))))
+      WAT
+    end
+
+    after { engine.stop_epoch_interval }
+
+    it "writes a Firefox Profiler profile" do
+      store.start_profiling(interval: 0.001, modules: [mod])
+      engine.start_epoch_interval(1)
+      Instance.new(store, mod).invoke("spin", 200_000_000)
+      engine.stop_epoch_interval
+
+      path = File.join(tmpdir, "profile.json")
+      expect(store.finish_profiling(path)).to be_nil
+
+      profile = JSON.parse(File.read(path))
+      expect(profile).to include("meta", "threads")
+    end
+
+    it "returns the profile when no path is given" do
+      store.start_profiling
+      Instance.new(store, mod).invoke("spin", 1)
+
+      expect(JSON.parse(store.finish_profiling)).to include("meta")
+    end
+
+    it "restores trapping on epoch deadlines once finished" do
+      store.start_profiling
+      store.finish_profiling
+      store.set_epoch_deadline(0)
+
+      expect { Instance.new(store, mod).invoke("spin", 1) }.to raise_error(Trap)
+    end
+
+    it "raises when profiling was not started" do
+      expect { store.finish_profiling }.to raise_error(Wasmtime::Error, "profiling was not started")
+    end
+
+    it "raises when started twice" do
+      store.start_profiling
+      expect { store.start_profiling }.to raise_error(Wasmtime::Error, "profiling was already started")
+    end
+
+    it "requires epoch interruption" do
+      store = Store.new(Engine.new)
+      expect { store.start_profiling }.to raise_error(Wasmtime::Error, /epoch_interruption/)
+    end
+  end
+end
@@ -45,4 +45,47 @@ RSpec.describe "Ractor", ractor: true do
       expect(ractor.take).to eq([1, 2, 3.0, 4.0])
     end
   end
+
+  it "supports compiling modules with a shared Engine" do
+    engine = Ractor.make_shareable(Wasmtime::Engine.new)
+
+    ractor = Ractor.new(engine, wat) do |engine, wat|
+      mod = Wasmtime::Module.new(engine, wat)
+      Wasmtime::Instance.new(Wasmtime::Store.new(engine), mod).invoke("hello")
+    end
+
+    expect(ractor.take).to eq([1, 2, 3.0, 4.0])
+  end
+
+  it "supports the compatibility key of shared Engines" do
+    engine = Ractor.make_shareable(Wasmtime::Engine.new)
+
+    expect(engine.precompile_compatibility_key).to be_frozen
+    expect(Ractor.new(engine) { |engine| engine.precompile_compatibility_key }.take)
+      .to eq(engine.precompile_compatibility_key)
+  end
+
+  it "supports deserializing modules in Ractors" do
+    engine = Ractor.make_shareable(Wasmtime::Engine.new)
+    serialized = Ractor.make_shareable(Wasmtime::Module.new(engine, wat).serialize)
+
+    ractor = Ractor.new(engine, serialized) do |engine, serialized|
+      mod = Wasmtime::Module.deserialize(engine, serialized)
+      Wasmtime::Instance.new(Wasmtime::Store.new(engine), mod).invoke("hello")
+    end
+
+    expect(ractor.take).to eq([1, 2, 3.0, 4.0])
+  end
+
+  it "supports sharing Components with Ractors" do
+    engine = Ractor.make_shareable(Wasmtime::Engine.new)
+    component = Ractor.make_shareable(Wasmtime::Component::Component.new(engine, "(component)"))
+
+    ractor = Ractor.new(engine, component) do |engine, component|
+      linker = Wasmtime::Component::Linker.new(engine)
+      linker.instantiate(Wasmtime::Store.new(engine), component).class
+    end
+
+    expect(ractor.take).to eq(Wasmtime::Component::Instance)
+  end
 end
@@ -0,0 +1,62 @@
+require "spec_helper"
+
+module Wasmtime
+  RSpec.describe ABI::CanonicalStrings do
+    let(:allocator) { '(func (export "malloc") (param i32) (result i32) (global.get $heap))' }
+    let(:instance) { Instance.new(store, Module.new(engine, <<~WAT)) }
+      (module
+        (memory (export "memory") 1)
+        (global $heap i32 (i32.const 1024))
+        (global $freed (export "freed") (mut i32) (i32.const 0))
+        #{allocator}
+        (func (export "echo") (param i32 i32) (result i32 i32)
+          (local.get 0) (local.get 1))
+        (func (export "echo-indirect") (param i32 i32) (result i32)
+          (i32.store (i32.const 0) (local.get 0))
+          (i32.store (i32.const 4) (local.get 1))
+          (i32.const 0))
+        (func (export "cabi_post_echo-indirect") (param i32)
+          (global.set $freed (i32.const 1)))
+        (func (export "consume") (param i32 i32)))
+    WAT
+
+    it "passes and returns strings as (ptr, len) pairs" do
+      expect(instance.call_with_string("echo", "Hello, 世界")).to eq("Hello, 世界")
+    end
+
+    it "reads strings returned through a pointer, then calls the post-return function" do
+      expect(described_class.call(instance, "echo-indirect", "hi")).to eq("hi")
+      expect(instance.export("freed").to_global.get).to eq(1)
+    end
+
+    it "returns nil for functions without results" do
+      expect(instance.call_with_string("consume", "hi")).to be_nil
+    end
+
+    it "writes strings to memory allocated by the instance" do
+      expect(described_class.write(instance, "abc")).to eq([1024, 3])
+      expect(described_class.read(instance, 1024, 3)).to eq("abc")
+    end
+
+    context "with cabi_realloc" do
+      let(:allocator) { '(func (export "cabi_realloc") (param i32 i32 i32 i32) (result i32) (i32.const 2048))' }
+
+      it "allocates with it" do
+        expect(described_class.write(instance, "abc")).to eq([2048, 3])
+      end
+    end
+
+    context "without allocator" do
+      let(:allocator) { "" }
+
+      it "raises" do
+        expect { instance.call_with_string("echo", "hi") }
+          .to raise_error(Wasmtime::Error, /export cabi_realloc or malloc/)
+      end
+    end
+
+    it "raises on unknown functions" do
+      expect { instance.call_with_string("nope", "hi") }.to raise_error(Wasmtime::Error, 'function "nope" not found')
+    end
+  end
+end
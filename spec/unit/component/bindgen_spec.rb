@@ -0,0 +1,49 @@
+require "spec_helper"
+require "wasmtime/component/bindgen"
+
+module Wasmtime
+  module Component
+    RSpec.describe ".bindgen" do
+      let(:source) { Component.bindgen("spec/fixtures/wit/greeter.wit", "greeter") }
+      let(:bindings) { Module.new.tap { |namespace| namespace.module_eval(source) } }
+
+      it "describes the world's functions" do
+        world = Component.wit_world("spec/fixtures/wit/greeter.wit", "greeter")
+
+        expect(world["name"]).to eq("greeter")
+        expect(world["exports"]).to eq([
+          {"name" => "greet", "interface" => nil, "params" => ["first-name", "excited"], "results" => 1}
+        ])
+        expect(world["imports"].map { |function| function.values_at("interface", "name") })
+          .to contain_exactly(["example:greeter/logger", "log"], [nil, "clock-now"])
+      end
+
+      it "generates exports calling the instance with keyword arguments" do
+        instance = double(Instance)
+        allow(instance).to receive(:invoke).with("greet", "Ruby", true).and_return("Hello, Ruby!")
+
+        exports = bindings::Greeter::Exports.new(instance)
+        expect(exports.greet(first_name: "Ruby", excited: true)).to eq("Hello, Ruby!")
+      end
+
+      it "generates an imports mixin" do
+        imports = bindings::Greeter::Imports
+        host = Class.new { include imports }.new
+
+        expect(host).to respond_to(:log, :clock_now)
+        expect { host.log(message: "hi") }.to raise_error(NotImplementedError, /must implement #log/)
+      end
+
+      it "names the module" do
+        source = Component.bindgen("spec/fixtures/wit/greeter.wit", module_name: "MyGreeter")
+
+        expect(source).to include("module MyGreeter")
+      end
+
+      it "raises on invalid WIT" do
+        expect { Component.bindgen("spec/fixtures/component_types.wat") }
+          .to raise_error(Wasmtime::Error, /Could not parse WIT/)
+      end
+    end
+  end
+end
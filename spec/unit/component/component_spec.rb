@@ -0,0 +1,101 @@
+require "spec_helper"
+
+module Wasmtime
+  module Component
+    RSpec.describe Component do
+      let(:wat) { "(component)" }
+
+      describe ".new" do
+        it "compiles WAT" do
+          expect(Component.new(engine, wat)).to be_instance_of(Component)
+        end
+
+        it "raises on invalid input" do
+          expect { Component.new(engine, "(module)") }
+            .to raise_error(Wasmtime::Error, /Could not build component/)
+        end
+
+        it "raises when the component model is disabled" do
+          expect { Component.new(Engine.new(wasm_component_model: false), wat) }
+            .to raise_error(Wasmtime::Error, /Could not build component/)
+        end
+      end
+
+      describe ".from_file" do
+        it "compiles from a file" do
+          component = Component.from_file(engine, "spec/fixtures/component_types.wat")
+          expect(component).to be_instance_of(Component)
+        end
+      end
+
+      describe ".deserialize" do
+        it "round-trips a serialized component" do
+          serialized = Component.new(engine, wat).serialize
+
+          expect(Component.deserialize(engine, serialized)).to be_instance_of(Component)
+        end
+
+        it "raises on invalid input" do
+          expect { Component.deserialize(engine, "foo") }
+            .to raise_error(Wasmtime::Error, /Could not deserialize component/)
+        end
+      end
+
+      describe ".deserialize_file" do
+        include_context(:tmpdir)
+
+        it "round-trips a serialized component" do
+          path = File.join(tmpdir, "component.cwasm")
+          File.binwrite(path, Component.new(engine, wat).serialize)
+
+          expect(Component.deserialize_file(engine, path)).to be_instance_of(Component)
+        end
+      end
+    end
+
+    RSpec.describe Linker do
+      describe "#instantiate" do
+        it "returns an instance" do
+          linker = Linker.new(engine)
+          component = Component.new(engine, "(component)")
+
+          expect(linker.instantiate(store, component)).to be_instance_of(Instance)
+        end
+
+        it "raises when imports are missing" do
+          linker = Linker.new(engine)
+          component = Component.new(engine, '(component (import "f" (func)))')
+
+          expect { linker.instantiate(store, component) }.to raise_error(Wasmtime::Error, /f/)
+        end
+      end
+    end
+
+    RSpec.describe Instance do
+      let(:instance) do
+        component = Component.from_file(engine, "spec/fixtures/component_types.wat")
+        Linker.new(engine).instantiate(store, component)
+      end
+
+      describe "#get_func" do
+        it "returns the exported function" do
+          expect(instance.get_func("noop")).to be_instance_of(Func)
+        end
+
+        it "returns nil when the function does not exist" do
+          expect(instance.get_func("nope")).to be_nil
+        end
+      end
+
+      describe "#invoke" do
+        it "calls the exported function" do
+          expect(instance.invoke("id-u32", 42)).to eq(42)
+        end
+
+        it "raises when the function does not exist" do
+          expect { instance.invoke("nope") }.to raise_error(Wasmtime::Error, 'function "nope" not found')
+        end
+      end
+    end
+  end
+end
@@ -0,0 +1,152 @@
+require "spec_helper"
+
+module Wasmtime
+  module Component
+    RSpec.describe Func do
+      let(:instance) do
+        component = Component.from_file(engine, "spec/fixtures/component_types.wat")
+        Linker.new(engine).instantiate(store, component)
+      end
+
+      def call(name, *args, unwrap_results: true)
+        instance.get_func(name, unwrap_results: unwrap_results).call(*args)
+      end
+
+      describe "#call" do
+        it "returns nil without results" do
+          expect(call("noop")).to be_nil
+        end
+
+        it "raises on wrong number of arguments" do
+          expect { call("id-u32") }
+            .to raise_error(ArgumentError, "wrong number of arguments (given 0, expected 1)")
+        end
+
+        it "raises a trap" do
+          expect { call("trap") }.to raise_error(Trap)
+        end
+
+        it "can be called multiple times" do
+          func = instance.get_func("id-string")
+          expect(func.call("a")).to eq("a")
+          expect(func.call("b")).to eq("b")
+        end
+      end
+
+      describe "conversions" do
+        cases = {
+          "id-bool" => [true, false],
+          "id-s8" => [-128, 127],
+          "id-u32" => [0, 2**32 - 1],
+          "id-s64" => [-2**63, 2**63 - 1],
+          "id-float64" => [-1.5, 3.25],
+          "id-char" => ["a", "🦀"],
+          "id-string" => ["", "Hello, 世界"],
+          "id-list" => [[], [1, 2, 3]],
+          "id-list-string" => [[], ["a", "bc"]],
+          "id-tuple" => [[1, -2]],
+          "id-point" => [{"x" => 1, "y" => -2}],
+          "id-person" => [{"name" => "Alice", "age" => 42}],
+          "id-filter" => [Variant.new("all"), Variant.new("limit", 10)],
+          "id-color" => [:red, :blue],
+          "id-perms" => [[], [:read, :exec]],
+          "id-option" => [nil, "foo"]
+        }
+
+        cases.each do |name, values|
+          values.each do |value|
+            it "round-trips #{value.inspect} through #{name}" do
+              expect(call(name, value)).to eq(value)
+            end
+          end
+        end
+
+        it "round-trips results when not unwrapping them" do
+          expect(call("id-result", Result.ok(1), unwrap_results: false)).to eq(Result.ok(1))
+          expect(call("id-result", Result.error("nope"), unwrap_results: false)).to eq(Result.error("nope"))
+        end
+
+        it "unwraps results" do
+          expect(call("id-result", Result.ok(1))).to eq(1)
+        end
+
+        it "raises error results" do
+          expect { call("id-result", Result.error("nope")) }
+            .to raise_error(Result::ErrorResult, /nope/) { |error| expect(error.error).to eq("nope") }
+        end
+
+        it "converts records from Hashes with Symbol keys" do
+          expect(call("id-point", {x: 1, y: -2})).to eq({"x" => 1, "y" => -2})
+        end
+
+        it "converts records from Structs" do
+          person = Struct.new(:name, :age).new("Alice", 42)
+          expect(call("id-person", person)).to eq({"name" => "Alice", "age" => 42})
+        end
+
+        it "converts variant cases, enums and flags from Strings and Symbols" do
+          expect(call("id-filter", Variant.new(:limit, 10))).to eq(Variant.new("limit", 10))
+          expect(call("id-color", "green")).to eq(:green)
+          expect(call("id-perms", ["read", :write])).to eq([:read, :write])
+        end
+
+        it "raises on invalid chars" do
+          expect { call("id-char", "ab") }.to raise_error(Wasmtime::Error, /single character/)
+        end
+
+        it "raises on missing record fields" do
+          expect { call("id-point", {"x" => 1}) }
+            .to raise_error(Wasmtime::Error, "record field missing: y")
+        end
+
+        it "raises on unknown variant cases" do
+          expect { call("id-filter", Variant.new("nope")) }
+            .to raise_error(Wasmtime::Error, "unknown variant case: nope")
+        end
+
+        it "raises on non-variants" do
+          expect { call("id-filter", "all") }.to raise_error(Wasmtime::Error, /expected a Wasmtime::Component::Variant/)
+        end
+
+        it "raises on unknown enum cases" do
+          expect { call("id-color", :pink) }.to raise_error(Wasmtime::Error)
+        end
+
+        it "raises on non-records" do
+          expect { call("id-point", 1) }.to raise_error(Wasmtime::Error, /expected a Hash or a Struct/)
+        end
+
+        it "raises on non-results" do
+          expect { call("id-result", 1) }.to raise_error(Wasmtime::Error, /expected a Wasmtime::Component::Result/)
+        end
+      end
+    end
+
+    RSpec.describe Result do
+      it "exposes its ok payload" do
+        result = Result.ok(1)
+        expect(result).to be_ok
+        expect(result.ok).to eq(1)
+        expect { result.error }.to raise_error(Result::UncheckedResult)
+      end
+
+      it "exposes its error payload" do
+        result = Result.error("nope")
+        expect(result).to be_error
+        expect(result.error).to eq("nope")
+        expect { result.ok }.to raise_error(Result::UncheckedResult)
+      end
+
+      it "unwraps its payload" do
+        expect(Result.ok(1).unwrap).to eq(1)
+        expect { Result.error("nope").unwrap }
+          .to raise_error(Result::ErrorResult, 'component returned an error result: "nope"')
+      end
+
+      it "compares by value" do
+        expect(Result.ok(1)).to eq(Result.ok(1))
+        expect(Result.ok(1)).not_to eq(Result.error(1))
+      end
+    end
+  end
+end
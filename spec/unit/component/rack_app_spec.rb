@@ -0,0 +1,65 @@
+require "spec_helper"
+require "stringio"
+require "wasmtime/component/rack_app"
+
+module Wasmtime
+  module Component
+    RSpec.describe RackApp do
+      let(:component) { Component.new(engine, "(component)") }
+      let(:app) { RackApp.new(engine, component) }
+      let(:env) do
+        {
+          "REQUEST_METHOD" => "POST",
+          "rack.url_scheme" => "https",
+          "HTTP_HOST" => "example.com",
+          "SCRIPT_NAME" => "/app",
+          "PATH_INFO" => "/items",
+          "QUERY_STRING" => "page=2",
+          "CONTENT_TYPE" => "application/json",
+          "HTTP_X_REQUEST_ID" => "42",
+          "rack.input" => StringIO.new("{}")
+        }
+      end
+
+      it "converts the Rack env into an HTTP request" do
+        expect_any_instance_of(Linker).to receive(:handle_http_request)
+          .with(
+            an_instance_of(Store),
+            component,
+            "POST",
+            "https://example.com/app/items?page=2",
+            contain_exactly(["host", "example.com"], ["content-type", "application/json"], ["x-request-id", "42"]),
+            "{}"
+          )
+          .and_return([200, [], ""])
+
+        app.call(env)
+      end
+
+      it "converts the response into a Rack response" do
+        allow_any_instance_of(Linker).to receive(:handle_http_request)
+          .and_return([201, [["content-type", "text/plain"], ["set-cookie", "a=1"], ["set-cookie", "b=2"]], "created"])
+
+        expect(app.call(env)).to eq([
+          201,
+          {"content-type" => "text/plain", "set-cookie" => ["a=1", "b=2"]},
+          ["created"]
+        ])
+      end
+
+      it "uses the given stores" do
+        store = Store.new(engine, wasi_http_ctx: WasiHttpCtxBuilder.new)
+        app = RackApp.new(engine, component) { store }
+        expect_any_instance_of(Linker).to receive(:handle_http_request)
+          .with(store, any_args)
+          .and_return([204, [], ""])
+
+        app.call(env)
+      end
+
+      it "propagates the component's errors" do
+        expect { app.call(env) }.to raise_error(Wasmtime::Error)
+      end
+    end
+  end
+end
@@ -0,0 +1,102 @@
+require "spec_helper"
+
+module Wasmtime
+  module Component
+    RSpec.describe "Linker#define_resource" do
+      let(:counter_class) do
+        Class.new do
+          def initialize(start)
+            @value = start
+          end
+
+          def increment
+            @value += 1
+          end
+        end
+      end
+
+      let(:component) { Component.new(engine, <<~WAT) }
+        (component
+          (import "example:counter/types" (instance $types
+            (export "counter" (type $counter (sub resource)))
+            (export "[constructor]counter" (func (param "start" u32) (result (own $counter))))
+            (export "[method]counter.increment" (func (param "self" (borrow $counter)) (result u32)))
+          ))
+          (alias export $types "counter" (type $counter))
+          (core func $new (canon lower (func $types "[constructor]counter")))
+          (core func $increment (canon lower (func $types "[method]counter.increment")))
+          (core func $drop (canon resource.drop $counter))
+          (core module $m
+            (import "" "new" (func $new (param i32) (result i32)))
+            (import "" "increment" (func $increment (param i32) (result i32)))
+            (import "" "drop" (func $drop (param i32)))
+            (func (export "count") (param i32) (result i32)
+              (local $handle i32)
+              (local $result i32)
+              (local.set $handle (call $new (local.get 0)))
+              (local.set $result (call $increment (local.get $handle)))
+              (call $drop (local.get $handle))
+              (local.get $result))
+          )
+          (core instance $i (instantiate $m
+            (with "" (instance
+              (export "new" (func $new))
+              (export "increment" (func $increment))
+              (export "drop" (func $drop))
+            ))
+          ))
+          (func (export "count") (param "start" u32) (result u32) (canon lift (core func $i "count")))
+        )
+      WAT
+
+      let(:linker) { Linker.new(engine) }
+
+      it "dispatches the resource's functions to the Ruby class" do
+        linker.define_resource(component, "example:counter/types", "counter", counter_class)
+
+        instance = linker.instantiate(store, component)
+        expect(instance.invoke("count", 41)).to eq(42)
+      end
+
+      it "raises errors from Ruby" do
+        counter_class.define_method(:increment) { raise "boom" }
+        linker.define_resource(component, "example:counter/types", "counter", counter_class)
+
+        instance = linker.instantiate(store, component)
+        expect { instance.invoke("count", 41) }.to raise_error(RuntimeError, "boom")
+      end
+
+      it "rejects objects of other classes returned as the resource" do
+        counter_class.define_singleton_method(:new) { |_start| Object.new }
+        linker.define_resource(component, "example:counter/types", "counter", counter_class)
+
+        instance = linker.instantiate(store, component)
+        expect { instance.invoke("count", 41) }
+          .to raise_error(Wasmtime::Error, /expected a .* resource, got Object/)
+      end
+
+      it "requires the interface to export the resource" do
+        expect { linker.define_resource(component, "example:counter/types", "widget", counter_class) }
+          .to raise_error(Wasmtime::Error, /does not export resource widget/)
+      end
+
+      it "requires the class to define the resource's methods" do
+        expect { linker.define_resource(component, "example:counter/types", "counter", Class.new) }
+          .to raise_error(Wasmtime::Error, /does not define #increment/)
+      end
+
+      it "requires the component to import the interface" do
+        expect { linker.define_resource(component, "example:other/types", "counter", counter_class) }
+          .to raise_error(Wasmtime::Error, /component does not import instance example:other\/types/)
+      end
+
+      it "is not supported in async engines" do
+        async_engine = Engine.new(async_support: true)
+        async_component = Component.new(async_engine, "(component)")
+
+        expect { Linker.new(async_engine).define_resource(async_component, "a:b/c", "d", counter_class) }
+          .to raise_error(Wasmtime::Error, /not supported in engines with async_support: true/)
+      end
+    end
+  end
+end
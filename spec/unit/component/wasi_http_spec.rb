@@ -0,0 +1,37 @@
+require "spec_helper"
+
+module Wasmtime
+  module Component
+    RSpec.describe WasiHttpCtxBuilder do
+      let(:component) { Component.new(engine, "(component)") }
+
+      it "is configured through chained methods" do
+        builder = WasiHttpCtxBuilder.new
+        expect(builder.allow_host("example.com")).to be(builder)
+        expect(builder.allow_host("*.example.org").set_connect_timeout(1)).to be(builder)
+        expect(builder.set_first_byte_timeout(2).set_between_bytes_timeout(0.5)).to be(builder)
+      end
+
+      it "rejects invalid timeouts" do
+        expect { WasiHttpCtxBuilder.new.set_connect_timeout(-1) }
+          .to raise_error(ArgumentError, /invalid timeout/)
+      end
+
+      it "lets components be instantiated with WASI HTTP" do
+        store = Store.new(engine, wasi_http_ctx: WasiHttpCtxBuilder.new.allow_host("example.com"))
+
+        expect(Linker.new(engine, wasi_http: true).instantiate(store, component)).to be_a(Instance)
+      end
+
+      it "requires a WASI HTTP context in the store" do
+        expect { Linker.new(engine, wasi_http: true).instantiate(store, component) }
+          .to raise_error(Wasmtime::Error, /Store is missing WASI HTTP configuration/)
+      end
+
+      it "is not supported in async engines" do
+        expect { Linker.new(Engine.new(async_support: true), wasi_http: true) }
+          .to raise_error(Wasmtime::Error, /not supported in engines with async_support: true/)
+      end
+    end
+  end
+end
@@ -0,0 +1,42 @@
+require "spec_helper"
+
+module Wasmtime
+  module Component
+    RSpec.describe "wasi:keyvalue" do
+      let(:component) { Component.new(engine, "(component)") }
+      let(:backend) do
+        Class.new do
+          def initialize = @data = Hash.new { |h, k| h[k] = {} }
+
+          def get(bucket, key) = @data[bucket][key]
+
+          def set(bucket, key, value) = @data[bucket][key] = value
+
+          def delete(bucket, key) = @data[bucket].delete(key)
+        end.new
+      end
+
+      it "lets components be instantiated with a key-value backend" do
+        store = Store.new(engine, wasi_keyvalue: backend)
+
+        expect(Linker.new(engine, wasi_keyvalue: true).instantiate(store, component))
+          .to be_a(Instance)
+      end
+
+      it "requires the backend to respond to get, set and delete" do
+        expect { Store.new(engine, wasi_keyvalue: Object.new) }
+          .to raise_error(ArgumentError, /wasi_keyvalue backend must respond to #get/)
+      end
+
+      it "requires a backend in the store" do
+        expect { Linker.new(engine, wasi_keyvalue: true).instantiate(store, component) }
+          .to raise_error(Wasmtime::Error, /Store is missing a wasi_keyvalue backend/)
+      end
+
+      it "is not supported in async engines" do
+        expect { Linker.new(Engine.new(async_support: true), wasi_keyvalue: true) }
+          .to raise_error(Wasmtime::Error, /not supported in engines with async_support: true/)
+      end
+    end
+  end
+end
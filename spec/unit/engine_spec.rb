@@ -27,7 +27,22 @@ module Wasmtime
         [:wasm_threads, true],
         [:wasm_multi_memory, true],
         [:wasm_memory64, true],
-        [:parallel_compilation, true]
+        [:wasm_component_model, true],
+        [:wasm_reference_types, true],
+        [:wasm_bulk_memory, true],
+        [:wasm_sign_extension, true],
+        [:wasm_multi_value, true],
+        [:wasm_simd, true],
+        [:wasm_relaxed_simd, true],
+        [:wasm_function_references, true],
+        [:async_support, true],
+        [:async_stack_size, 4 << 20, true],
+        [:memory_reservation, 1 << 20, true],
+        [:memory_guard_size, 64 << 10, true],
+        [:guard_before_linear_memory, false],
+        [:memory_init_cow, true],
+        [:parallel_compilation, true],
+        [:cranelift_nan_canonicalization, true]
       ].each do |option, valid, invalid = nil|
         it "supports #{option}" do
           Engine.new(option => valid)
@@ -35,13 +50,47 @@ module Wasmtime
         end
       end
 
+      it "traps guests exceeding max_wasm_stack" do
+        engine = Engine.new(max_wasm_stack: 64 << 10)
+        instance = Instance.new(Store.new(engine), Module.new(engine, <<~WAT))
+          (module
+            (func $recurse (export "recurse") (param i32) (result i32)
+              (if (result i32) (i32.eqz (local.get 0))
+                (then (i32.const 0))
+                (else (i32.add (i32.const 1) (call $recurse (i32.sub (local.get 0) (i32.const 1))))))))
+        WAT
+
+        expect(instance.invoke("recurse", 10)).to eq(10)
+        expect { instance.invoke("recurse", 1_000_000) }
+          .to raise_error(Trap) { |trap| expect(trap.code).to eq(Trap::STACK_OVERFLOW) }
+      end
+
+      it "runs Wasm with a small memory reservation" do
+        engine = Engine.new(memory_reservation: 1 << 20, memory_guard_size: 64 << 10)
+        store = Store.new(engine)
+        instance = Instance.new(store, Module.new(engine, <<~WAT))
+          (module
+            (memory (export "mem") 1)
+            (func (export "grow") (result i32) (memory.grow (i32.const 32))))
+        WAT
+
+        expect(instance.invoke("grow")).to eq(1)
+        expect(instance.export("mem").to_memory.size).to eq(33)
+      end
+
+      it "rejects an async stack smaller than max_wasm_stack" do
+        expect { Engine.new(async_support: true, async_stack_size: 1 << 20, max_wasm_stack: 2 << 20) }
+          .to raise_error(Wasmtime::Error, /async_stack_size/)
+      end
+
       profiler_options = [:none]
-      profiler_options.push(:jitdump, :vtune) if Gem::Platform.local.os == "linux"
+      profiler_options.push(:perfmap, :jitdump, :vtune) if Gem::Platform.local.os == "linux"
 
       # enum options represented as symbols
       [
         [:cranelift_opt_level, [:none, :speed, :speed_and_size]],
-        [:profiler, profiler_options]
+        [:profiler, profiler_options],
+        [:strategy, [:auto, :cranelift]]
       ].each do |option, valid|
         it "supports #{option}" do
           valid.each { |value| Engine.new(option => value) }
@@ -50,10 +99,170 @@ module Wasmtime
         end
       end
 
+      it "rejects disabling sign-extension operators" do
+        expect { Engine.new(wasm_sign_extension: false) }
+          .to raise_error(ArgumentError, /sign-extension operators cannot be disabled/)
+      end
+
+      it "rejects modules using disabled proposals" do
+        engine = Engine.new(wasm_multi_value: false)
+        expect { Module.new(engine, "(module (func (result i32 i32) i32.const 1 i32.const 2))") }
+          .to raise_error(Wasmtime::Error, /multi-value/)
+
+        engine = Engine.new(wasm_reference_types: false, wasm_bulk_memory: false)
+        expect { Module.new(engine, "(module (memory 1) (func (memory.fill (i32.const 0) (i32.const 0) (i32.const 0))))") }
+          .to raise_error(Wasmtime::Error, /bulk memory/)
+      end
+
+      it "rejects the winch strategy on unsupported targets" do
+        expect { Engine.new(strategy: :winch, target: "aarch64-unknown-linux-gnu") }
+          .to raise_error(ArgumentError, /The :winch strategy/)
+      end
+
+      it "supports cranelift flags" do
+        expect { Engine.new(target: "x86_64-unknown-linux-gnu", cranelift_flags: {"has_avx2" => true}) }
+          .not_to raise_error
+        expect { Engine.new(cranelift_flags: {"nope" => true}) }.to raise_error(Wasmtime::Error, /nope/)
+        expect { Engine.new(cranelift_flags: "nope") }.to raise_error(TypeError, /cranelift_flags/)
+      end
+
+      it "supports enabling cranelift flags" do
+        expect { Engine.new(target: "x86_64-unknown-linux-gnu", cranelift_flag_enable: ["has_avx2", "has_avx512f"]) }
+          .not_to raise_error
+        expect { Engine.new(cranelift_flag_enable: ["nope"]) }.to raise_error(Wasmtime::Error, /nope/)
+        expect { Engine.new(cranelift_flag_enable: "nope") }.to raise_error(TypeError, /cranelift_flag_enable/)
+      end
+
+      it "pins ISA extensions of precompiled modules" do
+        baseline = Engine.new(target: "x86_64-unknown-linux-gnu")
+        avx2 = Engine.new(target: "x86_64-unknown-linux-gnu", cranelift_flag_enable: ["has_avx2"])
+
+        expect(avx2.precompile_compatibility_key).not_to eq(baseline.precompile_compatibility_key)
+      end
+
       it "supports target options" do
         expect { Engine.new(target: "x86_64-unknown-linux-gnu") }.not_to raise_error
         expect { Engine.new(target: "nope") }.to raise_error(ArgumentError, /Unrecognized architecture/)
       end
+
+      describe "cache option" do
+        include_context(:tmpdir)
+
+        it "caches compiled modules in the configured directory" do
+          cache_dir = File.join(tmpdir, "cache")
+          config_path = File.join(tmpdir, "cache.toml")
+          File.write(config_path, <<~TOML)
+            [cache]
+            enabled = true
+            directory = #{cache_dir.inspect}
+          TOML
+
+          cached_engine = Engine.new(cache: config_path)
+          Module.new(cached_engine, "(module (func (export \"f\")))")
+
+          expect(Dir.glob(File.join(cache_dir, "**", "*")).select { |f| File.file?(f) }).not_to be_empty
+        end
+
+        it "supports the default cache config" do
+          expect { Engine.new(cache: true) }.not_to raise_error
+          expect { Engine.new(cache: false) }.not_to raise_error
+        end
+
+        it "raises on a missing config file" do
+          expect { Engine.new(cache: File.join(tmpdir, "nope.toml")) }
+            .to raise_error(Wasmtime::Error, /Failed to load cache config/)
+        end
+
+        it "raises on invalid values" do
+          expect { Engine.new(cache: 1) }.to raise_error(TypeError, /cache/)
+        end
+      end
+    end
+
+    describe "#memory_init_cow?" do
+      it "is false by default" do
+        expect(Engine.new).not_to be_memory_init_cow
+        expect(Engine.new(consume_fuel: true)).not_to be_memory_init_cow
+      end
+
+      it "is true when enabled" do
+        expect(Engine.new(memory_init_cow: true)).to be_memory_init_cow
+      end
+
+      it "initializes memories from data segments" do
+        engine = Engine.new(memory_init_cow: true)
+        mod = Module.new(engine, <<~WAT)
+          (module
+            (memory (export "mem") 1)
+            (data (i32.const 16) "hello"))
+        WAT
+        instance_pre = Linker.new(engine).instantiate_pre(mod)
+
+        2.times do
+          memory = instance_pre.instantiate(Store.new(engine)).export("mem").to_memory
+          expect(memory.read(16, 5)).to eq("hello")
+          memory.write(16, "world")
+        end
+      end
+    end
+
+    describe "#start_epoch_interval" do
+      let(:engine) { Engine.new(epoch_interruption: true) }
+
+      after { engine.stop_epoch_interval }
+
+      it "increments the epoch in the background" do
+        store = Store.new(engine)
+        store.set_epoch_deadline(1)
+        instance = Instance.new(store, Module.new(engine, <<~WAT))
+          (module (func (export "loop_forever") (loop br 0)))
+        WAT
+
+        engine.start_epoch_interval(1)
+        expect { instance.invoke("loop_forever") }.to raise_error(Trap)
+      end
+
+      it "rejects a zero interval" do
+        expect { engine.start_epoch_interval(0) }.to raise_error(ArgumentError, /at least 1 millisecond/)
+      end
+    end
+
+    describe "#close" do
+      let(:engine) { Engine.new(epoch_interruption: true) }
+
+      it "marks the engine as closed" do
+        expect(engine).not_to be_closed
+        engine.close
+        expect(engine).to be_closed
+        expect { engine.close }.not_to raise_error
+      end
+
+      it "raises when using the engine" do
+        engine.close
+
+        expect { Module.new(engine, "(module)") }.to raise_error(Wasmtime::Error, "engine is closed")
+        expect { Store.new(engine) }.to raise_error(Wasmtime::Error, "engine is closed")
+        expect { Linker.new(engine) }.to raise_error(Wasmtime::Error, "engine is closed")
+        expect { engine.precompile_module("(module)") }.to raise_error(Wasmtime::Error, "engine is closed")
+        expect { engine.start_epoch_interval(1) }.to raise_error(Wasmtime::Error, "engine is closed")
+      end
+
+      it "keeps existing stores working" do
+        store = Store.new(engine)
+        instance = Instance.new(store, Module.new(engine, <<~WAT))
+          (module (func (export "f") (result i32) i32.const 1))
+        WAT
+        engine.close
+
+        expect(instance.invoke("f")).to eq(1)
+      end
+
+      it "stops the epoch interval" do
+        engine.start_epoch_interval(1)
+        engine.close
+
+        expect(engine).to be_closed
+      end
     end
 
     describe ".precompile_module" do
@@ -67,6 +276,38 @@ module Wasmtime
         mod = Module.deserialize(engine, serialized)
         expect(mod).to be_instance_of(Wasmtime::Module)
       end
+
+      it "accepts Wasm binaries" do
+        wasm = Wasmtime.wat2wasm('(module (func (export "f") (result i32) i32.const 1))')
+        mod = Module.deserialize(engine, engine.precompile_module(wasm))
+        instance = Instance.new(Store.new(engine), mod)
+        expect(instance.invoke("f")).to eq(1)
+      end
+
+      it "raises on invalid input" do
+        expect { engine.precompile_module("(module") }.to raise_error(Wasmtime::Error)
+      end
+
+      it "cross-compiles for the engine's target" do
+        target = (RbConfig::CONFIG["host_cpu"] == "x86_64") ? "aarch64-unknown-linux-gnu" : "x86_64-unknown-linux-gnu"
+        compiled = Engine.new(target: target).precompile_module("(module)")
+
+        expect(compiled).to be_a(String)
+        expect { Module.deserialize(Engine.new, compiled) }.to raise_error(Wasmtime::Error)
+      end
+    end
+
+    describe ".precompile_component" do
+      it "can be used by Component.deserialize" do
+        compiled = engine.precompile_component("(component)")
+
+        expect(compiled).to be_instance_of(String)
+        expect(Component::Component.deserialize(engine, compiled)).to be_instance_of(Component::Component)
+      end
+
+      it "raises on invalid input" do
+        expect { engine.precompile_component("(module)") }.to raise_error(Wasmtime::Error)
+      end
     end
 
     describe "#precompile_compatibility_key" do
@@ -47,6 +47,56 @@ module Wasmtime
           expect { func.call }.to raise_error(error_class)
         end
 
+        it "re-raises the original exception object with its backtrace" do
+          error = error_class.new("boom")
+          func = Func.new(Store.new(engine), [], []) { raise error }
+
+          expect { func.call }.to raise_error(error_class, "boom") do |raised|
+            expect(raised).to equal(error)
+            expect(raised.backtrace.first).to include(__FILE__)
+          end
+        end
+
+        it "chains the host exception to a trap with the Wasm backtrace" do
+          mod = Module.new(engine, <<~WAT)
+            (module
+              (import "" "" (func $host))
+              (func (export "f") call $host))
+          WAT
+          instance = Instance.new(store, mod, [Func.new(store, [], []) { raise error_class, "boom" }])
+
+          expect { instance.invoke("f") }.to raise_error(error_class, "boom") do |error|
+            expect(error.cause).to be_a(Trap)
+            expect(error.cause.code).to be_nil
+            expect(error.cause.wasm_backtrace).not_to be_empty
+          end
+        end
+
+        it "keeps the cause of host exceptions raised with one" do
+          func = Func.new(store, [], []) do
+            raise "inner"
+          rescue
+            raise error_class
+          end
+
+          expect { func.call }.to raise_error(error_class) do |error|
+            expect(error.cause.message).to eq("inner")
+          end
+        end
+
+        it "bubbles host exception through nested Wasm calls" do
+          store = Store.new(engine)
+          mod = Module.new(engine, <<~WAT)
+            (module
+              (import "" "" (func $host))
+              (export "f" (func $host)))
+          WAT
+          inner = Instance.new(store, mod, [Func.new(store, [], []) { raise error_class, "inner" }])
+          outer = Instance.new(store, mod, [Func.new(store, [], []) { inner.invoke("f") }])
+
+          expect { outer.invoke("f") }.to raise_error(error_class, "inner")
+        end
+
         it "bubbles trap" do
           func = Instance.new(Store.new(engine), module_trapping_on_func)
             .export("f")
@@ -77,6 +127,44 @@ module Wasmtime
       end
     end
 
+    describe "hierarchy" do
+      it "raises CompileError on invalid modules" do
+        expect { Module.new(engine, "(module (func (result i32)))") }
+          .to raise_error(CompileError, /Could not build module/)
+        expect(ValidationError.ancestors).to include(CompileError, Wasmtime::Error)
+      end
+
+      it "raises LinkError with the offending import on unknown imports" do
+        mod = Module.new(engine, '(module (import "env" "log" (func)))')
+
+        expect { Linker.new(engine).instantiate(store, mod) }.to raise_error(LinkError) do |error|
+          expect(error.message).to match(/unknown import/)
+          expect(error.import_module).to eq("env")
+          expect(error.import_name).to eq("log")
+        end
+      end
+
+      it "raises LinkError on incompatible or missing imports" do
+        mod = Module.new(engine, '(module (import "env" "log" (func (param i32))))')
+
+        expect { Instance.new(store, mod, [Func.new(store, [], []) {}]) }
+          .to raise_error(LinkError) { |error| expect(error.import_name).to eq("log") }
+        expect { Instance.new(store, mod, []) }
+          .to raise_error(LinkError) { |error| expect(error.import_name).to be_nil }
+      end
+
+      it "raises ResourceLimitExceeded with the exceeded resource" do
+        store = Store.new(engine, limits: {instances: 1})
+        mod = Module.new(engine, "(module)")
+        Instance.new(store, mod)
+
+        expect { Instance.new(store, mod) }.to raise_error(ResourceLimitExceeded) do |error|
+          expect(error.resource).to eq(:instances)
+          expect(error.message).to eq("resource limit exceeded: instance count too high at 2")
+        end
+      end
+    end
+
     it "raises WasiExit on WASI's proc_exit" do
       linker = Linker.new(engine, wasi: true)
       store = Store.new(engine, wasi_ctx: WasiCtxBuilder.new.build)
@@ -88,6 +176,23 @@ module Wasmtime
       end
     end
 
+    context "with return_exit_code: true" do
+      let(:linker) { Linker.new(engine, wasi: true) }
+      let(:store) { Store.new(engine, wasi_ctx: WasiCtxBuilder.new.build, return_exit_code: true) }
+
+      it "returns 0 on WASI's proc_exit with code 0" do
+        instance = linker.instantiate(store, wasi_module_exiting)
+        expect(instance.invoke("_start")).to eq(0)
+      end
+
+      it "raises WasiExit on other exit codes" do
+        instance = linker.instantiate(store, wasi_module_exiting(2))
+        expect { instance.invoke("_start") }.to raise_error(WasiExit) do |wasi_exit|
+          expect(wasi_exit.code).to eq(2)
+        end
+      end
+    end
+
     def module_import_func_start
       Wasmtime::Module.new(engine, <<~WAT)
         (module
@@ -111,14 +216,14 @@ module Wasmtime
       WAT
     end
 
-    def wasi_module_exiting
+    def wasi_module_exiting(code = 0)
       Module.new(engine, <<~WAT)
         (module
           (import "wasi_unstable" "proc_exit"
             (func $__wasi_proc_exit (param i32)))
           (memory (export "memory") 0)
           (func $_start
-            (call $__wasi_proc_exit (i32.const 0)))
+            (call $__wasi_proc_exit (i32.const #{code})))
           (export "_start" (func $_start)))
       WAT
     end
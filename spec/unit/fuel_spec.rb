@@ -34,6 +34,13 @@ module Wasmtime
       end
     end
 
+    describe "#fuel_remaining" do
+      test_on_store_and_caller "is an alias of #get_fuel" do |store_like|
+        store_like.set_fuel(42)
+        expect(store_like.fuel_remaining).to eq(42)
+      end
+    end
+
     describe "#get_fuel" do
       test_on_store_and_caller "starts at 0" do |store_like|
         expect(store_like.get_fuel).to eq(0)
@@ -44,17 +51,95 @@ module Wasmtime
       end
     end
 
+    describe "#add_fuel" do
+      test_on_store_and_caller "adds to the remaining fuel" do |store_like|
+        store_like.set_fuel(100)
+        expect(store_like.add_fuel(50)).to be_nil
+        expect(store_like.get_fuel).to eq(150)
+      end
+
+      test_on_store_and_caller "raises when fuel isn't configured", :store_without_fuel do |store_like|
+        expect { store_like.add_fuel(100) }
+          .to(raise_error(Wasmtime::Error, /fuel is not configured in this store/))
+      end
+    end
+
+    describe "#fuel_consumed" do
+      it "starts at 0" do
+        expect(store.fuel_consumed).to eq(0)
+      end
+
+      it "returns the fuel consumed by Wasm execution" do
+        instance = Instance.new(store, fuel_module)
+        store.set_fuel(100)
+        store.add_fuel(50)
+        instance.invoke("f")
+
+        expect(store.fuel_consumed).to be > 0
+        expect(store.fuel_consumed + store.get_fuel).to eq(150)
+      end
+
+      it "keeps accumulating across set_fuel calls" do
+        instance = Instance.new(store, fuel_module)
+        store.set_fuel(100)
+        instance.invoke("f")
+        consumed = store.fuel_consumed
+
+        store.set_fuel(100)
+        expect(store.fuel_consumed).to eq(consumed)
+        instance.invoke("f")
+        expect(store.fuel_consumed).to eq(consumed * 2)
+      end
+
+      it "raises an error when fuel is not configured" do
+        expect { store_without_fuel.fuel_consumed }
+          .to(raise_error(Wasmtime::Error, /fuel is not configured in this store/))
+      end
+    end
+
+    describe "#set_instruction_limit" do
+      it "limits the instructions executed over the store's lifetime" do
+        instance = Instance.new(store, fuel_module)
+        store.set_instruction_limit(1_000)
+        instance.invoke("f")
+        consumed = store.instructions_consumed
+
+        expect(consumed).to be > 0
+        store.set_instruction_limit(consumed)
+        expect { instance.invoke("f") }.to raise_error(Trap) do |error|
+          expect(error.code).to eq(Trap::OUT_OF_FUEL)
+        end
+      end
+
+      it "counts instructions deterministically" do
+        counts = Array.new(2) do
+          store = Store.new(engine).tap { |s| s.set_instruction_limit(1_000) }
+          Instance.new(store, fuel_module).invoke("f")
+          store.instructions_consumed
+        end
+        expect(counts.uniq.size).to eq(1)
+      end
+
+      it "raises an error when fuel is not configured" do
+        expect { store_without_fuel.set_instruction_limit(100) }
+          .to(raise_error(Wasmtime::Error, /fuel is not configured in this store/))
+      end
+    end
+
     it "traps when Wasm execution runs out of fuel" do
-      mod = Module.new(engine, <<~WAT)
-        (module
-          (func (export "f") (result i32)
-            i32.const 42))
-      WAT
-      instance = Instance.new(store, mod)
+      instance = Instance.new(store, fuel_module)
       store.set_fuel(1)
       expect { instance.invoke("f") }.to raise_error(Trap, /all fuel consumed/) do |error|
         expect(error.code).to eq(Trap::OUT_OF_FUEL)
       end
     end
+
+    def fuel_module
+      Module.new(engine, <<~WAT)
+        (module
+          (func (export "f") (result i32)
+            i32.const 42))
+      WAT
+    end
   end
 end
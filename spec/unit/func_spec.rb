@@ -100,6 +100,52 @@ module Wasmtime
         expect(called).to be true
       end
 
+      it "exposes the store's data as #data in Linker-created instances" do
+        linker = Linker.new(engine)
+        linker.func_new("host", "set", [], []) { |caller| caller.data = caller.data + 1 }
+        mod = Module.new(engine, <<~WAT)
+          (module
+            (import "host" "set" (func $set))
+            (func (export "run") call $set))
+        WAT
+        store = Store.new(engine, 41)
+
+        linker.instantiate(store, mod).invoke("run")
+        expect(store.data).to eq(42)
+      end
+
+      it "can replace the store's data from the caller" do
+        store = Store.new(engine, :old)
+        func = Func.new(store, [], []) { |caller| caller.store_data = :new }
+
+        func.call
+        expect(store.data).to eq(:new)
+      end
+
+      it "keeps externref params alive while the guest holds them" do
+        instance = compile_externref_table_module
+        instance.invoke("store", +"foo")
+        generate_new_objects
+
+        expect(instance.export("table").to_table.get(0)).to eq("foo")
+      end
+
+      it "keeps externref results alive while the guest holds them" do
+        mod = Module.new(engine, <<~WAT)
+          (module
+            (import "" "" (func $make (result externref)))
+            (table (export "table") 1 externref)
+            (func (export "store")
+              (table.set (i32.const 0) (call $make))))
+        WAT
+        make = Func.new(store, [], [:externref]) { +"bar" }
+        instance = Instance.new(store, mod, [make])
+        instance.invoke("store")
+        generate_new_objects
+
+        expect(instance.export("table").to_table.get(0)).to eq("bar")
+      end
+
       it "disallows cross-store funcref arg" do
         store2 = Store.new(engine, {})
         func = Func.new(store, [:funcref], []) {}
@@ -115,6 +161,274 @@ module Wasmtime
 
         expect { func.call }.to raise_error(Wasmtime::Error, /cross-`Store`/)
       end
+
+      it "passes host funcs as funcref args that the guest can store and call" do
+        store = Store.new(engine)
+        instance = Instance.new(store, Module.new(engine, <<~WAT))
+          (module
+            (type $i32_to_i32 (func (param i32) (result i32)))
+            (table $t 1 funcref)
+            (func (export "register") (param funcref)
+              (table.set $t (i32.const 0) (local.get 0)))
+            (func (export "dispatch") (param i32) (result i32)
+              (call_indirect $t (type $i32_to_i32) (local.get 0) (i32.const 0))))
+        WAT
+        callback = Func.new(store, [:i32], [:i32]) { |_, x| x * 3 }
+
+        instance.invoke("register", callback)
+        expect(instance.invoke("dispatch", 5)).to eq(15)
+        expect(instance.export("dispatch").to_func.call(2)).to eq(6)
+      end
+
+      it "returns funcref results as callable Funcs" do
+        store = Store.new(engine)
+        instance = Instance.new(store, Module.new(engine, <<~WAT))
+          (module
+            (func $double (param i32) (result i32)
+              (i32.mul (local.get 0) (i32.const 2)))
+            (elem declare func $double)
+            (func (export "get") (result funcref)
+              (ref.func $double))
+            (func (export "null") (result funcref)
+              (ref.null func)))
+        WAT
+
+        func = instance.invoke("get")
+        expect(func).to be_instance_of(Func)
+        expect(func.call(21)).to eq(42)
+        expect(instance.invoke("null")).to be_nil
+      end
+
+      it "calls modules using typed function references" do
+        engine = Engine.new(wasm_function_references: true)
+        instance = Instance.new(Store.new(engine), Module.new(engine, <<~WAT))
+          (module
+            (type $t (func (param i32) (result i32)))
+            (func $double (type $t)
+              (i32.mul (local.get 0) (i32.const 2)))
+            (elem declare func $double)
+            (func (export "run") (param i32) (result i32)
+              (call_ref $t (local.get 0) (ref.func $double))))
+        WAT
+
+        expect(instance.invoke("run", 21)).to eq(42)
+      end
+
+      it "passes and returns v128 values as 16-byte Strings" do
+        store = Store.new(engine)
+        instance = Instance.new(store, Module.new(engine, <<~WAT))
+          (module
+            (func (export "add") (param v128 v128) (result v128)
+              (i32x4.add (local.get 0) (local.get 1))))
+        WAT
+
+        result = instance.invoke("add", [1, 2, 3, 4].pack("V4"), [10, 20, 30, 40].pack("V4"))
+        expect(result.encoding).to eq(Encoding::BINARY)
+        expect(result.unpack("V4")).to eq([11, 22, 33, 44])
+      end
+
+      it "converts v128 values from and to host functions" do
+        func = build_func([:v128], [:v128]) { |_caller, v| v.reverse }
+        expect(func.call((0..15).to_a.pack("C*"))).to eq((0..15).to_a.reverse.pack("C*"))
+      end
+
+      it "rejects v128 arguments of the wrong size" do
+        func = build_func([:v128], []) {}
+        expect { func.call("short") }.to raise_error(ArgumentError, /expected a 16-byte String for v128, got 5 bytes/)
+      end
+    end
+
+    describe ".call with numeric params taken by the block" do
+      it "passes the caller and params" do
+        func = build_func([:i32, :i64, :f32, :f64], [:f64]) do |caller, a, b, c, d|
+          expect(caller).to be_instance_of(Caller)
+          a + b + c + d
+        end
+        expect(func.call(1, 2, 0.5, 0.25)).to eq(3.75)
+      end
+
+      it "accepts an array of results" do
+        func = build_func([:i32], [:i32, :i32]) { |_caller, a| [a, a * 2] }
+        expect(func.call(2)).to eq([2, 4])
+      end
+
+      it "accepts an array of 1 element for single result" do
+        func = build_func([:i32], [:i32]) { |_caller, a| [a] }
+        expect(func.call(2)).to eq(2)
+      end
+
+      it "accepts results converted with to_ary" do
+        results = Struct.new(:to_ary)
+        func = build_func([:i32], [:i32, :i32]) { |_caller, a| results.new([a, a * 2]) }
+        expect(func.call(2)).to eq([2, 4])
+      end
+
+      it "rejects mismatching results size" do
+        func = build_func([:i32], [:i32, :i32]) { |_caller, a| a }
+        expect { func.call(1) }.to raise_error(Wasmtime::ResultError, /wrong number of results \(given 1, expected 2\)/)
+      end
+
+      it "rejects mismatching result type" do
+        func = build_func([:i32], [:i32]) { |_caller, _a| "foo" }
+        expect { func.call(1) }.to raise_error(Wasmtime::ResultError, /result at index 0/)
+      end
+
+      it "raises the block's errors" do
+        func = build_func([:i32], []) { |_caller, _a| raise "boom" }
+        expect { func.call(1) }.to raise_error(RuntimeError, "boom")
+      end
+    end
+
+    describe ".call with timeout" do
+      let(:engine) { Engine.new(epoch_interruption: true) }
+      let(:store) { Store.new(engine) }
+      let(:instance) do
+        Instance.new(store, Module.new(engine, <<~WAT))
+          (module
+            (func (export "loop_forever") (loop br 0))
+            (func (export "noop")))
+        WAT
+      end
+
+      it "raises Timeout when the call exceeds the timeout" do
+        func = instance.export("loop_forever").to_func
+        expect { func.call(timeout: 0.05) }.to raise_error(Timeout, /exceeded its timeout/)
+      end
+
+      it "returns normally within the timeout" do
+        expect(instance.export("noop").to_func.call(timeout: 1)).to be_nil
+      end
+
+      it "accepts a nil timeout" do
+        store.set_epoch_deadline(1)
+        expect(instance.export("noop").to_func.call(timeout: nil)).to be_nil
+      end
+
+      it "keeps the store's epoch deadline for later calls" do
+        store.set_epoch_deadline(2)
+        expect(instance.export("noop").to_func.call(timeout: 1)).to be_nil
+
+        engine.increment_epoch
+        expect(instance.export("noop").to_func.call).to be_nil
+        engine.increment_epoch
+        expect { instance.export("noop").to_func.call }.to raise_error(Trap)
+      end
+
+      it "times out calls past the store's epoch deadline" do
+        store.set_epoch_deadline(0)
+        expect { instance.export("loop_forever").to_func.call(timeout: 0.05) }
+          .to raise_error(Timeout, /exceeded its timeout/)
+      end
+
+      it "rejects negative timeouts" do
+        expect { instance.export("noop").to_func.call(timeout: -1) }.to raise_error(ArgumentError)
+      end
+
+      it "requires epoch interruption" do
+        func = Func.new(Store.new(Engine.new), [], []) {}
+        expect { func.call(timeout: 1) }.to raise_error(Wasmtime::Error, /epoch_interruption: true/)
+      end
+    end
+
+    describe ".call with results" do
+      let(:instance) do
+        Instance.new(store, Module.new(engine, <<~WAT))
+          (module
+            (func (export "none"))
+            (func (export "one") (result i32) i32.const 1)
+            (func (export "divmod") (param i32 i32) (result i32 i32)
+              (i32.div_u (local.get 0) (local.get 1))
+              (i32.rem_u (local.get 0) (local.get 1))))
+        WAT
+      end
+
+      it "defaults to :auto" do
+        expect(instance.export("none").to_func.call(results: :auto)).to be_nil
+        expect(instance.export("one").to_func.call).to eq(1)
+        expect(instance.export("divmod").to_func.call(7, 2)).to eq([3, 1])
+      end
+
+      it "returns an array for any arity with :array" do
+        expect(instance.export("none").to_func.call(results: :array)).to eq([])
+        expect(instance.export("one").to_func.call(results: :array)).to eq([1])
+        expect(instance.export("divmod").to_func.call(7, 2, results: :array)).to eq([3, 1])
+      end
+
+      it "instantiates a class with the results" do
+        div_mod = Struct.new(:quotient, :remainder)
+        result = instance.export("divmod").to_func.call(7, 2, results: div_mod)
+
+        expect(result).to eq(div_mod.new(3, 1))
+        expect(result.remainder).to eq(1)
+      end
+
+      it "rejects unknown shapes" do
+        expect { instance.export("one").to_func.call(results: :hash) }
+          .to raise_error(ArgumentError, /invalid :results/)
+      end
+    end
+
+    describe "#call_batch" do
+      let(:instance) do
+        compile(<<~WAT)
+          (module
+            (func (export "add") (param i32 i32) (result i32)
+              (i32.add (local.get 0) (local.get 1)))
+            (func (export "swap") (param i32 i64) (result i64 i32)
+              (local.get 1) (local.get 0))
+            (func (export "div") (param i32) (result i32)
+              (i32.div_u (i32.const 10) (local.get 0))))
+        WAT
+      end
+
+      it "returns the results of each call" do
+        add = instance.export("add").to_func
+        expect(add.call_batch([[1, 2], [3, 4], [5, 6]])).to eq([3, 7, 11])
+      end
+
+      it "returns an empty array for an empty batch" do
+        expect(instance.export("add").to_func.call_batch([])).to eq([])
+      end
+
+      it "returns arrays for multiple results" do
+        expect(instance.export("swap").to_func.call_batch([[1, 2], [3, 4]])).to eq([[2, 1], [4, 3]])
+      end
+
+      it "returns nil for functions without results" do
+        calls = []
+        func = Func.new(store, [:i32], []) { |_, x| calls << x }
+        expect(func.call_batch([[1], [2]])).to eq([nil, nil])
+        expect(calls).to eq([1, 2])
+      end
+
+      it "validates all the arguments before calling" do
+        calls = []
+        func = Func.new(store, [:i32], []) { |_, x| calls << x }
+        expect { func.call_batch([[1], ["foo"]]) }.to raise_error(TypeError, /\(param at index 0\)/)
+        expect { func.call_batch([[1], []]) }.to raise_error(ArgumentError, /wrong number of arguments/)
+        expect { func.call_batch([1]) }.to raise_error(TypeError)
+        expect(calls).to be_empty
+      end
+
+      it "stops at the first trap" do
+        expect { instance.export("div").to_func.call_batch([[1], [0], [2]]) }
+          .to raise_error(Trap) { |trap| expect(trap.code).to eq(Trap::INTEGER_DIVISION_BY_ZERO) }
+      end
+    end
+
+    describe "#type" do
+      it "returns the function's FuncType" do
+        type = build_func([:i32, :externref], [:f64]) {}.type
+        expect(type).to be_instance_of(FuncType)
+        expect(type.params).to eq([:i32, :externref])
+        expect(type.results).to eq([:f64])
+      end
+
+      it "is aliased as #signature" do
+        func = build_func([:i64], []) {}
+        expect(func.signature.params).to eq([:i64])
+        expect(func.signature.results).to eq([])
+      end
     end
 
     describe "Caller" do
@@ -159,6 +473,44 @@ module Wasmtime
         expect { mem.read(0, 3) }.to raise_error(Wasmtime::Error, message)
         expect { f1_export.call }.to raise_error(Wasmtime::Error, message)
       end
+
+      it "reads and writes the exported memory" do
+        mod = Module.new(engine, <<~WAT)
+          (module
+            (import "" "" (func $log (param i32 i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 8) "hello")
+            (func (export "run")
+              (call $log (i32.const 8) (i32.const 5))))
+        WAT
+        logged = nil
+        log = Func.new(store, [:i32, :i32], []) do |caller, ptr, len|
+          logged = caller.read_utf8(ptr, len)
+          caller.write(ptr, "HE")
+          expect(caller.read(ptr, len)).to eq("HEllo")
+          expect(caller.memory).to be_instance_of(Memory)
+        end
+
+        Instance.new(store, mod, [log]).invoke("run")
+        expect(logged).to eq("hello")
+      end
+
+      it "looks up memories by name" do
+        mod = Module.new(engine, <<~WAT)
+          (module
+            (import "" "" (func))
+            (memory (export "mem") 2)
+            (func (export "f") (call 0)))
+        WAT
+        func = Func.new(store, [], []) do |caller|
+          expect(caller.memory("mem").size).to eq(2)
+          expect { caller.memory }.to raise_error(Wasmtime::Error, "no memory exported as `memory`")
+          expect { caller.memory("f") }.to raise_error(Wasmtime::Error, "export `f` is not a memory")
+          expect { caller.read(0, 1) }.to raise_error(Wasmtime::Error, "no memory exported as `memory`")
+        end
+
+        Instance.new(store, mod, [func]).invoke("f")
+      end
     end
 
     private
@@ -167,5 +519,19 @@ module Wasmtime
       store = Store.new(engine, Object.new)
       Func.new(store, params, results, &block)
     end
+
+    def compile_externref_table_module
+      compile(<<~WAT)
+        (module
+          (table (export "table") 1 externref)
+          (func (export "store") (param externref)
+            (table.set (i32.const 0) (local.get 0))))
+      WAT
+    end
+
+    def generate_new_objects
+      "hi" * 3
+      GC.start
+    end
   end
 end
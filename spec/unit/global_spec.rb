@@ -28,6 +28,26 @@ module Wasmtime
       end
     end
 
+    describe ".new" do
+      it "creates a const global by default" do
+        global = Global.new(store, :i32, 1)
+        expect(global).to be_const
+        expect(global.get).to eq(1)
+      end
+
+      it "creates a var global when mutable" do
+        global = Global.new(store, :i64, 1, mutable: true)
+        expect(global).to be_var
+        global.set(2)
+        expect(global.get).to eq(2)
+      end
+
+      it "raises on invalid Wasm type" do
+        expect { Global.new(store, :nope, 1) }
+          .to raise_error(ArgumentError, /invalid WebAssembly type/)
+      end
+    end
+
     describe "#type" do
       it "returns the Wasm type as symbol" do
         global = Global.const(store, :i32, 1)
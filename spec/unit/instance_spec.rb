@@ -25,6 +25,69 @@ module Wasmtime
         memory = Memory.new(store, min_size: 1)
         Wasmtime::Instance.new(store, mod, [memory])
       end
+
+      it "shares an imported memory between the host and instances" do
+        writer = Module.new(engine, <<~WAT)
+          (module
+            (import "" "mem" (memory 1))
+            (func (export "write") (param i32 i32)
+              (i32.store (local.get 0) (local.get 1))))
+        WAT
+        reader = Module.new(engine, <<~WAT)
+          (module
+            (import "" "mem" (memory 1))
+            (func (export "read") (param i32) (result i32)
+              (i32.load (local.get 0))))
+        WAT
+        memory = Memory.new(store, min_size: 1, max_size: 2)
+
+        Instance.new(store, writer, [memory]).invoke("write", 8, 42)
+        expect(Instance.new(store, reader, [memory]).invoke("read", 8)).to eq(42)
+        expect(memory.read_i32(8)).to eq(42)
+
+        memory.write_i32(16, 7)
+        expect(Instance.new(store, reader, [memory]).invoke("read", 16)).to eq(7)
+      end
+
+      it "rejects an imported memory with incompatible limits" do
+        mod = Module.new(engine, <<~WAT)
+          (module
+            (import "" "" (memory 2)))
+        WAT
+        memory = Memory.new(store, min_size: 1)
+
+        expect { Instance.new(store, mod, [memory]) }.to raise_error(Wasmtime::Error, /incompatible import type/)
+      end
+
+      context "with a Hash of imports" do
+        let(:mod) do
+          Module.new(engine, <<~WAT)
+            (module
+              (import "env" "memory" (memory 1))
+              (import "env" "log" (func $log (param i32)))
+              (import "math" "double" (func $double (param i32) (result i32)))
+              (func (export "run") (param i32)
+                (call $log (call $double (local.get 0)))))
+          WAT
+        end
+
+        it "wraps Procs in Funcs with the imported signature" do
+          logged = []
+          memory = Memory.new(store, min_size: 1)
+          instance = Instance.new(store, mod, {
+            "env" => {"memory" => memory, "log" => ->(_caller, value) { logged << value }},
+            "math" => {"double" => Func.new(store, [:i32], [:i32]) { |_caller, x| x * 2 }}
+          })
+
+          instance.invoke("run", 21)
+          expect(logged).to eq([42])
+        end
+
+        it "raises on missing imports" do
+          expect { Instance.new(store, mod, {"env" => {}}) }
+            .to raise_error(Wasmtime::Error, "missing import env::memory")
+        end
+      end
     end
 
     describe "#exports" do
@@ -44,6 +107,26 @@ module Wasmtime
         expect(instance.exports["hello"].to_func).to be_a(Func)
         expect(instance.exports["mem"].to_memory).to be_a(Memory)
       end
+
+      it "returns each memory of multi-memory modules" do
+        engine = Engine.new(wasm_multi_memory: true)
+        store = Store.new(engine)
+        instance = Instance.new(store, Module.new(engine, <<~WAT))
+          (module
+            (memory (export "small") 1)
+            (memory (export "large") 3)
+            (func (export "store_large") (param i32 i32)
+              (i32.store 1 (local.get 0) (local.get 1))))
+        WAT
+
+        exports = instance.exports
+        expect(exports["small"].to_memory.size).to eq(1)
+        expect(exports["large"].to_memory.size).to eq(3)
+
+        instance.invoke("store_large", 0, 42)
+        expect(exports["large"].to_memory.read_i32(0)).to eq(42)
+        expect(exports["small"].to_memory.read_i32(0)).to eq(0)
+      end
     end
 
     describe "export" do
@@ -54,9 +137,30 @@ module Wasmtime
         WAT
         expect(instance.export("f").to_func).to be_a(Func)
       end
+
+      it "returns nil for an unknown export" do
+        instance = compile("(module)")
+        expect(instance.export("nope")).to be_nil
+      end
     end
 
     describe "invoke" do
+      it "raises ExportNotFound listing the exported functions" do
+        instance = compile(<<~WAT)
+          (module
+            (memory (export "mem") 1)
+            (func (export "add"))
+            (func (export "sub")))
+        WAT
+
+        expect { instance.invoke("nope") }.to raise_error(ExportNotFound) do |error|
+          expect(error.message).to eq('function "nope" not found (exported functions: add, sub)')
+          expect(error.name).to eq("nope")
+          expect(error.available).to eq(["add", "sub"])
+        end
+        expect { instance.invoke("mem") }.to raise_error(ExportNotFound)
+      end
+
       it "returns nil when func has no return value" do
         instance = compile(<<~WAT)
           (module
@@ -39,6 +39,20 @@ module Wasmtime
       expect { linker.instantiate(store, mod) }.not_to raise_error
     end
 
+    it "#define_unknown_imports_as_default_values" do
+      mod = Module.new(engine, <<~WAT)
+        (module
+          (import "" "" (func $f (result i32 i64)))
+          (func (export "run") (result i32 i64)
+            call $f))
+      WAT
+      linker = new_linker
+      expect { linker.instantiate(store, mod) }.to raise_error(Wasmtime::Error, /unknown import/)
+
+      linker.define_unknown_imports_as_default_values(mod)
+      expect(linker.instantiate(store, mod).invoke("run")).to eq([0, 0])
+    end
+
     describe "#define" do
       it "accepts memory" do
         linker = new_linker
@@ -91,6 +105,35 @@ module Wasmtime
         func = linker.get(Store.new(engine), "", "").to_func
         expect { func.call }.to change { calls }.by(1)
       end
+
+      it "is reused across instantiations, with the block kept alive by the linker" do
+        linker = new_linker
+        linker.func_new("host", "double", [:i32], [:i32]) { |_caller, x| x * 2 }
+        mod = Module.new(engine, <<~WAT)
+          (module
+            (import "host" "double" (func $double (param i32) (result i32)))
+            (func (export "run") (param i32) (result i32)
+              (call $double (local.get 0))))
+        WAT
+        GC.start
+
+        results = 3.times.map do |i|
+          linker.instantiate(Store.new(engine), mod).invoke("run", i)
+        end
+        expect(results).to eq([0, 2, 4])
+      end
+
+      it "retains its blocks once per store, however many times it instantiates" do
+        linker = new_linker
+        linker.func_new("host", "noop", [], []) {}
+        mod = Module.new(engine, '(module (import "host" "noop" (func)))')
+        store = Store.new(engine)
+
+        linker.instantiate(store, mod)
+        count = store.retained_count
+        10.times { linker.instantiate(store, mod) }
+        expect(store.retained_count).to eq(count)
+      end
     end
 
     describe "#get" do
@@ -108,11 +151,44 @@ module Wasmtime
       end
     end
 
-    it "#instance" do
-      linker = new_linker
-      mod = Module.new(engine, '(module (func (export "fn")))')
-      linker.instance(store, "mod", Wasmtime::Instance.new(store, mod))
-      expect(linker.get(store, "mod", "fn")).to be_truthy
+    describe "#instance" do
+      it "defines the instance's exports" do
+        linker = new_linker
+        mod = Module.new(engine, '(module (func (export "fn")))')
+        linker.instance(store, "mod", Wasmtime::Instance.new(store, mod))
+        expect(linker.get(store, "mod", "fn")).to be_truthy
+      end
+
+      it "chains modules, sharing the instance's state" do
+        libc = Module.new(engine, <<~WAT)
+          (module
+            (memory (export "memory") 1)
+            (func (export "store") (param i32 i32)
+              (i32.store (local.get 0) (local.get 1))))
+        WAT
+        app = Module.new(engine, <<~WAT)
+          (module
+            (import "libc" "memory" (memory 1))
+            (import "libc" "store" (func $store (param i32 i32)))
+            (func (export "run") (result i32)
+              (call $store (i32.const 8) (i32.const 42))
+              (i32.load (i32.const 8))))
+        WAT
+        linker = new_linker
+        libc_instance = linker.instantiate(store, libc)
+        linker.instance(store, "libc", libc_instance)
+
+        expect(linker.instantiate(store, app).invoke("run")).to eq(42)
+        expect(libc_instance.export("memory").to_memory.read(8, 1)).to eq("*")
+      end
+
+      it "raises for instances of other stores" do
+        mod = Module.new(engine, '(module (func (export "fn")))')
+        instance = Wasmtime::Instance.new(Store.new(engine), mod)
+
+        expect { new_linker.instance(store, "mod", instance) }
+          .to raise_error(Wasmtime::Error, /instance belongs to a different store/)
+      end
     end
 
     it "#module" do
@@ -122,6 +198,57 @@ module Wasmtime
       expect(linker.get(store, "mod1", "fn1")).to be_truthy
     end
 
+    describe "#module" do
+      let(:user_code) do
+        Module.new(engine, <<~WAT)
+          (module
+            (import "runtime" "next" (func $next (result i32)))
+            (func (export "run") (result i32)
+              call $next
+              drop
+              call $next))
+        WAT
+      end
+
+      it "initializes reactors once, sharing their state" do
+        runtime = Module.new(engine, <<~WAT)
+          (module
+            (global $count (mut i32) (i32.const 0))
+            (func (export "_initialize")
+              (global.set $count (i32.const 10)))
+            (func (export "next") (result i32)
+              (global.set $count (i32.add (global.get $count) (i32.const 1)))
+              (global.get $count)))
+        WAT
+        linker = new_linker
+        linker.module(store, "runtime", runtime)
+
+        expect(linker.instantiate(store, user_code).invoke("run")).to eq(12)
+        expect(linker.instantiate(store, user_code).invoke("run")).to eq(14)
+      end
+
+      it "instantiates commands on each call" do
+        command = Module.new(engine, <<~WAT)
+          (module
+            (global $count (mut i32) (i32.const 0))
+            (func (export "_start"))
+            (func (export "next") (result i32)
+              (global.set $count (i32.add (global.get $count) (i32.const 1)))
+              (global.get $count)))
+        WAT
+        linker = new_linker
+        linker.module(store, "runtime", command)
+
+        expect(linker.instantiate(store, user_code).invoke("run")).to eq(1)
+      end
+
+      it "raises traps of reactors' initialization" do
+        runtime = Module.new(engine, '(module (func (export "_initialize") unreachable))')
+
+        expect { new_linker.module(store, "runtime", runtime) }.to raise_error(Trap)
+      end
+    end
+
     it "#alias" do
       linker = new_linker
       store = Store.new(engine)
@@ -145,6 +272,53 @@ module Wasmtime
       expect(instance).to be_instance_of(Instance)
     end
 
+    describe "#instantiate_pre" do
+      it "returns an InstancePre" do
+        linker = new_linker
+        linker.func_new("", "", [], []) {}
+        expect(linker.instantiate_pre(func_reexport_module)).to be_instance_of(InstancePre)
+      end
+
+      it "raises on unknown imports" do
+        expect { new_linker.instantiate_pre(func_reexport_module) }
+          .to raise_error(Wasmtime::Error, /unknown import/)
+      end
+
+      it "instantiates in multiple stores" do
+        calls = 0
+        linker = new_linker
+        linker.func_new("", "", [], []) { calls += 1 }
+        instance_pre = linker.instantiate_pre(func_reexport_module)
+
+        2.times do
+          instance = instance_pre.instantiate(Store.new(engine))
+          expect(instance).to be_instance_of(Instance)
+          instance.invoke("f")
+        end
+        expect(calls).to eq(2)
+      end
+
+      it "keeps the linker's procs alive" do
+        calls = 0
+        linker = new_linker
+        linker.func_new("", "", [], []) { calls += 1 }
+        instance_pre = linker.instantiate_pre(func_reexport_module)
+        linker = nil # rubocop:disable Lint/UselessAssignment
+        GC.start
+
+        instance_pre.instantiate(Store.new(engine)).invoke("f")
+        expect(calls).to eq(1)
+      end
+
+      it "requires a WASI context when the linker has WASI" do
+        linker = Linker.new(engine, wasi: true)
+        instance_pre = linker.instantiate_pre(Module.new(engine, "(module)"))
+
+        expect { instance_pre.instantiate(Store.new(engine)) }
+          .to raise_error(Wasmtime::Error, /Store is missing WASI configuration/)
+      end
+    end
+
     it "#get_default" do
       linker = new_linker
       store = Store.new(engine)
@@ -0,0 +1,44 @@
+require "spec_helper"
+require "logger"
+require "stringio"
+
+module Wasmtime
+  RSpec.describe ".logger" do
+    let(:output) { StringIO.new }
+
+    after { Wasmtime.logger = nil }
+
+    it "is nil by default" do
+      expect(Wasmtime.logger).to be_nil
+    end
+
+    it "can be set" do
+      logger = Logger.new(output)
+      Wasmtime.logger = logger
+
+      expect(Wasmtime.logger).to be(logger)
+    end
+
+    it "receives compilation records" do
+      Wasmtime.logger = Logger.new(output, level: :debug)
+      Module.new(Engine.new, '(module (func (export "f") (result i32) i32.const 1))')
+
+      expect(output.string).not_to be_empty
+    end
+
+    it "only receives records of the logger's level" do
+      Wasmtime.logger = Logger.new(output, level: :error)
+      Module.new(Engine.new, '(module (func (export "f") (result i32) i32.const 1))')
+
+      expect(output.string).to be_empty
+    end
+
+    it "stops receiving records when set to nil" do
+      Wasmtime.logger = Logger.new(output, level: :debug)
+      Wasmtime.logger = nil
+      Module.new(Engine.new, '(module (func (export "f") (result i32) i32.const 1))')
+
+      expect(output.string).to be_empty
+    end
+  end
+end
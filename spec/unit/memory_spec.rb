@@ -36,6 +36,53 @@ module Wasmtime
       end
     end
 
+    describe "memory64" do
+      let(:engine) { Engine.new(wasm_memory64: true) }
+      let(:store) { Store.new(engine) }
+
+      it "creates 64-bit memories" do
+        mem = Memory.new(store, min_size: 1, max_size: 2**33, memory64: true)
+        expect(mem.type).to be_memory64
+        expect(mem.max_size).to eq(2**33)
+      end
+
+      it "rejects limits too large for 32-bit memories" do
+        expect { Memory.new(store, min_size: 1, max_size: 2**33) }
+          .to raise_error(Wasmtime::Error, /too large for a 32-bit memory/)
+      end
+
+      it "is accessible from 64-bit guests" do
+        mem = Memory.new(store, min_size: 1, memory64: true)
+        instance = Instance.new(store, Module.new(engine, <<~WAT), [mem])
+          (module
+            (import "" "" (memory i64 1))
+            (func (export "load") (param i64) (result i32)
+              (i32.load (local.get 0))))
+        WAT
+
+        mem.grow(1)
+        mem.write_i32(0x10000, 42)
+        expect(instance.invoke("load", 0x10000)).to eq(42)
+      end
+    end
+
+    describe "#type" do
+      it "returns the memory type with its limits" do
+        type = Memory.new(store, min_size: 1, max_size: 2).type
+        expect(type).to be_instance_of(MemoryType)
+        expect(type.min_size).to eq(1)
+        expect(type.max_size).to eq(2)
+        expect(type).not_to be_shared
+        expect(type).not_to be_memory64
+      end
+
+      it "reflects growth" do
+        mem = Memory.new(store, min_size: 1)
+        mem.grow(2)
+        expect(mem.type.min_size).to eq(3)
+      end
+    end
+
     describe "#grow" do
       it "returns the previous size" do
         mem = Memory.new(store, min_size: 2)
@@ -77,6 +124,37 @@ module Wasmtime
       end
     end
 
+    describe "typed accessors" do
+      [
+        [:i32, -42, "l<"],
+        [:u32, 2**32 - 1, "L<"],
+        [:i64, -(2**40), "q<"],
+        [:u64, 2**64 - 1, "Q<"],
+        [:f32, 1.5, "e"],
+        [:f64, -3.25, "E"]
+      ].each do |type, value, pack_format|
+        it "reads and writes #{type} as little-endian" do
+          mem = Memory.new(store, min_size: 1)
+          expect(mem.public_send(:"write_#{type}", 8, value)).to be_nil
+          expect(mem.public_send(:"read_#{type}", 8)).to eq(value)
+          expect(mem.read(8, [value].pack(pack_format).bytesize)).to eq([value].pack(pack_format))
+        end
+
+        it "raises on out of bounds #{type} access" do
+          mem = Memory.new(store, min_size: 1)
+          expect { mem.public_send(:"read_#{type}", 64 * 2**10 - 1) }
+            .to raise_error(Wasmtime::Error, "out of bounds memory access")
+          expect { mem.public_send(:"write_#{type}", 64 * 2**10 - 1, value) }
+            .to raise_error(Wasmtime::Error, "out of bounds memory access")
+        end
+      end
+
+      it "rejects values that don't fit the type" do
+        mem = Memory.new(store, min_size: 1)
+        expect { mem.write_u32(0, -1) }.to raise_error(RangeError)
+      end
+    end
+
     describe "#read_utf8" do
       it "reads a UTF-8 string" do
         mem = Memory.new(store, min_size: 1)
@@ -93,6 +171,134 @@ module Wasmtime
 
         expect { mem.read_utf8(0, 3) }.to raise_error(Wasmtime::Error, /invalid utf-8/)
       end
+
+      it "replaces invalid utf8 with invalid: :replace" do
+        mem = Memory.new(store, min_size: 1)
+        mem.write(0, [0x66, 0x80, 0x6f].pack("C*"))
+
+        expect(mem.read_utf8(0, 3, invalid: :replace)).to eq("f\uFFFDo")
+      end
+
+      it "rejects unknown invalid options" do
+        mem = Memory.new(store, min_size: 1)
+        expect { mem.read_utf8(0, 3, invalid: :ignore) }.to raise_error(ArgumentError, /invalid :invalid/)
+      end
+    end
+
+    describe "#read_utf16le" do
+      it "reads a UTF-16LE string as UTF-8" do
+        mem = Memory.new(store, min_size: 1)
+        mem.write(0, "héllo 🌍".encode("UTF-16LE").b)
+
+        str = mem.read_utf16le(0, 16)
+        expect(str).to eq("héllo 🌍")
+        expect(str.encoding).to eq(Encoding::UTF_8)
+      end
+
+      it "raises on unpaired surrogates" do
+        mem = Memory.new(store, min_size: 1)
+        mem.write(0, [0xD800, 0x41].pack("v*"))
+
+        expect { mem.read_utf16le(0, 4) }.to raise_error(Wasmtime::Error, /invalid utf-16/)
+      end
+
+      it "replaces unpaired surrogates with invalid: :replace" do
+        mem = Memory.new(store, min_size: 1)
+        mem.write(0, [0xD800, 0x41].pack("v*"))
+
+        expect(mem.read_utf16le(0, 4, invalid: :replace)).to eq("\uFFFDA")
+      end
+
+      it "raises on odd sizes" do
+        mem = Memory.new(store, min_size: 1)
+        expect { mem.read_utf16le(0, 3) }.to raise_error(Wasmtime::Error, /must be even/)
+      end
+    end
+
+    describe "#read_cstring" do
+      it "reads up to the NUL terminator" do
+        mem = Memory.new(store, min_size: 1)
+        mem.write(0, "foo\0bar")
+
+        str = mem.read_cstring(0, max: 16)
+        expect(str).to eq("foo")
+        expect(str.encoding).to eq(Encoding::UTF_8)
+      end
+
+      it "raises when there's no NUL within max bytes" do
+        mem = Memory.new(store, min_size: 1)
+        mem.write(0, "foobar\0")
+
+        expect { mem.read_cstring(0, max: 3) }.to raise_error(Wasmtime::Error, /no NUL terminator within 3 bytes/)
+      end
+
+      it "raises when reaching the end of memory" do
+        mem = Memory.new(store, min_size: 1)
+        mem.write(mem.data_size - 3, "foo")
+
+        expect { mem.read_cstring(mem.data_size - 3, max: 16) }
+          .to raise_error(Wasmtime::Error, /out of bounds memory access/)
+      end
+
+      it "replaces invalid utf8 with invalid: :replace" do
+        mem = Memory.new(store, min_size: 1)
+        mem.write(0, [0x80, 0x00].pack("C*"))
+
+        expect(mem.read_cstring(0, max: 2, invalid: :replace)).to eq("\uFFFD")
+      end
+    end
+
+    if defined?(IO::Buffer)
+      describe "IO::Buffer interop" do
+        it "exposes the memory as a read-only IO::Buffer" do
+          mem = Memory.new(store, min_size: 1)
+          mem.write(0, "foo")
+          buffer = mem.to_io_buffer
+
+          expect(buffer).to be_readonly
+          expect(buffer.size).to eq(mem.data_size)
+          expect(buffer.get_string(0, 3)).to eq("foo")
+        end
+
+        it "writes from an IO::Buffer" do
+          mem = Memory.new(store, min_size: 1)
+          mem.write_from(IO::Buffer.for("foo"), 2)
+
+          expect(mem.read(2, 3)).to eq("foo")
+        end
+
+        it "writes from a view of the same memory" do
+          mem = Memory.new(store, min_size: 1)
+          mem.write(0, "foo")
+          mem.write_from(mem.to_io_buffer.slice(0, 3), 1)
+
+          expect(mem.read(0, 4)).to eq("ffoo")
+        end
+
+        it "rejects objects that are not an IO::Buffer" do
+          mem = Memory.new(store, min_size: 1)
+
+          expect { mem.write_from("foo", 0) }.to raise_error(TypeError)
+        end
+
+        it "reads into an IO::Buffer" do
+          mem = Memory.new(store, min_size: 1)
+          mem.write(2, "foo")
+          buffer = IO::Buffer.new(8)
+
+          expect(mem.read_into(buffer, 2, 3)).to eq(3)
+          expect(buffer.get_string(0, 3)).to eq("foo")
+        end
+
+        it "errors when out of bounds" do
+          mem = Memory.new(store, min_size: 1)
+
+          expect { mem.write_from(IO::Buffer.for("foo"), 64 * 2**10 - 1) }
+            .to raise_error(Wasmtime::Error, "out of bounds memory access")
+          expect { mem.read_into(IO::Buffer.new(8), 64 * 2**10 - 1, 3) }
+            .to raise_error(Wasmtime::Error, "out of bounds memory access")
+        end
+      end
     end
 
     describe "#unsafe_slice" do
@@ -119,6 +325,21 @@ module Wasmtime
         end
       end
 
+      if defined?(IO::Buffer)
+        it "exposes a read-only IO::Buffer" do
+          mem = Memory.new(store, min_size: 1)
+          mem.write(0, "foo")
+          buffer = mem.read_unsafe_slice(0, 3).to_io_buffer
+
+          expect(buffer).to be_a(IO::Buffer)
+          expect(buffer).to be_readonly
+          expect(buffer.get_string).to eq("foo")
+
+          mem.write(0, "bar")
+          expect(buffer.get_string).to eq("bar")
+        end
+      end
+
       it "invalidates the size when the memory is resized" do
         mem = Memory.new(store, min_size: 1)
         mem.write(0, "foo")
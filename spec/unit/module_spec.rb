@@ -28,6 +28,162 @@ module Wasmtime
       end
     end
 
+    describe "#custom_sections" do
+      let(:wat) do
+        <<~WAT
+          (module $app
+            (@custom "version" "1.2.3")
+            (@custom "other" "x")
+            (@custom "version" "\00\ff"))
+        WAT
+      end
+      let(:mod) { Module.new(engine, wat, keep_custom_sections: true) }
+
+      it "returns the contents of the sections with the given name" do
+        sections = mod.custom_sections("version")
+        expect(sections).to eq(["1.2.3", "\x00\xFF".b])
+        expect(sections.map(&:encoding)).to all(eq(Encoding::BINARY))
+        expect(mod.custom_sections("nope")).to eq([])
+      end
+
+      it "only keeps the name section by default" do
+        mod = Module.new(engine, wat)
+        expect(mod.custom_sections("version")).to eq([])
+        expect(mod.custom_sections("name")).not_to be_empty
+      end
+
+      it "keeps the sections of modules compiled asynchronously" do
+        mod = Module.compile_async(engine, wat, keep_custom_sections: true).value
+        expect(mod.custom_sections("other")).to eq(["x"])
+      end
+
+      it "reads sections of modules loaded from files" do
+        mod = Module.from_file(engine, "spec/fixtures/empty.wat", keep_custom_sections: true)
+        expect(mod.custom_sections("version")).to eq([])
+      end
+
+      it "raises for deserialized modules" do
+        expect { Module.deserialize(engine, mod.serialize).custom_sections("version") }
+          .to raise_error(Wasmtime::Error, /not available on deserialized modules/)
+      end
+    end
+
+    describe "#name" do
+      it "returns the module's name" do
+        expect(Module.new(engine, "(module $app)").name).to eq("app")
+      end
+
+      it "returns nil for unnamed modules" do
+        expect(Module.new(engine, "(module)").name).to be_nil
+      end
+
+      it "is kept by deserialized modules" do
+        expect(Module.deserialize(engine, Module.new(engine, "(module $app)").serialize).name).to eq("app")
+      end
+    end
+
+    describe "#function_name" do
+      let(:mod) do
+        Module.new(engine, <<~WAT)
+          (module
+            (import "env" "log" (func $log))
+            (func $add (export "add"))
+            (func))
+        WAT
+      end
+
+      it "returns the names of imported and defined functions" do
+        expect(mod.function_name(0)).to eq("log")
+        expect(mod.function_name(1)).to eq("add")
+      end
+
+      it "returns nil for unnamed or unknown functions" do
+        expect(mod.function_name(2)).to be_nil
+        expect(mod.function_name(100)).to be_nil
+      end
+
+      it "raises for deserialized modules" do
+        expect { Module.deserialize(engine, mod.serialize).function_name(0) }
+          .to raise_error(Wasmtime::Error, /not available on deserialized modules/)
+      end
+    end
+
+    describe "#start_function?" do
+      it "returns whether the module has a start function" do
+        expect(Module.new(engine, "(module (func $init) (start $init))").start_function?).to be(true)
+        expect(Module.new(engine, "(module (func $init))").start_function?).to be(false)
+      end
+
+      it "raises for deserialized modules" do
+        expect { Module.deserialize(engine, Module.new(engine, "(module)").serialize).start_function? }
+          .to raise_error(Wasmtime::Error, /not available on deserialized modules/)
+      end
+    end
+
+    describe ".compile_async" do
+      it "returns a ModuleCompilation" do
+        expect(Module.compile_async(engine, "(module)")).to be_instance_of(ModuleCompilation)
+      end
+
+      it "compiles the module in the background" do
+        compilation = Module.compile_async(engine, '(module (func (export "f") (result i32) i32.const 1))')
+        mod = compilation.value
+
+        expect(compilation).to be_done
+        expect(Instance.new(store, mod).invoke("f")).to eq(1)
+      end
+
+      it "keeps the module's custom sections" do
+        mod = Module.compile_async(engine, '(module (@custom "version" "1.2.3"))').value
+        expect(mod.custom_sections("version")).to eq(["1.2.3"])
+      end
+
+      it "can be waited for many times" do
+        compilation = Module.compile_async(engine, "(module)")
+        expect(compilation.value).to be_instance_of(Module)
+        expect(compilation.value).to be_instance_of(Module)
+      end
+
+      it "raises compile errors when waited for" do
+        compilation = Module.compile_async(engine, "(module")
+        expect { compilation.value }.to raise_error(CompileError, /Could not build module/)
+        expect { compilation.value }.to raise_error(CompileError)
+      end
+    end
+
+    describe ".validate" do
+      it "returns true for valid modules" do
+        expect(Module.validate(engine, "(module)")).to be(true)
+        expect(Module.validate(engine, Wasmtime.wat2wasm("(module)"))).to be(true)
+      end
+
+      it "raises ValidationError with the offset of invalid items" do
+        wasm = Wasmtime.wat2wasm(<<~WAT)
+          (module
+            (func (result i32)
+              i64.const 1))
+        WAT
+
+        expect { Module.validate(engine, wasm) }.to raise_error(ValidationError) do |error|
+          expect(error.message).to include("type mismatch")
+          expect(error.message).not_to include("at offset")
+          expect(error.offset).to be_an(Integer)
+        end
+      end
+
+      it "raises ValidationError for invalid WAT" do
+        expect { Module.validate(engine, "(module") }.to raise_error(ValidationError) do |error|
+          expect(error.offset).to be_nil
+        end
+      end
+
+      it "validates against the engine's features" do
+        wasm = "(module (memory i64 1))"
+        expect { Module.validate(engine, wasm) }.to raise_error(ValidationError, /memory64/)
+        expect(Module.validate(Engine.new(wasm_memory64: true), wasm)).to be(true)
+      end
+    end
+
     describe ".deserialize_file" do
       include_context(:tmpdir)
       let(:tmpdir) { Dir.mktmpdir }
@@ -86,5 +242,105 @@ module Wasmtime
         expect(mod).to be_a(Wasmtime::Module)
       end
     end
+
+    describe "#imports" do
+      let(:mod) do
+        Module.new(engine, <<~WAT)
+          (module
+            (import "env" "f" (func (param i32 i64) (result f32)))
+            (import "env" "mem" (memory 1 2))
+            (import "env" "tbl" (table 1 funcref))
+            (import "env" "g" (global (mut i64))))
+        WAT
+      end
+
+      it "returns the module's imports" do
+        imports = mod.imports
+
+        expect(imports.map { |i| i.values_at("module", "name") })
+          .to eq([["env", "f"], ["env", "mem"], ["env", "tbl"], ["env", "g"]])
+      end
+
+      it "describes func imports" do
+        type = mod.imports[0]["type"]
+
+        expect(type).to be_instance_of(FuncType)
+        expect(type.params).to eq([:i32, :i64])
+        expect(type.results).to eq([:f32])
+      end
+
+      it "describes memory imports" do
+        type = mod.imports[1]["type"]
+
+        expect(type).to be_instance_of(MemoryType)
+        expect(type.min_size).to eq(1)
+        expect(type.max_size).to eq(2)
+        expect(type).not_to be_shared
+        expect(type).not_to be_memory64
+      end
+
+      it "describes table imports" do
+        type = mod.imports[2]["type"]
+
+        expect(type).to be_instance_of(TableType)
+        expect(type.type).to eq(:funcref)
+        expect(type.min_size).to eq(1)
+        expect(type.max_size).to be_nil
+      end
+
+      it "describes global imports" do
+        type = mod.imports[3]["type"]
+
+        expect(type).to be_instance_of(GlobalType)
+        expect(type.type).to eq(:i64)
+        expect(type).to be_var
+        expect(type).not_to be_const
+      end
+    end
+
+    describe "#resources_required" do
+      it "returns the module's defined memories and tables" do
+        mod = Module.new(engine, <<~WAT)
+          (module
+            (import "" "table" (table 100 funcref))
+            (memory 5)
+            (table 10 funcref)
+            (table 4 externref))
+        WAT
+
+        expect(mod.resources_required).to eq(
+          "num_memories" => 1,
+          "max_initial_memory_size" => 5,
+          "num_tables" => 2,
+          "max_initial_table_size" => 10
+        )
+      end
+
+      it "returns nil sizes for modules without memories nor tables" do
+        expect(Module.new(engine, "(module)").resources_required).to eq(
+          "num_memories" => 0,
+          "max_initial_memory_size" => nil,
+          "num_tables" => 0,
+          "max_initial_table_size" => nil
+        )
+      end
+    end
+
+    describe "#exports" do
+      it "returns the module's exports" do
+        mod = Module.new(engine, <<~WAT)
+          (module
+            (func (export "f") (param externref))
+            (memory (export "mem") 1)
+            (global (export "g") i32 (i32.const 1)))
+        WAT
+
+        exports = mod.exports
+        expect(exports.map { |e| e["name"] }).to eq(["f", "mem", "g"])
+        expect(exports.map { |e| e["type"].class }).to eq([FuncType, MemoryType, GlobalType])
+        expect(exports[0]["type"].params).to eq([:externref])
+        expect(exports[2]["type"]).to be_const
+      end
+    end
   end
 end
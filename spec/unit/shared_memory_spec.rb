@@ -0,0 +1,117 @@
+require "spec_helper"
+
+module Wasmtime
+  RSpec.describe SharedMemory do
+    let(:engine) { Engine.new(wasm_threads: true) }
+    let(:mem) { SharedMemory.new(engine, min_size: 1, max_size: 2) }
+
+    describe ".new" do
+      it "creates a shared memory" do
+        expect(mem).to be_instance_of(Wasmtime::SharedMemory)
+        expect(mem.min_size).to eq(1)
+        expect(mem.max_size).to eq(2)
+        expect(mem.size).to eq(1)
+        expect(mem.data_size).to eq(0x10000)
+      end
+
+      it "raises when threads are not enabled" do
+        expect { SharedMemory.new(Engine.new, min_size: 1, max_size: 2) }
+          .to raise_error(Wasmtime::Error)
+      end
+    end
+
+    describe "#grow" do
+      it "returns the previous size" do
+        expect(mem.grow(1)).to eq(1)
+        expect(mem.size).to eq(2)
+      end
+
+      it "raises when growing past the maximum" do
+        expect { mem.grow(2) }.to raise_error(Wasmtime::Error)
+      end
+    end
+
+    describe "#read, #write" do
+      it "reads and writes strings" do
+        mem.write(0, "foo")
+        expect(mem.read(0, 3)).to eq("foo")
+        expect(mem.read(0, 3).encoding).to eq(Encoding::ASCII_8BIT)
+      end
+
+      it "raises when out of bounds" do
+        expect { mem.read(0x10000, 1) }.to raise_error(Wasmtime::Error, "out of bounds memory access")
+        expect { mem.write(0xffff, "ab") }.to raise_error(Wasmtime::Error, "out of bounds memory access")
+      end
+    end
+
+    describe "atomics" do
+      it "loads, stores and adds 32-bit integers" do
+        mem.atomic_store_i32(4, 40)
+        expect(mem.atomic_add_i32(4, 2)).to eq(40)
+        expect(mem.atomic_load_i32(4)).to eq(42)
+      end
+
+      it "loads, stores and adds 64-bit integers" do
+        mem.atomic_store_i64(8, 2**40)
+        expect(mem.atomic_add_i64(8, 1)).to eq(2**40)
+        expect(mem.atomic_load_i64(8)).to eq(2**40 + 1)
+      end
+
+      it "raises on unaligned access" do
+        expect { mem.atomic_load_i32(2) }.to raise_error(Wasmtime::Error, "unaligned atomic access")
+        expect { mem.atomic_load_i64(4) }.to raise_error(Wasmtime::Error, "unaligned atomic access")
+      end
+
+      it "raises when out of bounds" do
+        expect { mem.atomic_load_i32(0x10000) }.to raise_error(Wasmtime::Error, "out of bounds memory access")
+      end
+
+      it "notifies no waiters" do
+        expect(mem.atomic_notify(0, 1)).to eq(0)
+      end
+    end
+
+    describe "as an import" do
+      let(:mod) do
+        Module.new(engine, <<~WAT)
+          (module
+            (memory (import "" "mem") 1 2 shared)
+            (func (export "load") (param i32) (result i32)
+              local.get 0
+              i32.atomic.load))
+        WAT
+      end
+
+      it "is shared with the instance" do
+        mem.atomic_store_i32(0, 42)
+        instance = Instance.new(Store.new(engine), mod, [mem])
+
+        expect(instance.invoke("load", 0)).to eq(42)
+      end
+
+      it "is shared across stores through a linker" do
+        linker = Linker.new(engine)
+        store = Store.new(engine)
+        linker.define(store, "", "mem", mem)
+        instance = linker.instantiate(store, mod)
+        mem.atomic_store_i32(4, 7)
+
+        expect(instance.invoke("load", 4)).to eq(7)
+        expect(Instance.new(Store.new(engine), mod, [mem]).invoke("load", 4)).to eq(7)
+      end
+    end
+
+    describe "as an export" do
+      it "is returned by Extern#to_shared_memory" do
+        mod = Module.new(engine, '(module (memory (export "mem") 1 2 shared))')
+        instance = Instance.new(Store.new(engine), mod)
+        export = instance.export("mem")
+
+        shared = export.to_shared_memory
+        expect(shared).to be_instance_of(Wasmtime::SharedMemory)
+        expect(shared.max_size).to eq(2)
+        expect { export.to_memory }.to raise_error(Wasmtime::ConversionError)
+      end
+    end
+  end
+end
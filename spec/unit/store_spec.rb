@@ -26,6 +26,21 @@ module Wasmtime
         expect(store.data.value).to eql({foo: "bar", baz: "qux"})
       end
 
+      it "can replace its data" do
+        store = Store.new(engine, :old)
+        data = Object.new
+        expect(store.data = data).to equal(data)
+        expect(store.data).to equal(data)
+      end
+
+      it "keeps replaced data alive" do
+        store = Store.new(engine)
+        store.data = +"replaced" * 4
+        GC.start(full_mark: true)
+        GC.compact
+        expect(store.data).to eq("replaced" * 4)
+      end
+
       context "limits" do
         [
           :memory_size,
@@ -119,5 +134,369 @@ module Wasmtime
         end
       end
     end
+
+    describe "#gc" do
+      let(:instance) do
+        compile(<<~WAT)
+          (module
+            (table $t (export "t") 1 externref)
+            (func (export "take") (param externref))
+            (func (export "keep") (param externref)
+              (table.set $t (i32.const 0) (local.get 0))))
+        WAT
+      end
+
+      it "releases externrefs no longer referenced by Wasm" do
+        baseline = store.retained_count
+        3.times { instance.invoke("take", Object.new) }
+        expect(store.retained_count).to eq(baseline + 3)
+
+        store.gc
+        expect(store.retained_count).to eq(baseline)
+      end
+
+      it "releases the blocks of funcs collected before being handed to Wasm" do
+        baseline = store.retained_count
+        define_funcs = -> { 10.times { Func.new(store, [], []) {} } }
+        define_funcs.call
+        expect(store.retained_count).to eq(baseline + 10)
+
+        GC.start
+        expect(store.retained_count).to be < baseline + 10
+      end
+
+      it "keeps the blocks of funcs handed to Wasm" do
+        table = Table.new(store, :funcref, nil, min_size: 1)
+        set_func = -> { table.set(0, Func.new(store, [], [:i32]) { 42 }) }
+        set_func.call
+        GC.start
+        store.gc
+
+        expect(table.get(0).call).to eq(42)
+      end
+
+      it "keeps externrefs referenced by Wasm" do
+        instance.invoke("keep", +"kept")
+        store.gc
+        GC.start
+
+        expect(store.retained_count).to be > 0
+        expect(instance.export("t").to_table.get(0)).to eq("kept")
+      end
+    end
+
+    describe "#set_limits" do
+      it "replaces the limits" do
+        store = Store.new(engine, limits: {memory_size: 150_000})
+        expect(store.set_limits(memory_size: 300_000)).to be_nil
+
+        mem = Memory.new(store, min_size: 1)
+        mem.grow(3)
+        expect { mem.grow(1) }.to raise_error(Wasmtime::Error, "failed to grow memory by `1`")
+      end
+
+      it "rejects non-numeric limits" do
+        expect { store.set_limits(memory_size: "bad") }.to raise_error(TypeError)
+      end
+
+      it "calls the block when a memory limit is hit" do
+        calls = []
+        store.set_limits(memory_size: 150_000) { |*args| calls << args }
+
+        mem = Memory.new(store, min_size: 1)
+        mem.grow(1)
+        expect(calls).to be_empty
+        expect { mem.grow(1) }.to raise_error(Wasmtime::Error)
+        expect(calls).to eq([[:memory, 2 * 0x10000, 3 * 0x10000]])
+      end
+
+      it "calls the block when a table limit is hit" do
+        calls = []
+        store.set_limits(table_elements: 1) { |*args| calls << args }
+
+        table = Table.new(store, :funcref, nil, min_size: 1)
+        expect { table.grow(1, nil) }.to raise_error(Wasmtime::Error)
+        expect(calls).to eq([[:table, 1, 2]])
+      end
+
+      it "calls the block when Wasm hits the limit" do
+        calls = []
+        store.set_limits(memory_size: 0x10000) { |*args| calls << args }
+        instance = compile(<<~WAT)
+          (module
+            (memory 1)
+            (func (export "grow") (result i32)
+              (memory.grow (i32.const 1))))
+        WAT
+
+        expect(instance.invoke("grow")).to eq(-1)
+        expect(calls).to eq([[:memory, 0x10000, 2 * 0x10000]])
+      end
+
+      it "propagates exceptions raised in the block" do
+        store.set_limits(memory_size: 0x10000) { raise "over limit" }
+        mem = Memory.new(store, min_size: 1)
+
+        expect { mem.grow(1) }.to raise_error(RuntimeError, "over limit")
+      end
+    end
+
+    describe "#on_memory_grow" do
+      it "is called with the current, desired and maximum sizes" do
+        calls = []
+        store.on_memory_grow { |*args| calls << args }
+
+        mem = Memory.new(store, min_size: 1, max_size: 3)
+        mem.grow(1)
+        expect(calls).to eq([[0, 0x10000, 3 * 0x10000], [0x10000, 2 * 0x10000, 3 * 0x10000]])
+      end
+
+      it "can veto the growth" do
+        mem = Memory.new(store, min_size: 1)
+        store.on_memory_grow { |_current, desired, _maximum| desired <= 2 * 0x10000 }
+
+        mem.grow(1)
+        expect { mem.grow(1) }.to raise_error(Wasmtime::Error, "failed to grow memory by `1`")
+        expect(mem.size).to eq(2)
+      end
+
+      it "makes Wasm's memory.grow return -1 when vetoed" do
+        instance = compile(<<~WAT)
+          (module
+            (memory 1)
+            (func (export "grow") (result i32)
+              (memory.grow (i32.const 1))))
+        WAT
+        store.on_memory_grow { false }
+
+        expect(instance.invoke("grow")).to eq(-1)
+      end
+
+      it "propagates exceptions raised in the block" do
+        mem = Memory.new(store, min_size: 1)
+        store.on_memory_grow { raise "no more memory" }
+
+        expect { mem.grow(1) }.to raise_error(RuntimeError, "no more memory")
+      end
+
+      it "is not called when a limit denies the growth" do
+        calls = []
+        store.set_limits(memory_size: 0x10000)
+        mem = Memory.new(store, min_size: 1)
+        store.on_memory_grow { |*args| calls << args }
+
+        expect { mem.grow(1) }.to raise_error(Wasmtime::Error)
+        expect(calls).to be_empty
+      end
+
+      it "can be removed" do
+        mem = Memory.new(store, min_size: 1)
+        store.on_memory_grow { false }
+        store.on_memory_grow
+
+        expect(mem.grow(1)).to eq(1)
+      end
+
+      it "is not supported in async engines" do
+        store = Store.new(Engine.new(async_support: true))
+
+        expect { store.on_memory_grow { true } }
+          .to raise_error(Wasmtime::Error, /not supported in engines with async_support: true/)
+      end
+    end
+
+    describe "#on_table_grow" do
+      it "can veto the growth" do
+        calls = []
+        table = Table.new(store, :funcref, nil, min_size: 1)
+        store.on_table_grow { |*args| calls << args && false }
+
+        expect { table.grow(1, nil) }.to raise_error(Wasmtime::Error)
+        expect(calls).to eq([[1, 2, nil]])
+      end
+    end
+
+    describe "#on_host_error" do
+      let(:mod) do
+        Module.new(engine, <<~WAT)
+          (module
+            (import "" "fail" (func $fail (param i32) (result i32)))
+            (import "" "log" (func $log))
+            (func (export "call_fail") (result i32) (call $fail (i32.const 1)))
+            (func (export "call_log") (call $log)))
+        WAT
+      end
+      let(:error_class) { Class.new(StandardError) }
+
+      def instance
+        fail = Func.new(store, [:i32], [:i32]) { |_caller, _arg| raise error_class, "failed" }
+        log = Func.new(store, [], []) { raise error_class, "failed" }
+        Instance.new(store, mod, [fail, log])
+      end
+
+      it "aborts the guest by default" do
+        expect { instance.invoke("call_fail") }.to raise_error(error_class, "failed")
+      end
+
+      it "converts exceptions to the results returned to the guest" do
+        calls = []
+        store.on_host_error do |error, results|
+          calls << [error.message, results]
+          -1
+        end
+
+        expect(instance.invoke("call_fail")).to eq(-1)
+        expect(instance.invoke("call_log")).to be_nil
+        expect(calls).to eq([["failed", [:i32]], ["failed", []]])
+      end
+
+      it "aborts the guest when the block raises" do
+        store.on_host_error { |error, _results| raise error }
+
+        expect { instance.invoke("call_fail") }.to raise_error(error_class, "failed")
+      end
+
+      it "validates the results of the block" do
+        store.on_host_error { |_error, _results| "nope" }
+
+        expect { instance.invoke("call_fail") }.to raise_error(ResultError)
+      end
+
+      it "doesn't convert exceptions other than StandardErrors" do
+        store.on_host_error { |_error, _results| -1 }
+        fail = Func.new(store, [:i32], [:i32]) { |_caller, _arg| raise NoMemoryError }
+        log = Func.new(store, [], []) {}
+
+        expect { Instance.new(store, mod, [fail, log]).invoke("call_fail") }.to raise_error(NoMemoryError)
+      end
+
+      it "can be removed" do
+        store.on_host_error { |_error, _results| -1 }
+        store.on_host_error
+
+        expect { instance.invoke("call_fail") }.to raise_error(error_class)
+      end
+    end
+
+    describe "#memory_consumed" do
+      it "counts the memories created in the store" do
+        expect(store.memory_consumed).to eq(0)
+
+        mem = Memory.new(store, min_size: 1)
+        expect(store.memory_consumed).to eq(0x10000)
+
+        mem.grow(2)
+        expect(store.memory_consumed).to eq(3 * 0x10000)
+      end
+
+      it "doesn't count denied growths" do
+        store.set_limits(memory_size: 0x10000)
+        mem = Memory.new(store, min_size: 1)
+
+        expect { mem.grow(1) }.to raise_error(Wasmtime::Error)
+        expect(store.memory_consumed).to eq(0x10000)
+      end
+    end
+
+    describe "#resource_usage" do
+      it "returns the resources used in the store" do
+        mod = Module.new(engine, "(module (memory 1) (table 4 funcref))")
+        Instance.new(store, mod)
+        Linker.new(engine).instantiate(store, mod)
+
+        expect(store.resource_usage).to eq(
+          "memory_bytes" => 2 * 0x10000,
+          "table_elements" => 8,
+          "instances" => 2
+        )
+      end
+    end
+
+    describe "#with_lock" do
+      let(:release) { Queue.new }
+
+      it "returns the block's result" do
+        expect(store.with_lock { 42 }).to eq(42)
+      end
+
+      it "is re-entrant" do
+        func = Func.new(store, [], [:i32]) { 1 }
+        expect(store.with_lock { store.with_lock { func.call } }).to eq(1)
+      end
+
+      it "makes threads wait for each other" do
+        holder = Thread.new { store.with_lock { release.pop } }
+        sleep 0.01 until holder.status == "sleep"
+        waiter = Thread.new { store.with_lock { :done } }
+
+        expect(waiter.join(0.05)).to be_nil
+        release << true
+        expect(waiter.value).to eq(:done)
+        holder.join
+      end
+
+      it "can interrupt a thread waiting for the lock" do
+        holder = Thread.new { store.with_lock { release.pop } }
+        sleep 0.01 until holder.status == "sleep"
+        waiter = Thread.new { store.with_lock { :done } }
+        sleep 0.01 until waiter.status == "sleep"
+
+        waiter.raise(Interrupt)
+        expect { waiter.join }.to raise_error(Interrupt)
+        release << true
+        holder.join
+        expect(store.with_lock { :free }).to eq(:free)
+      end
+
+      it "lets threads share a store" do
+        add = Func.new(store, [:i32, :i32], [:i32]) { |_, a, b| a + b }
+        threads = 4.times.map { |i| Thread.new { store.with_lock { add.call(i, 1) } } }
+
+        expect(threads.map(&:value)).to eq([1, 2, 3, 4])
+      end
+    end
+
+    describe "thread safety" do
+      let(:started) { Queue.new }
+      let(:release) { Queue.new }
+      let!(:caller_thread) do
+        func = Func.new(store, [], []) do
+          started << true
+          release.pop
+        end
+        Thread.new { func.call }.tap { started.pop }
+      end
+
+      after do
+        release << true
+        caller_thread.join
+      end
+
+      it "rejects calls from other threads while a call is running" do
+        func = Func.new(Store.new(engine), [], []) {}
+        other = Func.new(store, [], []) {}
+
+        expect { other.call }.to raise_error(Wasmtime::Error, /in use by another thread/)
+        expect { func.call }.not_to raise_error
+      end
+
+      it "rejects accessing the store from other threads while a call is running" do
+        expect { store.set_epoch_deadline(1) }.to raise_error(Wasmtime::Error, /in use by another thread/)
+        expect { store.gc }.to raise_error(Wasmtime::Error, /in use by another thread/)
+        expect { Memory.new(store, min_size: 1) }.to raise_error(Wasmtime::Error, /in use by another thread/)
+        expect { store.data = 1 }.to raise_error(Wasmtime::Error, /in use by another thread/)
+        expect { store.on_memory_grow { true } }.to raise_error(Wasmtime::Error, /in use by another thread/)
+        expect { store.resource_usage }.to raise_error(Wasmtime::Error, /in use by another thread/)
+        expect { store.retained_count }.to raise_error(Wasmtime::Error, /in use by another thread/)
+      end
+
+      it "lets threads wait for the call with #with_lock" do
+        waiter = Thread.new { store.with_lock { store.gc } }
+
+        expect(waiter.join(0.05)).to be_nil
+        release << true
+        expect(waiter.value).to be_nil
+      end
+    end
   end
 end
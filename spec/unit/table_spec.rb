@@ -102,6 +102,18 @@ module Wasmtime
         table = Table.new(store, :funcref, noop_func, min_size: 1)
         expect { table.set(0, 1) }.to raise_error(TypeError)
       end
+
+      it "writes an externref" do
+        value = BasicObject.new
+        table = Table.new(store, :externref, nil, min_size: 1)
+        table.set(0, value)
+        expect(table.get(0)).to equal(value)
+      end
+
+      it "raises when out of bound" do
+        table = Table.new(store, :funcref, nil, min_size: 1)
+        expect { table.set(1, noop_func) }.to raise_error(Wasmtime::Error)
+      end
     end
 
     it "keeps externrefs alive" do
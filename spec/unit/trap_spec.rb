@@ -24,7 +24,32 @@ module Wasmtime
     end
 
     describe "#wasm_backtrace" do
-      it "returns an enumerable of trace entries" do
+      it "returns the backtrace frames" do
+        expect(trap.wasm_backtrace.size).to eq(1)
+
+        frame = trap.wasm_backtrace.first
+        expect(frame).to be_a(Trap::Frame)
+        expect(frame.module_name).to be_nil
+        expect(frame.func_name).to be_nil
+        expect(frame.func_index).to eq(0)
+        expect(frame.module_offset).to eq(0x1a)
+      end
+
+      it "includes module and function names" do
+        mod = Module.new(engine, <<~WAT)
+          (module $my_module
+            (func $inner unreachable)
+            (func $outer (export "run") call $inner))
+        WAT
+        instance = Instance.new(store, mod)
+
+        expect { instance.invoke("run") }.to raise_error(Trap) do |trap|
+          frames = trap.wasm_backtrace
+          expect(frames.map(&:module_name)).to eq(["my_module", "my_module"])
+          expect(frames.map(&:func_name)).to eq(["inner", "outer"])
+          expect(frames.map(&:func_index)).to eq([0, 1])
+          expect(frames).to all(be_a(Trap::Frame))
+        end
       end
     end
 
@@ -0,0 +1,72 @@
+require "spec_helper"
+
+module Wasmtime
+  RSpec.describe TypedFunc do
+    let(:add) do
+      compile(<<~WAT).export("add").to_func
+        (module
+          (func (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1))))
+      WAT
+    end
+
+    describe "Func#typed" do
+      it "returns a TypedFunc when the signature matches" do
+        typed = add.typed([:i32, :i32], [:i32])
+        expect(typed).to be_instance_of(TypedFunc)
+        expect(typed.params).to eq([:i32, :i32])
+        expect(typed.results).to eq([:i32])
+      end
+
+      it "raises when the signature doesn't match" do
+        expect { add.typed([:i64, :i32], [:i32]) }
+          .to raise_error(Wasmtime::Error, /type mismatch: expected params \[:i64, :i32\] and results \[:i32\], got params \[:i32, :i32\] and results \[:i32\]/)
+        expect { add.typed([:i32, :i32], []) }.to raise_error(Wasmtime::Error, /type mismatch/)
+      end
+
+      it "raises SignatureMismatch with the expected and actual signatures" do
+        expect { add.typed([:i64, :i32], [:i32]) }.to raise_error(Wasmtime::SignatureMismatch) do |error|
+          expect(error.expected).to eq(params: [:i64, :i32], results: [:i32])
+          expect(error.actual).to eq(params: [:i32, :i32], results: [:i32])
+        end
+      end
+
+      it "rejects unknown types" do
+        expect { add.typed([:nope], []) }.to raise_error(ArgumentError)
+      end
+    end
+
+    describe "#call" do
+      it "calls the function" do
+        expect(add.typed([:i32, :i32], [:i32]).call(1, 2)).to eq(3)
+      end
+
+      it "rejects mismatching arguments size" do
+        typed = add.typed([:i32, :i32], [:i32])
+        expect { typed.call(1) }.to raise_error(ArgumentError, /wrong number of arguments \(given 1, expected 2\)/)
+      end
+
+      it "rejects mismatching argument type" do
+        typed = add.typed([:i32, :i32], [:i32])
+        expect { typed.call(1, "foo") }.to raise_error(TypeError, /\(param at index 1\)/)
+      end
+
+      it "returns an array for multiple results" do
+        func = Func.new(store, [:i64], [:i64, :f64]) { |_, x| [x, x.to_f] }
+        expect(func.typed([:i64], [:i64, :f64]).call(2)).to eq([2, 2.0])
+      end
+
+      it "calls functions taking references" do
+        func = Func.new(store, [:externref], [:externref]) { |_, ref| ref }
+        object = Object.new
+        expect(func.typed([:externref], [:externref]).call(object)).to be(object)
+      end
+    end
+
+    describe "#call_batch" do
+      it "calls the function once per arguments" do
+        expect(add.typed([:i32, :i32], [:i32]).call_batch([[1, 2], [3, 4]])).to eq([3, 7])
+      end
+    end
+  end
+end
@@ -0,0 +1,77 @@
+require "spec_helper"
+require "json"
+require "stringio"
+
+module Wasmtime
+  RSpec.describe Wasi::Command do
+    include_context(:tmpdir)
+
+    let(:wasi_module) { Module.from_file(engine, "spec/fixtures/wasi-debug.wasm") }
+
+    it "runs commands, capturing their output" do
+      result = Wasi::Command.run(
+        engine,
+        wasi_module,
+        argv: ["wasi-debug", "--flag"],
+        env: {"FOO" => "bar"},
+        stdin: "hello"
+      )
+
+      expect(result.status).to eq(0)
+      expect(result).to be_success
+      wasi = JSON.parse(result.stdout).fetch("wasi")
+      expect(wasi.fetch("args")).to eq(["wasi-debug", "--flag"])
+      expect(wasi.fetch("env").to_h).to eq("FOO" => "bar")
+      expect(wasi.fetch("stdin")).to eq("hello")
+      expect(JSON.parse(result.stderr).fetch("name")).to eq("stderr")
+    end
+
+    it "reads stdin from IOs and copies the output to IOs" do
+      stdout = StringIO.new
+      stderr = StringIO.new
+
+      result = Wasi::Command.run(engine, wasi_module, stdin: StringIO.new("from io"), stdout: stdout, stderr: stderr)
+
+      expect(JSON.parse(stdout.string).dig("wasi", "stdin")).to eq("from io")
+      expect(stdout.string).to eq(result.stdout)
+      expect(stderr.string).to eq(result.stderr)
+    end
+
+    it "maps directories" do
+      result = Wasi::Command.run(engine, wasi_module, dir_mappings: {"/data" => tmpdir})
+
+      expect(result).to be_success
+    end
+
+    it "limits the captured output" do
+      result = Wasi::Command.run(engine, wasi_module, output_capacity: 5)
+
+      expect(result.stdout.bytesize).to eq(5)
+    end
+
+    it "returns the exit code of commands calling proc_exit" do
+      mod = Module.new(engine, <<~WAT)
+        (module
+          (import "wasi_snapshot_preview1" "proc_exit" (func $exit (param i32)))
+          (memory (export "memory") 1)
+          (func (export "_start") (call $exit (i32.const 3))))
+      WAT
+
+      result = Wasi::Command.run(engine, mod)
+      expect(result.status).to eq(3)
+      expect(result).not_to be_success
+    end
+
+    it "runs programs multiple times" do
+      command = Wasi::Command.new(engine, wasi_module)
+
+      expect(JSON.parse(command.run(argv: ["a"]).stdout).dig("wasi", "args")).to eq(["a"])
+      expect(JSON.parse(command.run(argv: ["b"]).stdout).dig("wasi", "args")).to eq(["b"])
+    end
+
+    it "rejects other programs" do
+      expect { Wasi::Command.new(engine, "nope") }
+        .to raise_error(ArgumentError, /expected a Wasmtime::Module or a Wasmtime::Component::Component/)
+    end
+  end
+end
@@ -0,0 +1,40 @@
+require "spec_helper"
+
+module Wasmtime
+  RSpec.describe "wasi-nn" do
+    if defined?(Wasmtime::WasiNnCtxBuilder)
+      it "is configured through chained methods" do
+        builder = WasiNnCtxBuilder.new
+        expect(builder.allow_backend("openvino")).to be(builder)
+        expect(builder.preload_graph("openvino", "models/nope")).to be(builder)
+      end
+
+      it "defines wasi-nn in linkers" do
+        expect(Linker.new(engine, wasi_nn: true)).to be_a(Linker)
+        expect(Component::Linker.new(engine, wasi_nn: true)).to be_a(Component::Linker)
+      end
+
+      it "builds stores with a wasi-nn context" do
+        expect(Store.new(engine, wasi_nn_ctx: WasiNnCtxBuilder.new)).to be_a(Store)
+      end
+
+      it "rejects graphs of disallowed backends" do
+        builder = WasiNnCtxBuilder.new.allow_backend("onnx").preload_graph("openvino", "models/nope")
+
+        expect { Store.new(engine, wasi_nn_ctx: builder) }
+          .to raise_error(ArgumentError, /disallowed backend openvino/)
+      end
+
+      it "is not supported in async engines" do
+        expect { Linker.new(Engine.new(async_support: true), wasi_nn: true) }
+          .to raise_error(Wasmtime::Error, /not supported in engines with async_support: true/)
+      end
+    else
+      it "requires the wasi-nn feature" do
+        expect { Linker.new(engine, wasi_nn: true) }.to raise_error(Wasmtime::Error, /wasi-nn feature/)
+        expect { Component::Linker.new(engine, wasi_nn: true) }.to raise_error(Wasmtime::Error, /wasi-nn feature/)
+        expect { Store.new(engine, wasi_nn_ctx: Object.new) }.to raise_error(Wasmtime::Error, /wasi-nn feature/)
+      end
+    end
+  end
+end
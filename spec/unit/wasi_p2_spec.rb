@@ -0,0 +1,99 @@
+require "spec_helper"
+
+module Wasmtime
+  RSpec.describe WasiP2CtxBuilder do
+    let(:component) { Component::Component.new(engine, "(component)") }
+    let(:linker) { Component::Linker.new(engine, wasi: true) }
+
+    it "is configured through chained methods" do
+      builder = WasiP2CtxBuilder.new
+      expect(builder.inherit_stdin.set_stdin_string("in")).to be(builder)
+      expect(builder.inherit_stdout.set_stdout_buffer(+"", 100)).to be(builder)
+      expect(builder.inherit_stderr.set_stderr_buffer(+"", 100)).to be(builder)
+      expect(builder.inherit_env.set_env("A" => "1").env("B", "2")).to be(builder)
+      expect(builder.inherit_argv.set_argv(["app"])).to be(builder)
+      expect(builder.preopen_dir(".", "/", dir_perms: :read, file_perms: :read)).to be(builder)
+      expect(builder.set_random_seed(42).set_wall_clock(Time.at(0)).set_monotonic_clock(0)).to be(builder)
+      expect(builder.inherit_network).to be(builder)
+    end
+
+    it "copies its settings with #dup" do
+      builder = WasiP2CtxBuilder.new.set_argv(["app"])
+      copy = builder.dup
+
+      expect(copy).to be_a(WasiP2CtxBuilder)
+      expect(copy).not_to be(builder)
+      expect(linker.instantiate(Store.new(engine, wasi_p2_ctx: copy), component)).to be_a(Component::Instance)
+    end
+
+    describe "networking" do
+      it "is configured through chained methods" do
+        builder = WasiP2CtxBuilder.new
+        expect(builder.allow_socket_addr("127.0.0.1:8080").allow_socket_addr("::1")).to be(builder)
+        expect(builder.allow_socket_addr("[::1]:8080").allow_ip_name_lookup(true)).to be(builder)
+        expect(builder.deny_network.inherit_network).to be(builder)
+      end
+
+      it "rejects invalid socket addresses" do
+        expect { WasiP2CtxBuilder.new.allow_socket_addr("example.com:80") }
+          .to raise_error(ArgumentError, /invalid socket address/)
+      end
+
+      it "builds stores" do
+        builder = WasiP2CtxBuilder.new.allow_socket_addr("127.0.0.1:8080")
+
+        expect(Store.new(engine, wasi_p2_ctx: builder)).to be_a(Store)
+      end
+    end
+
+    it "rejects invalid permissions" do
+      expect { WasiP2CtxBuilder.new.preopen_dir(".", "/", dir_perms: :write) }
+        .to raise_error(ArgumentError, /:dir_perms/)
+    end
+
+    it "reports preopened directories that can't be opened" do
+      builder = WasiP2CtxBuilder.new.preopen_dir("/does/not/exist", "/")
+
+      expect { Store.new(engine, wasi_p2_ctx: builder) }
+        .to raise_error(Wasmtime::Error, /Failed to open directory/)
+    end
+
+    it "lets components be instantiated with WASI" do
+      store = Store.new(engine, wasi_p2_ctx: WasiP2CtxBuilder.new)
+
+      expect(linker.instantiate(store, component)).to be_a(Component::Instance)
+    end
+
+    it "can be combined with WASI HTTP" do
+      linker = Component::Linker.new(engine, wasi: true, wasi_http: true)
+      store = Store.new(
+        engine,
+        wasi_p2_ctx: WasiP2CtxBuilder.new,
+        wasi_http_ctx: Component::WasiHttpCtxBuilder.new
+      )
+
+      expect(linker.instantiate(store, component)).to be_a(Component::Instance)
+    end
+
+    it "requires a WASI preview 2 context in the store" do
+      expect { linker.instantiate(store, component) }
+        .to raise_error(Wasmtime::Error, /Store is missing WASI preview 2 configuration/)
+    end
+
+    it "requires commands to export wasi:cli/run" do
+      store = Store.new(engine, wasi_p2_ctx: WasiP2CtxBuilder.new)
+
+      expect { linker.run_command(store, component) }.to raise_error(Wasmtime::Error)
+    end
+
+    it "requires a WASI linker to run commands" do
+      expect { Component::Linker.new(engine).run_command(store, component) }
+        .to raise_error(Wasmtime::Error, /requires a Linker created with wasi: true/)
+    end
+
+    it "is not supported in async engines" do
+      expect { Component::Linker.new(Engine.new(async_support: true), wasi: true) }
+        .to raise_error(Wasmtime::Error, /not supported in engines with async_support: true/)
+    end
+  end
+end
@@ -1,5 +1,6 @@
 require "spec_helper"
 require "json"
+require "stringio"
 
 module Wasmtime
   RSpec.describe "WASI" do
@@ -34,6 +35,18 @@ module Wasmtime
         store = Store.new(@engine, wasi_ctx: WasiCtxBuilder.new.set_stdin_string("some str").build)
         linker.instantiate(store, wasi_module).invoke("_start")
       end
+
+      it "builds a new context for each store created with a WasiCtxBuilder" do
+        linker = Linker.new(@engine, wasi: true)
+        builder = WasiCtxBuilder.new.set_stdin_string("stdin content")
+
+        2.times do
+          stdout = +""
+          builder.set_stdout_buffer(stdout, 40_000)
+          linker.instantiate(Store.new(@engine, wasi_ctx: builder), wasi_module).invoke("_start")
+          expect(JSON.parse(stdout).dig("wasi", "stdin")).to eq("stdin content")
+        end
+      end
     end
 
     # Uses the program from spec/wasi-debug to test the WASI integration
@@ -55,11 +68,82 @@ module Wasmtime
         expect(stdout.dig("wasi", "stdin")).to eq("stdin content")
       end
 
+      it "writes std streams to buffers" do
+        stdout = +""
+        stderr = +""
+        wasi_config = WasiCtxBuilder.new
+          .set_stdin_string("stdin content")
+          .set_stdout_buffer(stdout, 40_000)
+          .set_stderr_buffer(stderr, 40_000)
+          .build
+
+        run_wasi_module(wasi_config)
+
+        expect(JSON.parse(stdout).fetch("name")).to eq("stdout")
+        expect(JSON.parse(stderr).fetch("name")).to eq("stderr")
+        expect(JSON.parse(stdout).dig("wasi", "stdin")).to eq("stdin content")
+      end
+
+      it "truncates buffered output past capacity" do
+        stdout = +""
+        wasi_config = WasiCtxBuilder.new.set_stdout_buffer(stdout, 5).build
+
+        run_wasi_module(wasi_config)
+
+        expect(stdout.bytesize).to eq(5)
+      end
+
+      it "keeps buffers alive without other references" do
+        wasi_config = WasiCtxBuilder.new.set_stdout_buffer(+"", 40_000).build
+        GC.start
+
+        expect { run_wasi_module(wasi_config) }.not_to raise_error
+      end
+
+      it "writes std streams to IO objects" do
+        stdout = StringIO.new
+        stderr = StringIO.new
+        wasi_config = WasiCtxBuilder.new
+          .set_stdout_io(stdout)
+          .set_stderr_io(stderr)
+          .build
+
+        run_wasi_module(wasi_config)
+
+        expect(JSON.parse(stdout.string).fetch("name")).to eq("stdout")
+        expect(JSON.parse(stderr.string).fetch("name")).to eq("stderr")
+      end
+
       it "reads stdin from string" do
         env = wasi_module_env { |config| config.set_stdin_string("¡UTF-8 from Ruby!") }
         expect(env.fetch("stdin")).to eq("¡UTF-8 from Ruby!")
       end
 
+      it "reads stdin from an IO" do
+        env = wasi_module_env { |config| config.set_stdin_io(StringIO.new("¡UTF-8 from Ruby!")) }
+        expect(env.fetch("stdin")).to eq("¡UTF-8 from Ruby!")
+      end
+
+      it "reads stdin from a pipe" do
+        reader, writer = IO.pipe
+        writer.write("from a pipe")
+        writer.close
+        env = wasi_module_env { |config| config.set_stdin_io(reader) }
+        expect(env.fetch("stdin")).to eq("from a pipe")
+      ensure
+        reader&.close
+      end
+
+      it "copies its settings with #dup" do
+        base = WasiCtxBuilder.new.set_argv(["base"]).env("A", "1")
+        copy = base.dup.set_argv(["copy"]).env("B", "2")
+
+        expect(wasi_builder_env(base).fetch("args")).to eq(["base"])
+        expect(wasi_builder_env(base).fetch("env").to_h).to eq("A" => "1")
+        expect(wasi_builder_env(copy).fetch("args")).to eq(["copy"])
+        expect(wasi_builder_env(copy).fetch("env").to_h).to eq("A" => "1", "B" => "2")
+      end
+
       it "uses specified args" do
         env = wasi_module_env { |config| config.set_argv(["foo", "bar"]) }
         expect(env.fetch("args")).to eq(["foo", "bar"])
@@ -80,6 +164,103 @@ module Wasmtime
         expect(env.fetch("env").to_h).to eq(ENV.to_h)
       end
 
+      it "inherits the process env" do
+        env = wasi_module_env { |config| config.inherit_env }
+        expect(env.fetch("env").to_h).to eq(ENV.to_h)
+      end
+
+      it "sets single env vars on top of the env" do
+        env = wasi_module_env do |config|
+          config.set_env("A" => "1", "B" => "2").env("B", "3").env("C", "4")
+        end
+        expect(env.fetch("env").to_h).to eq("A" => "1", "B" => "3", "C" => "4")
+      end
+
+      it "resets single env vars when replacing the env" do
+        env = wasi_module_env { |config| config.env("A", "1").set_env("B" => "2") }
+        expect(env.fetch("env").to_h).to eq("B" => "2")
+      end
+
+      it "inherits the process args" do
+        env = wasi_module_env { |config| config.inherit_argv }
+        expect(env.fetch("args")).not_to be_empty
+      end
+
+      describe "#preopen_dir" do
+        # wasi_snapshot_preview1 constants
+        rights_fd_read = 1 << 1
+        rights_fd_write = 1 << 6
+        oflags_creat = 1
+        errno_success = 0
+        errno_badf = 8
+        errno_perm = 63
+
+        before { File.write(tempfile_path("file.txt"), "content") }
+
+        it "gives access to the directory" do
+          instance = path_open_instance(WasiCtxBuilder.new.preopen_dir(tmpdir, "/data").build)
+
+          expect(path_open(instance, "file.txt", 0, rights_fd_read)).to eq(errno_success)
+          expect(path_open(instance, "new.txt", oflags_creat, rights_fd_write)).to eq(errno_success)
+          expect(File.exist?(tempfile_path("new.txt"))).to be true
+        end
+
+        it "denies writes to read-only files" do
+          ctx = WasiCtxBuilder.new.preopen_dir(tmpdir, "/data", file_perms: :read).build
+          instance = path_open_instance(ctx)
+
+          expect(path_open(instance, "file.txt", 0, rights_fd_read)).to eq(errno_success)
+          expect(path_open(instance, "file.txt", 0, rights_fd_write)).to eq(errno_perm)
+        end
+
+        it "denies creating files in read-only directories" do
+          ctx = WasiCtxBuilder.new.preopen_dir(tmpdir, "/data", dir_perms: :read).build
+          instance = path_open_instance(ctx)
+
+          expect(path_open(instance, "new.txt", oflags_creat, rights_fd_write)).to eq(errno_perm)
+          expect(File.exist?(tempfile_path("new.txt"))).to be false
+        end
+
+        it "has no directory by default" do
+          instance = path_open_instance(WasiCtxBuilder.new.build)
+          expect(path_open(instance, "file.txt", 0, rights_fd_read)).to eq(errno_badf)
+        end
+
+        it "rejects invalid permissions" do
+          expect { WasiCtxBuilder.new.preopen_dir(tmpdir, "/data", dir_perms: :nope) }
+            .to raise_error(ArgumentError, /invalid :dir_perms, expected one of \[:read, :mutate, :all\]/)
+          expect { WasiCtxBuilder.new.preopen_dir(tmpdir, "/data", file_perms: :nope) }
+            .to raise_error(ArgumentError, /invalid :file_perms, expected one of \[:read, :write, :all\]/)
+        end
+
+        it "raises when the host directory doesn't exist" do
+          builder = WasiCtxBuilder.new.preopen_dir(tempfile_path("nope"), "/data")
+          expect { builder.build }.to raise_error(Wasmtime::Error, /Failed to open directory/)
+        end
+      end
+
+      describe "randomness and clocks" do
+        it "seeds the random source" do
+          random = ->(seed) { random_clock_instance(WasiCtxBuilder.new.set_random_seed(seed)).invoke("random") }
+
+          expect(random.call(1)).to eq(random.call(1))
+          expect(random.call(1)).not_to eq(random.call(2))
+        end
+
+        it "freezes the wall clock" do
+          instance = random_clock_instance(WasiCtxBuilder.new.set_wall_clock(Time.at(1_700_000_000, 5, :nsec)))
+
+          expect(instance.invoke("clock", 0)).to eq(1_700_000_000_000_000_005)
+          expect(instance.invoke("clock", 0)).to eq(1_700_000_000_000_000_005)
+        end
+
+        it "freezes the monotonic clock" do
+          instance = random_clock_instance(WasiCtxBuilder.new.set_monotonic_clock(42))
+
+          expect(instance.invoke("clock", 1)).to eq(42)
+        end
+      end
+
       describe "WasiContext" do
         describe "deterministic" do
           before do
@@ -156,6 +337,59 @@ module Wasmtime
       JSON.parse(File.read(stdout_file)).fetch("wasi")
     end
 
+    def wasi_builder_env(builder)
+      stdout = +""
+      run_wasi_module(builder.set_stdout_buffer(stdout, 40_000))
+
+      JSON.parse(stdout).fetch("wasi")
+    end
+
+    def random_clock_instance(wasi_ctx_builder)
+      mod = Module.new(@engine, <<~WAT)
+        (module
+          (import "wasi_snapshot_preview1" "random_get"
+            (func $random_get (param i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "clock_time_get"
+            (func $clock_time_get (param i32 i64 i32) (result i32)))
+          (memory (export "memory") 1)
+          (func (export "random") (result i64)
+            (drop (call $random_get (i32.const 0) (i32.const 8)))
+            (i64.load (i32.const 0)))
+          (func (export "clock") (param i32) (result i64)
+            (drop (call $clock_time_get (local.get 0) (i64.const 1) (i32.const 0)))
+            (i64.load (i32.const 0))))
+      WAT
+      store = Store.new(@engine, wasi_ctx: wasi_ctx_builder.build)
+      Linker.new(@engine, wasi: true).instantiate(store, mod)
+    end
+
+    def path_open_instance(wasi_ctx)
+      mod = Module.new(@engine, <<~WAT)
+        (module
+          (import "wasi_snapshot_preview1" "path_open"
+            (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (func (export "open") (param $path_len i32) (param $oflags i32) (param $rights i64) (result i32)
+            (call $path_open
+              (i32.const 3) ;; first preopened fd
+              (i32.const 0) ;; dirflags
+              (i32.const 0) ;; path_ptr
+              (local.get $path_len)
+              (local.get $oflags)
+              (local.get $rights)
+              (i64.const 0) ;; rights_inheriting
+              (i32.const 0) ;; fdflags
+              (i32.const 1024)))) ;; opened_fd_ptr
+      WAT
+      linker = Linker.new(@engine, wasi: true)
+      linker.instantiate(Store.new(@engine, wasi_ctx: wasi_ctx), mod)
+    end
+
+    def path_open(instance, path, oflags, rights)
+      instance.export("memory").to_memory.write(0, path)
+      instance.invoke("open", path.bytesize, oflags, rights)
+    end
+
     def tempfile_path(name)
       File.join(tmpdir, name)
     end
@@ -17,5 +17,53 @@ module Wasmtime
         expect { Wasmtime.wat2wasm("not wat") }.to raise_error(Wasmtime::Error)
       end
     end
+
+    describe ".compilation_threads=" do
+      it "rejects zero threads" do
+        expect { Wasmtime.compilation_threads = 0 }.to raise_error(ArgumentError, /at least 1/)
+      end
+
+      it "can't be changed once parallel compilation started" do
+        Module.new(Engine.new(parallel_compilation: true), "(module (func) (func))")
+
+        expect { Wasmtime.compilation_threads = 2 }
+          .to raise_error(Wasmtime::Error, /Could not set compilation_threads/)
+      end
+    end
+
+    describe ".shutdown" do
+      it "closes all engines" do
+        skip "requires fork" unless Process.respond_to?(:fork)
+
+        # In a child process, not to close the engines of other specs.
+        pid = fork do
+          engine = Engine.new
+          Wasmtime.shutdown
+          exit!(engine.closed? && GLOBAL_ENGINE.closed?)
+        end
+        Process.wait(pid)
+
+        expect($?).to be_success
+      end
+    end
+
+    describe ".wasm2wat" do
+      it "returns a UTF-8 string" do
+        wat = Wasmtime.wasm2wat(Wasmtime.wat2wasm("(module)"))
+        expect(wat.encoding).to eq(Encoding::UTF_8)
+      end
+
+      it "round-trips with .wat2wasm" do
+        wasm = Wasmtime.wat2wasm('(module (func (export "f") (result i32) i32.const 42))')
+        wat = Wasmtime.wasm2wat(wasm)
+
+        expect(wat).to include('(export "f"')
+        expect(Wasmtime.wat2wasm(wat)).to eq(wasm)
+      end
+
+      it "raises on invalid Wasm" do
+        expect { Wasmtime.wasm2wat("not wasm") }.to raise_error(Wasmtime::Error)
+      end
+    end
   end
 end